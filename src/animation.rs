@@ -0,0 +1,176 @@
+//! Sprite animation.
+//!
+//! A [`SpriteAnimation`] steps a [`TextureAtlasSprite`] through a range of
+//! frames. A [`WalkCycle`] plays it while its entity moves, faster or slower
+//! with its speed, and rests on the first frame while it stands still.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::ops::Range;
+
+/// Sprite animation plugin.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (drive_walk_cycles, animate_sprites)
+                .chain()
+                .in_set(AnimationSystem::Animate)
+                .run_if(any_with_component::<SpriteAnimation>()),
+        );
+    }
+}
+
+/// Animation systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum AnimationSystem {
+    /// Sprites are moved to their next frame.
+    Animate,
+}
+
+/// Steps a sprite through a range of frames of its atlas.
+#[derive(Clone, Component, Debug)]
+pub struct SpriteAnimation {
+    /// The frames of the animation, as indices into the atlas.
+    pub frames: Range<usize>,
+    /// How many frames are shown each second.
+    pub fps: f32,
+    /// Whether the animation starts over after the last frame, or stays on
+    /// it.
+    pub looping: bool,
+    /// Whether the animation is playing.
+    pub playing: bool,
+    elapsed: f32,
+}
+
+impl SpriteAnimation {
+    /// Creates a new, looping `SpriteAnimation` that is playing.
+    pub fn new(frames: Range<usize>, fps: f32) -> SpriteAnimation {
+        SpriteAnimation {
+            frames,
+            fps,
+            looping: true,
+            playing: true,
+            elapsed: 0.,
+        }
+    }
+
+    /// Makes the animation stop on its last frame.
+    pub fn once(self) -> SpriteAnimation {
+        SpriteAnimation {
+            looping: false,
+            ..self
+        }
+    }
+
+    /// Makes the animation start paused.
+    pub fn paused(self) -> SpriteAnimation {
+        SpriteAnimation {
+            playing: false,
+            ..self
+        }
+    }
+
+    /// Pauses the animation and goes back to the first frame.
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.elapsed = 0.;
+    }
+
+    /// Plays the animation from the first frame.
+    pub fn restart(&mut self) {
+        self.playing = true;
+        self.elapsed = 0.;
+    }
+
+    /// The index of the frame in the atlas that should be shown.
+    pub fn frame(&self) -> usize {
+        let len = self.frames.len();
+
+        if len == 0 {
+            return self.frames.start;
+        }
+
+        let frame = (self.elapsed * self.fps) as usize;
+
+        let frame = if self.looping {
+            frame % len
+        } else {
+            frame.min(len - 1)
+        };
+
+        self.frames.start + frame
+    }
+
+    /// Checks if an animation that doesn't loop has reached its last frame.
+    pub fn finished(&self) -> bool {
+        !self.looping && (self.elapsed * self.fps) as usize >= self.frames.len().saturating_sub(1)
+    }
+
+    /// Moves the animation forward by some seconds, if it is playing.
+    pub fn tick(&mut self, delta: f32) {
+        if self.playing && !self.finished() {
+            self.elapsed += delta;
+        }
+    }
+}
+
+/// Plays an entity's [`SpriteAnimation`] while it moves sideways.
+///
+/// The animation plays at its own [`SpriteAnimation::fps`] when the entity
+/// moves at [`WalkCycle::speed`], and faster or slower as it speeds up or
+/// slows down.
+#[derive(Clone, Component, Debug)]
+pub struct WalkCycle {
+    /// The speed the animation is drawn for, in units per second.
+    pub speed: f32,
+    /// The animation's frame rate at [`WalkCycle::speed`].
+    pub fps: f32,
+}
+
+impl WalkCycle {
+    /// How slowly the entity can move and still count as walking.
+    pub const THRESHOLD: f32 = 1.;
+
+    /// Creates a new `WalkCycle`.
+    pub fn new(speed: f32, fps: f32) -> WalkCycle {
+        WalkCycle { speed, fps }
+    }
+}
+
+fn drive_walk_cycles(mut query: Query<(&WalkCycle, &Velocity, &mut SpriteAnimation)>) {
+    for (walk, velocity, mut animation) in query.iter_mut() {
+        let speed = velocity.linvel.x.abs();
+
+        if speed < WalkCycle::THRESHOLD {
+            if animation.playing {
+                animation.stop();
+            }
+
+            continue;
+        }
+
+        animation.playing = true;
+        animation.fps = walk.fps * (speed / walk.speed.max(f32::EPSILON)).clamp(0.5, 2.);
+    }
+}
+
+fn animate_sprites(
+    mut query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        animation.tick(time.delta_seconds());
+
+        let frame = animation.frame();
+
+        // do not trip change detection
+        if sprite.index != frame {
+            sprite.index = frame;
+        }
+    }
+}
@@ -0,0 +1,470 @@
+//! Arena mode.
+//!
+//! In [`GameMode::Arena`], the game loads a dedicated level
+//! ([`ArenaSettings::level`]) and sends waves of enemies at the player out of
+//! the `ArenaSpawnPoint`s placed in it, each wave bigger than the last. Kills
+//! on the beat are worth extra, and when the player dies the run's score is
+//! submitted to the [`Leaderboard`] and the arena starts over.
+//!
+//! The player goes in and out of the arena with [`Arena::TOGGLE_KEY`], which
+//! sets the [`GameMode`] state; leaving it puts the player back in the level
+//! they came from.
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{app::LdtkEntityAppExt as _, LdtkEntity};
+
+use std::time::Duration;
+
+use crate::combat::{CombatSystem, DamageEvent};
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::ai::{Aggro, Patrol};
+use crate::enemy::prefab::{Armament, EnemyAggro, EnemyArmament, EnemyPatrol, EnemyPrefab};
+use crate::enemy::AimAtPlayer;
+use crate::level::CurrentLevel;
+use crate::player::respawn::WorldRespawn;
+use crate::player::LocalPlayer;
+use crate::rng::GameRng;
+use crate::score::{Score, ScoreEvent, ScoreSystem};
+use crate::{GameMode, GameState};
+
+/// Arena mode plugin.
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ArenaSettings>()
+            .init_resource::<Arena>()
+            .init_resource::<Leaderboard>()
+            .add_event::<WaveStartedEvent>()
+            .add_event::<ArenaOverEvent>()
+            .register_ldtk_entity::<ArenaSpawnPointBundle>("ArenaSpawnPoint")
+            .add_systems(OnEnter(GameMode::Arena), enter_arena)
+            .add_systems(OnExit(GameMode::Arena), exit_arena)
+            .add_systems(Update, toggle_arena.run_if(in_state(GameState::InGame)))
+            .add_systems(
+                Update,
+                (
+                    (tick_beat, run_waves).chain(),
+                    score_on_beat.after(ScoreSystem::Score),
+                    end_arena_runs.after(CombatSystem::Damage),
+                )
+                    .run_if(in_state(GameMode::Arena).and_then(in_state(GameState::InGame))),
+            );
+    }
+}
+
+/// How the arena plays.
+#[derive(Clone, Debug, Resource)]
+pub struct ArenaSettings {
+    /// The identifier of the arena level.
+    pub level: String,
+    /// How many enemies are in the first wave.
+    pub first_wave: u32,
+    /// How many more enemies each wave has than the last.
+    pub wave_growth: u32,
+    /// The time between enemies spawning in a wave.
+    pub spawn_interval: Duration,
+    /// The time between clearing a wave and the next one starting.
+    pub wave_break: Duration,
+    /// The first wave where enemies shoot back.
+    pub armed_from_wave: u32,
+    /// The time between enemy shots.
+    pub fire_interval: Duration,
+    /// How much more likely a [wisp] is each wave, up to
+    /// [`ArenaSettings::max_wisp_chance`].
+    ///
+    /// [wisp]: crate::enemy::prefab::WispBundle
+    pub wisp_chance_per_wave: f32,
+    /// The most likely an enemy is to be a wisp.
+    pub max_wisp_chance: f32,
+    /// How far away arena enemies notice the player.
+    pub aggro_range: f32,
+    /// The tempo of the arena, in beats per minute.
+    pub bpm: f32,
+    /// How close to a beat a kill has to be to count as on the beat.
+    pub beat_window: Duration,
+    /// What points for kills on the beat are multiplied by.
+    pub beat_multiplier: u64,
+}
+
+impl ArenaSettings {
+    /// How many enemies are in a wave, starting at `1`.
+    pub fn wave_size(&self, wave: u32) -> u32 {
+        self.first_wave + self.wave_growth * wave.saturating_sub(1)
+    }
+
+    /// The chance of an enemy in a wave being a wisp.
+    pub fn wisp_chance(&self, wave: u32) -> f32 {
+        (self.wisp_chance_per_wave * wave.saturating_sub(1) as f32).min(self.max_wisp_chance)
+    }
+
+    /// The time between beats.
+    pub fn beat_interval(&self) -> Duration {
+        Duration::from_secs_f32(60. / self.bpm.max(1.))
+    }
+}
+
+impl Default for ArenaSettings {
+    fn default() -> ArenaSettings {
+        ArenaSettings {
+            level: "Arena".into(),
+            first_wave: 3,
+            wave_growth: 2,
+            spawn_interval: Duration::from_millis(750),
+            wave_break: Duration::from_secs(3),
+            armed_from_wave: 3,
+            fire_interval: Duration::from_secs(2),
+            wisp_chance_per_wave: 0.1,
+            max_wisp_chance: 0.5,
+            aggro_range: 128.,
+            bpm: 120.,
+            beat_window: Duration::from_millis(100),
+            beat_multiplier: 2,
+        }
+    }
+}
+
+/// The state of the current arena run.
+#[derive(Debug, Resource)]
+pub struct Arena {
+    wave: u32,
+    to_spawn: u32,
+    spawn_timer: Timer,
+    break_timer: Timer,
+    beat: Timer,
+    return_level: Option<CurrentLevel>,
+}
+
+impl Arena {
+    /// The key that goes in and out of the arena.
+    pub const TOGGLE_KEY: KeyCode = KeyCode::Tab;
+
+    /// The current wave, starting at `1`, or `0` before the first one.
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    /// How many enemies of the current wave have yet to spawn.
+    pub fn to_spawn(&self) -> u32 {
+        self.to_spawn
+    }
+
+    /// Checks if it is within `window` of a beat.
+    pub fn on_beat(&self, window: Duration) -> bool {
+        self.beat.elapsed() <= window || self.beat.remaining() <= window
+    }
+
+    /// Starts the run over from the first wave.
+    pub fn restart(&mut self, settings: &ArenaSettings) {
+        self.wave = 0;
+        self.to_spawn = 0;
+        self.spawn_timer = Timer::new(settings.spawn_interval, TimerMode::Repeating);
+        self.break_timer = Timer::new(settings.wave_break, TimerMode::Once);
+        self.beat = Timer::new(settings.beat_interval(), TimerMode::Repeating);
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Arena {
+        let mut arena = Arena {
+            wave: 0,
+            to_spawn: 0,
+            spawn_timer: Timer::default(),
+            break_timer: Timer::default(),
+            beat: Timer::default(),
+            return_level: None,
+        };
+
+        arena.restart(&ArenaSettings::default());
+        arena
+    }
+}
+
+/// Marks an enemy spawned by the arena.
+#[derive(Clone, Component, Debug, Default)]
+pub struct ArenaEnemy;
+
+/// Where arena enemies come from.
+#[derive(Clone, Component, Debug, Default)]
+pub struct ArenaSpawnPoint;
+
+/// An arena spawn point bundle.
+#[derive(Bundle, Default, LdtkEntity)]
+pub struct ArenaSpawnPointBundle {
+    pub spawn_point: ArenaSpawnPoint,
+}
+
+/// The best arena runs.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+}
+
+/// A single arena run on the [`Leaderboard`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    /// The score of the run.
+    pub points: u64,
+    /// The wave the run ended on.
+    pub wave: u32,
+}
+
+impl Leaderboard {
+    /// How many runs are kept.
+    pub const MAX_ENTRIES: usize = 10;
+
+    /// The runs, best first.
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    /// Submits a run.
+    ///
+    /// Returns where the run placed, starting at `0`, or `None` if it didn't
+    /// make the board.
+    pub fn submit(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        // ties go to the older run
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| (entry.points, entry.wave) > (e.points, e.wave))
+            .unwrap_or(self.entries.len());
+
+        if rank >= Leaderboard::MAX_ENTRIES {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(Leaderboard::MAX_ENTRIES);
+
+        Some(rank)
+    }
+}
+
+/// A wave started.
+#[derive(Clone, Debug, Event)]
+pub struct WaveStartedEvent {
+    /// The wave, starting at `1`.
+    pub wave: u32,
+    /// How many enemies are in the wave.
+    pub enemies: u32,
+}
+
+/// The player died in the arena.
+#[derive(Clone, Debug, Event)]
+pub struct ArenaOverEvent {
+    /// The run.
+    pub entry: LeaderboardEntry,
+    /// Where the run placed on the [`Leaderboard`], if it did.
+    pub rank: Option<usize>,
+}
+
+fn toggle_arena(
+    mut next_mode: ResMut<NextState<GameMode>>,
+    mode: Res<State<GameMode>>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    if !keyboard.just_pressed(Arena::TOGGLE_KEY) {
+        return;
+    }
+
+    next_mode.set(match mode.get() {
+        GameMode::Story => GameMode::Arena,
+        GameMode::Arena => GameMode::Story,
+    });
+}
+
+fn enter_arena(
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+    mut score: ResMut<Score>,
+    mut world_respawn: ResMut<WorldRespawn>,
+    current_level: Res<CurrentLevel>,
+    settings: Res<ArenaSettings>,
+) {
+    arena.restart(&settings);
+    arena.return_level = Some(current_level.clone());
+    score.reset();
+
+    commands.insert_resource(CurrentLevel(settings.level.clone()));
+    world_respawn.start_respawn();
+
+    crate::game_info!(Level, "entering arena {}", settings.level);
+}
+
+fn exit_arena(
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut world_respawn: ResMut<WorldRespawn>,
+    enemy_query: Query<Entity, With<ArenaEnemy>>,
+) {
+    for entity in enemy_query.iter() {
+        despawn_queue.push(entity, DespawnReason::Other("arena closed"));
+    }
+
+    if let Some(level) = arena.return_level.take() {
+        commands.insert_resource(level);
+        world_respawn.start_respawn();
+    }
+}
+
+fn tick_beat(mut arena: ResMut<Arena>, time: Res<Time>) {
+    arena.beat.tick(time.delta());
+}
+
+fn run_waves(
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+    mut wave_events: EventWriter<WaveStartedEvent>,
+    mut rng: ResMut<GameRng>,
+    spawn_point_query: Query<(&Parent, &Transform), With<ArenaSpawnPoint>>,
+    enemy_query: Query<(), With<ArenaEnemy>>,
+    settings: Res<ArenaSettings>,
+    time: Res<Time>,
+) {
+    // wait for the level to load
+    if spawn_point_query.is_empty() {
+        return;
+    }
+
+    if arena.to_spawn == 0 {
+        // wait for the wave to be cleared
+        if !enemy_query.is_empty() {
+            return;
+        }
+
+        arena.break_timer.tick(time.delta());
+
+        if !arena.break_timer.finished() {
+            return;
+        }
+
+        arena.break_timer.reset();
+        arena.spawn_timer.reset();
+
+        arena.wave += 1;
+        arena.to_spawn = settings.wave_size(arena.wave);
+
+        wave_events.send(WaveStartedEvent {
+            wave: arena.wave,
+            enemies: arena.to_spawn,
+        });
+
+        crate::game_info!(
+            Enemy,
+            "arena wave {} started with {} enemies",
+            arena.wave,
+            arena.to_spawn
+        );
+    }
+
+    arena.spawn_timer.tick(time.delta());
+
+    if !arena.spawn_timer.just_finished() {
+        return;
+    }
+
+    let spawn_points = spawn_point_query.iter().collect::<Vec<_>>();
+    let index = rng.range_u32(0, spawn_points.len() as u32) as usize;
+    let (level, transform) = spawn_points[index];
+
+    let prefab = if rng.chance(settings.wisp_chance(arena.wave)) {
+        EnemyPrefab::Wisp
+    } else {
+        EnemyPrefab::Howard
+    };
+
+    let mut enemy = commands.spawn(ArenaEnemy);
+    prefab.insert(&mut enemy, *transform);
+
+    if let EnemyPrefab::Howard = prefab {
+        enemy.insert((
+            EnemyPatrol(Some(Patrol::walk(EnemyPatrol::DEFAULT_SPEED))),
+            EnemyAggro(Some(Aggro::new(settings.aggro_range))),
+        ));
+    }
+
+    if arena.wave >= settings.armed_from_wave {
+        enemy.insert(EnemyArmament(Some(Armament {
+            interval: settings.fire_interval,
+            aim: AimAtPlayer::default(),
+        })));
+    }
+
+    let enemy = enemy.id();
+    commands.entity(level.get()).add_child(enemy);
+
+    arena.to_spawn -= 1;
+}
+
+fn score_on_beat(
+    mut reader: Local<ManualEventReader<ScoreEvent>>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut score: ResMut<Score>,
+    arena: Res<Arena>,
+    settings: Res<ArenaSettings>,
+) {
+    if !arena.on_beat(settings.beat_window) {
+        // catch up so late kills aren't counted on the next beat
+        reader.clear(&score_events);
+        return;
+    }
+
+    let bonuses = reader
+        .iter(&score_events)
+        // bonuses are not kills themselves
+        .filter(|ev| ev.kill.is_some())
+        .map(|ev| ScoreEvent {
+            points: ev.points * settings.beat_multiplier.saturating_sub(1),
+            combo: ev.combo,
+            position: ev.position,
+            kill: None,
+        })
+        .filter(|ev| ev.points > 0)
+        .collect::<Vec<_>>();
+
+    for ev in bonuses {
+        score.add(ev.points);
+        score_events.send(ev);
+    }
+}
+
+fn end_arena_runs(
+    mut damage_events: EventReader<DamageEvent>,
+    mut over_events: EventWriter<ArenaOverEvent>,
+    mut arena: ResMut<Arena>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut score: ResMut<Score>,
+    player_query: Query<(), With<LocalPlayer>>,
+    settings: Res<ArenaSettings>,
+) {
+    for ev in damage_events.iter() {
+        // the player can be hurt more than once as they die
+        if !player_query.contains(ev.target) || arena.wave == 0 {
+            continue;
+        }
+
+        let entry = LeaderboardEntry {
+            points: score.points(),
+            wave: arena.wave,
+        };
+
+        let rank = leaderboard.submit(entry.clone());
+
+        crate::game_info!(
+            Player,
+            "arena run over at wave {} with {} points",
+            entry.wave,
+            entry.points
+        );
+
+        over_events.send(ArenaOverEvent { entry, rank });
+
+        // the world respawns with the player, taking the enemies with it
+        arena.restart(&settings);
+        score.reset();
+    }
+}
@@ -0,0 +1,182 @@
+//! Texture atlases that can be referenced by key.
+//!
+//! Atlases in [`GameAssets`] are registered under fixed keys, and more can be
+//! added without recompiling by listing them in `manifest.atlases.ron`. Data
+//! definitions should look up textures in the [`AtlasRegistry`] by key, like
+//! enemies do with [`EnemyPrefab::atlas_key`].
+//!
+//! [`EnemyPrefab::atlas_key`]: crate::enemy::prefab::EnemyPrefab::atlas_key
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use crate::{GameAssets, GameState};
+
+/// Atlas registry plugin.
+pub struct AtlasRegistryPlugin;
+
+impl Plugin for AtlasRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<AtlasManifest>()
+            .add_asset_loader(AtlasManifestLoader)
+            .init_resource::<AtlasRegistry>()
+            .add_systems(Startup, load_atlas_manifest)
+            .add_systems(OnEnter(GameState::InGame), register_game_atlases)
+            .add_systems(PreUpdate, register_manifest_atlases);
+    }
+}
+
+/// All of the known texture atlases by key.
+#[derive(Debug, Default, Resource)]
+pub struct AtlasRegistry {
+    atlases: HashMap<String, Handle<TextureAtlas>>,
+}
+
+impl AtlasRegistry {
+    /// Gets an atlas by key.
+    pub fn get(&self, key: &str) -> Option<Handle<TextureAtlas>> {
+        self.atlases.get(key).cloned()
+    }
+
+    /// Adds an atlas under a key, replacing any atlas that was there before.
+    pub fn insert(&mut self, key: impl Into<String>, atlas: Handle<TextureAtlas>) {
+        self.atlases.insert(key.into(), atlas);
+    }
+
+    /// Iterates over all of the keys.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.atlases.keys().map(String::as_str)
+    }
+}
+
+/// A list of texture atlases to load.
+#[derive(Clone, Debug, Serialize, Deserialize, TypeUuid, TypePath)]
+#[uuid = "c1d8b7a2-3e4f-4a6b-8c9d-0e1f2a3b4c5d"]
+pub struct AtlasManifest {
+    /// The atlases by key.
+    #[serde(default)]
+    pub atlases: HashMap<String, AtlasDef>,
+}
+
+/// A texture atlas cut from a grid in an image.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AtlasDef {
+    /// The path to the image, relative to the assets folder.
+    pub path: String,
+    /// The size of a single tile in pixels.
+    pub tile_size: (f32, f32),
+    /// The number of columns in the grid.
+    pub columns: usize,
+    /// The number of rows in the grid.
+    pub rows: usize,
+    /// The space between tiles in pixels.
+    #[serde(default)]
+    pub padding: Option<(f32, f32)>,
+    /// The offset of the grid from the top left of the image in pixels.
+    #[serde(default)]
+    pub offset: Option<(f32, f32)>,
+}
+
+impl AtlasDef {
+    /// Creates the [`TextureAtlas`] for an image.
+    pub fn to_atlas(&self, image: Handle<Image>) -> TextureAtlas {
+        let to_vec2 = |(x, y): (f32, f32)| Vec2::new(x, y);
+
+        TextureAtlas::from_grid(
+            image,
+            to_vec2(self.tile_size),
+            self.columns,
+            self.rows,
+            self.padding.map(to_vec2),
+            self.offset.map(to_vec2),
+        )
+    }
+}
+
+/// Loads [`AtlasManifest`]s from `.atlases.ron` files.
+#[derive(Default)]
+pub struct AtlasManifestLoader;
+
+impl AssetLoader for AtlasManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest: AtlasManifest = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlases.ron"]
+    }
+}
+
+/// The handle of the manifest.
+#[derive(Resource)]
+struct AtlasManifestHandle(Handle<AtlasManifest>);
+
+fn load_atlas_manifest(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AtlasManifestHandle(
+        asset_server.load("manifest.atlases.ron"),
+    ));
+}
+
+fn register_game_atlases(mut registry: ResMut<AtlasRegistry>, assets: Res<GameAssets>) {
+    let atlases = [
+        ("platform", &assets.platform_atlas),
+        ("drum", &assets.drum_sheet),
+        ("player", &assets.player_sheet),
+        ("projectiles", &assets.projectile_sheet),
+        ("enemy/howard", &assets.enemy_howard),
+        ("enemy/wisp", &assets.enemy_wisp),
+    ];
+
+    for (key, atlas) in atlases {
+        // the manifest may have replaced it already
+        if registry.get(key).is_none() {
+            registry.insert(key, atlas.clone());
+        }
+    }
+}
+
+fn register_manifest_atlases(
+    mut asset_events: EventReader<AssetEvent<AtlasManifest>>,
+    mut registry: ResMut<AtlasRegistry>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    handle: Option<Res<AtlasManifestHandle>>,
+    manifests: Res<Assets<AtlasManifest>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    for ev in asset_events.iter() {
+        match ev {
+            AssetEvent::Created { handle: h } | AssetEvent::Modified { handle: h }
+                if *h == handle.0 =>
+            {
+                let Some(manifest) = manifests.get(h) else {
+                    continue;
+                };
+
+                for (key, def) in manifest.atlases.iter() {
+                    let atlas = def.to_atlas(asset_server.load(&def.path));
+
+                    registry.insert(key.clone(), texture_atlases.add(atlas));
+                }
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! Sound effects.
+//!
+//! Sounds with a position are spatialized around the [`PlayerCamera`], so
+//! things on the left of the screen are heard on the left.
+//!
+//! Every sound is a [`Voice`] in a [`SoundCategory`], and each category only
+//! plays so many voices at once, as set in [`AudioSettings`]. A sound played
+//! into a full category takes the place of the quietest-ranked voice, the
+//! lowest [priority](PlaySound::priority) and then the oldest, or isn't played
+//! if every voice outranks it. Ambient sounds duck while an important sound
+//! plays.
+
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+
+use crate::camera::PlayerCamera;
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::platform::{PlatformState, PlatformStateEvent};
+use crate::projectile::{BounceEvent, ProjectileSystem};
+use crate::settings::{AudioSettings, Settings};
+use crate::{GameAssets, GameState};
+
+/// Sound plugin.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySound>()
+            .init_resource::<PitchLadder>()
+            .init_resource::<Ducking>()
+            .add_systems(
+                Update,
+                play_bounce_sounds
+                    .run_if(in_state(GameState::InGame))
+                    .after(ProjectileSystem::Bounce),
+            )
+            .add_systems(
+                Update,
+                play_platform_sounds.run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_platform_loops
+                    .run_if(any_with_component::<PlatformLoop>())
+                    .after(TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                PostUpdate,
+                play_sounds
+                    // the calibration screen plays sounds too
+                    .run_if(not(in_state(GameState::AssetLoading)))
+                    .in_set(SoundSystem::Play),
+            )
+            .add_systems(
+                PostUpdate,
+                duck_ambience
+                    .run_if(any_with_component::<Voice>())
+                    .after(SoundSystem::Play),
+            );
+    }
+}
+
+/// Sound systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum SoundSystem {
+    /// [`PlaySound`] events are turned into sounds.
+    ///
+    /// Send [`PlaySound`] before this system.
+    Play,
+}
+
+/// Plays a one-shot sound.
+#[derive(Clone, Debug, Event)]
+pub struct PlaySound {
+    /// The sound to play.
+    pub sound: Handle<AudioSource>,
+    /// The volume of the sound, where `1.` is the volume of the source.
+    pub volume: f32,
+    /// The playback speed of the sound. This also affects the pitch.
+    pub speed: f32,
+    /// Where the sound comes from, in world coordinates.
+    ///
+    /// Sounds without a position are heard the same everywhere.
+    pub position: Option<Vec2>,
+    /// What kind of sound it is.
+    pub category: SoundCategory,
+    /// How important the sound is.
+    ///
+    /// A sound only takes the place of voices of the same or lower priority,
+    /// and sounds of at least [`AudioSettings::duck_priority`] duck ambient
+    /// sounds.
+    pub priority: u8,
+}
+
+impl PlaySound {
+    /// How many world units make up a unit of distance for spatial sounds.
+    ///
+    /// Spatial sounds are at full volume within this distance of the
+    /// listener, and fall off with the square of the distance past it.
+    pub const SPATIAL_SCALE: f32 = 96.;
+    /// The distance between the listener's ears, in spatial units.
+    pub const EAR_GAP: f32 = 1.;
+
+    /// Creates a new `PlaySound` at normal volume and speed.
+    pub fn new(sound: Handle<AudioSource>) -> PlaySound {
+        PlaySound {
+            sound,
+            volume: 1.,
+            speed: 1.,
+            position: None,
+            category: SoundCategory::default(),
+            priority: 0,
+        }
+    }
+
+    /// Makes the sound come from a position.
+    pub fn at(self, position: Vec2) -> PlaySound {
+        PlaySound {
+            position: Some(position),
+            ..self
+        }
+    }
+
+    /// Sets the volume.
+    pub fn volume(self, volume: f32) -> PlaySound {
+        PlaySound { volume, ..self }
+    }
+
+    /// Sets the speed.
+    pub fn speed(self, speed: f32) -> PlaySound {
+        PlaySound { speed, ..self }
+    }
+
+    /// Sets the category.
+    pub fn category(self, category: SoundCategory) -> PlaySound {
+        PlaySound { category, ..self }
+    }
+
+    /// Sets the priority. See [`PlaySound::priority`].
+    pub fn priority(self, priority: u8) -> PlaySound {
+        PlaySound { priority, ..self }
+    }
+}
+
+/// What kind of sound a [`Voice`] is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SoundCategory {
+    /// Menus and screens.
+    Ui,
+    /// Most things in the world.
+    #[default]
+    Effect,
+    /// Projectiles bouncing and hitting things, which there can be a lot of
+    /// at once.
+    Impact,
+    /// Loops in the background, like moving platforms.
+    Ambience,
+}
+
+impl SoundCategory {
+    /// How many voices of the category play at once.
+    pub fn voice_limit(self, settings: &AudioSettings) -> usize {
+        match self {
+            SoundCategory::Ui => settings.ui_voices,
+            SoundCategory::Effect => settings.effect_voices,
+            SoundCategory::Impact => settings.impact_voices,
+            SoundCategory::Ambience => settings.ambience_voices,
+        }
+    }
+}
+
+/// A sound that is playing.
+#[derive(Clone, Component, Debug)]
+pub struct Voice {
+    /// What kind of sound it is.
+    pub category: SoundCategory,
+    /// How important the sound is. See [`PlaySound::priority`].
+    pub priority: u8,
+    /// The volume the sound was played at, before ducking.
+    pub volume: f32,
+    /// When the sound started, in seconds since startup.
+    pub started: f32,
+}
+
+/// How far ambient sounds are turned down right now.
+#[derive(Clone, Debug, Resource)]
+pub struct Ducking {
+    /// The volume of ambient sounds, from `0.` to `1.`.
+    pub level: f32,
+}
+
+impl Ducking {
+    /// How fast ambient sounds duck and come back, in volume per second.
+    pub const SPEED: f32 = 4.;
+}
+
+impl Default for Ducking {
+    fn default() -> Ducking {
+        Ducking { level: 1. }
+    }
+}
+
+/// The looping sound of a moving platform.
+///
+/// This is spawned when the platform starts moving, follows it around and goes
+/// away once the platform stops or jams.
+#[derive(Clone, Component, Debug)]
+pub struct PlatformLoop {
+    /// The platform.
+    pub platform: Entity,
+}
+
+impl PlatformLoop {
+    /// The volume of the loop.
+    pub const VOLUME: f32 = 0.4;
+}
+
+/// The musical scale that bouncing projectiles climb.
+///
+/// Each bounce plays the bounce sound one step higher on the scale, wrapping
+/// around to the next octave.
+#[derive(Clone, Debug, Resource)]
+pub struct PitchLadder {
+    /// The steps of the scale in semitones above the root, within an octave.
+    pub steps: Vec<u32>,
+    /// The most octaves the ladder climbs before starting over.
+    pub octaves: u32,
+}
+
+impl PitchLadder {
+    /// The playback speed of the `n`th bounce, starting at `0`.
+    pub fn speed(&self, n: u32) -> f32 {
+        let len = self.steps.len() as u32;
+
+        if len == 0 {
+            return 1.;
+        }
+
+        let n = n % (len * self.octaves.max(1));
+        let semitones = self.steps[(n % len) as usize] + 12 * (n / len);
+
+        2f32.powf(semitones as f32 / 12.)
+    }
+}
+
+impl Default for PitchLadder {
+    /// A major pentatonic scale over two octaves.
+    fn default() -> PitchLadder {
+        PitchLadder {
+            steps: vec![0, 2, 4, 7, 9],
+            octaves: 2,
+        }
+    }
+}
+
+fn play_bounce_sounds(
+    mut bounce_events: EventReader<BounceEvent>,
+    mut play_sound_events: EventWriter<PlaySound>,
+    ladder: Res<PitchLadder>,
+    assets: Res<GameAssets>,
+) {
+    for ev in bounce_events.iter() {
+        let step = ev.count.saturating_sub(1);
+
+        play_sound_events.send(
+            PlaySound::new(assets.sound_bounce.clone())
+                .volume(0.5)
+                .speed(ladder.speed(step))
+                .category(SoundCategory::Impact),
+        );
+    }
+}
+
+fn play_platform_sounds(
+    mut commands: Commands,
+    mut state_events: EventReader<PlatformStateEvent>,
+    mut play_sound_events: EventWriter<PlaySound>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    platform_query: Query<&GlobalTransform>,
+    loop_query: Query<(Entity, &PlatformLoop)>,
+    listener_query: Query<&GlobalTransform, With<PlayerCamera>>,
+    assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    for ev in state_events.iter() {
+        let Ok(platform) = platform_query.get(ev.entity) else {
+            continue;
+        };
+
+        let position = platform.translation().truncate();
+
+        if ev.from == PlatformState::Moving {
+            for (entity, platform_loop) in loop_query.iter() {
+                if platform_loop.platform == ev.entity && !despawn_queue.contains(entity) {
+                    despawn_queue.push(entity, DespawnReason::Other("platform stopped"));
+                }
+            }
+        }
+
+        match ev.to {
+            PlatformState::Moving => {
+                let listener = listener_query.get_single().ok();
+                let volume = PlatformLoop::VOLUME * settings.audio.volume;
+
+                commands.spawn((
+                    SpatialAudioBundle {
+                        source: assets.sound_platform_loop.clone(),
+                        settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(volume)),
+                        spatial: SpatialSettings::new(
+                            spatial_listener(listener),
+                            PlaySound::EAR_GAP,
+                            spatial_position(position),
+                        ),
+                    },
+                    PlatformLoop {
+                        platform: ev.entity,
+                    },
+                    Voice {
+                        category: SoundCategory::Ambience,
+                        priority: 0,
+                        volume,
+                        started: time.elapsed_seconds(),
+                    },
+                ));
+            }
+            PlatformState::Idle if ev.arrived() => {
+                play_sound_events.send(
+                    PlaySound::new(assets.sound_platform_chime.clone())
+                        .volume(0.6)
+                        .priority(2)
+                        .at(position),
+                );
+            }
+            PlatformState::Jammed => {
+                play_sound_events.send(
+                    PlaySound::new(assets.sound_platform_clunk.clone())
+                        .priority(2)
+                        .at(position),
+                );
+            }
+            PlatformState::Idle => (),
+        }
+    }
+}
+
+fn follow_platform_loops(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    loop_query: Query<(Entity, &PlatformLoop, Option<&SpatialAudioSink>)>,
+    platform_query: Query<&GlobalTransform>,
+    listener_query: Query<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let listener = spatial_listener(listener_query.get_single().ok());
+
+    for (entity, platform_loop, sink) in loop_query.iter() {
+        // the platform was unloaded with its level
+        let Ok(platform) = platform_query.get(platform_loop.platform) else {
+            if !despawn_queue.contains(entity) {
+                despawn_queue.push(entity, DespawnReason::Other("platform unloaded"));
+            }
+
+            continue;
+        };
+
+        // the sink shows up once the sound starts playing
+        if let Some(sink) = sink {
+            sink.set_listener_position(listener, PlaySound::EAR_GAP);
+            sink.set_emitter_position(spatial_position(platform.translation().truncate()));
+        }
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut play_sound_events: EventReader<PlaySound>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    voice_query: Query<(Entity, &Voice)>,
+    listener_query: Query<&GlobalTransform, With<PlayerCamera>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let listener = listener_query.get_single().ok();
+    let now = time.elapsed_seconds();
+
+    // voices that can be stolen, and the categories of voices started this
+    // frame, which can't
+    let mut voices = voice_query
+        .iter()
+        .filter(|(entity, _)| !despawn_queue.contains(*entity))
+        .map(|(entity, voice)| (entity, voice.clone()))
+        .collect::<Vec<_>>();
+    let mut started: Vec<SoundCategory> = Vec::new();
+
+    for ev in play_sound_events.iter() {
+        let limit = ev.category.voice_limit(&settings.audio);
+        let playing = voices
+            .iter()
+            .filter(|(_, voice)| voice.category == ev.category)
+            .count()
+            + started.iter().filter(|&&c| c == ev.category).count();
+
+        if playing >= limit {
+            let stolen = voices
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, voice))| {
+                    voice.category == ev.category && voice.priority <= ev.priority
+                })
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    a.priority
+                        .cmp(&b.priority)
+                        .then(a.started.total_cmp(&b.started))
+                })
+                .map(|(i, _)| i);
+
+            let Some(stolen) = stolen else {
+                crate::game_debug!(Audio, "dropped {:?} sound", ev.category);
+                continue;
+            };
+
+            let (entity, _) = voices.swap_remove(stolen);
+
+            despawn_queue.push(entity, DespawnReason::Other("voice stolen"));
+        }
+
+        started.push(ev.category);
+
+        let volume = ev.volume * settings.audio.volume;
+        let voice = Voice {
+            category: ev.category,
+            priority: ev.priority,
+            volume,
+            started: now,
+        };
+
+        let playback = PlaybackSettings::DESPAWN
+            .with_volume(Volume::new_relative(volume))
+            .with_speed(ev.speed);
+
+        match ev.position {
+            Some(position) => {
+                commands.spawn((
+                    SpatialAudioBundle {
+                        source: ev.sound.clone(),
+                        settings: playback,
+                        spatial: SpatialSettings::new(
+                            spatial_listener(listener),
+                            PlaySound::EAR_GAP,
+                            spatial_position(position),
+                        ),
+                    },
+                    voice,
+                ));
+            }
+            None => {
+                commands.spawn((
+                    AudioBundle {
+                        source: ev.sound.clone(),
+                        settings: playback,
+                    },
+                    voice,
+                ));
+            }
+        }
+    }
+}
+
+fn duck_ambience(
+    mut ducking: ResMut<Ducking>,
+    voice_query: Query<(&Voice, Option<&AudioSink>, Option<&SpatialAudioSink>)>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let ducked = voice_query
+        .iter()
+        .any(|(voice, _, _)| voice.priority >= settings.audio.duck_priority);
+
+    let target = if ducked {
+        settings.audio.duck_volume
+    } else {
+        1.
+    };
+
+    let step = Ducking::SPEED * time.delta_seconds();
+    let level = ducking.level + (target - ducking.level).clamp(-step, step);
+
+    // do not trip change detection
+    if level != ducking.level {
+        ducking.level = level;
+    }
+
+    for (voice, sink, spatial_sink) in voice_query.iter() {
+        if voice.category != SoundCategory::Ambience {
+            continue;
+        }
+
+        let volume = voice.volume * ducking.level;
+
+        // the sink shows up once the sound starts playing
+        if let Some(sink) = sink {
+            sink.set_volume(volume);
+        }
+
+        if let Some(sink) = spatial_sink {
+            sink.set_volume(volume);
+        }
+    }
+}
+
+/// Where the listener is in spatial units.
+///
+/// Without a camera, the listener sits at the origin.
+fn spatial_listener(camera: Option<&GlobalTransform>) -> Transform {
+    let position = camera
+        .map(|t| t.translation().truncate())
+        .unwrap_or_default();
+
+    Transform::from_translation(spatial_position(position))
+}
+
+/// Converts a position in world coordinates to spatial units.
+fn spatial_position(position: Vec2) -> Vec3 {
+    (position / PlaySound::SPATIAL_SCALE).extend(0.)
+}
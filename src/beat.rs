@@ -0,0 +1,173 @@
+//! The beat of the music.
+//!
+//! The [`BeatClock`] keeps time to a tempo. On-beat actions are judged
+//! against it, shifted by the player's calibrated
+//! [latency](crate::settings::Settings::latency_offset), so a player whose
+//! speakers lag still lands on the beat they hear.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::GameState;
+
+/// Beat plugin.
+pub struct BeatPlugin;
+
+impl Plugin for BeatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BeatClock>()
+            .add_systems(
+                PreUpdate,
+                sync_beat_latency.run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(
+                PreUpdate,
+                tick_beat_clock
+                    .run_if(in_state(GameState::InGame))
+                    .after(sync_beat_latency),
+            );
+    }
+}
+
+/// Keeps time to a tempo.
+///
+/// The clock the game runs on is a resource, but anything can keep its own.
+#[derive(Clone, Debug, Resource)]
+pub struct BeatClock {
+    /// The tempo, in beats per minute.
+    pub bpm: f32,
+    /// How late the player acts on a beat, in seconds.
+    ///
+    /// The game's clock takes this from [`Settings::latency_offset`].
+    pub latency: f32,
+    elapsed: f32,
+    ticked: bool,
+}
+
+impl BeatClock {
+    /// The tempo of the game if nothing says otherwise.
+    pub const DEFAULT_BPM: f32 = 120.;
+    /// How far off the beat, in seconds, an action can be and still be
+    /// [`BeatJudgement::Perfect`].
+    pub const PERFECT_WINDOW: f32 = 0.05;
+    /// How far off the beat, in seconds, an action can be and still be
+    /// [`BeatJudgement::Good`].
+    pub const GOOD_WINDOW: f32 = 0.1;
+
+    /// Creates a new `BeatClock` at a tempo, with no latency.
+    pub fn new(bpm: f32) -> BeatClock {
+        BeatClock {
+            bpm,
+            latency: 0.,
+            elapsed: 0.,
+            ticked: false,
+        }
+    }
+
+    /// Moves the clock forward.
+    ///
+    /// Returns `true` if a beat was crossed.
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        let before = self.beats();
+
+        self.elapsed += delta.as_secs_f32();
+        self.ticked = self.beats() != before;
+        self.ticked
+    }
+
+    /// Starts the clock over at the first beat.
+    pub fn reset(&mut self) {
+        self.elapsed = 0.;
+        self.ticked = false;
+    }
+
+    /// Checks if a beat was crossed the last time the clock was ticked.
+    pub fn just_ticked(&self) -> bool {
+        self.ticked
+    }
+
+    /// The length of a beat, in seconds.
+    pub fn beat_length(&self) -> f32 {
+        60. / self.bpm.max(f32::EPSILON)
+    }
+
+    /// How many whole beats have passed.
+    pub fn beats(&self) -> u64 {
+        (self.elapsed / self.beat_length()) as u64
+    }
+
+    /// How far into the current beat the clock is, from `0.` to `1.`.
+    pub fn phase(&self) -> f32 {
+        (self.elapsed / self.beat_length()).fract()
+    }
+
+    /// The signed time from the closest beat to an action taken now, in
+    /// seconds, with [`BeatClock::latency`] taken out.
+    ///
+    /// Negative offsets are early and positive offsets are late.
+    pub fn offset(&self) -> f32 {
+        let length = self.beat_length();
+        let phase = (self.elapsed - self.latency).rem_euclid(length);
+
+        if phase > length / 2. {
+            phase - length
+        } else {
+            phase
+        }
+    }
+
+    /// The number of the beat closest to an action taken now, with
+    /// [`BeatClock::latency`] taken out.
+    pub fn nearest_beat(&self) -> u64 {
+        ((self.elapsed - self.latency) / self.beat_length())
+            .round()
+            .max(0.) as u64
+    }
+
+    /// Judges an action taken now.
+    pub fn judge(&self) -> BeatJudgement {
+        let offset = self.offset().abs();
+
+        if offset <= BeatClock::PERFECT_WINDOW {
+            BeatJudgement::Perfect
+        } else if offset <= BeatClock::GOOD_WINDOW {
+            BeatJudgement::Good
+        } else {
+            BeatJudgement::Miss
+        }
+    }
+}
+
+impl Default for BeatClock {
+    fn default() -> BeatClock {
+        BeatClock::new(BeatClock::DEFAULT_BPM)
+    }
+}
+
+/// How close to the beat an action was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BeatJudgement {
+    /// Within [`BeatClock::PERFECT_WINDOW`].
+    Perfect,
+    /// Within [`BeatClock::GOOD_WINDOW`].
+    Good,
+    /// Off the beat.
+    Miss,
+}
+
+impl BeatJudgement {
+    /// Checks if the action counts as on the beat.
+    pub fn on_beat(self) -> bool {
+        self != BeatJudgement::Miss
+    }
+}
+
+fn sync_beat_latency(mut clock: ResMut<BeatClock>, settings: Res<Settings>) {
+    clock.latency = settings.latency_offset;
+}
+
+fn tick_beat_clock(mut clock: ResMut<BeatClock>, time: Res<Time>) {
+    clock.tick(time.delta());
+}
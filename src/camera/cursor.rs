@@ -23,9 +23,12 @@ fn update_cursor_in_world(
     windows_query: Query<&Window>,
 ) {
     for (transform, camera, mut pos) in camera_query.iter_mut() {
-        // find window ref
-        let RenderTarget::Window(window_ref) = camera.target else {
-            continue;
+        // find window ref; a camera drawing into an image is stretched over
+        // the primary window by adaptive resolution
+        let window_ref = match &camera.target {
+            RenderTarget::Window(window_ref) => *window_ref,
+            RenderTarget::Image(_) => WindowRef::Primary,
+            _ => continue,
         };
 
         // get window
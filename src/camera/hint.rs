@@ -43,7 +43,6 @@ impl LdtkEntity for CameraHintBundle {
         _texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
         /*
-        bevy::log::info!("{}", entity_instance.width);
         // thankfully pixels are world units
         let hint_size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32)
             / layer_instance.grid_size as f32;*/
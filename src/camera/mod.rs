@@ -2,17 +2,23 @@
 
 pub mod cursor;
 pub mod hint;
+pub mod resolution;
+pub mod zoom;
 
 use bevy::core_pipeline::clear_color::ClearColorConfig;
 use bevy::ecs::query::QuerySingleError;
 use bevy::prelude::*;
-use bevy::render::camera::ScalingMode;
+use bevy::render::camera::{CameraUpdateSystem, ScalingMode};
 use bevy::transform::{systems::propagate_transforms, TransformSystem};
 
-use bevy_ecs_ldtk::{LdtkLevel, LevelSelection};
+use bevy_ecs_ldtk::{
+    ldtk::{ldtk_fields::LdtkFields as _, Level},
+    LdtkLevel,
+};
 
 //use std::time::Duration;
 
+use crate::level::CurrentLevel;
 use crate::player::LocalPlayer;
 
 pub const CLEAR_COLOR: Color = Color::rgb(0.03137, 0.03137, 0.03529);
@@ -31,6 +37,9 @@ impl Plugin for CameraPlugin {
                 (camera_follow, bind_camera, propagate_transforms)
                     .chain()
                     .in_set(CameraSystem::FinalizePosition)
+                    // the projection has to know about a resized window
+                    // before the camera is bound to the level
+                    .after(CameraUpdateSystem)
                     .after(TransformSystem::TransformPropagate),
             )
             .add_systems(Startup, spawn_camera);
@@ -47,7 +56,7 @@ pub enum CameraSystem {
 }
 
 /// A special component that makes the camera follow the player, and also
-/// adjusts the LevelSelection resource, among a lot of other things.
+/// adjusts the CurrentLevel resource, among a lot of other things.
 #[derive(Clone, Component, Debug, Default)]
 pub struct PlayerCamera;
 
@@ -56,6 +65,59 @@ pub struct PlayerCamera;
 pub struct Constrained {
     /// The level id the camera is constrained in.
     pub level_id: Option<String>,
+    /// The axis lock of the level the camera is constrained in.
+    ///
+    /// This is updated with the level's `LockX` and `LockY` fields.
+    pub axis_lock: AxisLock,
+    /// The bounds of the level the camera is constrained in, in world
+    /// coordinates.
+    pub bounds: Option<Rect>,
+}
+
+/// Locks the camera to a fixed position on either axis.
+///
+/// Useful for rooms that only scroll horizontally, or shafts that only scroll
+/// vertically.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AxisLock {
+    /// The fixed x position of the camera in world coordinates.
+    pub x: Option<f32>,
+    /// The fixed y position of the camera in world coordinates.
+    pub y: Option<f32>,
+}
+
+impl AxisLock {
+    /// Creates an `AxisLock` from the `LockX` and `LockY` fields of a level.
+    ///
+    /// The fields are in level pixel coordinates, so the level's transform is
+    /// needed to get them into world space. Missing fields are not locked.
+    pub fn from_level(level: &Level, transform: &GlobalTransform) -> AxisLock {
+        let lock_x = level.get_maybe_float_field("LockX").ok().copied().flatten();
+        let lock_y = level.get_maybe_float_field("LockY").ok().copied().flatten();
+
+        // ldtk y coordinates go down
+        let pos = transform.transform_point(Vec3::new(
+            lock_x.unwrap_or_default(),
+            level.px_hei as f32 - lock_y.unwrap_or_default(),
+            1.,
+        ));
+
+        AxisLock {
+            x: lock_x.map(|_| pos.x),
+            y: lock_y.map(|_| pos.y),
+        }
+    }
+
+    /// Applies the lock to a position.
+    pub fn apply(&self, pos: &mut Vec2) {
+        if let Some(x) = self.x {
+            pos.x = x;
+        }
+
+        if let Some(y) = self.y {
+            pos.y = y;
+        }
+    }
 }
 
 // TODO: refactor `Follow` into `...`
@@ -135,6 +197,23 @@ impl Follow {
         }
     }
 
+    /// Gets the size of the smallest box centered on [`Follow::midpoint`] that
+    /// contains all of the subjects.
+    ///
+    /// Returns `None` if there are no subjects.
+    pub fn spread<F>(&mut self, transform_query: &Query<&GlobalTransform, F>) -> Option<Vec2>
+    where
+        F: bevy::ecs::query::ReadOnlyWorldQuery,
+    {
+        let midpoint = self.midpoint(transform_query)?;
+
+        self.subjects
+            .iter()
+            .filter_map(|&e| transform_query.get(e).ok())
+            .map(|t| (t.translation().truncate() - midpoint).abs() * 2.)
+            .reduce(Vec2::max)
+    }
+
     /// Gets the midpoint of all of the subjects.
     ///
     /// Returns `None` if there are no subjects.
@@ -220,6 +299,7 @@ fn spawn_camera(mut commands: Commands) {
         },
         cursor::CursorWorldPosition::default(),
         Follow::default(),
+        zoom::ZoomToFit::default(),
         PlayerCamera::default(),
         Constrained::default(),
     ));
@@ -250,7 +330,7 @@ fn update_follow_lerp(mut follow_query: Query<&mut Follow>, time: Res<Time>) {
 
 fn update_current_level(
     camera_query: Query<&Constrained, (Changed<Constrained>, With<PlayerCamera>)>,
-    mut level_selection: ResMut<LevelSelection>,
+    mut current_level: ResMut<CurrentLevel>,
 ) {
     let Ok(constrained) = camera_query.get_single() else {
         return;
@@ -260,7 +340,10 @@ fn update_current_level(
         return;
     };
 
-    *level_selection = LevelSelection::Identifier(level_id);
+    // do not trip change detection
+    if current_level.0 != level_id {
+        current_level.0 = level_id;
+    }
 }
 
 fn camera_follow(
@@ -279,12 +362,23 @@ fn camera_follow(
 }
 
 fn bind_camera(
-    mut camera_query: Query<(&mut Transform, &mut Constrained, &OrthographicProjection)>,
+    mut camera_query: Query<(
+        &mut Transform,
+        &mut Constrained,
+        &OrthographicProjection,
+        Option<&PlayerCamera>,
+    )>,
     levels_query: Query<(&GlobalTransform, &Handle<LdtkLevel>)>,
+    player_query: Query<&GlobalTransform, With<LocalPlayer>>,
     levels: Res<Assets<LdtkLevel>>,
     //mut gizmos: Gizmos,
 ) {
-    for (mut transform, mut constrained, projection) in camera_query.iter_mut() {
+    let player = player_query
+        .get_single()
+        .ok()
+        .map(|t| t.translation().truncate());
+
+    for (mut transform, mut constrained, projection, player_camera) in camera_query.iter_mut() {
         // get level rectangles
         let bound_space = levels_query
             .iter()
@@ -300,7 +394,9 @@ fn bind_camera(
                 rect.min = t.transform_point(rect.min.extend(1.)).truncate();
                 rect.max = t.transform_point(rect.max.extend(1.)).truncate();
 
-                (rect, level.level.identifier.clone())
+                let axis_lock = AxisLock::from_level(&level.level, t);
+
+                (rect, level.level.identifier.clone(), axis_lock)
             })
             .collect::<Vec<_>>();
 
@@ -325,46 +421,230 @@ fn bind_camera(
 
         //gizmos.rect_2d((camera_rect.min + camera_rect.max) / 2., 0., camera_rect.max - camera_rect.min, Color::CYAN);
 
-        // constrain
-        let mtv = bound_space
-            .into_iter()
+        // find the level the camera is nearest to
+        let nearest = bound_space
+            .iter()
             // find minimum translation vectors for each aabb
-            .map(|(rect, lid)| {
-                let x = if camera_rect.width() > rect.width() {
-                    // there is no way to fit the camera in the rect, so use
-                    // the difference of the centers
-                    rect.center().x - camera_rect.center().x
-                } else {
-                    let left = rect.min.x - camera_rect.min.x;
-                    let right = camera_rect.max.x - rect.max.x;
-
-                    left.max(0.) - right.max(0.)
-                };
-
-                let y = if camera_rect.height() > rect.height() {
-                    // there is no way to fit the camera in the rect, so use
-                    // the difference of the centers
-                    rect.center().y - camera_rect.center().y
-                } else {
-                    let bottom = rect.min.y - camera_rect.min.y;
-                    let top = camera_rect.max.y - rect.max.y;
-
-                    bottom.max(0.) - top.max(0.)
-                };
-
-                (Vec2::new(x, y), lid)
-            })
+            .map(|(rect, ..)| minimum_translation(camera_rect, *rect))
+            .enumerate()
             .reduce(|acc, v| {
-                if v.0.length_squared() < acc.0.length_squared() {
+                if v.1.length_squared() < acc.1.length_squared() {
                     v
                 } else {
                     acc
                 }
             });
 
-        if let Some((mtv, level_id)) = mtv {
-            constrained.level_id = Some(level_id);
-            transform.translation += mtv.extend(0.);
+        let Some((nearest, _)) = nearest else {
+            continue;
+        };
+
+        // the player camera selects the level the player is in, so the level
+        // does not flap while the camera sits on a seam
+        let selected = match (player_camera, player) {
+            (Some(_), Some(player)) => select_level(
+                constrained.level_id.as_deref(),
+                player,
+                bound_space
+                    .iter()
+                    .map(|(rect, lid, _)| (*rect, lid.as_str())),
+                LEVEL_SELECTION_MARGIN,
+            ),
+            _ => None,
+        };
+
+        // fallback to the level the camera was constrained in
+        let selected = selected.unwrap_or(nearest);
+
+        let (rect, level_id, axis_lock) = &bound_space[selected];
+
+        // constrain to the selected level, so the camera is in the level the
+        // state says it is
+        let mtv = minimum_translation(camera_rect, *rect);
+
+        transform.translation += mtv.extend(0.);
+
+        if constrained.level_id.as_ref() != Some(level_id) {
+            constrained.level_id = Some(level_id.clone());
+        }
+
+        constrained.axis_lock = *axis_lock;
+        constrained.bounds = Some(*rect);
+
+        // lock axes after constraining
+        let mut translation = transform.translation.truncate();
+        axis_lock.apply(&mut translation);
+        transform.translation = translation.extend(transform.translation.z);
+    }
+}
+
+/// How far, in world units, the player must be outside of the current level
+/// before another level can be selected.
+pub const LEVEL_SELECTION_MARGIN: f32 = 8.;
+
+/// Finds the minimum translation to move `camera_rect` inside of `bounds`.
+///
+/// If the camera is larger than the bounds on an axis, the camera is centered
+/// on that axis.
+pub fn minimum_translation(camera_rect: Rect, bounds: Rect) -> Vec2 {
+    let x = if camera_rect.width() > bounds.width() {
+        // there is no way to fit the camera in the rect, so use the
+        // difference of the centers
+        bounds.center().x - camera_rect.center().x
+    } else {
+        let left = bounds.min.x - camera_rect.min.x;
+        let right = camera_rect.max.x - bounds.max.x;
+
+        left.max(0.) - right.max(0.)
+    };
+
+    let y = if camera_rect.height() > bounds.height() {
+        // there is no way to fit the camera in the rect, so use the
+        // difference of the centers
+        bounds.center().y - camera_rect.center().y
+    } else {
+        let bottom = bounds.min.y - camera_rect.min.y;
+        let top = camera_rect.max.y - bounds.max.y;
+
+        bottom.max(0.) - top.max(0.)
+    };
+
+    Vec2::new(x, y)
+}
+
+/// Selects the level a subject is in, returning its index.
+///
+/// The current level stays selected as long as the subject is within `margin`
+/// of it. Returns `None` if the subject isn't in any level.
+pub fn select_level<'a>(
+    current: Option<&str>,
+    subject: Vec2,
+    levels: impl Iterator<Item = (Rect, &'a str)>,
+    margin: f32,
+) -> Option<usize> {
+    let mut found = None;
+
+    for (i, (rect, level_id)) in levels.enumerate() {
+        if Some(level_id) == current {
+            let grown = Rect::from_corners(rect.min - margin, rect.max + margin);
+
+            if grown.contains(subject) {
+                // hysteresis
+                return Some(i);
+            }
+        } else if found.is_none() && rect.contains(subject) {
+            found = Some(i);
         }
     }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(min: (f32, f32), max: (f32, f32)) -> Rect {
+        Rect::from_corners(Vec2::new(min.0, min.1), Vec2::new(max.0, max.1))
+    }
+
+    #[test]
+    fn minimum_translation_inside() {
+        let bounds = rect((0., 0.), (100., 100.));
+        let camera = rect((10., 10.), (50., 50.));
+
+        assert_eq!(minimum_translation(camera, bounds), Vec2::ZERO);
+    }
+
+    #[test]
+    fn minimum_translation_overlapping_edges() {
+        let bounds = rect((0., 0.), (100., 100.));
+
+        // left
+        let camera = rect((-10., 10.), (30., 50.));
+        assert_eq!(minimum_translation(camera, bounds), Vec2::new(10., 0.));
+
+        // right
+        let camera = rect((80., 10.), (120., 50.));
+        assert_eq!(minimum_translation(camera, bounds), Vec2::new(-20., 0.));
+
+        // bottom
+        let camera = rect((10., -5.), (50., 35.));
+        assert_eq!(minimum_translation(camera, bounds), Vec2::new(0., 5.));
+
+        // top
+        let camera = rect((10., 70.), (50., 110.));
+        assert_eq!(minimum_translation(camera, bounds), Vec2::new(0., -10.));
+    }
+
+    #[test]
+    fn minimum_translation_larger_than_bounds() {
+        let bounds = rect((0., 0.), (100., 50.));
+        // wider and taller than the bounds
+        let camera = rect((20., 20.), (160., 100.));
+
+        // centered on both axes
+        assert_eq!(minimum_translation(camera, bounds), Vec2::new(-40., -35.));
+    }
+
+    fn levels() -> Vec<(Rect, &'static str)> {
+        vec![
+            (rect((0., 0.), (100., 100.)), "a"),
+            (rect((100., 0.), (200., 100.)), "b"),
+        ]
+    }
+
+    #[test]
+    fn select_level_stays_within_margin() {
+        // just past the seam, but within the margin of the current level
+        let subject = Vec2::new(104., 50.);
+
+        let selected = select_level(Some("a"), subject, levels().into_iter(), 8.);
+
+        assert_eq!(selected, Some(0));
+    }
+
+    #[test]
+    fn select_level_crosses_seam() {
+        // past the margin of the current level
+        let subject = Vec2::new(112., 50.);
+
+        let selected = select_level(Some("a"), subject, levels().into_iter(), 8.);
+
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn select_level_without_current() {
+        let subject = Vec2::new(150., 50.);
+
+        let selected = select_level(None, subject, levels().into_iter(), 8.);
+
+        assert_eq!(selected, Some(1));
+
+        let outside = Vec2::new(300., 50.);
+
+        assert_eq!(select_level(None, outside, levels().into_iter(), 8.), None);
+    }
+
+    #[test]
+    fn select_level_overlapping_rects() {
+        let levels = vec![
+            (rect((0., 0.), (100., 100.)), "a"),
+            (rect((50., 0.), (150., 100.)), "b"),
+        ];
+
+        // in both levels, the current one wins
+        let subject = Vec2::new(75., 50.);
+
+        let selected = select_level(Some("b"), subject, levels.clone().into_iter(), 8.);
+        assert_eq!(selected, Some(1));
+
+        let selected = select_level(Some("a"), subject, levels.clone().into_iter(), 8.);
+        assert_eq!(selected, Some(0));
+
+        // without a current level, the first one wins
+        let selected = select_level(None, subject, levels.into_iter(), 8.);
+        assert_eq!(selected, Some(0));
+    }
 }
@@ -0,0 +1,389 @@
+//! Adaptive resolution.
+//!
+//! Browsers on slow machines often can't draw the game at the full resolution
+//! of the screen sixty times a second. [`AdaptiveResolution`] watches how long
+//! frames take, and steps the resolution the game is drawn at down through
+//! each [`RenderScale`] when they take too long, and back up once they have
+//! been quick for a while.
+//!
+//! The [`PlayerCamera`] draws into a [`RenderImage`] the size of the step
+//! instead of the window, so the GPU really does draw fewer pixels, and the
+//! image is stretched over the window by a second camera. The UI is drawn by
+//! that camera too, so it keeps its size and stays sharp at every step.
+//!
+//! It takes a lot longer to step up than down, and a step up that is undone
+//! straight away makes the next one wait twice as long, so the resolution
+//! doesn't flicker between two steps. Players can pick a step themselves with
+//! [`Settings::resolution`], which stops the steps from changing.
+//!
+//! [`Settings::resolution`]: crate::settings::Settings::resolution
+
+use bevy::core_pipeline::{clear_color::ClearColorConfig, tonemapping::Tonemapping};
+use bevy::prelude::*;
+use bevy::render::camera::{RenderTarget, ScalingMode};
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+
+use serde::{Deserialize, Serialize};
+
+use super::PlayerCamera;
+use crate::settings::Settings;
+
+/// Adaptive resolution plugin.
+pub struct AdaptiveResolutionPlugin;
+
+impl Plugin for AdaptiveResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AdaptiveResolution>()
+            .add_systems(Startup, spawn_upscale_camera)
+            .add_systems(
+                Last,
+                (
+                    target_player_camera,
+                    measure_frame_time.run_if(adapting),
+                    apply_render_scale,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The image the [`PlayerCamera`] draws into, which is stretched over the
+/// window.
+#[derive(Clone, Debug, Resource)]
+pub struct RenderImage(pub Handle<Image>);
+
+/// The sprite that stretches the [`RenderImage`] over the window.
+#[derive(Clone, Component, Debug, Default)]
+struct UpscaleSprite;
+
+/// The layer that only the camera stretching the [`RenderImage`] sees.
+const UPSCALE_LAYER: u8 = RenderLayers::TOTAL_LAYERS as u8 - 1;
+
+/// How much of the screen's resolution the game is drawn at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RenderScale {
+    /// The full resolution of the screen.
+    #[default]
+    Full,
+    /// Three quarters of the resolution of the screen.
+    ThreeQuarters,
+    /// Half of the resolution of the screen.
+    Half,
+    /// A few screen pixels to every pixel of the camera, so the pixel art
+    /// stays sharp.
+    ///
+    /// This is the biggest whole multiple of the camera's height that is no
+    /// more than half of the screen's, or just the camera's height on small
+    /// screens.
+    Pixel,
+}
+
+impl RenderScale {
+    /// Every step, from the highest resolution to the lowest.
+    pub const ALL: [RenderScale; 4] = [
+        RenderScale::Full,
+        RenderScale::ThreeQuarters,
+        RenderScale::Half,
+        RenderScale::Pixel,
+    ];
+
+    /// The next lower step, if there is one.
+    pub fn lower(self) -> Option<RenderScale> {
+        let index = RenderScale::ALL.iter().position(|&s| s == self)?;
+        RenderScale::ALL.get(index + 1).copied()
+    }
+
+    /// The next higher step, if there is one.
+    pub fn higher(self) -> Option<RenderScale> {
+        let index = RenderScale::ALL.iter().position(|&s| s == self)?;
+        index.checked_sub(1).map(|index| RenderScale::ALL[index])
+    }
+
+    /// Finds how many pixels the picture is drawn with to every logical pixel
+    /// of a window, or `None` to use the scale factor of the screen.
+    ///
+    /// `height` is the logical height of the window and `view_height` is the
+    /// height of the camera, in world units.
+    pub fn scale_factor(self, base: f64, height: f64, view_height: Option<f64>) -> Option<f64> {
+        match self {
+            RenderScale::Full => None,
+            RenderScale::ThreeQuarters => Some(base * 0.75),
+            RenderScale::Half => Some(base * 0.5),
+            RenderScale::Pixel => {
+                let Some(view_height) = view_height.filter(|_| height > 0.) else {
+                    return Some(base * 0.5);
+                };
+
+                let multiple = (height * base * 0.5 / view_height).floor().max(1.);
+                Some(multiple * view_height / height)
+            }
+        }
+    }
+}
+
+/// Steps the resolution up and down to keep up with the target frame rate.
+#[derive(Clone, Debug, Resource)]
+pub struct AdaptiveResolution {
+    /// The step the game is drawn at.
+    pub scale: RenderScale,
+    /// The average time a frame takes, in seconds.
+    pub frame_time: f32,
+    /// How long frames have to be quick before the resolution steps up, in
+    /// seconds.
+    pub step_up_after: f32,
+    slow_for: f32,
+    fast_for: f32,
+    since_step_up: Option<f32>,
+}
+
+impl AdaptiveResolution {
+    /// The frame time the game aims for, in seconds.
+    pub const TARGET_FRAME_TIME: f32 = 1. / 60.;
+    /// An average frame time slower than this is too slow.
+    pub const SLOW_FRAME_TIME: f32 = 1. / 50.;
+    /// An average frame time quicker than this is quick enough to step up.
+    pub const FAST_FRAME_TIME: f32 = 1. / 58.;
+    /// How long frames have to be slow before the resolution steps down, in
+    /// seconds.
+    pub const STEP_DOWN_AFTER: f32 = 2.;
+    /// How long frames have to be quick before the first step up, in seconds.
+    pub const STEP_UP_AFTER: f32 = 10.;
+    /// The longest frames ever have to be quick before a step up, in seconds.
+    pub const MAX_STEP_UP_AFTER: f32 = 120.;
+    /// How much of each new frame goes into the average.
+    pub const SMOOTHING: f32 = 0.1;
+    /// The longest a frame can count as, so a hitch from loading a level
+    /// doesn't drag the average down on its own.
+    pub const MAX_FRAME_TIME: f32 = 0.1;
+
+    /// Adds the time of a frame, returning the step to change to, if it
+    /// should change.
+    pub fn measure(&mut self, delta: f32) -> Option<RenderScale> {
+        let delta = delta.min(AdaptiveResolution::MAX_FRAME_TIME);
+
+        self.frame_time += (delta - self.frame_time) * AdaptiveResolution::SMOOTHING;
+
+        if let Some(since_step_up) = &mut self.since_step_up {
+            *since_step_up += delta;
+        }
+
+        if self.frame_time > AdaptiveResolution::SLOW_FRAME_TIME {
+            self.slow_for += delta;
+            self.fast_for = 0.;
+        } else if self.frame_time < AdaptiveResolution::FAST_FRAME_TIME {
+            self.fast_for += delta;
+            self.slow_for = 0.;
+        } else {
+            self.slow_for = 0.;
+            self.fast_for = 0.;
+        }
+
+        if self.slow_for > AdaptiveResolution::STEP_DOWN_AFTER {
+            let lower = self.scale.lower()?;
+
+            // the last step up didn't hold, so wait longer before the next
+            if self
+                .since_step_up
+                .map(|since| since < self.step_up_after)
+                .unwrap_or_default()
+            {
+                self.step_up_after =
+                    (self.step_up_after * 2.).min(AdaptiveResolution::MAX_STEP_UP_AFTER);
+            }
+
+            self.step_to(lower);
+            self.since_step_up = None;
+            Some(lower)
+        } else if self.fast_for > self.step_up_after {
+            let higher = self.scale.higher()?;
+
+            self.step_to(higher);
+            self.since_step_up = Some(0.);
+            Some(higher)
+        } else {
+            None
+        }
+    }
+
+    fn step_to(&mut self, scale: RenderScale) {
+        self.scale = scale;
+        self.slow_for = 0.;
+        self.fast_for = 0.;
+        // the frames before the step say nothing about the ones after
+        self.frame_time = AdaptiveResolution::TARGET_FRAME_TIME;
+    }
+}
+
+impl Default for AdaptiveResolution {
+    fn default() -> AdaptiveResolution {
+        AdaptiveResolution {
+            scale: RenderScale::Full,
+            frame_time: AdaptiveResolution::TARGET_FRAME_TIME,
+            step_up_after: AdaptiveResolution::STEP_UP_AFTER,
+            slow_for: 0.,
+            fast_for: 0.,
+            since_step_up: None,
+        }
+    }
+}
+
+fn adapting(settings: Res<Settings>) -> bool {
+    settings.resolution.is_none()
+}
+
+fn measure_frame_time(mut resolution: ResMut<AdaptiveResolution>, time: Res<Time>) {
+    let frame_time = resolution.frame_time;
+
+    // do not trip change detection
+    if let Some(scale) = resolution
+        .bypass_change_detection()
+        .measure(time.delta_seconds())
+    {
+        resolution.set_changed();
+
+        crate::game_info!(
+            Camera,
+            "frames average {:.1}ms, drawing at {:?}",
+            frame_time * 1000.,
+            scale
+        );
+    }
+}
+
+fn spawn_upscale_camera(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(render_image(UVec2::ONE));
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                // after the player camera has drawn into the image
+                order: 1,
+                ..Default::default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+            },
+            // the player camera already tonemapped the picture
+            tonemapping: Tonemapping::None,
+            ..Default::default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+    ));
+
+    commands.spawn((
+        SpriteBundle {
+            texture: image.clone(),
+            ..Default::default()
+        },
+        RenderLayers::layer(UPSCALE_LAYER),
+        UpscaleSprite,
+    ));
+
+    commands.insert_resource(RenderImage(image));
+}
+
+fn target_player_camera(
+    mut commands: Commands,
+    mut camera_query: Query<(Entity, &mut Camera), Added<PlayerCamera>>,
+    render_image: Res<RenderImage>,
+) {
+    for (entity, mut camera) in camera_query.iter_mut() {
+        camera.target = RenderTarget::Image(render_image.0.clone());
+
+        // the ui is drawn over the stretched picture instead, at the size of
+        // the window
+        commands
+            .entity(entity)
+            .insert(UiCameraConfig { show_ui: false });
+    }
+}
+
+fn apply_render_scale(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<&OrthographicProjection, With<PlayerCamera>>,
+    mut sprite_query: Query<&mut Sprite, With<UpscaleSprite>>,
+    mut images: ResMut<Assets<Image>>,
+    render_image: Res<RenderImage>,
+    resolution: Res<AdaptiveResolution>,
+    settings: Res<Settings>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let scale = settings.resolution.unwrap_or(resolution.scale);
+
+    let view_height = match camera_query.get_single().map(|p| &p.scaling_mode) {
+        Ok(ScalingMode::FixedVertical(height)) => Some(*height as f64),
+        _ => None,
+    };
+
+    let scale_factor = scale
+        .scale_factor(
+            window.resolution.base_scale_factor(),
+            window.height() as f64,
+            view_height,
+        )
+        .unwrap_or(window.scale_factor());
+
+    let window_size = Vec2::new(window.width(), window.height());
+    let size = UVec2::new(
+        ((window.width() as f64 * scale_factor).round() as u32).max(1),
+        ((window.height() as f64 * scale_factor).round() as u32).max(1),
+    );
+
+    // do not trip change detection
+    let resized = images
+        .get(&render_image.0)
+        .is_some_and(|image| image.size() != size.as_vec2());
+
+    if resized {
+        if let Some(image) = images.get_mut(&render_image.0) {
+            image.resize(extent(size));
+        }
+    }
+
+    for mut sprite in sprite_query.iter_mut() {
+        // do not trip change detection
+        if sprite.custom_size != Some(window_size) {
+            sprite.custom_size = Some(window_size);
+        }
+    }
+}
+
+/// Creates an empty image a camera can draw into.
+fn render_image(size: UVec2) -> Image {
+    let size = extent(size);
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..Default::default()
+    };
+
+    // fill it with zeroes
+    image.resize(size);
+    image
+}
+
+fn extent(size: UVec2) -> Extent3d {
+    Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    }
+}
@@ -0,0 +1,84 @@
+//! Camera zoom.
+
+use bevy::prelude::*;
+
+use super::{CameraSystem, Constrained, Follow};
+
+/// Camera zoom plugin.
+pub struct CameraZoomPlugin;
+
+impl Plugin for CameraZoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, zoom_to_fit.after(CameraSystem::Tween));
+    }
+}
+
+/// Zooms the camera out so that all of the subjects of a [`Follow`] stay in
+/// view.
+///
+/// The camera will never zoom out further than the bounds of its
+/// [`Constrained`] level, and will zoom back in when the subjects converge.
+#[derive(Clone, Component, Debug)]
+pub struct ZoomToFit {
+    /// The space around the subjects that should also be in view, in world
+    /// units.
+    pub margin: Vec2,
+    /// The maximum scale of the projection.
+    pub max_scale: f32,
+    /// How fast the camera zooms, in scale per second.
+    pub speed: f32,
+}
+
+impl Default for ZoomToFit {
+    fn default() -> ZoomToFit {
+        ZoomToFit {
+            margin: Vec2::new(48., 48.),
+            max_scale: 2.,
+            speed: 2.,
+        }
+    }
+}
+
+fn zoom_to_fit(
+    mut camera_query: Query<(
+        &mut OrthographicProjection,
+        &mut Follow,
+        &ZoomToFit,
+        Option<&Constrained>,
+    )>,
+    transform_query: Query<&GlobalTransform, Without<Follow>>,
+    time: Res<Time>,
+) {
+    for (mut projection, mut follow, zoom, constrained) in camera_query.iter_mut() {
+        // size of the camera when it is not zoomed
+        let base_size = projection.area.size() / projection.scale;
+
+        if base_size.x <= 0. || base_size.y <= 0. {
+            continue;
+        }
+
+        let mut target = 1.;
+
+        if follow.subjects().len() > 1 {
+            if let Some(spread) = follow.spread(&transform_query) {
+                let fit = (spread + zoom.margin) / base_size;
+
+                target = fit.max_element().clamp(1., zoom.max_scale);
+            }
+        }
+
+        // do not show more than the level
+        if let Some(bounds) = constrained.and_then(|c| c.bounds) {
+            let fit = bounds.size() / base_size;
+
+            target = target.min(fit.min_element().max(1.));
+        }
+
+        let diff = target - projection.scale;
+        let step = zoom.speed * time.delta_seconds();
+
+        if diff.abs() > f32::EPSILON {
+            projection.scale += diff.clamp(-step, step);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Carrying and throwing things.
+//!
+//! Thrown things temporarily become [`Projectile`]s, so they can ring drums,
+//! hit enemies and be taken in by acceptors like any other note.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::time::Duration;
+
+use crate::enemy::{EnemySystem, Hostility};
+use crate::fx::tint::TintStack;
+use crate::physics;
+use crate::player::controller::{Controller, ControllerSystem};
+use crate::projectile::{Projectile, ProjectileSystem};
+use crate::{GameAssets, GameState};
+
+/// Carry plugin.
+pub struct CarryPlugin;
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<BigNoteBundle>("BigNote")
+            .add_systems(Update, setup_big_notes.run_if(in_state(GameState::InGame)))
+            .add_systems(
+                Update,
+                (pick_up_or_throw, follow_carrier)
+                    .chain()
+                    .in_set(CarrySystem::Carry)
+                    .after(ControllerSystem::ScanInput),
+            )
+            .add_systems(
+                Update,
+                land_thrown
+                    .in_set(CarrySystem::Land)
+                    .after(EnemySystem::RegisterHits)
+                    .after(ProjectileSystem::Bounce)
+                    .before(ProjectileSystem::Despawn),
+            );
+    }
+}
+
+/// Carry systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum CarrySystem {
+    /// Picks up, moves and throws carried things.
+    Carry,
+    /// Turns thrown things back into carryable things.
+    Land,
+}
+
+/// Something that can be carried by a [`Carrier`].
+#[derive(Clone, Component, Debug)]
+pub struct Carryable {
+    /// How heavy the thing is.
+    ///
+    /// A carrier carrying something with a weight of `1.` moves at half
+    /// speed.
+    pub weight: f32,
+}
+
+impl Default for Carryable {
+    fn default() -> Carryable {
+        Carryable { weight: 0.5 }
+    }
+}
+
+/// Something that can carry [`Carryable`]s.
+#[derive(Clone, Component, Debug)]
+pub struct Carrier {
+    /// How far the carrier can reach to pick things up, in world units.
+    pub reach: f32,
+    /// How fast things are thrown, in world units per second.
+    pub throw_speed: f32,
+    /// Where carried things are held relative to the carrier.
+    pub hold_offset: Vec2,
+    carrying: Option<Entity>,
+    carried_weight: f32,
+}
+
+impl Carrier {
+    /// The entity being carried.
+    pub fn carrying(&self) -> Option<Entity> {
+        self.carrying
+    }
+
+    /// The factor movement is scaled by from the weight of the carried thing.
+    pub fn burden(&self) -> f32 {
+        if self.carrying.is_some() {
+            1. / (1. + self.carried_weight)
+        } else {
+            1.
+        }
+    }
+}
+
+impl Default for Carrier {
+    fn default() -> Carrier {
+        Carrier {
+            reach: 12.,
+            throw_speed: 192.,
+            hold_offset: Vec2::new(0., 10.),
+            carrying: None,
+            carried_weight: 0.,
+        }
+    }
+}
+
+/// Marks a [`Carryable`] that is being carried.
+///
+/// The associated entity is the [`Carrier`].
+#[derive(Clone, Component, Debug)]
+pub struct Carried(pub Entity);
+
+/// Marks a [`Carryable`] that has been thrown.
+///
+/// Thrown things are [`Projectile`]s until they hit something, or the timer
+/// finishes.
+#[derive(Clone, Component, Debug)]
+pub struct Thrown(Timer);
+
+impl Default for Thrown {
+    fn default() -> Thrown {
+        Thrown(Timer::new(Duration::from_secs(2), TimerMode::Once))
+    }
+}
+
+/// A big note that can be carried and thrown.
+#[derive(Bundle)]
+pub struct BigNoteBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub sprite: TextureAtlasSprite,
+    pub rigidbody: RigidBody,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub mass: ColliderMassProperties,
+    pub velocity: Velocity,
+    pub locked_axes: LockedAxes,
+    pub carryable: Carryable,
+    pub big_note: BigNote,
+}
+
+impl Default for BigNoteBundle {
+    fn default() -> BigNoteBundle {
+        BigNoteBundle {
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            computed_visibility: ComputedVisibility::default(),
+            texture_atlas: Default::default(),
+            sprite: TextureAtlasSprite::new(3),
+            rigidbody: RigidBody::Dynamic,
+            collider: Collider::cuboid(5., 4.),
+            collision_groups: BigNote::collision_groups(),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            mass: ColliderMassProperties::Density(4.),
+            velocity: Velocity::default(),
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            carryable: Carryable::default(),
+            big_note: BigNote,
+        }
+    }
+}
+
+impl LdtkEntity for BigNoteBundle {
+    fn bundle_entity(
+        _entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        BigNoteBundle::default()
+    }
+}
+
+/// A marker component for big notes.
+#[derive(Clone, Component, Debug, Default)]
+pub struct BigNote;
+
+impl BigNote {
+    /// The collision groups of a big note at rest.
+    pub fn collision_groups() -> CollisionGroups {
+        CollisionGroups::new(physics::COLLISION_GROUP_SOLID, Group::all())
+    }
+}
+
+fn setup_big_notes(
+    mut big_notes_query: Query<&mut Handle<TextureAtlas>, Added<BigNote>>,
+    assets: Res<GameAssets>,
+) {
+    for mut texture_atlas in big_notes_query.iter_mut() {
+        *texture_atlas = assets.projectile_sheet.clone();
+    }
+}
+
+fn pick_up_or_throw(
+    mut commands: Commands,
+    mut carrier_query: Query<(Entity, &GlobalTransform, &Controller, &mut Carrier)>,
+    mut carryable_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Carryable,
+            &mut RigidBody,
+            &mut CollisionGroups,
+            &mut Velocity,
+        ),
+        (Without<Carried>, Without<Thrown>),
+    >,
+    mut carried_query: Query<(&mut RigidBody, &mut Velocity), With<Carried>>,
+) {
+    for (carrier_entity, carrier_transform, controller, mut carrier) in carrier_query.iter_mut() {
+        if !controller.interact() {
+            continue;
+        }
+
+        if let Some(carried) = carrier.carrying.take() {
+            // throw
+            let Ok((mut rigidbody, mut velocity)) = carried_query.get_mut(carried) else {
+                continue;
+            };
+
+            *rigidbody = RigidBody::Dynamic;
+            velocity.linvel = controller.shoot_dir() * carrier.throw_speed;
+
+            commands.entity(carried).remove::<Carried>().insert((
+                Thrown::default(),
+                Projectile::default(),
+                Hostility::Friendly,
+            ));
+
+            continue;
+        }
+
+        // find closest carryable
+        let position = carrier_transform.translation().truncate();
+
+        let closest = carryable_query
+            .iter()
+            .map(|(entity, transform, ..)| {
+                (
+                    entity,
+                    transform.translation().truncate().distance(position),
+                )
+            })
+            .filter(|(_, dist)| *dist <= carrier.reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((entity, _)) = closest else {
+            continue;
+        };
+
+        let Ok((_, _, carryable, mut rigidbody, mut collision_groups, mut velocity)) =
+            carryable_query.get_mut(entity)
+        else {
+            continue;
+        };
+
+        *rigidbody = RigidBody::KinematicPositionBased;
+        // do not shove the carrier around
+        collision_groups.filters = Group::empty();
+        velocity.linvel = Vec2::ZERO;
+
+        carrier.carrying = Some(entity);
+        carrier.carried_weight = carryable.weight;
+
+        commands.entity(entity).insert(Carried(carrier_entity));
+    }
+}
+
+fn follow_carrier(
+    mut carrier_query: Query<(&GlobalTransform, &mut Carrier)>,
+    mut carried_query: Query<(&mut Transform, Option<&Parent>), With<Carried>>,
+    parent_query: Query<&GlobalTransform, Without<Carrier>>,
+) {
+    for (carrier_transform, mut carrier) in carrier_query.iter_mut() {
+        let Some(carried) = carrier.carrying else {
+            continue;
+        };
+
+        let Ok((mut transform, parent)) = carried_query.get_mut(carried) else {
+            // carried thing was despawned
+            carrier.carrying = None;
+            continue;
+        };
+
+        let mut target = carrier_transform.translation() + carrier.hold_offset.extend(0.);
+        target.z = transform.translation.z;
+
+        // carried things are usually parented to the level
+        if let Some(parent_transform) = parent.and_then(|p| parent_query.get(p.get()).ok()) {
+            target = parent_transform.affine().inverse().transform_point3(target);
+        }
+
+        transform.translation = target;
+    }
+}
+
+fn land_thrown(
+    mut commands: Commands,
+    mut thrown_query: Query<(
+        Entity,
+        &mut Thrown,
+        &mut Projectile,
+        &mut CollisionGroups,
+        Option<&mut TintStack>,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, mut thrown, mut projectile, mut collision_groups, tint_stack) in
+        thrown_query.iter_mut()
+    {
+        thrown.0.tick(time.delta());
+
+        if !projectile.absorbed && !thrown.0.finished() {
+            continue;
+        }
+
+        // heavy notes are not absorbed
+        projectile.absorbed = false;
+
+        *collision_groups = BigNote::collision_groups();
+
+        if let Some(mut tint_stack) = tint_stack {
+            tint_stack.remove(Hostility::TINT);
+        }
+
+        commands
+            .entity(entity)
+            .remove::<(Thrown, Projectile, Hostility)>();
+    }
+}
@@ -0,0 +1,244 @@
+//! Damage between things.
+//!
+//! Everything that hurts something else goes through a [`DamageEvent`]:
+//! projectile hits, hazards like spikes and touching enemies. Anything that
+//! reacts to being hurt (dying, flashing, keeping score) should read those
+//! instead of the physics or projectile events they come from.
+//!
+//! Projectiles and enemies only hurt things of the other [`Hostility`].
+//! [`Hazard`]s have no side; they hurt anything that touches them.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use crate::enemy::{DeathTimer, Enemy, Hostility};
+use crate::projectile::{HitEvent, NoHurt, Projectile, ProjectileSystem};
+
+/// Combat plugin.
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>().add_systems(
+            Update,
+            (projectile_damage, contact_damage, hazard_damage)
+                .in_set(CombatSystem::Damage)
+                .after(ProjectileSystem::Event),
+        );
+    }
+}
+
+/// Combat systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum CombatSystem {
+    /// [`DamageEvent`]s are sent.
+    ///
+    /// Read [`DamageEvent`]s after this system.
+    Damage,
+}
+
+/// Something was hurt.
+#[derive(Clone, Debug, Event)]
+pub struct DamageEvent {
+    /// The entity that was hurt.
+    pub target: Entity,
+    /// How much it hurt, where `1.` is a normal projectile.
+    pub amount: f32,
+    /// What hurt the target, if it still exists.
+    pub source: Option<Entity>,
+    /// How the target was hurt.
+    pub kind: DamageKind,
+}
+
+/// How something was hurt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DamageKind {
+    /// Hit by a projectile, beam or explosion.
+    Projectile,
+    /// Touched something hostile.
+    #[default]
+    Contact,
+    /// Touched spikes, or another [`Hazard`].
+    Spikes,
+}
+
+/// Hurts things of the other [`Hostility`] that touch this.
+///
+/// [`Enemy`]s hurt on contact even without this, as if they had the default.
+#[derive(Clone, Component, Debug)]
+pub struct ContactDamage {
+    /// How much it hurts.
+    pub amount: f32,
+    /// How it hurts.
+    pub kind: DamageKind,
+}
+
+impl Default for ContactDamage {
+    fn default() -> ContactDamage {
+        ContactDamage {
+            amount: 1.,
+            kind: DamageKind::Contact,
+        }
+    }
+}
+
+/// Part of the level that hurts anything that touches it, like spikes.
+///
+/// Unlike [`ContactDamage`], hazards are not enemies and have no
+/// [`Hostility`], so they hurt friendlies and enemies alike.
+#[derive(Clone, Component, Debug)]
+pub struct Hazard {
+    /// How much it hurts.
+    pub amount: f32,
+    /// How it hurts.
+    pub kind: DamageKind,
+}
+
+impl Default for Hazard {
+    fn default() -> Hazard {
+        Hazard {
+            amount: 1.,
+            kind: DamageKind::Spikes,
+        }
+    }
+}
+
+/// How much damage something can take before it dies.
+#[derive(Clone, Component, Debug)]
+pub struct Health {
+    /// The health left.
+    pub current: f32,
+    /// The health when unhurt.
+    pub max: f32,
+}
+
+impl Health {
+    /// Creates a new, unhurt `Health`.
+    pub fn new(max: f32) -> Health {
+        Health { current: max, max }
+    }
+
+    /// Takes damage, returning `true` if this was the killing blow.
+    pub fn damage(&mut self, amount: f32) -> bool {
+        let was_dead = self.is_dead();
+
+        self.current = (self.current - amount).max(0.);
+
+        !was_dead && self.is_dead()
+    }
+
+    /// Heals some health back, up to [`Health::max`].
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Checks if there is no health left.
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
+
+impl Default for Health {
+    fn default() -> Health {
+        Health::new(1.)
+    }
+}
+
+fn projectile_damage(
+    mut hit_events: EventReader<HitEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    source_query: Query<(&Hostility, Option<&Projectile>), Without<NoHurt>>,
+    target_query: Query<&Hostility>,
+) {
+    for ev in hit_events.iter() {
+        // beams and explosions hurt too, they just aren't projectiles
+        let Ok((source_hostility, projectile)) = source_query.get(ev.projectile) else {
+            continue;
+        };
+
+        let Ok(target_hostility) = target_query.get(ev.entity) else {
+            continue;
+        };
+
+        if source_hostility == target_hostility {
+            continue;
+        }
+
+        damage_events.send(DamageEvent {
+            target: ev.entity,
+            amount: projectile.map(|p| p.strength).unwrap_or(1.),
+            source: Some(ev.projectile),
+            kind: DamageKind::Projectile,
+        });
+    }
+}
+
+fn contact_damage(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    source_query: Query<
+        (&Hostility, Option<&ContactDamage>),
+        (Or<(With<Enemy>, With<ContactDamage>)>, Without<DeathTimer>),
+    >,
+    target_query: Query<&Hostility, Without<Projectile>>,
+) {
+    for ev in collision_events.iter() {
+        let CollisionEvent::Started(c1, c2, _) = *ev else {
+            continue;
+        };
+
+        for (target, source) in [(c1, c2), (c2, c1)] {
+            let Ok((source_hostility, contact_damage)) = source_query.get(source) else {
+                continue;
+            };
+
+            let Ok(target_hostility) = target_query.get(target) else {
+                continue;
+            };
+
+            if source_hostility == target_hostility {
+                continue;
+            }
+
+            let ContactDamage { amount, kind } = contact_damage.cloned().unwrap_or_default();
+
+            damage_events.send(DamageEvent {
+                target,
+                amount,
+                source: Some(source),
+                kind,
+            });
+        }
+    }
+}
+
+fn hazard_damage(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    hazard_query: Query<&Hazard>,
+    target_query: Query<(), (With<Hostility>, Without<Projectile>, Without<DeathTimer>)>,
+) {
+    for ev in collision_events.iter() {
+        let CollisionEvent::Started(c1, c2, _) = *ev else {
+            continue;
+        };
+
+        for (target, source) in [(c1, c2), (c2, c1)] {
+            let Ok(hazard) = hazard_query.get(source) else {
+                continue;
+            };
+
+            if !target_query.contains(target) {
+                continue;
+            }
+
+            damage_events.send(DamageEvent {
+                target,
+                amount: hazard.amount,
+                source: Some(source),
+                kind: hazard.kind,
+            });
+        }
+    }
+}
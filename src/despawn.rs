@@ -0,0 +1,153 @@
+//! Batched despawning.
+//!
+//! Gameplay systems should not despawn entities directly. More than one
+//! system may decide to get rid of the same entity in the same frame (a
+//! projectile absorbed by an acceptor that also ran out of time, say), and
+//! despawning it twice warns or worse. Instead, [`DespawnQueue::push`] the
+//! entity and it is despawned once in [`DespawnSystem::Apply`], just before
+//! the physics step.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::collections::{HashMap, VecDeque};
+
+/// Despawn plugin.
+pub struct DespawnPlugin;
+
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DespawnQueue>().add_systems(
+            PostUpdate,
+            apply_despawn_queue
+                .in_set(DespawnSystem::Apply)
+                .before(PhysicsSet::SyncBackend),
+        );
+    }
+}
+
+/// Despawn systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum DespawnSystem {
+    /// Queued entities are despawned.
+    Apply,
+}
+
+/// Why an entity was despawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DespawnReason {
+    /// A projectile was absorbed, or couldn't fit back in the pool.
+    Absorbed,
+    /// An effect or projectile ran out of time.
+    Expired,
+    /// An enemy died.
+    Died,
+    /// A signal reached the end of the pipes or merged into another.
+    Consumed,
+    /// The world was reset.
+    Respawn,
+    /// Something else, described by the caller.
+    Other(&'static str),
+}
+
+/// Entities waiting to be despawned.
+#[derive(Debug, Resource)]
+pub struct DespawnQueue {
+    /// How many despawns are kept around in [`DespawnQueue::history`].
+    pub history_len: usize,
+    queued: Vec<Entity>,
+    reasons: HashMap<Entity, DespawnReason>,
+    history: VecDeque<(Entity, DespawnReason)>,
+    duplicates: u64,
+}
+
+impl DespawnQueue {
+    /// Queues an entity and its descendants to be despawned.
+    ///
+    /// Returns `false` if the entity was already queued this frame; the first
+    /// reason is kept.
+    pub fn push(&mut self, entity: Entity, reason: DespawnReason) -> bool {
+        if let Some(first) = self.reasons.get(&entity) {
+            self.duplicates += 1;
+
+            crate::game_debug!(
+                Despawn,
+                "{:?} queued twice ({:?}, then {:?})",
+                entity,
+                first,
+                reason
+            );
+
+            return false;
+        }
+
+        self.queued.push(entity);
+        self.reasons.insert(entity, reason);
+        true
+    }
+
+    /// Checks if an entity is going to be despawned this frame.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.reasons.contains_key(&entity)
+    }
+
+    /// The most recent despawns, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &(Entity, DespawnReason)> {
+        self.history.iter()
+    }
+
+    /// How many times an entity was queued when it already was.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+}
+
+impl Default for DespawnQueue {
+    fn default() -> DespawnQueue {
+        DespawnQueue {
+            history_len: 64,
+            queued: Vec::new(),
+            reasons: HashMap::new(),
+            history: VecDeque::new(),
+            duplicates: 0,
+        }
+    }
+}
+
+fn apply_despawn_queue(world: &mut World) {
+    let (queued, mut reasons) = {
+        let mut queue = world.resource_mut::<DespawnQueue>();
+        let queue = &mut *queue;
+
+        (
+            std::mem::take(&mut queue.queued),
+            std::mem::take(&mut queue.reasons),
+        )
+    };
+
+    let mut despawned = Vec::with_capacity(queued.len());
+
+    // in the order they were queued, so this is the same every run
+    for entity in queued {
+        let reason = reasons
+            .remove(&entity)
+            .unwrap_or(DespawnReason::Other("unknown"));
+
+        // an ancestor may have taken it with it
+        let Some(entity_mut) = world.get_entity_mut(entity) else {
+            continue;
+        };
+
+        entity_mut.despawn_recursive();
+        despawned.push((entity, reason));
+    }
+
+    let mut queue = world.resource_mut::<DespawnQueue>();
+
+    queue.history.extend(despawned);
+
+    while queue.history.len() > queue.history_len {
+        queue.history.pop_front();
+    }
+}
@@ -6,12 +6,18 @@ use bevy_rapier2d::prelude::*;
 
 use bevy_ecs_ldtk::{
     app::{LdtkEntity, LdtkEntityAppExt as _},
-    ldtk::{LayerInstance, TilesetDefinition},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
     EntityInstance,
 };
 
-use crate::projectile::{ProjectileSystem, HitEvent, prefab::{CreateProjectile, ProjectilePrefab}};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::projectile::{ProjectileSystem, HitEvent, knockback::KnockbackImmune, prefab::{CreateProjectile, ProjectilePrefab}};
+use crate::interactions::{InteractionSystem, Junction, Signal, SignalData, SignalEvent};
+use crate::level::{pipe::PipeInput, Iid};
 use crate::enemy::Hostility;
+use crate::fx::absorb::Absorb;
 use crate::{physics, GameState, GameAssets};
 
 pub struct DrumPlugin;
@@ -20,22 +26,309 @@ impl Plugin for DrumPlugin {
     fn build(&self, app: &mut App) {
         app
             .register_ldtk_entity::<DrumBundle>("Drum")
+            .add_event::<DrumHitEvent>()
+            .init_resource::<Resonance>()
+            .add_systems(
+                Update,
+                upgrade_resonates.run_if(any_with_component::<ResonatesByIid>()),
+            )
             .add_systems(
                 Update,
-                handle_projectiles
-                    .after(ProjectileSystem::Event),
+                (handle_projectiles, resonate_drums)
+                    .chain()
+                    .in_set(DrumSystem::Hit)
+                    .after(ProjectileSystem::Event)
+                    .before(ProjectileSystem::Despawn),
+            )
+            .add_systems(
+                Update,
+                (
+                    repair_drums_over_time,
+                    repair_drums_by_signal.after(InteractionSystem::TravelSignal),
+                ),
             )
             .add_systems(
                 PostUpdate,
-                setup_added_drums
+                (setup_added_drums, update_drum_sprites)
+                    .chain()
                     .run_if(in_state(GameState::InGame)),
             );
     }
 }
 
-/// A drum will produce allied beat notes when hit.
+/// Drum systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum DrumSystem {
+    /// Drums are hit and [`DrumHitEvent`]s are sent.
+    Hit,
+}
+
+/// A drum will produce a note of the same hostility as whatever hit it.
+///
+/// A drum hooked up to a tile of the `Pipes` layer can send a signal down the
+/// pipe instead, or as well; see [`DrumEmits`].
+#[derive(Clone, Component, Debug)]
+pub struct Drum {
+    /// The projectile the drum produces.
+    ///
+    /// It is sent off at [`Drum::velocity`], whatever velocity it has here.
+    pub output: ProjectilePrefab,
+    /// How fast and which way the projectile leaves the drum, in world units
+    /// per second.
+    pub velocity: Vec2,
+    /// What the drum puts out when it is hit.
+    pub emits: DrumEmits,
+}
+
+impl Drum {
+    /// How fast notes leave a drum if the level doesn't say, in world units
+    /// per second.
+    pub const DEFAULT_SPEED: f32 = 16.;
+    /// The way notes leave a drum if the level doesn't say, in degrees
+    /// counterclockwise from the right.
+    pub const DEFAULT_DIRECTION: f32 = 90.;
+    /// How far from the center of the drum notes appear, in world units.
+    pub const OUTPUT_DISTANCE: f32 = 14.;
+
+    /// The projectile the drum produces, going the way it should.
+    pub fn prefab(&self) -> ProjectilePrefab {
+        self.output.with_velocity(self.velocity)
+    }
+
+    /// Where the projectile appears, from the center of the drum.
+    pub fn output_offset(&self) -> Vec2 {
+        self.velocity.try_normalize().unwrap_or(Vec2::Y) * Drum::OUTPUT_DISTANCE
+    }
+}
+
+impl Default for Drum {
+    /// Initializes a `Drum` that produces beats straight up.
+    fn default() -> Drum {
+        let velocity = Vec2::Y * Drum::DEFAULT_SPEED;
+
+        Drum {
+            output: ProjectilePrefab::Beat {
+                initial_velocity: velocity,
+            },
+            velocity,
+            emits: DrumEmits::Projectile,
+        }
+    }
+}
+
+/// What a [`Drum`] puts out when it is hit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrumEmits {
+    /// A projectile, see [`Drum::output`].
+    #[default]
+    Projectile,
+    /// A signal down every pipe of the drum's [`Junction`].
+    Signal,
+    /// Both a projectile and a signal.
+    Both,
+}
+
+impl DrumEmits {
+    /// Finds what a drum emits by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<DrumEmits> {
+        match name {
+            "Projectile" => Some(DrumEmits::Projectile),
+            "Signal" => Some(DrumEmits::Signal),
+            "Both" => Some(DrumEmits::Both),
+            _ => None,
+        }
+    }
+
+    /// Checks if a projectile is put out.
+    pub fn projectile(self) -> bool {
+        matches!(self, DrumEmits::Projectile | DrumEmits::Both)
+    }
+
+    /// Checks if a signal is put out.
+    pub fn signal(self) -> bool {
+        matches!(self, DrumEmits::Signal | DrumEmits::Both)
+    }
+}
+
+/// Sent when a [`Drum`] is hit and makes a sound.
+#[derive(Debug, Event)]
+pub struct DrumHitEvent {
+    /// The drum.
+    pub drum: Entity,
+    /// The hostility of the projectile that hit it.
+    pub hostility: Hostility,
+    /// Whether the drum rang along with another drum, instead of being hit.
+    ///
+    /// See [`Resonates`].
+    pub resonance: bool,
+}
+
+/// Drums that ring along with this one a moment after it rings, as if they
+/// were hit by the same thing.
+///
+/// A drum rings at most once for each hit that starts a chain, so drums that
+/// resonate with each other don't ring forever.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Resonates(pub Vec<Entity>);
+
+impl Resonates {
+    /// How long after a drum rings the drums it resonates with ring.
+    pub const DELAY: Duration = Duration::from_millis(150);
+    /// The most drums a single hit can ring, counting the one that was hit.
+    pub const MAX_CHAIN: usize = 16;
+}
+
+/// Slightly indirect version of [`Resonates`].
+///
+/// Links are moved over to [`Resonates`] one at a time as the drums they
+/// point to are spawned, and this is removed once they all are.
 #[derive(Clone, Component, Debug, Default)]
-pub struct Drum;
+pub struct ResonatesByIid(Vec<String>);
+
+/// The drums waiting to ring along with another.
+#[derive(Debug, Default, Resource)]
+pub struct Resonance {
+    pending: Vec<PendingRing>,
+    /// Every drum that has rung, or is waiting to, in each chain.
+    chains: HashMap<u64, HashSet<Entity>>,
+    next_chain: u64,
+}
+
+impl Resonance {
+    /// Starts a chain from a drum that was hit.
+    fn start(&mut self, drum: Entity, resonates: &Resonates, hostility: Hostility) {
+        let chain = self.next_chain;
+        self.next_chain = self.next_chain.wrapping_add(1);
+
+        self.chains.insert(chain, HashSet::from([drum]));
+        self.schedule(chain, resonates, hostility);
+        self.prune();
+    }
+
+    /// Makes the drums a drum resonates with ring after [`Resonates::DELAY`],
+    /// unless they have already rung in the chain.
+    fn schedule(&mut self, chain: u64, resonates: &Resonates, hostility: Hostility) {
+        let Some(rung) = self.chains.get_mut(&chain) else {
+            return;
+        };
+
+        for &drum in resonates.0.iter() {
+            if rung.len() >= Resonates::MAX_CHAIN {
+                break;
+            }
+
+            // already rung in this chain
+            if !rung.insert(drum) {
+                continue;
+            }
+
+            self.pending.push(PendingRing {
+                drum,
+                hostility,
+                timer: Timer::new(Resonates::DELAY, TimerMode::Once),
+                chain,
+            });
+        }
+    }
+
+    /// Forgets the chains with no drums left to ring.
+    fn prune(&mut self) {
+        let pending = &self.pending;
+
+        self.chains
+            .retain(|chain, _| pending.iter().any(|ring| ring.chain == *chain));
+    }
+}
+
+#[derive(Debug)]
+struct PendingRing {
+    drum: Entity,
+    hostility: Hostility,
+    timer: Timer,
+    chain: u64,
+}
+
+/// The skin of a [`Drum`], which may break after being hit too many times.
+///
+/// A broken skin does not produce beats until it is repaired, either by
+/// routing a friendly signal to the drum or by waiting.
+#[derive(Clone, Component, Debug)]
+pub struct DrumSkin {
+    /// How many hits the skin can take before it breaks.
+    ///
+    /// If this is `None`, the skin never breaks.
+    pub durability: Option<u32>,
+    hits: u32,
+    repair: Timer,
+}
+
+impl DrumSkin {
+    /// Creates a new `DrumSkin` that breaks after `durability` hits and repairs
+    /// itself after `repair_time`.
+    pub fn new(durability: u32, repair_time: Duration) -> DrumSkin {
+        DrumSkin {
+            durability: Some(durability),
+            hits: 0,
+            repair: Timer::new(repair_time, TimerMode::Once),
+        }
+    }
+
+    /// Checks if the skin is broken.
+    pub fn broken(&self) -> bool {
+        self.durability.map(|d| self.hits >= d).unwrap_or(false)
+    }
+
+    /// Checks if the skin is cracked, i.e. it has taken at least half of its
+    /// durability in hits.
+    pub fn cracked(&self) -> bool {
+        self.durability.map(|d| self.hits * 2 >= d).unwrap_or(false)
+    }
+
+    /// Registers a hit on the skin.
+    ///
+    /// Returns `false` if the skin was already broken and the hit should not
+    /// produce a beat.
+    pub fn hit(&mut self) -> bool {
+        if self.broken() {
+            return false;
+        }
+
+        self.hits += 1;
+
+        if self.broken() {
+            self.repair.reset();
+        }
+
+        true
+    }
+
+    /// Repairs the skin completely.
+    pub fn repair(&mut self) {
+        self.hits = 0;
+    }
+
+    /// The index of the sprite in the drum sheet.
+    pub fn sprite_index(&self) -> usize {
+        if self.broken() {
+            2
+        } else if self.cracked() {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for DrumSkin {
+    /// Initializes an unbreakable `DrumSkin`.
+    fn default() -> DrumSkin {
+        DrumSkin {
+            durability: None,
+            hits: 0,
+            repair: Timer::new(Duration::from_secs(10), TimerMode::Once),
+        }
+    }
+}
 
 #[derive(Bundle)]
 pub struct DrumBundle {
@@ -43,11 +336,18 @@ pub struct DrumBundle {
     global_transform: GlobalTransform,
     visibility: Visibility,
     computed_visibility: ComputedVisibility,
-    image: Handle<Image>,
-    sprite: Sprite,
+    texture_atlas: Handle<TextureAtlas>,
+    sprite: TextureAtlasSprite,
     collider: Collider,
     collision_groups: CollisionGroups,
     drum: Drum,
+    skin: DrumSkin,
+    knockback_immune: KnockbackImmune,
+    junction: Junction,
+    pipe: PipeInput,
+    resonates: Resonates,
+    resonates_iid: ResonatesByIid,
+    iid: Iid,
 }
 
 impl Default for DrumBundle {
@@ -62,57 +362,348 @@ impl Default for DrumBundle {
                 physics::COLLISION_GROUP_SOLID,
                 Group::all(),
             ),
-            image: Default::default(),
-            sprite: Sprite::default(),
-            drum: Drum,
+            texture_atlas: Default::default(),
+            sprite: TextureAtlasSprite::default(),
+            drum: Drum::default(),
+            skin: DrumSkin::default(),
+            knockback_immune: KnockbackImmune,
+            junction: Junction::default(),
+            pipe: PipeInput::default(),
+            resonates: Resonates::default(),
+            resonates_iid: ResonatesByIid::default(),
+            iid: Iid::default(),
         }
     }
 }
 
 impl LdtkEntity for DrumBundle {
     fn bundle_entity(
-        _entity_instance: &EntityInstance,
-        _layer_instance: &LayerInstance,
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
         _tileset: Option<&Handle<Image>>,
         _tileset_definition: Option<&TilesetDefinition>,
         _asset_server: &AssetServer,
         _texture_atlases: &mut Assets<TextureAtlas>
     ) -> Self {
-        DrumBundle::default()
+        // drums without a durability never break
+        let durability = entity_instance
+            .get_maybe_int_field("Durability")
+            .ok()
+            .copied()
+            .flatten()
+            .filter(|d| *d > 0);
+
+        let repair_time = entity_instance
+            .get_maybe_float_field("RepairTime")
+            .ok()
+            .copied()
+            .flatten()
+            .map(Duration::from_secs_f32)
+            .unwrap_or(Duration::from_secs(10));
+
+        // degrees counterclockwise from the right
+        let angle = entity_instance
+            .get_maybe_float_field("Direction")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(Drum::DEFAULT_DIRECTION)
+            .to_radians();
+
+        let speed = entity_instance
+            .get_maybe_float_field("Speed")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(Drum::DEFAULT_SPEED);
+
+        let velocity = Vec2::from_angle(angle) * speed;
+
+        let output = entity_instance
+            .get_maybe_enum_field("Output")
+            .ok() // may not exist
+            .and_then(|output| output.as_deref())
+            .and_then(|output| {
+                let prefab = ProjectilePrefab::from_name(output, velocity);
+
+                if prefab.is_none() {
+                    crate::game_warn!(Projectile, "unknown drum output {}", output);
+                }
+
+                prefab
+            })
+            .unwrap_or_else(|| Drum::default().output);
+
+        let emits = entity_instance
+            .get_maybe_enum_field("Emits")
+            .ok() // may not exist
+            .and_then(|emits| emits.as_deref())
+            .and_then(|emits| {
+                let drum_emits = DrumEmits::from_name(emits);
+
+                if drum_emits.is_none() {
+                    crate::game_warn!(Interactions, "unknown drum emits {}", emits);
+                }
+
+                drum_emits
+            })
+            .unwrap_or_default();
+
+        let resonates = entity_instance
+            .get_maybe_entity_refs_field("Resonates")
+            .map(|refs| {
+                refs.iter()
+                    .flatten()
+                    .map(|r| r.entity_iid.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        DrumBundle {
+            drum: Drum {
+                output,
+                velocity,
+                emits,
+            },
+            resonates_iid: ResonatesByIid(resonates),
+            iid: Iid::from(entity_instance),
+            pipe: PipeInput::from_field(entity_instance, layer_instance, "Pipe"),
+            skin: match durability {
+                Some(durability) => DrumSkin::new(durability as u32, repair_time),
+                None => DrumSkin::default(),
+            },
+            ..Default::default()
+        }
     }
 }
 
 fn setup_added_drums(
-    mut added_drums_query: Query<&mut Handle<Image>, Added<Drum>>,
+    mut added_drums_query: Query<&mut Handle<TextureAtlas>, Added<Drum>>,
     assets: Res<GameAssets>,
 ) {
-    for mut image in added_drums_query.iter_mut() {
-        *image = assets.drum_image.clone();
+    for mut texture_atlas in added_drums_query.iter_mut() {
+        *texture_atlas = assets.drum_sheet.clone();
+    }
+}
+
+fn update_drum_sprites(
+    mut drum_query: Query<(&DrumSkin, &mut TextureAtlasSprite), Changed<DrumSkin>>,
+) {
+    for (skin, mut sprite) in drum_query.iter_mut() {
+        sprite.index = skin.sprite_index();
+    }
+}
+
+fn repair_drums_over_time(mut drum_query: Query<&mut DrumSkin>, time: Res<Time>) {
+    for mut skin in drum_query.iter_mut() {
+        if !skin.broken() {
+            continue;
+        }
+
+        skin.repair.tick(time.delta());
+
+        if skin.repair.finished() {
+            skin.repair();
+        }
+    }
+}
+
+fn repair_drums_by_signal(
+    mut signal_events: EventReader<SignalEvent>,
+    mut drum_query: Query<&mut DrumSkin>,
+    signal_query: Query<&Signal>,
+) {
+    for ev in signal_events.iter() {
+        let Ok(mut skin) = drum_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        if skin.broken() && signal.data.hostility == Hostility::Friendly {
+            skin.repair();
+        }
+    }
+}
+
+fn upgrade_resonates(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut ResonatesByIid, &mut Resonates)>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for (entity, mut iid_requests, mut resonates) in query.iter_mut() {
+        // drums that aren't spawned yet wait, without holding up the rest
+        iid_requests.0.retain(|iid_request| {
+            let found = iid_query
+                .iter()
+                .find(|(_, iid)| iid.0 == *iid_request)
+                .map(|(e, _)| e);
+
+            match found {
+                Some(found) => {
+                    resonates.0.push(found);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        if iid_requests.0.is_empty() {
+            commands.entity(entity).remove::<ResonatesByIid>();
+        }
     }
 }
 
 fn handle_projectiles(
     mut commands: Commands,
     mut projectile_hit_events: EventReader<HitEvent>,
-    drum_query: Query<&GlobalTransform, With<Drum>>,
+    mut drum_hit_events: EventWriter<DrumHitEvent>,
+    mut drum_query: Query<(
+        &GlobalTransform,
+        &Drum,
+        &mut DrumSkin,
+        Option<&Junction>,
+        Option<&Resonates>,
+    )>,
     projectile_query: Query<&Hostility>,
+    mut resonance: ResMut<Resonance>,
 ) {
     for ev in projectile_hit_events.iter() {
-        let Ok(drum_transform) = drum_query.get(ev.entity) else {
+        let Ok((drum_transform, drum, mut skin, junction, resonates)) =
+            drum_query.get_mut(ev.entity)
+        else {
+            continue;
+        };
+
+        let Ok(&hostility) = projectile_query.get(ev.projectile) else {
             continue;
         };
 
-        let Ok(hostility) = projectile_query.get(ev.projectile) else {
+        // broken skins do not make a sound
+        if !skin.hit() {
+            continue;
+        }
+
+        commands.add(Absorb::new(ev.projectile, ev.entity));
+
+        drum_hit_events.send(DrumHitEvent {
+            drum: ev.entity,
+            hostility,
+            resonance: false,
+        });
+
+        ring(
+            &mut commands,
+            ev.entity,
+            drum_transform,
+            drum,
+            junction,
+            hostility,
+        );
+
+        if let Some(resonates) = resonates {
+            resonance.start(ev.entity, resonates, hostility);
+        }
+    }
+}
+
+fn resonate_drums(
+    mut commands: Commands,
+    mut drum_hit_events: EventWriter<DrumHitEvent>,
+    drum_query: Query<(
+        &GlobalTransform,
+        &Drum,
+        &DrumSkin,
+        Option<&Junction>,
+        Option<&Resonates>,
+    )>,
+    mut resonance: ResMut<Resonance>,
+    time: Res<Time>,
+) {
+    // do not trip change detection
+    if resonance.pending.is_empty() {
+        return;
+    }
+
+    let mut pending = std::mem::take(&mut resonance.pending);
+
+    for ring_request in pending.iter_mut() {
+        ring_request.timer.tick(time.delta());
+    }
+
+    let (finished, waiting) = pending
+        .into_iter()
+        .partition::<Vec<_>, _>(|ring_request| ring_request.timer.finished());
+
+    resonance.pending = waiting;
+
+    for ring_request in finished {
+        let Ok((drum_transform, drum, skin, junction, resonates)) =
+            drum_query.get(ring_request.drum)
+        else {
             continue;
         };
 
-        let mut location = drum_transform.translation();
-        location.y += 14.;
+        // broken skins do not resonate, and ringing along doesn't wear them
+        if skin.broken() {
+            continue;
+        }
+
+        drum_hit_events.send(DrumHitEvent {
+            drum: ring_request.drum,
+            hostility: ring_request.hostility,
+            resonance: true,
+        });
+
+        ring(
+            &mut commands,
+            ring_request.drum,
+            drum_transform,
+            drum,
+            junction,
+            ring_request.hostility,
+        );
+
+        if let Some(resonates) = resonates {
+            resonance.schedule(ring_request.chain, resonates, ring_request.hostility);
+        }
+    }
+
+    resonance.prune();
+}
+
+/// Puts out whatever a drum emits, for something of a hostility.
+fn ring(
+    commands: &mut Commands,
+    entity: Entity,
+    drum_transform: &GlobalTransform,
+    drum: &Drum,
+    junction: Option<&Junction>,
+    hostility: Hostility,
+) {
+    if drum.emits.projectile() {
+        let location = drum_transform.translation() + drum.output_offset().extend(0.);
 
         // create projectile
-        // FIXME magic
-        commands.add(CreateProjectile::new(ProjectilePrefab::Beat { initial_velocity: Vec2::Y * 16. }, location)
-            .hostility(hostility.clone()));
+        commands.add(CreateProjectile::new(drum.prefab(), location).hostility(hostility));
+    }
+
+    if drum.emits.signal() {
+        // send a signal down the pipe the drum is hooked up to
+        for pipe in junction.iter().flat_map(|junction| junction.pipes.iter()) {
+            commands.spawn((
+                SpatialBundle::default(),
+                Signal {
+                    data: SignalData::new(hostility),
+                    source: entity,
+                    destination: Some(pipe.receiver),
+                    position: 0.,
+                    speed: pipe.speed,
+                },
+            ));
+        }
     }
 }
 
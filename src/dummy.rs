@@ -0,0 +1,362 @@
+//! Training dummies.
+//!
+//! A [`Dummy`] takes hits from projectiles of either [`Hostility`] but never
+//! dies. It has no hostility of its own, so projectiles don't hurt it through
+//! [`DamageEvent`]s; it reads [`HitEvent`]s instead. Every hit flashes it and
+//! floats a [`DamageNumber`] off of it, and a small readout above it shows the
+//! damage per second over the last [`Dummy::window`].
+//!
+//! [`Hostility`]: crate::enemy::Hostility
+//! [`DamageEvent`]: crate::combat::DamageEvent
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::fx::absorb::Absorb;
+use crate::fx::tint::TintStack;
+use crate::physics;
+use crate::projectile::{ContactBehavior, HitEvent, Projectile, ProjectileSystem};
+use crate::GameAssets;
+
+/// Training dummy plugin.
+pub struct DummyPlugin;
+
+impl Plugin for DummyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<DummyBundle>("Dummy")
+            .add_systems(Update, setup_dummies)
+            .add_systems(
+                Update,
+                (register_dummy_hits, update_dummy_readouts)
+                    .chain()
+                    .run_if(any_with_component::<Dummy>())
+                    .after(ProjectileSystem::Event)
+                    .before(ProjectileSystem::Despawn),
+            )
+            .add_systems(
+                Update,
+                float_damage_numbers.run_if(any_with_component::<DamageNumber>()),
+            );
+    }
+}
+
+/// Something that takes hits for practice.
+#[derive(Clone, Component, Debug)]
+pub struct Dummy {
+    /// How far back hits count towards the damage per second.
+    pub window: Duration,
+    hits: VecDeque<(f32, f32)>,
+    total: f32,
+}
+
+impl Dummy {
+    /// The window of a dummy if the level doesn't say.
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(3);
+    /// The color of a dummy.
+    pub const COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+
+    /// Creates a new `Dummy` that counts hits over a window.
+    pub fn new(window: Duration) -> Dummy {
+        Dummy {
+            window,
+            hits: VecDeque::new(),
+            total: 0.,
+        }
+    }
+
+    /// Records a hit at a time, in seconds since startup.
+    pub fn hit(&mut self, now: f32, amount: f32) {
+        self.hits.push_back((now, amount));
+        self.total += amount;
+        self.forget(now);
+    }
+
+    /// Forgets hits that have fallen out of the window.
+    pub fn forget(&mut self, now: f32) {
+        let window = self.window.as_secs_f32();
+
+        while let Some(&(time, _)) = self.hits.front() {
+            if now - time <= window {
+                break;
+            }
+
+            self.hits.pop_front();
+        }
+    }
+
+    /// The damage per second over the window.
+    ///
+    /// Call [`Dummy::forget`] first so old hits don't count.
+    pub fn dps(&self) -> f32 {
+        let window = self.window.as_secs_f32().max(f32::EPSILON);
+
+        self.hits.iter().map(|(_, amount)| amount).sum::<f32>() / window
+    }
+
+    /// All the damage the dummy has ever taken.
+    pub fn total(&self) -> f32 {
+        self.total
+    }
+}
+
+impl Default for Dummy {
+    fn default() -> Dummy {
+        Dummy::new(Dummy::DEFAULT_WINDOW)
+    }
+}
+
+/// The damage per second shown above a [`Dummy`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct DummyReadout {
+    shown: Option<u64>,
+}
+
+/// A number that floats up off of a [`Dummy`] and fades away.
+#[derive(Clone, Component, Debug)]
+pub struct DamageNumber {
+    timer: Timer,
+}
+
+impl DamageNumber {
+    /// How long damage numbers last.
+    pub const LIFETIME: Duration = Duration::from_millis(600);
+    /// How fast damage numbers rise.
+    pub const SPEED: f32 = 24.;
+}
+
+impl Default for DamageNumber {
+    fn default() -> DamageNumber {
+        DamageNumber {
+            timer: Timer::new(DamageNumber::LIFETIME, TimerMode::Once),
+        }
+    }
+}
+
+/// A training dummy.
+#[derive(Bundle)]
+pub struct DummyBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub sprite: TextureAtlasSprite,
+    pub tint: TintStack,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub sensor: Sensor,
+    pub contact_behavior: ContactBehavior,
+    pub dummy: Dummy,
+}
+
+impl DummyBundle {
+    /// Creates a new `DummyBundle`.
+    pub fn new(transform: Transform) -> DummyBundle {
+        DummyBundle {
+            transform,
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            computed_visibility: ComputedVisibility::default(),
+            texture_atlas: Default::default(),
+            sprite: TextureAtlasSprite {
+                color: Dummy::COLOR,
+                ..Default::default()
+            },
+            tint: TintStack::new(Dummy::COLOR),
+            collider: Collider::cuboid(8., 8.),
+            // caught by projectiles of either side, and nothing else
+            collision_groups: CollisionGroups::new(
+                physics::COLLISION_GROUP_FRIENDLY | physics::COLLISION_GROUP_HOSTILE,
+                physics::COLLISION_GROUP_PROJECTILE,
+            ),
+            sensor: Sensor,
+            contact_behavior: ContactBehavior::Absorb,
+            dummy: Dummy::default(),
+        }
+    }
+}
+
+impl LdtkEntity for DummyBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let window = entity_instance
+            .get_maybe_float_field("Window")
+            .ok()
+            .copied()
+            .flatten()
+            .map(|window| Duration::from_secs_f32(window.max(0.1)))
+            .unwrap_or(Dummy::DEFAULT_WINDOW);
+
+        let mut bundle = DummyBundle::new(Transform::default());
+
+        bundle.dummy = Dummy::new(window);
+
+        bundle
+    }
+}
+
+/// The size of a glyph in `ui/digits.png`.
+const GLYPH_SIZE: Vec2 = Vec2::new(4., 6.);
+
+/// Spawns the digits of a number as sprites, centered on the parent.
+fn spawn_digits(parent: &mut ChildBuilder, assets: &GameAssets, number: u64, color: Color) {
+    let digits = number.to_string();
+    let advance = GLYPH_SIZE.x + 1.;
+    let left = -(digits.len() as f32 - 1.) * advance / 2.;
+
+    for (i, digit) in digits.bytes().enumerate() {
+        parent.spawn(SpriteSheetBundle {
+            texture_atlas: assets.ui_digits.clone(),
+            sprite: TextureAtlasSprite {
+                index: (digit - b'0') as usize,
+                color,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(left + i as f32 * advance, 0., 0.),
+            ..Default::default()
+        });
+    }
+}
+
+fn setup_dummies(
+    mut commands: Commands,
+    mut dummy_query: Query<(Entity, &mut Handle<TextureAtlas>), Added<Dummy>>,
+    assets: Res<GameAssets>,
+) {
+    for (entity, mut texture_atlas) in dummy_query.iter_mut() {
+        *texture_atlas = assets.enemy_howard.clone();
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                SpatialBundle::from_transform(Transform::from_xyz(0., 16., 1.)),
+                DummyReadout::default(),
+            ));
+        });
+    }
+}
+
+fn register_dummy_hits(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitEvent>,
+    mut dummy_query: Query<(&mut Dummy, &GlobalTransform)>,
+    projectile_query: Query<&Projectile>,
+    assets: Res<GameAssets>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for ev in hit_events.iter() {
+        let Ok((mut dummy, transform)) = dummy_query.get_mut(ev.entity) else {
+            continue;
+        };
+
+        // beams and explosions hit like a normal projectile
+        let amount = projectile_query
+            .get(ev.projectile)
+            .map(|p| p.strength)
+            .unwrap_or(1.);
+
+        dummy.hit(now, amount);
+
+        commands.add(Absorb::new(ev.projectile, ev.entity));
+
+        let position = ev
+            .point
+            .unwrap_or_else(|| transform.translation().truncate());
+
+        commands
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_translation(position.extend(10.))),
+                DamageNumber::default(),
+            ))
+            .with_children(|parent| {
+                spawn_digits(parent, &assets, amount.ceil() as u64, Color::WHITE);
+            });
+
+        crate::game_debug!(
+            Enemy,
+            "dummy {:?} hit for {}, {} total",
+            ev.entity,
+            amount,
+            dummy.total()
+        );
+    }
+}
+
+fn update_dummy_readouts(
+    mut commands: Commands,
+    mut dummy_query: Query<(&mut Dummy, &Children)>,
+    mut readout_query: Query<(Entity, &mut DummyReadout)>,
+    assets: Res<GameAssets>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for (mut dummy, children) in dummy_query.iter_mut() {
+        dummy.forget(now);
+
+        let dps = dummy.dps().round() as u64;
+
+        for &child in children.iter() {
+            let Ok((readout_entity, mut readout)) = readout_query.get_mut(child) else {
+                continue;
+            };
+
+            if readout.shown == Some(dps) {
+                continue;
+            }
+
+            readout.shown = Some(dps);
+
+            commands
+                .entity(readout_entity)
+                .despawn_descendants()
+                .with_children(|parent| {
+                    spawn_digits(parent, &assets, dps, Dummy::COLOR);
+                });
+        }
+    }
+}
+
+fn float_damage_numbers(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut number_query: Query<(Entity, &mut DamageNumber, &mut Transform, &Children)>,
+    mut sprite_query: Query<&mut TextureAtlasSprite>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut transform, children) in number_query.iter_mut() {
+        number.timer.tick(time.delta());
+
+        if number.timer.finished() {
+            despawn_queue.push(entity, DespawnReason::Expired);
+            continue;
+        }
+
+        transform.translation.y += DamageNumber::SPEED * time.delta_seconds();
+
+        let left = number.timer.percent_left();
+
+        for &child in children.iter() {
+            if let Ok(mut sprite) = sprite_query.get_mut(child) {
+                sprite.color.set_a(left);
+            }
+        }
+    }
+}
@@ -0,0 +1,585 @@
+//! Enemy movement.
+//!
+//! Enemies with a [`Patrol`] walk around on their own, turning around at walls
+//! and, unless they are cliff walkers, at the edges of platforms. Enemies with
+//! [`Aggro`] chase the player once they see them, and go back to patrolling
+//! after losing sight of them for a while. Flying enemies [`Swoop`] at the
+//! player instead of chasing them.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::time::Duration;
+
+use super::{DeathTimer, EnemySystem, EnemyTargets, Hostility};
+use crate::physics;
+
+/// Enemy AI plugin.
+pub struct EnemyAiPlugin;
+
+impl Plugin for EnemyAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                start_moving,
+                update_aggro,
+                patrol,
+                chase,
+                swoop,
+                flip_moving_sprites,
+            )
+                .chain()
+                .run_if(any_with_component::<Patrol>().or_else(any_with_component::<Aggro>()))
+                .after(EnemySystem::RegisterHits),
+        )
+        .add_systems(
+            FixedUpdate,
+            probe_ground.run_if(any_with_component::<Patrol>()),
+        );
+    }
+}
+
+/// Walks an enemy back and forth.
+///
+/// The enemy is made a kinematic body when this is added, so it doesn't fall;
+/// it moves along whatever it was placed on. The ground ahead is probed every
+/// fixed tick, and the enemy turns around before it walks off an edge.
+///
+/// Cliff walkers are made dynamic bodies instead, and walk off edges on
+/// purpose, falling to whatever is below.
+#[derive(Clone, Component, Debug)]
+pub struct Patrol {
+    /// How fast the enemy walks, in units per second.
+    pub speed: f32,
+    /// Where the enemy walks.
+    pub route: PatrolRoute,
+    /// Whether the enemy walks off edges instead of turning around.
+    pub cliff_walker: bool,
+    direction: Vec2,
+}
+
+impl Patrol {
+    /// How far ahead an enemy looks for walls and edges, past its collider.
+    pub const LOOKAHEAD: f32 = 2.;
+    /// How close an enemy has to be to a waypoint to go to the next one.
+    pub const WAYPOINT_RADIUS: f32 = 1.;
+
+    /// Creates a new `Patrol` that walks until it meets a wall or an edge,
+    /// then turns around.
+    pub fn walk(speed: f32) -> Patrol {
+        Patrol {
+            speed,
+            route: PatrolRoute::WallToEdge,
+            cliff_walker: false,
+            direction: Vec2::X,
+        }
+    }
+
+    /// Makes the enemy walk off edges instead of turning around.
+    pub fn walk_off_edges(self) -> Patrol {
+        Patrol {
+            cliff_walker: true,
+            ..self
+        }
+    }
+
+    /// Creates a new `Patrol` that walks between points, relative to where
+    /// the enemy starts, and loops back to the first.
+    pub fn waypoints(speed: f32, points: Vec<Vec2>) -> Patrol {
+        Patrol {
+            speed,
+            route: PatrolRoute::Waypoints {
+                points,
+                origin: None,
+                next: 0,
+            },
+            cliff_walker: false,
+            direction: Vec2::ZERO,
+        }
+    }
+
+    /// The direction the enemy is walking in.
+    pub fn direction(&self) -> Vec2 {
+        self.direction
+    }
+}
+
+/// Makes an enemy chase the [`LocalPlayer`](crate::player::LocalPlayer) when
+/// it is close.
+///
+/// A friendly enemy chases the closest hostile enemy instead (see
+/// [`EnemyTargets`]).
+///
+/// A chasing enemy stops patrolling. Like a [`Patrol`], the enemy is made a
+/// kinematic body when this is added; it only chases left and right, and
+/// stops at walls and edges.
+#[derive(Clone, Component, Debug)]
+pub struct Aggro {
+    /// How close the player has to be to be noticed.
+    pub range: f32,
+    /// Whether the enemy has to see the player, i.e. there is no level
+    /// collision in the way.
+    pub requires_los: bool,
+    /// How fast the enemy gets up to speed, in units per second squared.
+    pub acceleration: f32,
+    /// How fast the enemy chases.
+    pub speed: f32,
+    grace: Timer,
+    chasing: bool,
+    target: Option<Vec2>,
+}
+
+impl Aggro {
+    /// Creates a new `Aggro` that needs to see the player.
+    pub fn new(range: f32) -> Aggro {
+        Aggro {
+            range,
+            requires_los: true,
+            acceleration: 128.,
+            speed: 48.,
+            grace: Timer::new(Duration::from_secs(2), TimerMode::Once),
+            chasing: false,
+            target: None,
+        }
+    }
+
+    /// Sets how long the enemy keeps chasing after losing sight of the
+    /// player.
+    pub fn grace(self, grace: Duration) -> Aggro {
+        Aggro {
+            grace: Timer::new(grace, TimerMode::Once),
+            ..self
+        }
+    }
+
+    /// Checks if the enemy is chasing the player.
+    pub fn is_chasing(&self) -> bool {
+        self.chasing
+    }
+}
+
+/// Makes a flying enemy swoop at the player when its [`Aggro`] is chasing,
+/// instead of walking after them.
+///
+/// The enemy flies in a straight line toward where it saw the player, then
+/// flies back to where it started and hovers until it can swoop again.
+#[derive(Clone, Component, Debug)]
+pub struct Swoop {
+    /// How fast the enemy swoops, in units per second.
+    pub speed: f32,
+    /// How fast the enemy flies back, in units per second.
+    pub return_speed: f32,
+    duration: Timer,
+    cooldown: Timer,
+    home: Option<Vec2>,
+    state: SwoopState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SwoopState {
+    Hovering,
+    Swooping(Vec2),
+    Returning,
+}
+
+impl Swoop {
+    /// Creates a new `Swoop`.
+    pub fn new(speed: f32) -> Swoop {
+        Swoop {
+            speed,
+            return_speed: speed / 2.,
+            duration: Timer::new(Duration::from_millis(600), TimerMode::Once),
+            cooldown: Timer::new(Duration::from_secs(2), TimerMode::Once),
+            home: None,
+            state: SwoopState::Hovering,
+        }
+    }
+
+    /// Sets how long the enemy waits between swoops.
+    pub fn cooldown(self, cooldown: Duration) -> Swoop {
+        Swoop {
+            cooldown: Timer::new(cooldown, TimerMode::Once),
+            ..self
+        }
+    }
+
+    /// Checks if the enemy is swooping or flying back.
+    pub fn is_swooping(&self) -> bool {
+        self.state != SwoopState::Hovering
+    }
+}
+
+/// Where a [`Patrol`] goes.
+#[derive(Clone, Debug)]
+pub enum PatrolRoute {
+    /// Walk left and right, turning at walls and, unless the enemy is a cliff
+    /// walker, edges.
+    WallToEdge,
+    /// Walk between points.
+    Waypoints {
+        /// The points, relative to where the enemy started.
+        points: Vec<Vec2>,
+        /// Where the enemy started.
+        origin: Option<Vec2>,
+        /// The index of the next point.
+        next: usize,
+    },
+}
+
+fn start_moving(
+    mut commands: Commands,
+    added_query: Query<(Entity, Option<&Patrol>), Or<(Added<Patrol>, Added<Aggro>)>>,
+) {
+    for (entity, patrol) in added_query.iter() {
+        // cliff walkers need gravity to fall off things
+        let body = if patrol.is_some_and(|p| p.cliff_walker) {
+            RigidBody::Dynamic
+        } else {
+            RigidBody::KinematicVelocityBased
+        };
+
+        commands
+            .entity(entity)
+            .insert((body, Velocity::default(), LockedAxes::ROTATION_LOCKED));
+    }
+}
+
+fn patrol(
+    mut patrol_query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Collider,
+        &mut Patrol,
+        &mut Velocity,
+        Option<&Aggro>,
+        Option<&DeathTimer>,
+    )>,
+    physics: Res<RapierContext>,
+) {
+    for (entity, transform, collider, mut patrol, mut velocity, aggro, dying) in
+        patrol_query.iter_mut()
+    {
+        if dying.is_some() {
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        if aggro.is_some_and(Aggro::is_chasing) {
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let half_extents = collider
+            .as_cuboid()
+            .map(|c| c.half_extents())
+            .unwrap_or(Vec2::splat(8.));
+
+        let current = patrol.direction;
+
+        let direction = match &mut patrol.route {
+            PatrolRoute::WallToEdge => {
+                let direction = if current.x < 0. { -Vec2::X } else { Vec2::X };
+
+                // edges are left to `probe_ground`
+                if wall_ahead(&physics, entity, position, half_extents, direction) {
+                    -direction
+                } else {
+                    direction
+                }
+            }
+            PatrolRoute::Waypoints {
+                points,
+                origin,
+                next,
+            } => {
+                let origin = *origin.get_or_insert(position);
+
+                let Some(mut target) = points.get(*next).map(|p| origin + *p) else {
+                    velocity.linvel = Vec2::ZERO;
+                    continue;
+                };
+
+                if target.distance(position) <= Patrol::WAYPOINT_RADIUS {
+                    *next = (*next + 1) % points.len();
+                    target = origin + points[*next];
+                }
+
+                (target - position).normalize_or_zero()
+            }
+        };
+
+        patrol.direction = direction;
+
+        if patrol.cliff_walker {
+            // leave falling to gravity
+            velocity.linvel.x = direction.x * patrol.speed;
+        } else {
+            velocity.linvel = direction * patrol.speed;
+        }
+    }
+}
+
+fn probe_ground(
+    mut patrol_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Collider,
+            &mut Patrol,
+            Option<&Aggro>,
+        ),
+        Without<DeathTimer>,
+    >,
+    physics: Res<RapierContext>,
+) {
+    for (entity, transform, collider, mut patrol, aggro) in patrol_query.iter_mut() {
+        if patrol.cliff_walker
+            || !matches!(patrol.route, PatrolRoute::WallToEdge)
+            || aggro.is_some_and(Aggro::is_chasing)
+        {
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let half_extents = collider
+            .as_cuboid()
+            .map(|c| c.half_extents())
+            .unwrap_or(Vec2::splat(8.));
+
+        let direction = if patrol.direction.x < 0. {
+            -Vec2::X
+        } else {
+            Vec2::X
+        };
+
+        if !ground_ahead(&physics, entity, position, half_extents, direction) {
+            patrol.direction = -direction;
+        }
+    }
+}
+
+fn update_aggro(
+    mut aggro_query: Query<
+        (Entity, &GlobalTransform, &mut Aggro, Option<&Hostility>),
+        Without<DeathTimer>,
+    >,
+    targets: EnemyTargets,
+    physics: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut aggro, hostility) in aggro_query.iter_mut() {
+        let position = transform.translation().truncate();
+        let hostility = hostility.copied().unwrap_or(Hostility::Hostile);
+        let target = targets.closest(hostility, position);
+
+        let sees = target.is_some_and(|target| {
+            let offset = target - position;
+            let distance = offset.length();
+
+            if distance > aggro.range {
+                return false;
+            }
+
+            if !aggro.requires_los || distance == 0. {
+                return true;
+            }
+
+            // anything solid in the way blocks the view
+            physics
+                .cast_ray(
+                    position,
+                    offset / distance,
+                    distance,
+                    true,
+                    solid_filter(entity),
+                )
+                .is_none()
+        });
+
+        if sees {
+            aggro.chasing = true;
+            aggro.target = target;
+            aggro.grace.reset();
+        } else if aggro.chasing && aggro.grace.tick(time.delta()).finished() {
+            aggro.chasing = false;
+            aggro.target = None;
+        }
+    }
+}
+
+fn chase(
+    mut chase_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Collider,
+            &Aggro,
+            &mut Velocity,
+            Option<&Patrol>,
+        ),
+        (Without<DeathTimer>, Without<Swoop>),
+    >,
+    physics: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    for (entity, transform, collider, aggro, mut velocity, patrol) in chase_query.iter_mut() {
+        if !aggro.is_chasing() {
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let half_extents = collider
+            .as_cuboid()
+            .map(|c| c.half_extents())
+            .unwrap_or(Vec2::splat(8.));
+
+        // keep going toward where the player was last seen
+        let direction = match aggro.target {
+            Some(target) if (target.x - position.x).abs() > 1. => {
+                Vec2::new((target.x - position.x).signum(), 0.)
+            }
+            _ => Vec2::ZERO,
+        };
+
+        let cliff_walker = patrol.is_some_and(|p| p.cliff_walker);
+
+        let desired = if direction != Vec2::ZERO
+            && !wall_ahead(&physics, entity, position, half_extents, direction)
+            && (cliff_walker || ground_ahead(&physics, entity, position, half_extents, direction))
+        {
+            direction * aggro.speed
+        } else {
+            Vec2::ZERO
+        };
+
+        let step = aggro.acceleration * time.delta_seconds();
+
+        if cliff_walker {
+            // leave falling to gravity
+            velocity.linvel.x += (desired.x - velocity.linvel.x).clamp(-step, step);
+        } else {
+            velocity.linvel += (desired - velocity.linvel).clamp_length_max(step);
+        }
+    }
+}
+
+fn swoop(
+    mut swoop_query: Query<(
+        &GlobalTransform,
+        &Aggro,
+        &mut Swoop,
+        &mut Velocity,
+        Option<&DeathTimer>,
+    )>,
+    time: Res<Time>,
+) {
+    for (transform, aggro, mut swoop, mut velocity, dying) in swoop_query.iter_mut() {
+        if dying.is_some() {
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let home = *swoop.home.get_or_insert(position);
+
+        swoop.cooldown.tick(time.delta());
+
+        match swoop.state {
+            SwoopState::Hovering => {
+                // the hover bobs up and down, only stop drifting sideways
+                velocity.linvel.x = 0.;
+
+                let Some(target) = aggro.target.filter(|_| aggro.is_chasing()) else {
+                    continue;
+                };
+
+                if swoop.cooldown.finished() {
+                    let direction = (target - position).normalize_or_zero();
+
+                    swoop.state = SwoopState::Swooping(direction);
+                    swoop.duration.reset();
+                }
+            }
+            SwoopState::Swooping(direction) => {
+                velocity.linvel = direction * swoop.speed;
+
+                if swoop.duration.tick(time.delta()).finished() {
+                    swoop.state = SwoopState::Returning;
+                }
+            }
+            SwoopState::Returning => {
+                let offset = home - position;
+
+                if offset.length() <= Patrol::WAYPOINT_RADIUS {
+                    velocity.linvel = Vec2::ZERO;
+                    swoop.state = SwoopState::Hovering;
+                    swoop.cooldown.reset();
+                } else {
+                    velocity.linvel = offset.clamp_length_max(swoop.return_speed);
+                }
+            }
+        }
+    }
+}
+
+fn flip_moving_sprites(
+    mut query: Query<(&Velocity, &mut TextureAtlasSprite), Or<(With<Patrol>, With<Aggro>)>>,
+) {
+    for (velocity, mut sprite) in query.iter_mut() {
+        let flip_x = velocity.linvel.x < 0.;
+
+        // do not trip change detection
+        if velocity.linvel.x != 0. && sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+    }
+}
+
+fn solid_filter(entity: Entity) -> QueryFilter<'static> {
+    QueryFilter::new()
+        .groups(CollisionGroups::new(
+            Group::all(),
+            physics::COLLISION_GROUP_SOLID,
+        ))
+        .exclude_collider(entity)
+        .exclude_sensors()
+}
+
+/// Checks if there is a wall ahead of an enemy.
+fn wall_ahead(
+    physics: &RapierContext,
+    entity: Entity,
+    position: Vec2,
+    half_extents: Vec2,
+    direction: Vec2,
+) -> bool {
+    let reach = half_extents.x + Patrol::LOOKAHEAD;
+
+    physics
+        .cast_ray(position, direction, reach, true, solid_filter(entity))
+        .is_some()
+}
+
+/// Checks if there is ground to walk on just ahead of an enemy, by casting a
+/// ray down from past the front of its collider.
+fn ground_ahead(
+    physics: &RapierContext,
+    entity: Entity,
+    position: Vec2,
+    half_extents: Vec2,
+    direction: Vec2,
+) -> bool {
+    let reach = half_extents.x + Patrol::LOOKAHEAD;
+
+    physics
+        .cast_ray(
+            position + direction * reach,
+            -Vec2::Y,
+            half_extents.y + Patrol::LOOKAHEAD,
+            true,
+            solid_filter(entity),
+        )
+        .is_some()
+}
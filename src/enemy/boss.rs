@@ -0,0 +1,558 @@
+//! Bosses.
+//!
+//! A [`Boss`] fights in [`BossPhase`]s. Each phase has its own fire pattern,
+//! movement and vulnerability windows, and the boss moves on to the next one
+//! as its health runs out. The fight starts when the player comes close, and
+//! [`BossPhaseEvent`]s and a [`BossDefeatedEvent`] are sent as it goes, so
+//! the camera, music and UI can follow along.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::time::Duration;
+
+use super::prefab::EnemyPrefab;
+use super::{
+    ActivateOnDeathByIid, AimAtPlayer, DeathTimer, Enemy, EnemyBundle, EnemySystem, RespawnPolicy,
+};
+use crate::combat::Health;
+use crate::fx::tint::{PushTint, TintStack};
+use crate::level::Iid;
+use crate::player::LocalPlayer;
+use crate::projectile::prefab::ProjectilePrefab;
+use crate::projectile::spawner::{Charge, FirePattern, Spawner};
+use crate::projectile::split::SplitOnAbsorb;
+
+/// Boss plugin.
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BossPhaseEvent>()
+            .add_event::<BossDefeatedEvent>()
+            .register_ldtk_entity::<BossBundle>("Boss")
+            .add_systems(
+                Update,
+                (
+                    engage_bosses,
+                    advance_boss_phases,
+                    move_bosses,
+                    open_boss_windows,
+                    defeat_bosses,
+                )
+                    .chain()
+                    .after(EnemySystem::RegisterHits)
+                    .distributive_run_if(any_with_component::<Boss>()),
+            );
+    }
+}
+
+/// A boss.
+///
+/// Bosses sit still and hold their fire until the player comes within
+/// [`Boss::engage_range`].
+#[derive(Clone, Component, Debug)]
+pub struct Boss {
+    /// The phases of the fight, in order.
+    pub phases: Vec<BossPhase>,
+    /// How close the player has to be to start the fight.
+    pub engage_range: f32,
+    phase: Option<usize>,
+    elapsed: f32,
+    window: Timer,
+    vulnerable: bool,
+    home: Option<Vec3>,
+}
+
+impl Boss {
+    /// How close the player has to be if the level doesn't say.
+    pub const DEFAULT_ENGAGE_RANGE: f32 = 160.;
+    /// The health of bosses if the level doesn't say.
+    pub const DEFAULT_HEALTH: f32 = 30.;
+    /// The key of the tint of a boss with a closed window.
+    pub const CLOSED_TINT: &'static str = "closed window";
+
+    /// Creates a new `Boss`.
+    pub fn new(phases: Vec<BossPhase>) -> Boss {
+        Boss {
+            phases,
+            engage_range: Boss::DEFAULT_ENGAGE_RANGE,
+            phase: None,
+            elapsed: 0.,
+            window: Timer::default(),
+            vulnerable: true,
+            home: None,
+        }
+    }
+
+    /// Sets how close the player has to be to start the fight.
+    pub fn engage_range(self, engage_range: f32) -> Boss {
+        Boss {
+            engage_range,
+            ..self
+        }
+    }
+
+    /// The index of the current phase, or `None` if the fight hasn't started.
+    pub fn phase(&self) -> Option<usize> {
+        self.phase
+    }
+
+    /// The current phase, or `None` if the fight hasn't started.
+    pub fn current_phase(&self) -> Option<&BossPhase> {
+        self.phase.and_then(|phase| self.phases.get(phase))
+    }
+
+    /// Checks if the fight has started.
+    pub fn is_engaged(&self) -> bool {
+        self.phase.is_some()
+    }
+
+    /// Checks if the boss can be hurt right now.
+    pub fn is_vulnerable(&self) -> bool {
+        self.vulnerable
+    }
+
+    /// The phases of a boss placed in a level.
+    ///
+    /// The boss sways back and forth firing spreads, then starts bursting
+    /// with windows where it can't be hurt, then fires rings all around
+    /// itself with shorter windows, notes that burst into more notes when they
+    /// land.
+    pub fn default_phases() -> Vec<BossPhase> {
+        vec![
+            BossPhase {
+                until_health: 0.66,
+                pattern: FirePattern::Spread { count: 3, arc: 0.6 },
+                fire_interval: Duration::from_millis(1500),
+                projectile_speed: 64.,
+                movement: BossMovement::Sway {
+                    extent: Vec2::new(48., 0.),
+                    period: 4.,
+                },
+                windows: None,
+                split: None,
+            },
+            BossPhase {
+                until_health: 0.33,
+                pattern: FirePattern::Burst {
+                    count: 4,
+                    interval: Duration::from_millis(150),
+                },
+                fire_interval: Duration::from_secs(2),
+                projectile_speed: 96.,
+                movement: BossMovement::Sway {
+                    extent: Vec2::new(64., 16.),
+                    period: 3.,
+                },
+                windows: Some(VulnerabilityWindows {
+                    open: Duration::from_secs(2),
+                    closed: Duration::from_millis(1500),
+                }),
+                split: None,
+            },
+            BossPhase {
+                until_health: 0.,
+                pattern: FirePattern::Radial { count: 8 },
+                fire_interval: Duration::from_millis(1200),
+                projectile_speed: 72.,
+                movement: BossMovement::Sway {
+                    extent: Vec2::new(80., 24.),
+                    period: 2.5,
+                },
+                windows: Some(VulnerabilityWindows {
+                    open: Duration::from_millis(1500),
+                    closed: Duration::from_secs(2),
+                }),
+                split: Some(SplitOnAbsorb {
+                    prefab: ProjectilePrefab::QuarterRest {
+                        initial_velocity: Vec2::ZERO,
+                    },
+                    count: 3,
+                    spread: std::f32::consts::FRAC_PI_2,
+                }),
+            },
+        ]
+    }
+
+    fn enter_phase(&mut self, phase: usize) {
+        self.phase = Some(phase);
+        self.elapsed = 0.;
+        self.vulnerable = true;
+
+        if let Some(windows) = self.current_phase().and_then(|p| p.windows) {
+            self.window = Timer::new(windows.open, TimerMode::Once);
+        }
+    }
+}
+
+impl Default for Boss {
+    fn default() -> Boss {
+        Boss::new(Boss::default_phases())
+    }
+}
+
+/// A single phase of a [`Boss`] fight.
+#[derive(Clone, Debug)]
+pub struct BossPhase {
+    /// The boss moves on to the next phase once its health is at or under
+    /// this fraction of its max.
+    pub until_health: f32,
+    /// How the boss fires.
+    pub pattern: FirePattern,
+    /// The time between volleys.
+    pub fire_interval: Duration,
+    /// The speed of the boss's projectiles.
+    pub projectile_speed: f32,
+    /// How the boss moves.
+    pub movement: BossMovement,
+    /// When the boss can be hurt, if not always.
+    pub windows: Option<VulnerabilityWindows>,
+    /// Whether the boss's notes burst into more notes when they are absorbed.
+    pub split: Option<SplitOnAbsorb>,
+}
+
+/// How a [`Boss`] moves during a phase, around where it was when the fight
+/// started.
+#[derive(Clone, Debug, Default)]
+pub enum BossMovement {
+    /// Stays put.
+    #[default]
+    Hold,
+    /// Sways side to side by `extent.x` and bobs by `extent.y`, twice as
+    /// fast, making a figure eight. Takes `period` seconds to sway once.
+    Sway { extent: Vec2, period: f32 },
+    /// Loops through points at a speed, in world units per second.
+    Path { points: Vec<Vec2>, speed: f32 },
+}
+
+impl BossMovement {
+    /// Where the boss is after some time in the phase, relative to where the
+    /// fight started.
+    pub fn offset(&self, elapsed: f32) -> Vec2 {
+        match self {
+            BossMovement::Hold => Vec2::ZERO,
+            BossMovement::Sway { extent, period } => {
+                let t = elapsed * std::f32::consts::TAU / period.max(f32::EPSILON);
+
+                Vec2::new(extent.x * t.sin(), extent.y * (2. * t).sin())
+            }
+            BossMovement::Path { points, speed } => path_offset(points, elapsed * speed),
+        }
+    }
+}
+
+/// When a [`Boss`] can be hurt during a phase.
+///
+/// The boss is open to damage for `open`, then shrugs it off for `closed`,
+/// over and over.
+#[derive(Clone, Copy, Debug)]
+pub struct VulnerabilityWindows {
+    /// How long the boss can be hurt for.
+    pub open: Duration,
+    /// How long the boss can't be hurt for.
+    pub closed: Duration,
+}
+
+/// A boss fight started or moved on to a new phase.
+#[derive(Clone, Debug, Event)]
+pub struct BossPhaseEvent {
+    /// The boss.
+    pub boss: Entity,
+    /// The index of the new phase.
+    pub phase: usize,
+    /// The index of the last phase, or `None` if the fight just started.
+    pub previous: Option<usize>,
+}
+
+/// A boss was beaten.
+///
+/// This is sent as the boss starts dying, before the [`EnemyDiedEvent`].
+///
+/// [`EnemyDiedEvent`]: super::EnemyDiedEvent
+#[derive(Clone, Debug, Event)]
+pub struct BossDefeatedEvent {
+    /// The boss.
+    pub boss: Entity,
+}
+
+/// A boss.
+#[derive(Bundle)]
+pub struct BossBundle {
+    enemy_bundle: EnemyBundle,
+    enemy_prefab: EnemyPrefab,
+    texture_atlas: Handle<TextureAtlas>,
+    sprite: TextureAtlasSprite,
+    activate_on_death: ActivateOnDeathByIid,
+    boss: Boss,
+    iid: Iid,
+    respawn: RespawnPolicy,
+}
+
+impl BossBundle {
+    /// Creates a new `BossBundle` with the [default phases].
+    ///
+    /// [default phases]: Boss::default_phases
+    pub fn new(transform: Transform) -> BossBundle {
+        BossBundle {
+            enemy_bundle: EnemyBundle {
+                transform,
+                collider: Collider::cuboid(16., 16.),
+                health: Health::new(Boss::DEFAULT_HEALTH),
+                ..Default::default()
+            },
+            enemy_prefab: EnemyPrefab::Boss,
+            texture_atlas: Default::default(),
+            sprite: TextureAtlasSprite {
+                custom_size: Some(EnemyPrefab::Boss.icon_size() * 2.),
+                ..Default::default()
+            },
+            activate_on_death: ActivateOnDeathByIid(None),
+            boss: Boss::default(),
+            iid: Iid::default(),
+            respawn: RespawnPolicy::default(),
+        }
+    }
+}
+
+impl LdtkEntity for BossBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let activate_ref = entity_instance
+            .get_maybe_entity_ref_field("ActivateOnDeath")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        let float_field = |name: &str| {
+            entity_instance
+                .get_maybe_float_field(name)
+                .ok()
+                .copied()
+                .flatten()
+        };
+
+        let health = float_field("Health").unwrap_or(Boss::DEFAULT_HEALTH);
+        let engage_range = float_field("EngageRange").unwrap_or(Boss::DEFAULT_ENGAGE_RANGE);
+
+        let mut bundle = BossBundle::new(Transform::default());
+
+        bundle.enemy_bundle.health = Health::new(health);
+        bundle.activate_on_death = ActivateOnDeathByIid(activate_ref);
+        bundle.boss = bundle.boss.engage_range(engage_range);
+        bundle.iid = Iid::from(entity_instance);
+        bundle.respawn = RespawnPolicy::from_entity_instance(entity_instance);
+
+        bundle
+    }
+}
+
+fn path_offset(points: &[Vec2], distance: f32) -> Vec2 {
+    let Some(&first) = points.first() else {
+        return Vec2::ZERO;
+    };
+
+    let segments = || {
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(&a, &b)| (a, b))
+    };
+
+    let length = segments().map(|(a, b)| a.distance(b)).sum::<f32>();
+
+    if length <= f32::EPSILON {
+        return first;
+    }
+
+    let mut distance = distance.rem_euclid(length);
+
+    for (a, b) in segments() {
+        let segment = a.distance(b);
+
+        if distance <= segment {
+            return a.lerp(b, distance / segment.max(f32::EPSILON));
+        }
+
+        distance -= segment;
+    }
+
+    first
+}
+
+fn arm_boss(commands: &mut Commands, entity: Entity, phase: &BossPhase) {
+    let mut spawner = Spawner::default();
+    spawner.pattern = phase.pattern;
+    spawner.split = phase.split.clone();
+
+    commands.entity(entity).insert((
+        spawner,
+        Charge::new(phase.fire_interval, 1),
+        AimAtPlayer {
+            speed: phase.projectile_speed,
+            range: None,
+        },
+    ));
+}
+
+fn engage_bosses(
+    mut commands: Commands,
+    mut boss_query: Query<(Entity, &GlobalTransform, &Transform, &mut Boss), Without<DeathTimer>>,
+    player_query: Query<&GlobalTransform, With<LocalPlayer>>,
+    mut phase_events: EventWriter<BossPhaseEvent>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+
+    let player = player.translation().truncate();
+
+    for (entity, global_transform, transform, mut boss) in boss_query.iter_mut() {
+        if boss.is_engaged() || boss.phases.is_empty() {
+            continue;
+        }
+
+        let position = global_transform.translation().truncate();
+
+        if position.distance_squared(player) > boss.engage_range * boss.engage_range {
+            continue;
+        }
+
+        boss.home = Some(transform.translation);
+        boss.enter_phase(0);
+        arm_boss(&mut commands, entity, &boss.phases[0]);
+
+        phase_events.send(BossPhaseEvent {
+            boss: entity,
+            phase: 0,
+            previous: None,
+        });
+
+        crate::game_info!(Enemy, "{:?} engaged the player", entity);
+    }
+}
+
+fn advance_boss_phases(
+    mut commands: Commands,
+    mut boss_query: Query<(Entity, &Health, &mut Boss), Without<DeathTimer>>,
+    mut phase_events: EventWriter<BossPhaseEvent>,
+) {
+    for (entity, health, mut boss) in boss_query.iter_mut() {
+        let Some(previous) = boss.phase else {
+            continue;
+        };
+
+        let left = health.current / health.max.max(f32::EPSILON);
+
+        // big hits can skip phases
+        let mut phase = previous;
+
+        while phase + 1 < boss.phases.len() && left <= boss.phases[phase].until_health {
+            phase += 1;
+        }
+
+        if phase == previous {
+            continue;
+        }
+
+        boss.enter_phase(phase);
+        arm_boss(&mut commands, entity, &boss.phases[phase]);
+
+        phase_events.send(BossPhaseEvent {
+            boss: entity,
+            phase,
+            previous: Some(previous),
+        });
+
+        crate::game_info!(Enemy, "{:?} entered phase {}", entity, phase);
+    }
+}
+
+fn move_bosses(
+    mut boss_query: Query<(&mut Transform, &mut Boss), Without<DeathTimer>>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut boss) in boss_query.iter_mut() {
+        let Some(home) = boss.home else {
+            continue;
+        };
+
+        boss.elapsed += time.delta_seconds();
+
+        let Some(phase) = boss.current_phase() else {
+            continue;
+        };
+
+        let offset = phase.movement.offset(boss.elapsed);
+
+        transform.translation = home + offset.extend(0.);
+    }
+}
+
+fn open_boss_windows(
+    mut commands: Commands,
+    mut boss_query: Query<
+        (Entity, &mut Boss, &mut Enemy, Option<&mut TintStack>),
+        Without<DeathTimer>,
+    >,
+    time: Res<Time>,
+) {
+    for (entity, mut boss, mut enemy, tint_stack) in boss_query.iter_mut() {
+        // phases without windows are open the whole time
+        if let Some(windows) = boss.current_phase().and_then(|p| p.windows) {
+            boss.window.tick(time.delta());
+
+            if boss.window.finished() {
+                boss.vulnerable = !boss.vulnerable;
+
+                let duration = if boss.vulnerable {
+                    windows.open
+                } else {
+                    windows.closed
+                };
+
+                boss.window = Timer::new(duration, TimerMode::Once);
+            }
+        }
+
+        // do not trip change detection
+        if enemy.invincible == boss.vulnerable {
+            enemy.invincible = !boss.vulnerable;
+
+            match (boss.vulnerable, tint_stack) {
+                (true, Some(mut tint_stack)) => tint_stack.remove(Boss::CLOSED_TINT),
+                (true, None) => (),
+                (false, _) => commands.add(PushTint::new(
+                    entity,
+                    Boss::CLOSED_TINT,
+                    TintStack::STATUS,
+                    Color::GRAY,
+                )),
+            }
+        }
+    }
+}
+
+fn defeat_bosses(
+    boss_query: Query<Entity, (With<Boss>, Added<DeathTimer>)>,
+    mut defeated_events: EventWriter<BossDefeatedEvent>,
+) {
+    for entity in boss_query.iter() {
+        defeated_events.send(BossDefeatedEvent { boss: entity });
+
+        crate::game_info!(Enemy, "{:?} was defeated", entity);
+    }
+}
@@ -1,15 +1,35 @@
 //! Enemy things.
 
+pub mod ai;
+pub mod boss;
 pub mod prefab;
+pub mod shield;
+pub mod spawner;
 
+use bevy::ecs::query::Has;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
 use bevy_rapier2d::prelude::*;
 
+use bevy_ecs_ldtk::{ldtk::ldtk_fields::LdtkFields as _, EntityInstance};
+
+use self::prefab::EnemyPrefab;
+use crate::combat::{CombatSystem, DamageEvent, DamageKind, Health};
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::fx::absorb::{Absorb, FlashSprite};
+use crate::fx::particles::{ParticleSpec, SpawnParticles};
+use crate::fx::tint::{PushTint, TintStack};
 use crate::level::Iid;
 use crate::physics;
+use crate::pickup::{Pickup, PickupBundle};
 use crate::platform::ActivateEvent;
-use crate::projectile::{HitEvent, Projectile, ProjectileSystem};
+use crate::player::LocalPlayer;
+use crate::projectile::{
+    spawner::{Charge, SpawnProjectile, Spawner, SpawnerSystem},
+    HitEvent, Pierce, Projectile, ProjectileSystem,
+};
+use crate::rng::GameRng;
 
 use std::time::Duration;
 
@@ -18,10 +38,14 @@ pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, upgrade_activate_on_death)
+        app.add_event::<EnemyDiedEvent>()
+            .add_event::<EnemyConvertedEvent>()
+            .add_systems(Update, upgrade_activate_on_death)
             .add_systems(
                 Update,
-                despawn_dead_enemies.before(EnemySystem::RegisterHits),
+                despawn_dead_enemies
+                    .in_set(EnemySystem::Despawn)
+                    .before(EnemySystem::RegisterHits),
             )
             .add_systems(
                 Update,
@@ -31,13 +55,32 @@ impl Plugin for EnemyPlugin {
                     .after(ProjectileSystem::Bounce)
                     .after(ProjectileSystem::Event),
             )
-            .add_systems(Update, tint_dying_enemies.after(EnemySystem::RegisterHits));
+            .add_systems(
+                Update,
+                damage_enemies
+                    .in_set(EnemySystem::RegisterHits)
+                    .after(CombatSystem::Damage)
+                    .after(check_for_enemy_hits),
+            )
+            .add_systems(
+                Update,
+                (start_death_sequences, dissolve_dying_enemies)
+                    .chain()
+                    .after(EnemySystem::RegisterHits),
+            )
+            .add_systems(
+                Update,
+                aim_at_player
+                    .run_if(any_with_component::<AimAtPlayer>())
+                    .after(SpawnerSystem::TickTimer)
+                    .before(SpawnerSystem::Spawn),
+            );
     }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
 pub enum EnemySystem {
-    /// Register hits on enemies.
+    /// Register hits and damage on enemies.
     RegisterHits,
     /// Despawns dead enemies.
     Despawn,
@@ -54,6 +97,7 @@ pub struct EnemyBundle {
     pub collision_groups: CollisionGroups,
     pub hostility: Hostility,
     pub enemy: Enemy,
+    pub health: Health,
 }
 
 impl Default for EnemyBundle {
@@ -67,6 +111,7 @@ impl Default for EnemyBundle {
             collision_groups: CollisionGroups::new(physics::COLLISION_GROUP_HOSTILE, Group::all()),
             hostility: Hostility::Hostile,
             enemy: Enemy::default(),
+            health: Health::default(),
         }
     }
 }
@@ -87,6 +132,148 @@ impl Enemy {
     }
 }
 
+/// An enemy died.
+///
+/// The enemy is despawned when this is sent, so everything about it that
+/// might be interesting is copied here.
+#[derive(Debug, Event)]
+pub struct EnemyDiedEvent {
+    /// The enemy.
+    pub entity: Entity,
+    /// What kind of enemy it was, if it came from a prefab.
+    pub prefab: Option<EnemyPrefab>,
+    /// Where the enemy died.
+    pub position: Vec2,
+    /// The hostility of the enemy.
+    pub hostility: Hostility,
+    /// The instance identifier of the enemy, if it was placed in a level.
+    pub iid: Option<String>,
+    /// Whether the enemy comes back when the world respawns.
+    pub respawn: RespawnPolicy,
+    /// How far to look for a pipe to send a signal into, if the enemy sends
+    /// one. See [`SignalOnDeath`].
+    pub signal_radius: Option<f32>,
+}
+
+/// A hostile enemy was won over by a friendly projectile.
+///
+/// See [`Convertible`].
+#[derive(Debug, Event)]
+pub struct EnemyConvertedEvent {
+    /// The enemy.
+    pub entity: Entity,
+}
+
+/// Lets a hostile enemy be won over.
+///
+/// Instead of being hurt by a friendly projectile, the enemy turns
+/// [`Hostility::Friendly`], like a signal recolored by a pipe. It then takes
+/// the friendly collision group, shoots friendly projectiles and goes after
+/// the other enemies.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Convertible;
+
+/// Finds what enemies go after.
+///
+/// Hostile enemies go after the [`LocalPlayer`]. Friendly enemies, like
+/// [`Convertible`] ones that were won over, go after the closest hostile
+/// enemy.
+#[derive(SystemParam)]
+pub struct EnemyTargets<'w, 's> {
+    player_query: Query<'w, 's, &'static GlobalTransform, With<LocalPlayer>>,
+    enemy_query: Query<
+        'w,
+        's,
+        (&'static GlobalTransform, &'static Hostility),
+        (With<Enemy>, Without<DeathTimer>),
+    >,
+}
+
+impl<'w, 's> EnemyTargets<'w, 's> {
+    /// The position of the target of an enemy of some hostility at a
+    /// position, if there is any.
+    pub fn closest(&self, hostility: Hostility, position: Vec2) -> Option<Vec2> {
+        match hostility {
+            Hostility::Hostile => self
+                .player_query
+                .get_single()
+                .ok()
+                .map(|t| t.translation().truncate()),
+            Hostility::Friendly => self
+                .enemy_query
+                .iter()
+                .filter(|(_, hostility)| **hostility == Hostility::Hostile)
+                .map(|(t, _)| t.translation().truncate())
+                .min_by(|a, b| {
+                    a.distance_squared(position)
+                        .total_cmp(&b.distance_squared(position))
+                }),
+        }
+    }
+}
+
+/// Makes an enemy with a [`Spawner`] and [`Charge`] shoot at the player
+/// whenever it has a charge.
+///
+/// Friendly enemies shoot at their [target](EnemyTargets) instead.
+#[derive(Clone, Component, Debug)]
+pub struct AimAtPlayer {
+    /// The speed of the projectiles.
+    pub speed: f32,
+    /// How close the player has to be for the enemy to shoot, if at all.
+    pub range: Option<f32>,
+}
+
+impl Default for AimAtPlayer {
+    fn default() -> AimAtPlayer {
+        AimAtPlayer {
+            speed: 64.,
+            range: None,
+        }
+    }
+}
+
+/// Whether an enemy placed in a level comes back after it is killed and the
+/// world respawns.
+///
+/// The whole world is spawned again when the player respawns, so every enemy
+/// comes back by default. Enemies that activate something on death should
+/// always come back, or whatever they activate is reset without them.
+///
+/// See [`FallenEnemies`](crate::player::respawn::FallenEnemies).
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RespawnPolicy {
+    /// Stays dead once killed.
+    Never,
+    /// Comes back if it was killed since the player last reached a
+    /// checkpoint, and stays dead otherwise.
+    OnCheckpoint,
+    /// Always comes back.
+    #[default]
+    Always,
+}
+
+impl RespawnPolicy {
+    /// Reads the `Respawn` field of an LDtk entity, one of `Never`,
+    /// `OnCheckpoint` or `Always`.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> RespawnPolicy {
+        let policy = entity_instance
+            .get_maybe_enum_field("Respawn")
+            .ok()
+            .and_then(|policy| policy.as_deref());
+
+        match policy {
+            Some("Never") => RespawnPolicy::Never,
+            Some("OnCheckpoint") => RespawnPolicy::OnCheckpoint,
+            Some("Always") | None => RespawnPolicy::Always,
+            Some(other) => {
+                crate::game_warn!(Level, "unknown respawn policy {}", other);
+                RespawnPolicy::Always
+            }
+        }
+    }
+}
+
 /// Sends an [`ActivateEvent`] on death.
 #[derive(Clone, Component, Debug, Default)]
 pub struct ActivateOnDeath(Option<Entity>);
@@ -96,12 +283,100 @@ pub struct ActivateOnDeath(Option<Entity>);
 pub struct ActivateOnDeathByIid(Option<String>);
 
 /// A timer for an enemy to [die](https://youtu.be/h3k5EAN97wE).
+///
+/// Dying enemies flash, burst into particles of their [`Hostility`] color and
+/// fade away before they are despawned.
 #[derive(Clone, Component, Debug)]
 pub struct DeathTimer(Timer);
 
+impl DeathTimer {
+    /// How long enemies take to die.
+    pub const DURATION: Duration = Duration::from_millis(350);
+    /// How long dying enemies flash before they fade.
+    pub const FLASH: Duration = Duration::from_millis(100);
+    /// How many particles enemies burst into.
+    pub const PARTICLES: u32 = 12;
+    /// The key of the dying tint.
+    pub const TINT: &'static str = "death";
+}
+
 impl Default for DeathTimer {
     fn default() -> DeathTimer {
-        DeathTimer(Timer::new(Duration::from_millis(100), TimerMode::Once))
+        DeathTimer(Timer::new(DeathTimer::DURATION, TimerMode::Once))
+    }
+}
+
+/// Gives an enemy a chance of leaving a [`Pickup`] behind when it dies.
+#[derive(Clone, Component, Debug)]
+pub struct DropOnDeath {
+    /// The chance of a drop, from `0.` to `1.`.
+    pub chance: f32,
+    /// What is dropped.
+    pub pickup: Pickup,
+}
+
+impl DropOnDeath {
+    /// Reads a drop from the fields of an LDtk entity.
+    ///
+    /// Enemies only drop things if their `DropChance` field is set.
+    /// `DropKind` is one of `Health`, `Charge` or `Points`, and `DropPoints`
+    /// is how much health or how many points the drop gives.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> DropOnDeath {
+        let float_field = |name: &str| {
+            entity_instance
+                .get_maybe_float_field(name)
+                .ok()
+                .copied()
+                .flatten()
+        };
+
+        let kind = entity_instance
+            .get_maybe_enum_field("DropKind")
+            .ok()
+            .and_then(|kind| kind.as_deref());
+
+        DropOnDeath {
+            chance: float_field("DropChance").unwrap_or(DropOnDeath::default().chance),
+            pickup: Pickup::from_fields(kind, float_field("DropPoints")),
+        }
+    }
+}
+
+impl Default for DropOnDeath {
+    fn default() -> DropOnDeath {
+        DropOnDeath {
+            chance: 0.,
+            pickup: Pickup::default(),
+        }
+    }
+}
+
+/// Sends a signal into the pipes near an enemy when it dies, as though the
+/// note it was made of went back into the network.
+///
+/// The signal goes into the closest junction within
+/// [`SignalOnDeath::radius`], and takes the other [`Hostility`] from the
+/// enemy: a hostile enemy gives back a friendly signal, and an enemy that was
+/// won over gives back a hostile one.
+#[derive(Clone, Component, Debug, Default)]
+pub struct SignalOnDeath {
+    /// How far to look for a junction, in world units. Enemies with a radius
+    /// of `0.` send nothing.
+    pub radius: f32,
+}
+
+impl SignalOnDeath {
+    /// Reads the `SignalRadius` field of an LDtk entity.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> SignalOnDeath {
+        SignalOnDeath {
+            radius: entity_instance
+                .get_maybe_float_field("SignalRadius")
+                .ok()
+                .copied()
+                .flatten()
+                .unwrap_or_default()
+                .max(0.),
+        }
     }
 }
 
@@ -114,6 +389,9 @@ pub enum Hostility {
 }
 
 impl Hostility {
+    /// The key of the tint of a projectile's hostility color.
+    pub const TINT: &'static str = "hostility";
+
     /// Returns the collision groups appropriate for a projectile of this
     /// hostility.
     pub fn collision_groups_projectile(self) -> CollisionGroups {
@@ -129,6 +407,32 @@ impl Hostility {
         }
     }
 
+    /// Returns the collision group of units, like enemies or the player, of
+    /// this hostility.
+    pub fn collision_group(self) -> Group {
+        match self {
+            Hostility::Friendly => physics::COLLISION_GROUP_FRIENDLY,
+            Hostility::Hostile => physics::COLLISION_GROUP_HOSTILE,
+        }
+    }
+
+    /// Finds a hostility by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<Hostility> {
+        match name {
+            "Friendly" => Some(Hostility::Friendly),
+            "Hostile" => Some(Hostility::Hostile),
+            _ => None,
+        }
+    }
+
+    /// Returns the other `Hostility`.
+    pub const fn opposite(self) -> Hostility {
+        match self {
+            Hostility::Friendly => Hostility::Hostile,
+            Hostility::Hostile => Hostility::Friendly,
+        }
+    }
+
     /// Returns the associated color of the `Hostility`.
     pub const fn color(self) -> Color {
         match self {
@@ -170,36 +474,205 @@ fn upgrade_activate_on_death(
 fn check_for_enemy_hits(
     mut commands: Commands,
     mut projectile_hit_events: EventReader<HitEvent>,
-    mut projectile_query: Query<&mut Projectile>,
-    enemies_query: Query<(Entity, &Enemy), Without<DeathTimer>>,
+    mut projectile_query: Query<(&mut Projectile, Option<&mut Pierce>, Option<&mut Velocity>)>,
+    enemies_query: Query<
+        (
+            Entity,
+            &Enemy,
+            Option<&Health>,
+            Option<&Hostility>,
+            Has<Convertible>,
+        ),
+        Without<DeathTimer>,
+    >,
 ) {
     for ev in projectile_hit_events.iter() {
-        let Ok((enemy_entity, enemy)) = enemies_query.get(ev.entity) else {
+        let Ok((enemy_entity, enemy, health, hostility, convertible)) =
+            enemies_query.get(ev.entity)
+        else {
+            continue;
+        };
+
+        // won over, not killed
+        let converts = convertible && hostility == Some(&Hostility::Hostile);
+
+        let mut pierced = false;
+
+        if let Ok((mut projectile, pierce, velocity)) = projectile_query.get_mut(ev.projectile) {
+            // only pierce through enemies the hit is going to kill
+            let lethal = !enemy.invincible
+                && !converts
+                && health.map_or(true, |health| health.current <= projectile.strength);
+
+            match pierce {
+                Some(mut pierce) if pierce.remaining > 0 && lethal => {
+                    pierce.remaining -= 1;
+                    pierced = true;
+                    projectile.absorbed = false;
+
+                    // carry on as if nothing was in the way
+                    if let Some(mut velocity) = velocity {
+                        velocity.linvel = projectile.incoming_velocity();
+                    }
+                }
+                // despawn projectile
+                _ => projectile.absorbed = true,
+            }
+        }
+
+        if pierced {
+            // let the projectile through while the enemy dies
+            commands.entity(enemy_entity).insert(Sensor);
+        } else {
+            // damage flashes the enemy instead
+            commands.add(Absorb::new(ev.projectile, enemy_entity).flash(false));
+        }
+    }
+}
+
+fn damage_enemies(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut converted_events: EventWriter<EnemyConvertedEvent>,
+    mut enemies_query: Query<
+        (
+            &Enemy,
+            Option<&mut Health>,
+            Option<&mut Hostility>,
+            Option<&mut CollisionGroups>,
+            Has<Convertible>,
+        ),
+        Without<DeathTimer>,
+    >,
+    mut converted: Local<Vec<Entity>>,
+) {
+    converted.clear();
+
+    for ev in damage_events.iter() {
+        // the rest of the volley that converted an enemy doesn't hurt it
+        if converted.contains(&ev.target) {
+            continue;
+        }
+
+        let Ok((enemy, health, hostility, collision_groups, convertible)) =
+            enemies_query.get_mut(ev.target)
+        else {
             continue;
         };
 
-        // despawn projectile
-        if let Ok(mut projectile) = projectile_query.get_mut(ev.projectile) {
-            projectile.absorbed = true;
+        if let Some(mut hostility) = hostility.filter(|h| {
+            convertible && **h == Hostility::Hostile && ev.kind == DamageKind::Projectile
+        }) {
+            *hostility = Hostility::Friendly;
+
+            if let Some(mut collision_groups) = collision_groups {
+                collision_groups.memberships = (collision_groups.memberships
+                    - Hostility::Hostile.collision_group())
+                    | Hostility::Friendly.collision_group();
+            }
+
+            commands.add(PushTint::new(
+                ev.target,
+                Hostility::TINT,
+                TintStack::HOSTILITY,
+                Hostility::Friendly.color(),
+            ));
+            commands.add(FlashSprite(ev.target));
+
+            converted.push(ev.target);
+            converted_events.send(EnemyConvertedEvent { entity: ev.target });
+
+            crate::game_debug!(Enemy, "{:?} converted", ev.target);
+            continue;
         }
 
-        if !enemy.invincible {
-            commands.entity(enemy_entity).insert(DeathTimer::default());
+        if enemy.invincible {
+            commands.add(FlashSprite(ev.target));
+            continue;
+        }
+
+        // enemies without health die in one hit
+        let died = match health {
+            Some(mut health) => health.damage(ev.amount),
+            None => true,
+        };
+
+        if died {
+            commands.entity(ev.target).insert(DeathTimer::default());
+        } else {
+            commands.add(FlashSprite(ev.target));
         }
     }
 }
 
 fn despawn_dead_enemies(
     mut commands: Commands,
-    mut enemies_query: Query<(Entity, &mut DeathTimer, Option<&ActivateOnDeath>)>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut enemies_query: Query<(
+        Entity,
+        &mut DeathTimer,
+        &GlobalTransform,
+        &Transform,
+        Option<&Parent>,
+        Option<&Hostility>,
+        Option<&EnemyPrefab>,
+        Option<&ActivateOnDeath>,
+        Option<&DropOnDeath>,
+        Option<&Iid>,
+        Option<&RespawnPolicy>,
+        Option<&SignalOnDeath>,
+    )>,
     mut activate_events: EventWriter<ActivateEvent>,
+    mut died_events: EventWriter<EnemyDiedEvent>,
+    mut rng: ResMut<GameRng>,
     time: Res<Time>,
 ) {
-    for (entity, mut death_timer, activate) in enemies_query.iter_mut() {
+    for (
+        entity,
+        mut death_timer,
+        transform,
+        local_transform,
+        parent,
+        hostility,
+        prefab,
+        activate,
+        drop,
+        iid,
+        respawn,
+        signal,
+    ) in enemies_query.iter_mut()
+    {
         death_timer.0.tick(time.delta());
 
         if death_timer.0.finished() {
-            commands.entity(entity).despawn_recursive();
+            despawn_queue.push(entity, DespawnReason::Died);
+
+            if let Some(drop) = drop.filter(|drop| rng.chance(drop.chance)) {
+                let pickup = commands
+                    .spawn(PickupBundle::new(
+                        drop.pickup.clone(),
+                        local_transform.translation,
+                    ))
+                    .id();
+
+                // stay with the level
+                if let Some(parent) = parent {
+                    commands.entity(parent.get()).add_child(pickup);
+                }
+            }
+
+            died_events.send(EnemyDiedEvent {
+                entity,
+                prefab: prefab.cloned(),
+                position: transform.translation().truncate(),
+                hostility: hostility.copied().unwrap_or(Hostility::Hostile),
+                // enemies spawned outside of LDtk have no iid
+                iid: iid.map(|iid| iid.0.clone()).filter(|iid| !iid.is_empty()),
+                respawn: respawn.copied().unwrap_or_default(),
+                signal_radius: signal
+                    .map(|signal| signal.radius)
+                    .filter(|radius| *radius > 0.),
+            });
 
             if let Some(activate) = activate.and_then(|a| a.0) {
                 activate_events.send(ActivateEvent(activate));
@@ -208,8 +681,102 @@ fn despawn_dead_enemies(
     }
 }
 
-fn tint_dying_enemies(mut enemies_query: Query<&mut TextureAtlasSprite, Added<DeathTimer>>) {
-    for mut sprite in enemies_query.iter_mut() {
-        sprite.color = Color::WHITE * 255.;
+fn aim_at_player(
+    mut enemies_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &AimAtPlayer,
+            &Charge,
+            &mut Spawner,
+            Option<&Hostility>,
+        ),
+        Without<DeathTimer>,
+    >,
+    targets: EnemyTargets,
+    mut spawn_events: EventWriter<SpawnProjectile>,
+) {
+    for (entity, transform, aim, charge, mut spawner, hostility) in enemies_query.iter_mut() {
+        if !charge.has_charge() {
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let hostility = hostility.copied().unwrap_or(Hostility::Hostile);
+
+        let Some(target) = targets.closest(hostility, position) else {
+            continue;
+        };
+
+        let offset = target - position;
+
+        if let Some(range) = aim.range {
+            if offset.length_squared() > range * range {
+                continue;
+            }
+        }
+
+        let Some(direction) = offset.try_normalize() else {
+            continue;
+        };
+
+        spawner.initial_velocity = direction * aim.speed;
+        spawn_events.send(SpawnProjectile::new(entity));
+    }
+}
+
+fn start_death_sequences(
+    mut commands: Commands,
+    enemies_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&Hostility>,
+            Has<TextureAtlasSprite>,
+        ),
+        Added<DeathTimer>,
+    >,
+) {
+    for (entity, transform, hostility, has_sprite) in enemies_query.iter() {
+        if has_sprite {
+            commands.add(PushTint::new(
+                entity,
+                DeathTimer::TINT,
+                TintStack::DEATH,
+                Color::WHITE * 255.,
+            ));
+        }
+
+        // let projectiles and the player through the corpse
+        commands.entity(entity).insert(Sensor);
+
+        let color = hostility.copied().unwrap_or(Hostility::Hostile).color();
+        let spec = ParticleSpec::square(2., color)
+            .lifetime(0.3..0.6)
+            .velocity(Vec2::new(-48., -16.), Vec2::new(48., 64.))
+            .gravity(160.)
+            .fade_to(color.with_a(0.));
+
+        commands
+            .add(SpawnParticles::new(spec, transform.translation()).count(DeathTimer::PARTICLES));
+    }
+}
+
+fn dissolve_dying_enemies(mut enemies_query: Query<(&DeathTimer, &mut TintStack)>) {
+    for (death_timer, mut tint_stack) in enemies_query.iter_mut() {
+        let elapsed = death_timer.0.elapsed();
+
+        if elapsed < DeathTimer::FLASH {
+            continue;
+        }
+
+        let fade = DeathTimer::DURATION - DeathTimer::FLASH;
+        let left = 1. - (elapsed - DeathTimer::FLASH).as_secs_f32() / fade.as_secs_f32();
+
+        tint_stack.push(
+            DeathTimer::TINT,
+            TintStack::DEATH,
+            Color::WHITE.with_a(left.clamp(0., 1.)),
+        );
     }
 }
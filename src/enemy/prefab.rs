@@ -1,5 +1,6 @@
 //! Prefab stuff.
 
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 
 use bevy_rapier2d::prelude::*;
@@ -10,18 +11,45 @@ use bevy_ecs_ldtk::{
     EntityInstance,
 };
 
-use super::{ActivateOnDeathByIid, EnemyBundle};
+use std::ops::Range;
+use std::time::Duration;
 
-use crate::{GameAssets, GameState};
+use super::ai::{Aggro, Patrol, Swoop};
+use super::boss::BossBundle;
+use super::shield::{Shield, ShieldBundle};
+use super::{
+    ActivateOnDeathByIid, AimAtPlayer, Convertible, DropOnDeath, EnemyBundle, RespawnPolicy,
+    SignalOnDeath,
+};
+
+use crate::animation::{SpriteAnimation, WalkCycle};
+use crate::atlas::AtlasRegistry;
+use crate::combat::Health;
+use crate::level::Iid;
+use crate::physics;
+use crate::projectile::spawner::{Charge, Spawner};
+use crate::projectile::SineWave;
+use crate::GameState;
 
 pub struct EnemyPrefabPlugin;
 
 impl Plugin for EnemyPrefabPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<HowardBundle>("Howard")
+            .register_ldtk_entity::<WispBundle>("Wisp")
             .add_systems(
                 Update,
                 setup_enemy_prefab.run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    arm_enemies,
+                    start_enemy_patrols,
+                    start_enemy_aggro,
+                    raise_enemy_shields,
+                    make_enemies_convertible,
+                ),
             );
     }
 }
@@ -33,6 +61,59 @@ pub enum EnemyPrefab {
     ///
     /// See [`HowardBundle`].
     Howard,
+    /// Wisp.
+    ///
+    /// See [`WispBundle`].
+    Wisp,
+    /// A boss, drawn as a big Howard.
+    ///
+    /// See [`BossBundle`].
+    Boss,
+}
+
+impl EnemyPrefab {
+    /// Finds a prefab by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<EnemyPrefab> {
+        match name {
+            "Howard" => Some(EnemyPrefab::Howard),
+            "Wisp" => Some(EnemyPrefab::Wisp),
+            "Boss" => Some(EnemyPrefab::Boss),
+            _ => None,
+        }
+    }
+
+    /// The key of the enemy's texture atlas in the [`AtlasRegistry`].
+    pub fn atlas_key(&self) -> &'static str {
+        match self {
+            EnemyPrefab::Howard | EnemyPrefab::Boss => "enemy/howard",
+            EnemyPrefab::Wisp => "enemy/wisp",
+        }
+    }
+
+    /// The texture atlas of the enemy, if it is registered.
+    pub fn atlas(&self, registry: &AtlasRegistry) -> Option<Handle<TextureAtlas>> {
+        registry.get(self.atlas_key())
+    }
+
+    /// The size of a single frame of the enemy's texture atlas, in pixels.
+    pub fn icon_size(&self) -> Vec2 {
+        match self {
+            EnemyPrefab::Howard | EnemyPrefab::Boss => Vec2::new(32., 24.),
+            EnemyPrefab::Wisp => Vec2::new(16., 16.),
+        }
+    }
+
+    /// Makes an entity into this enemy, for enemies spawned outside of LDtk.
+    ///
+    /// The enemy is set up like one placed in a level with none of its fields
+    /// set.
+    pub fn insert(&self, entity: &mut EntityCommands, transform: Transform) {
+        match self {
+            EnemyPrefab::Howard => entity.insert(HowardBundle::new(transform)),
+            EnemyPrefab::Wisp => entity.insert(WispBundle::new(transform)),
+            EnemyPrefab::Boss => entity.insert(BossBundle::new(transform)),
+        };
+    }
 }
 
 /// Howard.
@@ -43,6 +124,266 @@ pub struct HowardBundle {
     texture_atlas: Handle<TextureAtlas>,
     sprite: TextureAtlasSprite,
     activate_on_death: ActivateOnDeathByIid,
+    armament: EnemyArmament,
+    patrol: EnemyPatrol,
+    aggro: EnemyAggro,
+    shield: EnemyShield,
+    convertible: EnemyConvertible,
+    drop: DropOnDeath,
+    signal: SignalOnDeath,
+    iid: Iid,
+    respawn: RespawnPolicy,
+    animation: SpriteAnimation,
+    walk_cycle: WalkCycle,
+}
+
+/// Gives an enemy a [`Shield`].
+///
+/// This is replaced by a child with a [`ShieldBundle`] once the enemy is
+/// spawned.
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemyShield(pub Option<Shield>);
+
+impl EnemyShield {
+    /// How far shields reach from the middle of the enemy.
+    pub const RADIUS: f32 = 14.;
+
+    /// Reads a shield from the fields of an LDtk entity.
+    ///
+    /// Enemies only have shields if their `ShieldArc` field is set, in
+    /// degrees. `ShieldFacing` is the angle the shield faces, in degrees
+    /// counterclockwise from the right.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> EnemyShield {
+        let float_field = |name: &str| {
+            entity_instance
+                .get_maybe_float_field(name)
+                .ok()
+                .copied()
+                .flatten()
+        };
+
+        let Some(arc) = float_field("ShieldArc") else {
+            return EnemyShield(None);
+        };
+
+        let facing = float_field("ShieldFacing").unwrap_or(0.).to_radians();
+
+        EnemyShield(Some(
+            Shield::new(arc.to_radians()).facing(Vec2::from_angle(facing)),
+        ))
+    }
+}
+
+/// Makes an enemy [`Convertible`].
+///
+/// This is replaced by the marker once the enemy is spawned.
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemyConvertible(pub bool);
+
+impl EnemyConvertible {
+    /// Reads the `Convertible` field of an LDtk entity.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> EnemyConvertible {
+        EnemyConvertible(
+            entity_instance
+                .get_bool_field("Convertible")
+                .ok()
+                .copied()
+                .unwrap_or(false),
+        )
+    }
+}
+
+/// Wisp.
+///
+/// Wisps fly through level collision, bob up and down on a [`SineWave`] and
+/// [`Swoop`] at the player when they see them.
+#[derive(Bundle)]
+pub struct WispBundle {
+    enemy_bundle: EnemyBundle,
+    enemy_prefab: EnemyPrefab,
+    texture_atlas: Handle<TextureAtlas>,
+    sprite: TextureAtlasSprite,
+    activate_on_death: ActivateOnDeathByIid,
+    armament: EnemyArmament,
+    aggro: Aggro,
+    swoop: Swoop,
+    hover: SineWave,
+    convertible: EnemyConvertible,
+    drop: DropOnDeath,
+    signal: SignalOnDeath,
+    iid: Iid,
+    respawn: RespawnPolicy,
+}
+
+/// Gives an enemy a [`Spawner`] and [`Charge`] that [aims at the player].
+///
+/// This is replaced by those components once the enemy is spawned.
+///
+/// [aims at the player]: AimAtPlayer
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemyArmament(pub Option<Armament>);
+
+/// See [`EnemyArmament`].
+#[derive(Clone, Debug)]
+pub struct Armament {
+    /// The time between shots.
+    pub interval: Duration,
+    /// How the enemy aims.
+    pub aim: AimAtPlayer,
+}
+
+impl Armament {
+    /// Reads an armament from the fields of an LDtk entity.
+    ///
+    /// Enemies are only armed if they have a `FireInterval`.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Option<Armament> {
+        let interval = entity_instance
+            .get_maybe_float_field("FireInterval")
+            .ok()
+            .copied()
+            .flatten()?;
+        let speed = entity_instance
+            .get_maybe_float_field("ProjectileSpeed")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(AimAtPlayer::default().speed);
+        let range = entity_instance
+            .get_maybe_float_field("AimRange")
+            .ok()
+            .copied()
+            .flatten();
+
+        Some(Armament {
+            interval: Duration::from_secs_f32(interval.max(0.05)),
+            aim: AimAtPlayer { speed, range },
+        })
+    }
+}
+
+/// Gives an enemy a [`Patrol`].
+///
+/// This is replaced by the [`Patrol`] once the enemy is spawned.
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemyPatrol(pub Option<Patrol>);
+
+impl EnemyPatrol {
+    /// The speed of patrolling enemies if the level doesn't say.
+    pub const DEFAULT_SPEED: f32 = 24.;
+
+    /// Reads a patrol from the fields of an LDtk entity.
+    ///
+    /// Enemies only patrol if their `Patrol` field is set; otherwise they
+    /// stand still. `PatrolOffEdges` makes them walk off edges instead of
+    /// turning around.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> EnemyPatrol {
+        let patrol = entity_instance
+            .get_bool_field("Patrol")
+            .ok()
+            .copied()
+            .unwrap_or(false);
+
+        if !patrol {
+            return EnemyPatrol(None);
+        }
+
+        let speed = entity_instance
+            .get_maybe_float_field("PatrolSpeed")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(EnemyPatrol::DEFAULT_SPEED);
+
+        let off_edges = entity_instance
+            .get_bool_field("PatrolOffEdges")
+            .ok()
+            .copied()
+            .unwrap_or(false);
+
+        let patrol = Patrol::walk(speed);
+
+        EnemyPatrol(Some(if off_edges {
+            patrol.walk_off_edges()
+        } else {
+            patrol
+        }))
+    }
+}
+
+/// Gives an enemy [`Aggro`].
+///
+/// This is replaced by the [`Aggro`] once the enemy is spawned.
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemyAggro(pub Option<Aggro>);
+
+impl EnemyAggro {
+    /// Reads aggro from the fields of an LDtk entity.
+    ///
+    /// Enemies only chase the player if their `AggroRange` field is set.
+    /// `AggroNeedsSight` can be unset so enemies chase through walls.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> EnemyAggro {
+        let Some(range) = entity_instance
+            .get_maybe_float_field("AggroRange")
+            .ok()
+            .copied()
+            .flatten()
+        else {
+            return EnemyAggro(None);
+        };
+
+        let requires_los = entity_instance
+            .get_bool_field("AggroNeedsSight")
+            .ok()
+            .copied()
+            .unwrap_or(true);
+
+        EnemyAggro(Some(Aggro {
+            requires_los,
+            ..Aggro::new(range)
+        }))
+    }
+}
+
+impl HowardBundle {
+    /// The frames of Howard's walk cycle.
+    pub const WALK_FRAMES: Range<usize> = 0..7;
+    /// How fast Howard's walk cycle plays at [`EnemyPatrol::DEFAULT_SPEED`].
+    pub const WALK_FPS: f32 = 10.;
+
+    /// Creates a new, unarmed `HowardBundle` that stands still.
+    pub fn new(transform: Transform) -> HowardBundle {
+        HowardBundle {
+            enemy_bundle: EnemyBundle {
+                transform,
+                collider: Collider::cuboid(8., 8.),
+                health: Health::new(1.),
+                ..Default::default()
+            },
+            enemy_prefab: EnemyPrefab::Howard,
+            texture_atlas: Default::default(),
+            sprite: Default::default(),
+            activate_on_death: ActivateOnDeathByIid(None),
+            armament: EnemyArmament(None),
+            patrol: EnemyPatrol(None),
+            aggro: EnemyAggro(None),
+            shield: EnemyShield(None),
+            convertible: EnemyConvertible(false),
+            drop: DropOnDeath::default(),
+            signal: SignalOnDeath::default(),
+            iid: Iid::default(),
+            respawn: RespawnPolicy::default(),
+            animation: HowardBundle::walk_animation(),
+            walk_cycle: HowardBundle::walk_cycle(),
+        }
+    }
+
+    fn walk_animation() -> SpriteAnimation {
+        SpriteAnimation::new(HowardBundle::WALK_FRAMES, HowardBundle::WALK_FPS).paused()
+    }
+
+    fn walk_cycle() -> WalkCycle {
+        WalkCycle::new(EnemyPatrol::DEFAULT_SPEED, HowardBundle::WALK_FPS)
+    }
 }
 
 impl LdtkEntity for HowardBundle {
@@ -62,13 +403,134 @@ impl LdtkEntity for HowardBundle {
             .and_then(|a| a.as_ref())
             .map(|a| a.entity_iid.clone());
 
+        let health = entity_instance
+            .get_maybe_float_field("Health")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or(1.);
+
         HowardBundle {
             enemy_bundle: EnemyBundle {
                 collider: Collider::cuboid(8., 8.),
+                health: Health::new(health),
                 ..Default::default()
             },
             enemy_prefab: EnemyPrefab::Howard,
             activate_on_death: ActivateOnDeathByIid(activate_ref),
+            armament: EnemyArmament(Armament::from_entity_instance(entity_instance)),
+            patrol: EnemyPatrol::from_entity_instance(entity_instance),
+            aggro: EnemyAggro::from_entity_instance(entity_instance),
+            shield: EnemyShield::from_entity_instance(entity_instance),
+            convertible: EnemyConvertible::from_entity_instance(entity_instance),
+            drop: DropOnDeath::from_entity_instance(entity_instance),
+            signal: SignalOnDeath::from_entity_instance(entity_instance),
+            iid: Iid::from(entity_instance),
+            respawn: RespawnPolicy::from_entity_instance(entity_instance),
+            animation: HowardBundle::walk_animation(),
+            walk_cycle: HowardBundle::walk_cycle(),
+            texture_atlas: Default::default(),
+            sprite: Default::default(),
+        }
+    }
+}
+
+impl WispBundle {
+    /// How far away wisps notice the player if the level doesn't say.
+    pub const DEFAULT_AGGRO_RANGE: f32 = 96.;
+    /// How fast wisps swoop if the level doesn't say.
+    pub const DEFAULT_SWOOP_SPEED: f32 = 96.;
+
+    /// Creates a new, unarmed `WispBundle`.
+    pub fn new(transform: Transform) -> WispBundle {
+        WispBundle {
+            enemy_bundle: EnemyBundle {
+                transform,
+                collider: Collider::ball(6.),
+                // fly through the level
+                collision_groups: CollisionGroups::new(
+                    physics::COLLISION_GROUP_HOSTILE,
+                    Group::all() - physics::COLLISION_GROUP_SOLID,
+                ),
+                health: Health::new(1.),
+                ..Default::default()
+            },
+            enemy_prefab: EnemyPrefab::Wisp,
+            texture_atlas: Default::default(),
+            sprite: Default::default(),
+            activate_on_death: ActivateOnDeathByIid(None),
+            armament: EnemyArmament(None),
+            aggro: Aggro::new(WispBundle::DEFAULT_AGGRO_RANGE),
+            swoop: Swoop::new(WispBundle::DEFAULT_SWOOP_SPEED),
+            hover: SineWave {
+                axis: Vec2::Y,
+                period: 3.,
+                amp: 4.,
+                ..Default::default()
+            },
+            convertible: EnemyConvertible(false),
+            drop: DropOnDeath::default(),
+            signal: SignalOnDeath::default(),
+            iid: Iid::default(),
+            respawn: RespawnPolicy::default(),
+        }
+    }
+}
+
+impl LdtkEntity for WispBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let activate_ref = entity_instance
+            .get_maybe_entity_ref_field("ActivateOnDeath")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        let float_field = |name: &str| {
+            entity_instance
+                .get_maybe_float_field(name)
+                .ok()
+                .copied()
+                .flatten()
+        };
+
+        let health = float_field("Health").unwrap_or(1.);
+        let range = float_field("AggroRange").unwrap_or(WispBundle::DEFAULT_AGGRO_RANGE);
+        let speed = float_field("SwoopSpeed").unwrap_or(WispBundle::DEFAULT_SWOOP_SPEED);
+
+        WispBundle {
+            enemy_bundle: EnemyBundle {
+                collider: Collider::ball(6.),
+                // fly through the level
+                collision_groups: CollisionGroups::new(
+                    physics::COLLISION_GROUP_HOSTILE,
+                    Group::all() - physics::COLLISION_GROUP_SOLID,
+                ),
+                health: Health::new(health),
+                ..Default::default()
+            },
+            enemy_prefab: EnemyPrefab::Wisp,
+            activate_on_death: ActivateOnDeathByIid(activate_ref),
+            armament: EnemyArmament(Armament::from_entity_instance(entity_instance)),
+            aggro: Aggro::new(range),
+            swoop: Swoop::new(speed),
+            hover: SineWave {
+                axis: Vec2::Y,
+                period: 3.,
+                amp: 4.,
+                ..Default::default()
+            },
+            convertible: EnemyConvertible::from_entity_instance(entity_instance),
+            drop: DropOnDeath::from_entity_instance(entity_instance),
+            signal: SignalOnDeath::from_entity_instance(entity_instance),
+            iid: Iid::from(entity_instance),
+            respawn: RespawnPolicy::from_entity_instance(entity_instance),
             texture_atlas: Default::default(),
             sprite: Default::default(),
         }
@@ -77,11 +539,83 @@ impl LdtkEntity for HowardBundle {
 
 fn setup_enemy_prefab(
     mut enemy_prefab_query: Query<(&mut Handle<TextureAtlas>, &EnemyPrefab), Added<EnemyPrefab>>,
-    assets: Res<GameAssets>,
+    registry: Res<AtlasRegistry>,
 ) {
     for (mut texture_handle, enemy_prefab) in enemy_prefab_query.iter_mut() {
-        match enemy_prefab {
-            EnemyPrefab::Howard => *texture_handle = assets.enemy_howard.clone(),
+        let Some(atlas) = enemy_prefab.atlas(&registry) else {
+            crate::game_warn!(Enemy, "no atlas registered as {}", enemy_prefab.atlas_key());
+            continue;
+        };
+
+        *texture_handle = atlas;
+    }
+}
+
+fn arm_enemies(mut commands: Commands, armament_query: Query<(Entity, &EnemyArmament)>) {
+    for (entity, armament) in armament_query.iter() {
+        let mut entity = commands.entity(entity);
+
+        entity.remove::<EnemyArmament>();
+
+        if let Some(armament) = &armament.0 {
+            entity.insert((
+                Spawner::default(),
+                Charge::new(armament.interval, 1),
+                armament.aim.clone(),
+            ));
+        }
+    }
+}
+
+fn start_enemy_patrols(mut commands: Commands, patrol_query: Query<(Entity, &EnemyPatrol)>) {
+    for (entity, patrol) in patrol_query.iter() {
+        let mut entity = commands.entity(entity);
+
+        entity.remove::<EnemyPatrol>();
+
+        if let Some(patrol) = &patrol.0 {
+            entity.insert(patrol.clone());
+        }
+    }
+}
+
+fn start_enemy_aggro(mut commands: Commands, aggro_query: Query<(Entity, &EnemyAggro)>) {
+    for (entity, aggro) in aggro_query.iter() {
+        let mut entity = commands.entity(entity);
+
+        entity.remove::<EnemyAggro>();
+
+        if let Some(aggro) = &aggro.0 {
+            entity.insert(aggro.clone());
+        }
+    }
+}
+
+fn raise_enemy_shields(mut commands: Commands, shield_query: Query<(Entity, &EnemyShield)>) {
+    for (entity, shield) in shield_query.iter() {
+        let mut entity = commands.entity(entity);
+
+        entity.remove::<EnemyShield>();
+
+        if let Some(shield) = &shield.0 {
+            entity.with_children(|enemy| {
+                enemy.spawn(ShieldBundle::new(shield.clone(), EnemyShield::RADIUS));
+            });
+        }
+    }
+}
+
+fn make_enemies_convertible(
+    mut commands: Commands,
+    convertible_query: Query<(Entity, &EnemyConvertible)>,
+) {
+    for (entity, convertible) in convertible_query.iter() {
+        let mut entity = commands.entity(entity);
+
+        entity.remove::<EnemyConvertible>();
+
+        if convertible.0 {
+            entity.insert(Convertible);
         }
     }
 }
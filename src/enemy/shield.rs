@@ -0,0 +1,190 @@
+//! Enemy shields.
+//!
+//! A [`Shield`] is a sensor around an enemy that turns friendly projectiles
+//! coming from the front back the way they came, as hostile projectiles.
+//! Projectiles from behind go through and hit the enemy as usual, so shielded
+//! enemies have to be hit from the other side, or with a projectile routed
+//! around them through pipes and drums.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use super::Hostility;
+use crate::physics;
+use crate::projectile::{ContactBehavior, HitEvent, Projectile, ProjectileSystem};
+
+/// Enemy shield plugin.
+pub struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                face_shields,
+                reflect_off_shields
+                    .in_set(ProjectileSystem::Bounce)
+                    .after(ProjectileSystem::Event)
+                    .before(ProjectileSystem::Despawn),
+            )
+                .chain()
+                .distributive_run_if(any_with_component::<Shield>()),
+        );
+    }
+}
+
+/// Reflects friendly projectiles that hit from the front.
+///
+/// This goes on a child of the enemy, see [`ShieldBundle`]. The shield turns
+/// with the enemy's sprite.
+#[derive(Clone, Component, Debug)]
+pub struct Shield {
+    /// How much of the enemy the shield covers, in radians.
+    pub arc: f32,
+    /// The direction the middle of the shield faces, when the enemy's sprite
+    /// isn't flipped.
+    pub facing: Vec2,
+    flipped: bool,
+}
+
+impl Shield {
+    /// Creates a new `Shield` facing right.
+    pub fn new(arc: f32) -> Shield {
+        Shield {
+            arc,
+            facing: Vec2::X,
+            flipped: false,
+        }
+    }
+
+    /// Sets the direction the shield faces.
+    pub fn facing(self, facing: Vec2) -> Shield {
+        Shield {
+            facing: facing.normalize_or_zero(),
+            ..self
+        }
+    }
+
+    /// The direction the middle of the shield faces right now.
+    pub fn world_facing(&self) -> Vec2 {
+        if self.flipped {
+            Vec2::new(-self.facing.x, self.facing.y)
+        } else {
+            self.facing
+        }
+    }
+
+    /// Checks if something coming from a direction, pointing from the enemy
+    /// outward, hits the shield.
+    pub fn covers(&self, from: Vec2) -> bool {
+        let Some(from) = from.try_normalize() else {
+            return false;
+        };
+
+        from.dot(self.world_facing()) >= (self.arc / 2.).cos()
+    }
+}
+
+/// A shield around an enemy.
+#[derive(Bundle)]
+pub struct ShieldBundle {
+    pub shield: Shield,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub collision_groups: CollisionGroups,
+    pub contact_behavior: ContactBehavior,
+    pub transform: TransformBundle,
+}
+
+impl ShieldBundle {
+    /// Creates a new `ShieldBundle` reaching `radius` from the enemy.
+    pub fn new(shield: Shield, radius: f32) -> ShieldBundle {
+        ShieldBundle {
+            shield,
+            collider: Collider::ball(radius),
+            sensor: Sensor,
+            collision_groups: CollisionGroups::new(
+                physics::COLLISION_GROUP_HOSTILE,
+                physics::COLLISION_GROUP_PROJECTILE,
+            ),
+            contact_behavior: ContactBehavior::Bounce,
+            transform: TransformBundle::default(),
+        }
+    }
+}
+
+fn face_shields(
+    mut shield_query: Query<(&Parent, &mut Shield)>,
+    sprite_query: Query<&TextureAtlasSprite>,
+) {
+    for (parent, mut shield) in shield_query.iter_mut() {
+        let Ok(sprite) = sprite_query.get(parent.get()) else {
+            continue;
+        };
+
+        // do not trip change detection
+        if shield.flipped != sprite.flip_x {
+            shield.flipped = sprite.flip_x;
+        }
+    }
+}
+
+fn reflect_off_shields(
+    mut hit_events: EventReader<HitEvent>,
+    shield_query: Query<(&GlobalTransform, &Shield)>,
+    mut projectile_query: Query<(
+        &GlobalTransform,
+        &mut Transform,
+        &mut Projectile,
+        &mut Velocity,
+        &mut Hostility,
+    )>,
+) {
+    for ev in hit_events.iter() {
+        let Ok((shield_transform, shield)) = shield_query.get(ev.entity) else {
+            continue;
+        };
+
+        let Ok((projectile_transform, mut transform, mut projectile, mut velocity, mut hostility)) =
+            projectile_query.get_mut(ev.projectile)
+        else {
+            continue;
+        };
+
+        if *hostility != Hostility::Friendly {
+            continue;
+        }
+
+        // the shield is a sensor, so the projectile is never stopped by it
+        projectile.absorbed = false;
+
+        let from = if velocity.linvel != Vec2::ZERO {
+            -velocity.linvel
+        } else {
+            projectile_transform.translation().truncate()
+                - shield_transform.translation().truncate()
+        };
+
+        // from behind, the projectile goes on to hit the enemy
+        if !shield.covers(from) {
+            continue;
+        }
+
+        let normal = shield.world_facing();
+        let mut reflected = velocity.linvel - 2. * velocity.linvel.dot(normal) * normal;
+
+        // always leave through the front
+        if reflected.dot(normal) <= 0. {
+            reflected = normal * velocity.linvel.length();
+        }
+
+        velocity.linvel = reflected;
+        *hostility = Hostility::Hostile;
+
+        let rot = reflected.y.atan2(reflected.x);
+        transform.rotation = Quat::from_axis_angle(Vec3::Z, rot);
+
+        crate::game_debug!(Enemy, "{:?} reflected off a shield", ev.projectile);
+    }
+}
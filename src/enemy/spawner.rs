@@ -0,0 +1,402 @@
+//! Enemy spawners.
+//!
+//! An [`EnemySpawner`] waits for an [`ActivateEvent`], then sends out its
+//! [`EnemyWave`]s one after the other, each once the last one is dead. When
+//! the last wave is cleared, it sends an [`EnemySpawnerClearedEvent`] and
+//! activates its target, so clearing a room can open the door out of it.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::prefab::EnemyPrefab;
+use super::DeathTimer;
+use crate::level::Iid;
+use crate::platform::ActivateEvent;
+
+/// Enemy spawner plugin.
+pub struct EnemySpawnerPlugin;
+
+impl Plugin for EnemySpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<EnemySpawnerBundle>("EnemySpawner")
+            .add_event::<EnemyWaveClearedEvent>()
+            .add_event::<EnemySpawnerClearedEvent>()
+            .add_systems(
+                Update,
+                (
+                    upgrade_spawner_targets,
+                    activate_enemy_spawners,
+                    run_enemy_spawners,
+                )
+                    .chain()
+                    .distributive_run_if(any_with_component::<EnemySpawner>()),
+            );
+    }
+}
+
+/// A bundle for an [`EnemySpawner`].
+#[derive(Bundle, Default)]
+pub struct EnemySpawnerBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub iid: Iid,
+    pub spawner: EnemySpawner,
+}
+
+impl LdtkEntity for EnemySpawnerBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let activate_ref = entity_instance
+            .get_maybe_entity_ref_field("Activates")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        EnemySpawnerBundle {
+            iid: Iid::from(entity_instance),
+            spawner: EnemySpawner {
+                activates_iid: activate_ref,
+                ..EnemySpawner::new(EnemyWave::from_entity_instance(entity_instance))
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Spawns waves of enemies when activated.
+///
+/// Enemies are spawned where the spawner is, as siblings of it.
+#[derive(Clone, Component, Debug, Default)]
+pub struct EnemySpawner {
+    /// The waves, in order.
+    pub waves: Vec<EnemyWave>,
+    /// The entity activated when every wave is cleared.
+    pub activates: Option<Entity>,
+    activates_iid: Option<String>,
+    state: SpawnerState,
+}
+
+impl EnemySpawner {
+    /// Creates a new `EnemySpawner`.
+    pub fn new(waves: Vec<EnemyWave>) -> EnemySpawner {
+        EnemySpawner {
+            waves,
+            ..Default::default()
+        }
+    }
+
+    /// The index of the current wave, or `None` if the spawner hasn't been
+    /// activated or is done.
+    pub fn wave(&self) -> Option<usize> {
+        match self.state {
+            SpawnerState::Waiting { wave, .. }
+            | SpawnerState::Spawning { wave, .. }
+            | SpawnerState::Fighting { wave } => Some(wave),
+            SpawnerState::Idle | SpawnerState::Cleared => None,
+        }
+    }
+
+    /// Checks if every wave has been cleared.
+    pub fn is_cleared(&self) -> bool {
+        matches!(self.state, SpawnerState::Cleared)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+enum SpawnerState {
+    #[default]
+    Idle,
+    Waiting {
+        wave: usize,
+        timer: Timer,
+    },
+    Spawning {
+        wave: usize,
+        spawned: u32,
+        timer: Timer,
+    },
+    Fighting {
+        wave: usize,
+    },
+    Cleared,
+}
+
+/// A single wave of an [`EnemySpawner`].
+#[derive(Clone, Debug)]
+pub struct EnemyWave {
+    /// What enemy the wave is made of.
+    pub prefab: EnemyPrefab,
+    /// How many enemies are in the wave.
+    pub count: u32,
+    /// The time between the last wave being cleared, or the spawner being
+    /// activated, and this wave starting.
+    pub delay: Duration,
+    /// The time between enemies spawning.
+    pub interval: Duration,
+}
+
+impl EnemyWave {
+    /// How many enemies are in a wave if the level doesn't say.
+    pub const DEFAULT_COUNT: u32 = 3;
+    /// The delay before a wave if the level doesn't say.
+    pub const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+    /// The time between enemies if the level doesn't say.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Reads waves from the fields of an LDtk entity.
+    ///
+    /// Each wave takes its enemy from `WaveEnemies`, its count from
+    /// `WaveCounts` and its delay from `WaveDelays`, in seconds; there are as
+    /// many waves as the longest of these. Waves missing an enemy are
+    /// [Howards](EnemyPrefab::Howard). `SpawnInterval` is the time between
+    /// enemies in every wave, in seconds.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Vec<EnemyWave> {
+        let prefabs = entity_instance
+            .get_maybe_enums_field("WaveEnemies")
+            .map(|prefabs| {
+                prefabs
+                    .iter()
+                    .map(|prefab| {
+                        let prefab = prefab.as_ref()?;
+                        let found = EnemyPrefab::from_name(prefab);
+
+                        if found.is_none() {
+                            crate::game_warn!(Enemy, "unknown enemy {}", prefab);
+                        }
+
+                        found
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let counts = entity_instance
+            .get_maybe_ints_field("WaveCounts")
+            .map(|counts| counts.to_vec())
+            .unwrap_or_default();
+        let delays = entity_instance
+            .get_maybe_floats_field("WaveDelays")
+            .map(|delays| delays.to_vec())
+            .unwrap_or_default();
+
+        let interval = entity_instance
+            .get_maybe_float_field("SpawnInterval")
+            .ok()
+            .copied()
+            .flatten()
+            .map(|interval| Duration::from_secs_f32(interval.max(0.)))
+            .unwrap_or(EnemyWave::DEFAULT_INTERVAL);
+
+        let len = prefabs.len().max(counts.len()).max(delays.len()).max(1);
+
+        (0..len)
+            .map(|i| EnemyWave {
+                prefab: prefabs
+                    .get(i)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or(EnemyPrefab::Howard),
+                count: counts
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .map(|count| count.max(0) as u32)
+                    .unwrap_or(EnemyWave::DEFAULT_COUNT),
+                delay: delays
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .map(|delay| Duration::from_secs_f32(delay.max(0.)))
+                    .unwrap_or(EnemyWave::DEFAULT_DELAY),
+                interval,
+            })
+            .collect()
+    }
+}
+
+/// Marks an enemy spawned by an [`EnemySpawner`].
+#[derive(Clone, Component, Debug)]
+pub struct SpawnedBy(pub Entity);
+
+/// Every enemy of a wave died.
+#[derive(Clone, Debug, Event)]
+pub struct EnemyWaveClearedEvent {
+    /// The spawner.
+    pub spawner: Entity,
+    /// The index of the wave.
+    pub wave: usize,
+}
+
+/// Every wave of a spawner was cleared.
+#[derive(Clone, Debug, Event)]
+pub struct EnemySpawnerClearedEvent {
+    /// The spawner.
+    pub spawner: Entity,
+}
+
+fn upgrade_spawner_targets(
+    mut spawner_query: Query<&mut EnemySpawner>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for mut spawner in spawner_query.iter_mut() {
+        let Some(iid_request) = &spawner.activates_iid else {
+            continue;
+        };
+
+        let found = iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == *iid_request)
+            .map(|(e, _)| e);
+
+        if let Some(found) = found {
+            spawner.activates = Some(found);
+            spawner.activates_iid = None;
+        }
+    }
+}
+
+fn activate_enemy_spawners(
+    mut activate_events: EventReader<ActivateEvent>,
+    mut spawner_query: Query<&mut EnemySpawner>,
+) {
+    for ev in activate_events.iter() {
+        let Ok(mut spawner) = spawner_query.get_mut(ev.0) else {
+            continue;
+        };
+
+        // only the first activation counts
+        if !matches!(spawner.state, SpawnerState::Idle) {
+            continue;
+        }
+
+        let Some(first) = spawner.waves.first() else {
+            continue;
+        };
+
+        spawner.state = SpawnerState::Waiting {
+            wave: 0,
+            timer: Timer::new(first.delay, TimerMode::Once),
+        };
+
+        crate::game_debug!(Enemy, "{:?} activated", ev.0);
+    }
+}
+
+fn run_enemy_spawners(
+    mut commands: Commands,
+    mut spawner_query: Query<(Entity, &Transform, Option<&Parent>, &mut EnemySpawner)>,
+    spawned_query: Query<&SpawnedBy, Without<DeathTimer>>,
+    mut wave_events: EventWriter<EnemyWaveClearedEvent>,
+    mut cleared_events: EventWriter<EnemySpawnerClearedEvent>,
+    mut activate_events: EventWriter<ActivateEvent>,
+    time: Res<Time>,
+) {
+    let mut alive: HashMap<Entity, u32> = HashMap::new();
+
+    for spawned_by in spawned_query.iter() {
+        *alive.entry(spawned_by.0).or_default() += 1;
+    }
+
+    for (entity, transform, parent, mut spawner) in spawner_query.iter_mut() {
+        let EnemySpawner {
+            waves,
+            activates,
+            state,
+            ..
+        } = &mut *spawner;
+
+        match state {
+            SpawnerState::Idle | SpawnerState::Cleared => continue,
+            SpawnerState::Waiting { wave, timer } => {
+                timer.tick(time.delta());
+
+                if timer.finished() {
+                    let wave = *wave;
+
+                    *state = SpawnerState::Spawning {
+                        wave,
+                        spawned: 0,
+                        timer: Timer::new(waves[wave].interval, TimerMode::Repeating),
+                    };
+                }
+            }
+            SpawnerState::Spawning {
+                wave,
+                spawned,
+                timer,
+            } => {
+                let info = &waves[*wave];
+
+                timer.tick(time.delta());
+
+                // the first enemy comes out right away
+                let due = if *spawned == 0 {
+                    1
+                } else {
+                    timer.times_finished_this_tick()
+                };
+
+                for _ in 0..due.min(info.count - *spawned) {
+                    let mut enemy = commands.spawn(SpawnedBy(entity));
+                    info.prefab.insert(&mut enemy, *transform);
+
+                    let enemy = enemy.id();
+
+                    if let Some(parent) = parent {
+                        commands.entity(parent.get()).add_child(enemy);
+                    }
+
+                    *spawned += 1;
+                }
+
+                if *spawned >= info.count {
+                    let wave = *wave;
+
+                    *state = SpawnerState::Fighting { wave };
+                }
+            }
+            SpawnerState::Fighting { wave } => {
+                if alive.get(&entity).copied().unwrap_or_default() > 0 {
+                    continue;
+                }
+
+                let wave = *wave;
+
+                wave_events.send(EnemyWaveClearedEvent {
+                    spawner: entity,
+                    wave,
+                });
+
+                if let Some(next) = waves.get(wave + 1) {
+                    *state = SpawnerState::Waiting {
+                        wave: wave + 1,
+                        timer: Timer::new(next.delay, TimerMode::Once),
+                    };
+                } else {
+                    *state = SpawnerState::Cleared;
+
+                    cleared_events.send(EnemySpawnerClearedEvent { spawner: entity });
+
+                    if let Some(target) = *activates {
+                        activate_events.send(ActivateEvent(target));
+                    }
+
+                    crate::game_info!(Enemy, "{:?} cleared", entity);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Pausing and stepping the game a frame at a time.
+//!
+//! Only built with the `devtools` feature. `F6` pauses and resumes the game.
+//! While paused, `F7` steps one frame and `F8` steps to the next
+//! `FixedUpdate` tick. The frame and tick numbers are shown in the "Frame
+//! Step" window, so the order things happen in can be followed through the
+//! projectile and signal systems.
+//!
+//! Pausing pauses [`Time`] and the physics pipeline, and lets go of every
+//! [`Controller`] so input doesn't fire, jump or interact while paused. With
+//! no time passing, no physics events and no input, nothing sends events for
+//! the event-driven systems either. Stepping advances [`Time`] by a fixed
+//! amount and takes input for a single frame.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+
+use bevy_rapier2d::prelude::*;
+
+use std::time::Duration;
+
+use crate::player::controller::{Controller, ControllerSystem};
+
+/// Frame stepping plugin.
+pub struct FrameStepPlugin;
+
+impl Plugin for FrameStepPlugin {
+    fn build(&self, app: &mut App) {
+        use bevy_inspector_egui::bevy_egui::EguiPlugin;
+
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<FrameStep>()
+            .add_systems(First, apply_frame_step.before(TimeSystem))
+            .add_systems(First, count_frames.after(TimeSystem))
+            .add_systems(FixedUpdate, count_ticks)
+            .add_systems(
+                PreUpdate,
+                (
+                    read_frame_step_input
+                        .after(InputSystem)
+                        .before(ControllerSystem::ScanInput),
+                    release_controllers
+                        .run_if(not(running))
+                        .after(ControllerSystem::ScanInput),
+                ),
+            )
+            .add_systems(Update, frame_step_ui);
+    }
+}
+
+/// Frame stepping state.
+#[derive(Clone, Debug, Resource)]
+pub struct FrameStep {
+    /// Whether the game is paused.
+    pub paused: bool,
+    /// How much time passes when stepping a single frame.
+    pub frame_time: Duration,
+    pending: Option<Step>,
+    stepping: bool,
+    frame: u64,
+    tick: u64,
+}
+
+impl FrameStep {
+    /// The key that pauses and resumes the game.
+    pub const PAUSE_KEY: KeyCode = KeyCode::F6;
+    /// The key that steps a single frame.
+    pub const STEP_FRAME_KEY: KeyCode = KeyCode::F7;
+    /// The key that steps to the next `FixedUpdate` tick.
+    pub const STEP_TICK_KEY: KeyCode = KeyCode::F8;
+
+    /// Steps a single frame, if paused.
+    pub fn step_frame(&mut self) {
+        self.pending = Some(Step::Frame);
+    }
+
+    /// Steps to the next `FixedUpdate` tick, if paused.
+    pub fn step_tick(&mut self) {
+        self.pending = Some(Step::Tick);
+    }
+
+    /// Checks if the game runs this frame, because it isn't paused or is
+    /// stepping.
+    pub fn is_running(&self) -> bool {
+        !self.paused || self.stepping
+    }
+
+    /// How many frames the game has run for, not counting paused frames.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// How many `FixedUpdate` ticks the game has run for.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+}
+
+impl Default for FrameStep {
+    fn default() -> FrameStep {
+        FrameStep {
+            paused: false,
+            frame_time: Duration::from_secs_f64(1. / 60.),
+            pending: None,
+            stepping: false,
+            frame: 0,
+            tick: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step {
+    Frame,
+    Tick,
+}
+
+fn read_frame_step_input(keyboard: Res<Input<KeyCode>>, mut frame_step: ResMut<FrameStep>) {
+    if keyboard.just_pressed(FrameStep::PAUSE_KEY) {
+        frame_step.paused = !frame_step.paused;
+    }
+
+    if keyboard.just_pressed(FrameStep::STEP_FRAME_KEY) {
+        frame_step.step_frame();
+    }
+
+    if keyboard.just_pressed(FrameStep::STEP_TICK_KEY) {
+        frame_step.step_tick();
+    }
+}
+
+/// A run condition that checks if the game runs this frame.
+///
+/// See [`FrameStep::is_running`].
+pub fn running(frame_step: Res<FrameStep>) -> bool {
+    frame_step.is_running()
+}
+
+fn release_controllers(mut controller_query: Query<&mut Controller>) {
+    for mut controller in controller_query.iter_mut() {
+        controller.release();
+    }
+}
+
+fn apply_frame_step(
+    mut frame_step: ResMut<FrameStep>,
+    mut time: ResMut<Time>,
+    mut update_strategy: ResMut<TimeUpdateStrategy>,
+    mut physics_config: ResMut<RapierConfiguration>,
+    fixed_time: Res<FixedTime>,
+) {
+    // the last step is over
+    if frame_step.stepping {
+        *update_strategy = TimeUpdateStrategy::Automatic;
+        frame_step.stepping = false;
+    }
+
+    let step = frame_step.pending.take();
+
+    if !frame_step.paused {
+        if time.is_paused() {
+            time.unpause();
+            physics_config.physics_pipeline_active = true;
+        }
+
+        return;
+    }
+
+    match step {
+        Some(step) => {
+            let duration = match step {
+                Step::Frame => frame_step.frame_time,
+                // just enough for one more tick
+                Step::Tick => fixed_time.period.saturating_sub(fixed_time.accumulated()),
+            };
+
+            *update_strategy = TimeUpdateStrategy::ManualDuration(duration);
+            frame_step.stepping = true;
+
+            time.unpause();
+            physics_config.physics_pipeline_active = true;
+        }
+        None => {
+            time.pause();
+            physics_config.physics_pipeline_active = false;
+        }
+    }
+}
+
+fn count_frames(mut frame_step: ResMut<FrameStep>, time: Res<Time>) {
+    if !time.is_paused() {
+        frame_step.frame += 1;
+    }
+}
+
+fn count_ticks(mut frame_step: ResMut<FrameStep>) {
+    frame_step.tick += 1;
+}
+
+fn frame_step_ui(
+    mut contexts: bevy_inspector_egui::bevy_egui::EguiContexts,
+    mut frame_step: ResMut<FrameStep>,
+) {
+    use bevy_inspector_egui::egui;
+
+    egui::Window::new("Frame Step").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!("frame {}", frame_step.frame));
+        ui.label(format!("tick {}", frame_step.tick));
+
+        ui.horizontal(|ui| {
+            let label = if frame_step.paused { "Resume" } else { "Pause" };
+
+            if ui.button(label).clicked() {
+                frame_step.paused = !frame_step.paused;
+            }
+
+            ui.add_enabled_ui(frame_step.paused, |ui| {
+                if ui.button("Step frame").clicked() {
+                    frame_step.step_frame();
+                }
+
+                if ui.button("Step tick").clicked() {
+                    frame_step.step_tick();
+                }
+            });
+        });
+    });
+}
@@ -0,0 +1,241 @@
+//! Feedback for things taking in projectiles.
+//!
+//! Anything that absorbs a projectile (acceptors, drums, enemies) should
+//! [`Absorb`] it, so the note visually sinks into it.
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::time::Duration;
+
+use super::tint::{PushTint, TintStack};
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::projectile::Projectile;
+use crate::settings::AccessibilitySettings;
+
+/// Absorb effects plugin.
+pub struct AbsorbFxPlugin;
+
+impl Plugin for AbsorbFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                update_ghost_projectiles.run_if(any_with_component::<GhostProjectile>()),
+                update_flashes.run_if(any_with_component::<Flash>()),
+            ),
+        );
+    }
+}
+
+/// A spooky ghost.
+///
+/// This is created when something [`Absorb`]s a [`Projectile`], but it wants
+/// the projectile to visually go into it.
+#[derive(Clone, Component, Debug, Default)]
+pub struct GhostProjectile {
+    initial: Vec2,
+    target: Vec2,
+    time_to_live: Timer,
+}
+
+impl GhostProjectile {
+    /// Creates a new `GhostProjectile`.
+    pub fn new(initial: Vec2, target: Vec2, duration: Duration) -> GhostProjectile {
+        GhostProjectile {
+            initial,
+            target,
+            time_to_live: Timer::new(duration, TimerMode::Once),
+        }
+    }
+}
+
+/// Briefly tints a sprite white.
+///
+/// The flash is a [`TintStack::FLASH`] tint, taken off when the flash is over.
+#[derive(Clone, Component, Debug)]
+pub struct Flash {
+    timer: Timer,
+}
+
+impl Flash {
+    /// The color of a flashing sprite.
+    pub const COLOR: Color = Color::rgb(4., 4., 4.);
+    /// The key of the flash's tint.
+    pub const TINT: &'static str = "flash";
+
+    /// Creates a new `Flash` that lasts for a while.
+    pub fn new(duration: Duration) -> Flash {
+        Flash {
+            timer: Timer::new(duration, TimerMode::Once),
+        }
+    }
+}
+
+/// A command that briefly tints an entity's sprite white.
+pub struct FlashSprite(pub Entity);
+
+impl Command for FlashSprite {
+    fn apply(self, world: &mut World) {
+        let FlashSprite(entity) = self;
+
+        let flashing = world
+            .get_resource::<AccessibilitySettings>()
+            .map_or(true, |accessibility| accessibility.flashing);
+
+        if !flashing {
+            return;
+        }
+
+        let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+            return;
+        };
+
+        if !entity_mut.contains::<TextureAtlasSprite>() {
+            return;
+        }
+
+        entity_mut.insert(Flash::new(Duration::from_millis(80)));
+
+        PushTint::new(entity, Flash::TINT, TintStack::FLASH, Flash::COLOR).apply(world);
+    }
+}
+
+/// A command that makes a projectile visually sink into an entity.
+///
+/// This only creates the effect; the projectile should still be absorbed or
+/// despawned by the caller.
+pub struct Absorb {
+    projectile: Entity,
+    into: Entity,
+    flash: bool,
+}
+
+impl Absorb {
+    /// Creates a new `Absorb`.
+    pub fn new(projectile: Entity, into: Entity) -> Absorb {
+        Absorb {
+            projectile,
+            into,
+            flash: true,
+        }
+    }
+
+    /// Sets whether the entity taking in the projectile flashes.
+    pub fn flash(self, flash: bool) -> Absorb {
+        Absorb { flash, ..self }
+    }
+}
+
+impl Command for Absorb {
+    fn apply(self, world: &mut World) {
+        let Absorb {
+            projectile,
+            into,
+            flash,
+        } = self;
+
+        // only real projectiles leave ghosts
+        if world.get::<Projectile>(projectile).is_none() {
+            return;
+        }
+
+        let Some(target) = world.get::<GlobalTransform>(into).map(|t| t.translation()) else {
+            return;
+        };
+
+        if let Some(ghost) = create_ghost(world, projectile, target.truncate()) {
+            world.spawn(ghost);
+        }
+
+        if flash {
+            FlashSprite(into).apply(world);
+        }
+    }
+}
+
+fn create_ghost(
+    world: &World,
+    projectile: Entity,
+    target: Vec2,
+) -> Option<(SpriteSheetBundle, GhostProjectile)> {
+    let transform = world.get::<GlobalTransform>(projectile)?;
+    let speed = world
+        .get::<Velocity>(projectile)
+        .map(|v| v.linvel.length())
+        .unwrap_or_default();
+
+    // some projectiles keep their sprite in a child, or deeper, like under a
+    // squish
+    let mut stack = vec![projectile];
+    let sprite_entity = std::iter::from_fn(|| {
+        let entity = stack.pop()?;
+        stack.extend(world.get::<Children>(entity).into_iter().flatten());
+        Some(entity)
+    })
+    .find(|&e| {
+        world.get::<TextureAtlasSprite>(e).is_some()
+            && world.get::<Handle<TextureAtlas>>(e).is_some()
+    })?;
+
+    let duration = if speed > 0. {
+        std::cmp::min(
+            Duration::from_secs_f32(16. / speed),
+            Duration::from_millis(500),
+        )
+    } else {
+        Duration::from_millis(500)
+    };
+
+    Some((
+        SpriteSheetBundle {
+            sprite: world.get::<TextureAtlasSprite>(sprite_entity)?.clone(),
+            texture_atlas: world.get::<Handle<TextureAtlas>>(sprite_entity)?.clone(),
+            transform: transform.compute_transform(),
+            ..Default::default()
+        },
+        GhostProjectile::new(transform.translation().truncate(), target, duration),
+    ))
+}
+
+fn update_ghost_projectiles(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut ghost_query: Query<(Entity, &mut Transform, &mut GhostProjectile)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut ghost) in ghost_query.iter_mut() {
+        ghost.time_to_live.tick(time.delta());
+
+        if ghost.time_to_live.finished() {
+            despawn_queue.push(entity, DespawnReason::Expired);
+        } else {
+            // lerp
+            transform.translation = ghost
+                .initial
+                .lerp(ghost.target, ghost.time_to_live.percent())
+                .extend(transform.translation.z);
+
+            transform.scale = Vec3::splat(1. - ghost.time_to_live.percent());
+        }
+    }
+}
+
+fn update_flashes(
+    mut commands: Commands,
+    mut flash_query: Query<(Entity, &mut Flash, Option<&mut TintStack>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, tint_stack) in flash_query.iter_mut() {
+        flash.timer.tick(time.delta());
+
+        if flash.timer.finished() {
+            if let Some(mut tint_stack) = tint_stack {
+                tint_stack.remove(Flash::TINT);
+            }
+
+            commands.entity(entity).remove::<Flash>();
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Visual effects shared between gameplay modules.
+
+pub mod absorb;
+pub mod particles;
+pub mod tint;
+
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+/// All effect plugins.
+pub struct FxPlugins;
+
+impl PluginGroup for FxPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(absorb::AbsorbFxPlugin)
+            .add(particles::ParticleFxPlugin)
+            .add(tint::TintPlugin)
+    }
+}
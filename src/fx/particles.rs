@@ -0,0 +1,404 @@
+//! Particles.
+//!
+//! A [`Particle`] moves, falls, changes color and animates over its lifetime,
+//! then despawns. Particles are usually spawned from a [`ParticleSpec`],
+//! either all at once with [`SpawnParticles`] or over time with a
+//! [`ParticleEmitter`].
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::rng::GameRng;
+
+/// Particle effects plugin.
+pub struct ParticleFxPlugin;
+
+impl Plugin for ParticleFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                emit_particles.run_if(any_with_component::<ParticleEmitter>()),
+                update_particles.run_if(any_with_component::<Particle>()),
+            ),
+        );
+    }
+}
+
+/// A particle.
+///
+/// The entity needs a [`Sprite`] or a [`TextureAtlasSprite`] for color and
+/// animation to show.
+#[derive(Clone, Component, Debug)]
+pub struct Particle {
+    /// The velocity of the particle, in units per second.
+    pub velocity: Vec2,
+    /// How fast the particle falls, in units per second squared.
+    pub gravity: f32,
+    /// The color at the start and end of the particle's life, if it changes.
+    pub colors: Option<(Color, Color)>,
+    /// The atlas frames played over the particle's life, if it animates.
+    pub frames: Option<Range<usize>>,
+    timer: Timer,
+}
+
+impl Particle {
+    /// Creates a new `Particle` that sits still for its lifetime.
+    pub fn new(lifetime: Duration) -> Particle {
+        Particle {
+            velocity: Vec2::ZERO,
+            gravity: 0.,
+            colors: None,
+            frames: None,
+            timer: Timer::new(lifetime, TimerMode::Once),
+        }
+    }
+
+    /// Creates a new `Particle` that plays atlas frames, each for
+    /// `frame_duration`.
+    pub fn animated(frames: Range<usize>, frame_duration: Duration) -> Particle {
+        let lifetime = frame_duration * frames.len() as u32;
+
+        Particle {
+            frames: Some(frames),
+            ..Particle::new(lifetime)
+        }
+    }
+
+    /// How far through its life the particle is, from `0.` to `1.`.
+    pub fn percent(&self) -> f32 {
+        self.timer.percent()
+    }
+}
+
+/// How a particle is drawn.
+#[derive(Clone, Debug)]
+pub enum ParticleSprite {
+    /// A plain square of a size.
+    Square(f32),
+    /// Frames of a texture atlas, played over the particle's life.
+    Atlas {
+        atlas: Handle<TextureAtlas>,
+        frames: Range<usize>,
+    },
+}
+
+/// A description of particles.
+///
+/// Values given as ranges are picked at random for each particle.
+#[derive(Clone, Debug)]
+pub struct ParticleSpec {
+    /// How the particle is drawn.
+    pub sprite: ParticleSprite,
+    /// How long the particle lives, in seconds.
+    pub lifetime: Range<f32>,
+    /// The smallest velocity of the particle.
+    pub velocity_min: Vec2,
+    /// The largest velocity of the particle.
+    pub velocity_max: Vec2,
+    /// How fast the particle falls, in units per second squared.
+    pub gravity: f32,
+    /// The color of the particle.
+    pub color: Color,
+    /// The color the particle fades to at the end of its life, if any.
+    pub end_color: Option<Color>,
+    /// The scale of the particle.
+    pub scale: f32,
+    /// The rotation of the particle, in radians.
+    pub rotation: f32,
+}
+
+impl ParticleSpec {
+    /// Creates a new `ParticleSpec` for squares that sit still for a
+    /// fraction of a second.
+    pub fn square(size: f32, color: Color) -> ParticleSpec {
+        ParticleSpec {
+            sprite: ParticleSprite::Square(size),
+            lifetime: 0.3..0.3,
+            velocity_min: Vec2::ZERO,
+            velocity_max: Vec2::ZERO,
+            gravity: 0.,
+            color,
+            end_color: None,
+            scale: 1.,
+            rotation: 0.,
+        }
+    }
+
+    /// Creates a new `ParticleSpec` that plays atlas frames, each for
+    /// `frame_duration`.
+    pub fn atlas(
+        atlas: Handle<TextureAtlas>,
+        frames: Range<usize>,
+        frame_duration: Duration,
+        color: Color,
+    ) -> ParticleSpec {
+        let lifetime = frame_duration.as_secs_f32() * frames.len() as f32;
+
+        ParticleSpec {
+            sprite: ParticleSprite::Atlas { atlas, frames },
+            lifetime: lifetime..lifetime,
+            ..ParticleSpec::square(1., color)
+        }
+    }
+
+    /// Dust kicked up by feet.
+    pub fn dust(color: Color) -> ParticleSpec {
+        ParticleSpec::square(1., color)
+            .lifetime(0.2..0.4)
+            .velocity(Vec2::new(-16., 4.), Vec2::new(16., 12.))
+            .fade_to(color.with_a(0.))
+    }
+
+    /// Sets how long particles live, in seconds.
+    pub fn lifetime(self, lifetime: Range<f32>) -> ParticleSpec {
+        ParticleSpec { lifetime, ..self }
+    }
+
+    /// Sets the range of velocities.
+    pub fn velocity(self, min: Vec2, max: Vec2) -> ParticleSpec {
+        ParticleSpec {
+            velocity_min: min,
+            velocity_max: max,
+            ..self
+        }
+    }
+
+    /// Sets the gravity.
+    pub fn gravity(self, gravity: f32) -> ParticleSpec {
+        ParticleSpec { gravity, ..self }
+    }
+
+    /// Fades particles to a color over their life.
+    pub fn fade_to(self, end_color: Color) -> ParticleSpec {
+        ParticleSpec {
+            end_color: Some(end_color),
+            ..self
+        }
+    }
+
+    /// Sets the scale.
+    pub fn scale(self, scale: f32) -> ParticleSpec {
+        ParticleSpec { scale, ..self }
+    }
+
+    /// Sets the rotation.
+    pub fn rotation(self, rotation: f32) -> ParticleSpec {
+        ParticleSpec { rotation, ..self }
+    }
+
+    fn spawn(&self, world: &mut World, position: Vec3) -> Entity {
+        let (velocity, lifetime) = match world.get_resource_mut::<GameRng>() {
+            Some(mut rng) => (
+                Vec2::new(
+                    rng.range_f32(self.velocity_min.x, self.velocity_max.x),
+                    rng.range_f32(self.velocity_min.y, self.velocity_max.y),
+                ),
+                rng.range_f32(self.lifetime.start, self.lifetime.end),
+            ),
+            None => (
+                (self.velocity_min + self.velocity_max) / 2.,
+                (self.lifetime.start + self.lifetime.end) / 2.,
+            ),
+        };
+
+        let transform = Transform::from_translation(position)
+            .with_rotation(Quat::from_rotation_z(self.rotation))
+            .with_scale(Vec3::new(self.scale, self.scale, 1.));
+
+        let particle = Particle {
+            velocity,
+            gravity: self.gravity,
+            colors: self.end_color.map(|end_color| (self.color, end_color)),
+            frames: None,
+            timer: Timer::new(Duration::from_secs_f32(lifetime), TimerMode::Once),
+        };
+
+        match &self.sprite {
+            ParticleSprite::Square(size) => world
+                .spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: self.color,
+                            custom_size: Some(Vec2::splat(*size)),
+                            ..Default::default()
+                        },
+                        transform,
+                        ..Default::default()
+                    },
+                    particle,
+                ))
+                .id(),
+            ParticleSprite::Atlas { atlas, frames } => world
+                .spawn((
+                    SpriteSheetBundle {
+                        texture_atlas: atlas.clone(),
+                        sprite: TextureAtlasSprite {
+                            color: self.color,
+                            ..TextureAtlasSprite::new(frames.start)
+                        },
+                        transform,
+                        ..Default::default()
+                    },
+                    Particle {
+                        frames: Some(frames.clone()),
+                        ..particle
+                    },
+                ))
+                .id(),
+        }
+    }
+}
+
+/// A command that spawns a burst of particles.
+pub struct SpawnParticles {
+    spec: ParticleSpec,
+    position: Vec3,
+    count: u32,
+}
+
+impl SpawnParticles {
+    /// Creates a new `SpawnParticles` with a single particle.
+    pub fn new(spec: ParticleSpec, position: Vec3) -> SpawnParticles {
+        SpawnParticles {
+            spec,
+            position,
+            count: 1,
+        }
+    }
+
+    /// Sets the number of particles.
+    pub fn count(self, count: u32) -> SpawnParticles {
+        SpawnParticles { count, ..self }
+    }
+}
+
+impl Command for SpawnParticles {
+    fn apply(self, world: &mut World) {
+        for _ in 0..self.count {
+            self.spec.spawn(world, self.position);
+        }
+    }
+}
+
+/// Spawns particles where it is, every so often.
+#[derive(Clone, Component, Debug)]
+pub struct ParticleEmitter {
+    /// The particles spawned.
+    pub spec: ParticleSpec,
+    /// How many particles are spawned at a time.
+    pub count: u32,
+    interval: Timer,
+    lifetime: Option<Timer>,
+}
+
+impl ParticleEmitter {
+    /// Creates a new `ParticleEmitter` that spawns a particle every
+    /// `interval`, forever.
+    pub fn new(spec: ParticleSpec, interval: Duration) -> ParticleEmitter {
+        ParticleEmitter {
+            spec,
+            count: 1,
+            interval: Timer::new(interval, TimerMode::Repeating),
+            lifetime: None,
+        }
+    }
+
+    /// Sets how many particles are spawned at a time.
+    pub fn count(self, count: u32) -> ParticleEmitter {
+        ParticleEmitter { count, ..self }
+    }
+
+    /// Stops the emitter after a while.
+    ///
+    /// The emitter is removed from its entity when it stops.
+    pub fn lifetime(self, lifetime: Duration) -> ParticleEmitter {
+        ParticleEmitter {
+            lifetime: Some(Timer::new(lifetime, TimerMode::Once)),
+            ..self
+        }
+    }
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    mut emitter_query: Query<(Entity, &GlobalTransform, &mut ParticleEmitter)>,
+    time: Res<Time>,
+) {
+    for (entity, transform, mut emitter) in emitter_query.iter_mut() {
+        emitter.interval.tick(time.delta());
+
+        for _ in 0..emitter.interval.times_finished_this_tick() {
+            commands.add(
+                SpawnParticles::new(emitter.spec.clone(), transform.translation())
+                    .count(emitter.count),
+            );
+        }
+
+        let Some(lifetime) = emitter.lifetime.as_mut() else {
+            continue;
+        };
+
+        if lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<ParticleEmitter>();
+        }
+    }
+}
+
+fn update_particles(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut particle_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Particle,
+        Option<&mut Sprite>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut transform, mut particle, sprite, atlas_sprite) in particle_query.iter_mut() {
+        particle.timer.tick(time.delta());
+
+        if particle.timer.finished() {
+            despawn_queue.push(entity, DespawnReason::Expired);
+            continue;
+        }
+
+        particle.velocity.y -= particle.gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.);
+
+        let percent = particle.percent();
+
+        if let Some((start, end)) = particle.colors {
+            let color = lerp_color(start, end, percent);
+
+            if let Some(mut sprite) = sprite {
+                sprite.color = color;
+            }
+
+            if let Some(mut sprite) = atlas_sprite.as_deref_mut() {
+                sprite.color = color;
+            }
+        }
+
+        if let (Some(frames), Some(mut sprite)) = (&particle.frames, atlas_sprite) {
+            if !frames.is_empty() {
+                let frame = (percent * frames.len() as f32) as usize;
+                sprite.index = frames.start + frame.min(frames.len() - 1);
+            }
+        }
+    }
+}
+
+fn lerp_color(start: Color, end: Color, t: f32) -> Color {
+    let [r, g, b, a] = start.as_rgba_f32();
+    let color = Vec4::new(r, g, b, a).lerp(Vec4::from(end.as_rgba_f32()), t);
+
+    Color::rgba(color.x, color.y, color.z, color.w)
+}
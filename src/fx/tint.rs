@@ -0,0 +1,178 @@
+//! Sprite tints.
+//!
+//! Systems that want to color a sprite push a [`Tint`] onto its [`TintStack`]
+//! instead of writing the sprite's color. The tint with the highest priority
+//! wins, and a single system writes it to the sprite in
+//! [`TintSystem::Resolve`], so a flash ending can't undo a death fade or a
+//! hostility change. That system also keeps colors from going brighter than
+//! white when [flashing](AccessibilitySettings::flashing) is off.
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use crate::settings::AccessibilitySettings;
+
+/// Tint plugin.
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, resolve_tints.in_set(TintSystem::Resolve));
+    }
+}
+
+/// Tint systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum TintSystem {
+    /// The color of every changed [`TintStack`] is written to its sprite.
+    Resolve,
+}
+
+/// A single tint on a [`TintStack`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tint {
+    /// What pushed the tint.
+    ///
+    /// Pushing a tint replaces the tint with the same key.
+    pub key: &'static str,
+    /// Higher priorities win.
+    pub priority: i32,
+    /// The color of the sprite while this tint wins.
+    pub color: Color,
+}
+
+/// The tints on a sprite.
+///
+/// Works for both [`Sprite`]s and [`TextureAtlasSprite`]s.
+#[derive(Clone, Component, Debug)]
+pub struct TintStack {
+    /// The color of the sprite with no tints.
+    pub base: Color,
+    tints: Vec<Tint>,
+}
+
+impl TintStack {
+    /// The priority of the color of a [`Hostility`](crate::enemy::Hostility).
+    pub const HOSTILITY: i32 = 0;
+    /// The priority of lasting states, like a closed vulnerability window.
+    pub const STATUS: i32 = 10;
+    /// The priority of a short flash when hit.
+    pub const FLASH: i32 = 20;
+    /// The priority of dying, which beats everything.
+    pub const DEATH: i32 = 30;
+
+    /// Creates a new, empty `TintStack`.
+    pub fn new(base: Color) -> TintStack {
+        TintStack {
+            base,
+            tints: Vec::new(),
+        }
+    }
+
+    /// Pushes a tint, replacing the one with the same key.
+    pub fn push(&mut self, key: &'static str, priority: i32, color: Color) {
+        self.remove(key);
+        self.tints.push(Tint {
+            key,
+            priority,
+            color,
+        });
+    }
+
+    /// Removes the tint with a key, if any.
+    pub fn remove(&mut self, key: &'static str) {
+        self.tints.retain(|tint| tint.key != key);
+    }
+
+    /// Checks if there is a tint with a key.
+    pub fn contains(&self, key: &'static str) -> bool {
+        self.tints.iter().any(|tint| tint.key == key)
+    }
+
+    /// The tints, in the order they were pushed.
+    pub fn tints(&self) -> &[Tint] {
+        &self.tints
+    }
+
+    /// The color the sprite should be.
+    ///
+    /// Between tints of the same priority, the last one pushed wins.
+    pub fn color(&self) -> Color {
+        self.tints
+            .iter()
+            .max_by_key(|tint| tint.priority)
+            .map(|tint| tint.color)
+            .unwrap_or(self.base)
+    }
+}
+
+impl Default for TintStack {
+    fn default() -> TintStack {
+        TintStack::new(Color::WHITE)
+    }
+}
+
+/// A command that pushes a tint onto an entity, giving it a [`TintStack`] if
+/// it doesn't have one.
+pub struct PushTint {
+    /// The entity.
+    pub entity: Entity,
+    /// The tint.
+    pub tint: Tint,
+}
+
+impl PushTint {
+    /// Creates a new `PushTint`.
+    pub fn new(entity: Entity, key: &'static str, priority: i32, color: Color) -> PushTint {
+        PushTint {
+            entity,
+            tint: Tint {
+                key,
+                priority,
+                color,
+            },
+        }
+    }
+}
+
+impl Command for PushTint {
+    fn apply(self, world: &mut World) {
+        let PushTint { entity, tint } = self;
+
+        let Some(mut entity) = world.get_entity_mut(entity) else {
+            return;
+        };
+
+        if let Some(mut stack) = entity.get_mut::<TintStack>() {
+            stack.push(tint.key, tint.priority, tint.color);
+        } else {
+            let mut stack = TintStack::default();
+            stack.push(tint.key, tint.priority, tint.color);
+            entity.insert(stack);
+        }
+    }
+}
+
+fn resolve_tints(
+    mut tint_query: Query<
+        (
+            &TintStack,
+            Option<&mut Sprite>,
+            Option<&mut TextureAtlasSprite>,
+        ),
+        Changed<TintStack>,
+    >,
+    accessibility: Res<AccessibilitySettings>,
+) {
+    for (stack, sprite, atlas_sprite) in tint_query.iter_mut() {
+        let color = accessibility.limit_flash(stack.color());
+
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+
+        if let Some(mut sprite) = atlas_sprite {
+            sprite.color = color;
+        }
+    }
+}
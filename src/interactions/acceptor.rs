@@ -4,12 +4,14 @@ use bevy::ecs::query::WorldQuery;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use std::time::Duration;
+use crate::enemy::{EnemyDiedEvent, EnemySystem, Hostility};
+use crate::fx::absorb::Absorb;
+use crate::projectile::{
+    beam::Beam, explosion::Explosion, pool::ReleaseProjectile, BounceEvent, HitEvent, Projectile,
+    ProjectileSystem,
+};
 
-use crate::enemy::Hostility;
-use crate::projectile::{HitEvent, Projectile, ProjectileSystem};
-
-use super::{Signal, SignalData, SignalEvent};
+use super::{Junction, Signal, SignalConsumer, SignalData, SignalEvent};
 
 /// Acceptor plugin.
 pub struct AcceptorPlugin;
@@ -22,7 +24,12 @@ impl Plugin for AcceptorPlugin {
                 .after(ProjectileSystem::Event)
                 .before(ProjectileSystem::Despawn),
         )
-        .add_systems(Update, update_ghost_projectiles);
+        .add_systems(
+            Update,
+            inject_death_signals
+                .run_if(on_event::<EnemyDiedEvent>())
+                .after(EnemySystem::Despawn),
+        );
     }
 }
 
@@ -39,27 +46,19 @@ pub struct AcceptorBundle {
 /// hit the collider on this object, instead of being absorbed, they will be
 /// disabled and an associated [`Signal`] is created.
 #[derive(Clone, Component, Debug, Default)]
-pub struct Acceptor;
-
-/// A spooky ghost.
-///
-/// This is created when an acceptor accepts a [`Projectile`], but it wants the
-/// projectile to visually go into the acceptor.
-#[derive(Clone, Component, Debug, Default)]
-pub struct GhostProjectile {
-    initial: Vec2,
-    target: Vec2,
-    time_to_live: Timer,
+pub struct Acceptor {
+    /// The only hostility of projectile taken in, or `None` to take in any.
+    ///
+    /// Projectiles of the other hostility bounce off.
+    pub accepts: Option<Hostility>,
 }
 
-impl GhostProjectile {
-    /// Creates a new `GhostProjectile`.
-    pub fn new(initial: Vec2, target: Vec2, duration: Duration) -> GhostProjectile {
-        GhostProjectile {
-            initial,
-            target,
-            time_to_live: Timer::new(duration, TimerMode::Once),
-        }
+impl Acceptor {
+    /// Checks if a projectile of a hostility is taken in.
+    pub fn takes(&self, hostility: Hostility) -> bool {
+        self.accepts
+            .map(|accepts| accepts == hostility)
+            .unwrap_or(true)
     }
 }
 
@@ -70,32 +69,48 @@ struct ProjectileQuery {
     name: DebugName,
     projectile: &'static mut Projectile,
     hostility: &'static Hostility,
+    velocity: &'static mut Velocity,
+    transform: &'static mut Transform,
     //rigidbody: &'static mut RigidBody,
     //collision_groups: &'static mut CollisionGroups,
     //visibility: &'static mut Visibility,
 }
 
-#[derive(WorldQuery)]
-struct CreateGhostQuery {
-    sprite: &'static TextureAtlasSprite,
-    texture_atlas: &'static Handle<TextureAtlas>,
-    transform: &'static GlobalTransform,
-    velocity: &'static Velocity,
-}
-
 fn accept_projectiles(
     mut commands: Commands,
     mut hit_events: EventReader<HitEvent>,
     acceptor_query: Query<(Entity, &GlobalTransform, &Acceptor)>,
-    mut projectile_query: Query<(ProjectileQuery, CreateGhostQuery)>,
+    mut projectile_query: Query<ProjectileQuery>,
+    explosion_query: Query<&Hostility, Or<(With<Explosion>, With<Beam>)>>,
     mut signal_events: EventWriter<SignalEvent>,
+    mut bounce_events: EventWriter<BounceEvent>,
 ) {
     for ev in hit_events.iter() {
         match (
             projectile_query.get_mut(ev.projectile),
             acceptor_query.get(ev.entity),
         ) {
-            (Ok((mut proj, create_ghost)), Ok((me, acceptor_transform, _acceptor))) => {
+            (Ok(mut proj), Ok((_, _, acceptor))) if !acceptor.takes(*proj.hostility) => {
+                // bounce off of the intake
+                proj.projectile.absorbed = false;
+
+                let incoming = proj.projectile.incoming_velocity();
+                proj.velocity.linvel = match ev.normal {
+                    Some(normal) => incoming - 2. * incoming.dot(normal) * normal,
+                    None => -incoming,
+                };
+
+                let rot = proj.velocity.linvel.y.atan2(proj.velocity.linvel.x);
+                proj.transform.rotation = Quat::from_axis_angle(Vec3::Z, rot);
+
+                bounce_events.send(BounceEvent {
+                    projectile: proj.entity,
+                    count: 1,
+                });
+
+                crate::game_debug!(Interactions, "rejected projectile {:?}", proj.name);
+            }
+            (Ok(mut proj), Ok((me, _, _acceptor))) => {
                 // accept projectile
                 //*proj.visibility = Visibility::Hidden;
                 //*proj.rigidbody = RigidBody::Fixed;
@@ -103,27 +118,12 @@ fn accept_projectiles(
                 // cancel absorb
                 proj.projectile.absorbed = false;
 
-                commands.entity(proj.entity).despawn_recursive();
-
-                bevy::log::info!("accepted projectile {:?}", proj.name);
-
-                // create new ghost
-                commands.spawn((
-                    SpriteSheetBundle {
-                        sprite: create_ghost.sprite.clone(),
-                        texture_atlas: create_ghost.texture_atlas.clone(),
-                        transform: create_ghost.transform.clone().into(),
-                        ..Default::default()
-                    },
-                    GhostProjectile::new(
-                        create_ghost.transform.translation().truncate(),
-                        acceptor_transform.translation().truncate(),
-                        std::cmp::min(
-                            Duration::from_secs_f32(16. / create_ghost.velocity.linvel.length()),
-                            Duration::from_millis(500),
-                        ),
-                    ),
-                ));
+                // the ghost is made from the projectile, so it must be created
+                // before the projectile is released
+                commands.add(Absorb::new(proj.entity, me));
+                commands.add(ReleaseProjectile(proj.entity));
+
+                crate::game_debug!(Interactions, "accepted projectile {:?}", proj.name);
 
                 // create new signal
                 let signal = commands
@@ -131,7 +131,8 @@ fn accept_projectiles(
                         SpatialBundle::default(),
                         Signal::at(
                             SignalData {
-                                hostility: proj.hostility.clone(),
+                                strength: proj.projectile.strength,
+                                ..SignalData::new(proj.hostility.clone())
                             },
                             me,
                         ),
@@ -144,30 +145,88 @@ fn accept_projectiles(
                     overfill: 0.,
                 });
             }
+            (Err(_), Ok((me, _, acceptor))) => {
+                // explosions and beams make signals without being taken in
+                let Ok(hostility) = explosion_query.get(ev.projectile) else {
+                    continue;
+                };
+
+                if !acceptor.takes(*hostility) {
+                    continue;
+                }
+
+                let signal = commands
+                    .spawn((
+                        SpatialBundle::default(),
+                        Signal::at(SignalData::new(hostility.clone()), me),
+                    ))
+                    .id();
+                signal_events.send(SignalEvent {
+                    receiver: me,
+                    sender: me,
+                    signal,
+                    overfill: 0.,
+                });
+            }
             // skip other events
             _ => (),
         }
     }
 }
 
-fn update_ghost_projectiles(
+fn inject_death_signals(
     mut commands: Commands,
-    mut ghost_query: Query<(Entity, &mut Transform, &mut GhostProjectile)>,
-    time: Res<Time>,
+    mut died_events: EventReader<EnemyDiedEvent>,
+    mut signal_events: EventWriter<SignalEvent>,
+    junction_query: Query<(Entity, &GlobalTransform), (With<Junction>, Without<SignalConsumer>)>,
 ) {
-    for (entity, mut transform, mut ghost) in ghost_query.iter_mut() {
-        ghost.time_to_live.tick(time.delta());
-
-        if ghost.time_to_live.finished() {
-            commands.entity(entity).despawn_recursive();
-        } else {
-            // lerp
-            transform.translation = ghost
-                .initial
-                .lerp(ghost.target, ghost.time_to_live.percent())
-                .extend(transform.translation.z);
-
-            transform.scale = Vec3::splat(1. - ghost.time_to_live.percent());
-        }
+    for ev in died_events.iter() {
+        let Some(radius) = ev.signal_radius else {
+            continue;
+        };
+
+        // only plain junctions, so a gate or door isn't set off by an enemy
+        // dying next to it
+        let nearest = junction_query
+            .iter()
+            .map(|(entity, transform)| {
+                (
+                    entity,
+                    transform.translation().truncate().distance(ev.position),
+                )
+            })
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity);
+
+        let Some(junction) = nearest else {
+            continue;
+        };
+
+        // the signal takes the side the enemy was not on: a hostile enemy
+        // gives back a friendly note, and one that was won over gives back a
+        // hostile one
+        let hostility = ev.hostility.opposite();
+
+        crate::game_debug!(
+            Interactions,
+            "enemy {:?} sent a {:?} signal into {:?}",
+            ev.entity,
+            hostility,
+            junction
+        );
+
+        let signal = commands
+            .spawn((
+                SpatialBundle::default(),
+                Signal::at(SignalData::new(hostility), junction),
+            ))
+            .id();
+        signal_events.send(SignalEvent {
+            receiver: junction,
+            sender: junction,
+            signal,
+            overfill: 0.,
+        });
     }
 }
@@ -0,0 +1,175 @@
+//! Keeps the number of live signals in check.
+//!
+//! Every signal loses some [energy](super::SignalData::energy) at each
+//! junction and dies out once it has none, so a loop of pipes can't keep a
+//! signal going forever. A loop that copies signals can still make a lot of
+//! them before they die out, so once a network of connected pipes has more
+//! than [`SignalBudget::max_per_network`] signals, the ones with the least
+//! energy left are despawned first.
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+
+use super::{merge_signals, signal_travel, InteractionSystem, Junction, Signal};
+use crate::despawn::{DespawnQueue, DespawnReason};
+
+/// Signal budget plugin.
+pub struct SignalBudgetPlugin;
+
+impl Plugin for SignalBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SignalBudget>().add_systems(
+            Update,
+            cap_signals
+                .run_if(any_with_component::<Signal>())
+                .in_set(InteractionSystem::TravelSignal)
+                .after(merge_signals)
+                .before(signal_travel),
+        );
+    }
+}
+
+/// Limits on live signals, and how often they were hit.
+#[derive(Debug, Resource)]
+pub struct SignalBudget {
+    /// The most signals that can be alive at once in one network of
+    /// connected pipes.
+    pub max_per_network: usize,
+    /// The number of live signals.
+    pub live: usize,
+    /// The most signals that were ever alive at once.
+    pub peak: usize,
+    /// How many signals died out from running out of energy.
+    pub exhausted: u64,
+    /// How many signals were despawned for going over the cap.
+    pub capped: u64,
+}
+
+impl Default for SignalBudget {
+    fn default() -> SignalBudget {
+        SignalBudget {
+            max_per_network: 128,
+            live: 0,
+            peak: 0,
+            exhausted: 0,
+            capped: 0,
+        }
+    }
+}
+
+fn cap_signals(
+    mut budget: ResMut<SignalBudget>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    signal_query: Query<(Entity, &Signal)>,
+    junction_query: Query<(Entity, &Junction)>,
+) {
+    // merged signals linger until the queue is applied
+    let signals = signal_query
+        .iter()
+        .filter(|(entity, _)| !despawn_queue.contains(*entity))
+        .collect::<Vec<_>>();
+
+    // do not trip change detection
+    if budget.live != signals.len() {
+        budget.live = signals.len();
+    }
+
+    if budget.peak < signals.len() {
+        budget.peak = signals.len();
+    }
+
+    // no network can be over if all of them together aren't
+    if signals.len() <= budget.max_per_network {
+        return;
+    }
+
+    let mut networks = Networks::new(&junction_query);
+
+    let mut by_network: HashMap<Entity, Vec<(Entity, &Signal)>> = HashMap::new();
+
+    for (entity, signal) in signals {
+        by_network
+            .entry(networks.root(signal.source))
+            .or_default()
+            .push((entity, signal));
+    }
+
+    for (root, mut signals) in by_network {
+        if signals.len() <= budget.max_per_network {
+            continue;
+        }
+
+        let over = signals.len() - budget.max_per_network;
+
+        // signals that went around the most loops go first
+        signals.sort_by_key(|(_, signal)| signal.data.energy);
+
+        for &(entity, _) in &signals[..over] {
+            despawn_queue.push(entity, DespawnReason::Other("signal cap"));
+        }
+
+        if budget.capped == 0 {
+            crate::game_warn!(
+                Interactions,
+                "network of {:?} went over {} signals; is there a loop?",
+                root,
+                budget.max_per_network
+            );
+        }
+
+        crate::game_debug!(
+            Interactions,
+            "network of {:?} had {} signals, dropped {}",
+            root,
+            signals.len(),
+            over
+        );
+
+        budget.capped += over as u64;
+    }
+}
+
+/// The networks of connected junctions.
+struct Networks {
+    parents: HashMap<Entity, Entity>,
+}
+
+impl Networks {
+    fn new(junction_query: &Query<(Entity, &Junction)>) -> Networks {
+        let mut networks = Networks {
+            parents: HashMap::new(),
+        };
+
+        for (entity, junction) in junction_query.iter() {
+            for pipe in junction.pipes.iter() {
+                networks.join(entity, pipe.receiver);
+            }
+        }
+
+        networks
+    }
+
+    /// Finds the junction that stands for the network of another.
+    fn root(&mut self, mut entity: Entity) -> Entity {
+        while let Some(&parent) = self.parents.get(&entity) {
+            // skip a step on the way up, so the next search is shorter
+            if let Some(&grandparent) = self.parents.get(&parent) {
+                self.parents.insert(entity, grandparent);
+            }
+
+            entity = parent;
+        }
+
+        entity
+    }
+
+    fn join(&mut self, a: Entity, b: Entity) {
+        let a = self.root(a);
+        let b = self.root(b);
+
+        if a != b {
+            self.parents.insert(a, b);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Counters for the pipe network.
+//!
+//! A [`Counter`] takes in the signals that reach it and only sends one on,
+//! and activates its target, once enough of them have come in.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use super::{InteractionSystem, Junction, Signal, SignalConsumer, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::level::Iid;
+use crate::platform::ActivateEvent;
+
+/// Counter plugin.
+pub struct CounterPlugin;
+
+impl Plugin for CounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, upgrade_counter_targets)
+            .add_systems(
+                Update,
+                count_signals
+                    .run_if(any_with_component::<Counter>())
+                    .after(InteractionSystem::TravelSignal),
+            );
+    }
+}
+
+/// A bundle for a [`Counter`].
+#[derive(Bundle, Clone, Debug)]
+pub struct CounterBundle {
+    pub counter: Counter,
+    pub consumer: SignalConsumer,
+}
+
+impl CounterBundle {
+    /// Creates a new `CounterBundle`.
+    pub fn new(counter: Counter) -> CounterBundle {
+        CounterBundle {
+            counter,
+            consumer: SignalConsumer,
+        }
+    }
+}
+
+/// Counts the signals that reach a junction.
+///
+/// Once [`Counter::required`] signals have come in, the last one is sent on
+/// down every other pipe, the [target](Counter::activates) is activated and
+/// the count starts over.
+#[derive(Clone, Component, Debug)]
+pub struct Counter {
+    /// How many signals it takes.
+    pub required: u32,
+    /// How many signals have come in so far.
+    pub current: u32,
+    /// How long the counter waits for the next signal before starting over,
+    /// if it ever does.
+    pub timeout: Option<Duration>,
+    /// The entity activated when the count is reached.
+    pub activates: Option<Entity>,
+    activates_iid: Option<String>,
+    last: f32,
+}
+
+impl Counter {
+    /// Creates a new `Counter` that needs some signals and never times out.
+    pub fn new(required: u32) -> Counter {
+        Counter {
+            required,
+            current: 0,
+            timeout: None,
+            activates: None,
+            activates_iid: None,
+            last: 0.,
+        }
+    }
+
+    /// Sets the timeout. See [`Counter::timeout`].
+    pub fn with_timeout(self, timeout: Duration) -> Counter {
+        Counter {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the target by its LDtk iid, to be found once it is spawned.
+    pub fn with_activates_iid(self, iid: String) -> Counter {
+        Counter {
+            activates_iid: Some(iid),
+            ..self
+        }
+    }
+
+    /// Counts a signal that came in at a time in seconds.
+    ///
+    /// Returns `true` if the count was reached.
+    pub fn count(&mut self, now: f32) -> bool {
+        if self.timed_out(now) {
+            self.current = 0;
+        }
+
+        self.current += 1;
+        self.last = now;
+
+        if self.current >= self.required {
+            self.current = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks if the counter has waited too long for its next signal.
+    pub fn timed_out(&self, now: f32) -> bool {
+        self.timeout
+            .map(|timeout| self.current > 0 && now - self.last > timeout.as_secs_f32())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Counter {
+        Counter::new(3)
+    }
+}
+
+fn upgrade_counter_targets(
+    mut counter_query: Query<&mut Counter>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for mut counter in counter_query.iter_mut() {
+        let Some(iid_request) = &counter.activates_iid else {
+            continue;
+        };
+
+        let found = iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == *iid_request)
+            .map(|(e, _)| e);
+
+        if let Some(found) = found {
+            counter.activates = Some(found);
+            counter.activates_iid = None;
+        }
+    }
+}
+
+fn count_signals(
+    mut commands: Commands,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut counter_query: Query<(&mut Counter, &Junction)>,
+    signal_query: Query<&Signal>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for ev in signal_events.iter() {
+        let Ok((mut counter, junction)) = counter_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+
+        if !counter.count(now) {
+            crate::game_debug!(
+                Interactions,
+                "counter {:?} at {}/{}",
+                ev.receiver,
+                counter.current,
+                counter.required
+            );
+            continue;
+        }
+
+        crate::game_debug!(Interactions, "counter {:?} reached", ev.receiver);
+
+        if let Some(target) = counter.activates {
+            activate_events.send(ActivateEvent(target));
+        }
+
+        for pipe in junction.pipes.iter().filter(|p| p.receiver != ev.sender) {
+            commands.spawn((
+                SpatialBundle::default(),
+                Signal {
+                    data: signal.data.clone(),
+                    source: ev.receiver,
+                    destination: Some(pipe.receiver),
+                    position: 0.,
+                    speed: pipe.speed,
+                },
+            ));
+        }
+    }
+
+    // do not trip change detection
+    for (mut counter, _) in counter_query.iter_mut() {
+        if counter.timed_out(now) {
+            counter.current = 0;
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Echoes for the pipe network.
+//!
+//! An [`Echo`] sits on a junction and lets every signal through as normal,
+//! then sends a copy of it on the same way after a delay. A loop with an echo
+//! in it keeps itself going, so echoes hold back when there are too many
+//! signals around already.
+
+use bevy::prelude::*;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{InteractionSystem, Signal, SignalData, SignalEvent};
+
+/// Echo plugin.
+pub struct EchoPlugin;
+
+impl Plugin for EchoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                record_echoes.after(InteractionSystem::TravelSignal),
+                release_echoes.before(InteractionSystem::TravelSignal),
+            )
+                .distributive_run_if(any_with_component::<Echo>()),
+        );
+    }
+}
+
+/// Repeats every signal that goes through a junction after a delay.
+#[derive(Clone, Component, Debug)]
+pub struct Echo {
+    /// How long after a signal its echo is sent.
+    pub delay: Duration,
+    /// How much of a signal's strength its echo keeps.
+    ///
+    /// Echoes that end up weaker than [`Echo::MIN_STRENGTH`] are never sent.
+    pub falloff: f32,
+    pending: VecDeque<PendingEcho>,
+}
+
+/// Marks a signal that is an echo, so the echo that sent it doesn't echo it
+/// again when it comes back around.
+#[derive(Clone, Component, Debug)]
+pub struct Echoed(pub Entity);
+
+#[derive(Clone, Debug)]
+struct PendingEcho {
+    due: f32,
+    data: SignalData,
+    sender: Entity,
+}
+
+impl Echo {
+    /// The delay of an echo if the level doesn't say.
+    pub const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+    /// How many echoes can be waiting at once. Signals that come in while
+    /// the echo is full are let through without one.
+    pub const MAX_PENDING: usize = 4;
+    /// Echoes aren't sent while there are this many signals in the world.
+    pub const SIGNAL_LIMIT: usize = 64;
+    /// The weakest echo that is sent.
+    pub const MIN_STRENGTH: f32 = 0.1;
+
+    /// Creates a new `Echo` with a delay, that keeps all of a signal's
+    /// strength.
+    pub fn new(delay: Duration) -> Echo {
+        Echo {
+            delay,
+            falloff: 1.,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Sets the falloff. See [`Echo::falloff`].
+    pub fn with_falloff(self, falloff: f32) -> Echo {
+        Echo { falloff, ..self }
+    }
+
+    /// Takes in a signal from `sender` at a time in seconds, to be echoed
+    /// later.
+    pub fn push(&mut self, sender: Entity, mut data: SignalData, now: f32) {
+        data.strength *= self.falloff;
+
+        if data.strength < Echo::MIN_STRENGTH || self.pending.len() >= Echo::MAX_PENDING {
+            return;
+        }
+
+        self.pending.push_back(PendingEcho {
+            due: now + self.delay.as_secs_f32(),
+            data,
+            sender,
+        });
+    }
+
+    /// Takes the next echo that is due at a time in seconds, and who it
+    /// seems to come from.
+    pub fn pop_due(&mut self, now: f32) -> Option<(Entity, SignalData)> {
+        if self.pending.front()?.due > now {
+            return None;
+        }
+
+        self.pending
+            .pop_front()
+            .map(|pending| (pending.sender, pending.data))
+    }
+}
+
+impl Default for Echo {
+    fn default() -> Echo {
+        Echo::new(Echo::DEFAULT_DELAY)
+    }
+}
+
+fn record_echoes(
+    mut signal_events: EventReader<SignalEvent>,
+    mut echo_query: Query<&mut Echo>,
+    signal_query: Query<(&Signal, Option<&Echoed>)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for ev in signal_events.iter() {
+        let Ok(mut echo) = echo_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        let Ok((signal, echoed)) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        // a loop with an echo in it grows by one signal a lap, not double
+        if echoed.map(|echoed| echoed.0) == Some(ev.receiver) {
+            continue;
+        }
+
+        echo.push(ev.sender, signal.data.clone(), now);
+    }
+}
+
+fn release_echoes(
+    mut commands: Commands,
+    mut signal_events: EventWriter<SignalEvent>,
+    mut echo_query: Query<(Entity, &mut Echo)>,
+    signal_query: Query<(), With<Signal>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    let mut signals = signal_query.iter().count();
+
+    for (entity, mut echo) in echo_query.iter_mut() {
+        while let Some((sender, data)) = echo.pop_due(now) {
+            // the echo fades out rather than flood the pipes
+            if signals >= Echo::SIGNAL_LIMIT {
+                crate::game_debug!(Interactions, "echo {:?} held back", entity);
+                continue;
+            }
+
+            signals += 1;
+
+            let signal = commands
+                .spawn((
+                    SpatialBundle::default(),
+                    Signal::at(data, entity),
+                    Echoed(entity),
+                ))
+                .id();
+
+            // routed as though it just came in from the same pipe
+            signal_events.send(SignalEvent {
+                sender,
+                receiver: entity,
+                signal,
+                overfill: 0.,
+            });
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Platforms driven by signals.
+//!
+//! A [`MovingPlatform`] only goes one way when it is activated. A
+//! [`PlatformController`] hooked up to the pipes turns each signal that
+//! reaches it into a [`PlatformCommand`] for its platform, picked by the
+//! hostility of the signal, so drums and acceptors can send an elevator back
+//! and forth.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use super::{InteractionSystem, Junction, Signal, SignalConsumer, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::Hostility;
+use crate::level::{pipe::PipeInput, Iid};
+use crate::platform::MovingPlatform;
+
+/// Elevator plugin.
+pub struct ElevatorPlugin;
+
+impl Plugin for ElevatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PlatformControllerBundle>("PlatformController")
+            .add_systems(Update, upgrade_controller_targets)
+            .add_systems(
+                Update,
+                command_platforms
+                    .run_if(any_with_component::<PlatformController>())
+                    .after(InteractionSystem::TravelSignal),
+            );
+    }
+}
+
+/// Something to tell a [`MovingPlatform`] to do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlatformCommand {
+    /// Go to the end location.
+    GoToEnd,
+    /// Go back to the start location.
+    ReturnToStart,
+    /// Turn around, or go to the far end if sitting still.
+    #[default]
+    Toggle,
+    /// Stop where it is.
+    Stop,
+}
+
+impl PlatformCommand {
+    /// Finds a command by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<PlatformCommand> {
+        match name {
+            "GoToEnd" => Some(PlatformCommand::GoToEnd),
+            "ReturnToStart" => Some(PlatformCommand::ReturnToStart),
+            "Toggle" => Some(PlatformCommand::Toggle),
+            "Stop" => Some(PlatformCommand::Stop),
+            _ => None,
+        }
+    }
+
+    /// Tells a platform to do this.
+    pub fn apply(self, platform: &mut MovingPlatform) {
+        match self {
+            PlatformCommand::GoToEnd => platform.go_to_end(),
+            PlatformCommand::ReturnToStart => platform.return_to_start(),
+            PlatformCommand::Toggle => platform.toggle(),
+            PlatformCommand::Stop => platform.stop(),
+        }
+    }
+}
+
+/// Tells a [`MovingPlatform`] what to do with every signal that reaches it.
+#[derive(Clone, Component, Debug)]
+pub struct PlatformController {
+    /// The platform.
+    pub platform: Option<Entity>,
+    /// What friendly signals tell the platform to do.
+    pub friendly: PlatformCommand,
+    /// What hostile signals tell the platform to do.
+    pub hostile: PlatformCommand,
+    platform_iid: Option<String>,
+}
+
+impl PlatformController {
+    /// Creates a new `PlatformController` that sends commands to a platform.
+    pub fn new(friendly: PlatformCommand, hostile: PlatformCommand) -> PlatformController {
+        PlatformController {
+            platform: None,
+            friendly,
+            hostile,
+            platform_iid: None,
+        }
+    }
+
+    /// The command for a signal of a hostility.
+    pub fn command(&self, hostility: Hostility) -> PlatformCommand {
+        match hostility {
+            Hostility::Friendly => self.friendly,
+            Hostility::Hostile => self.hostile,
+        }
+    }
+}
+
+impl Default for PlatformController {
+    fn default() -> PlatformController {
+        PlatformController::new(PlatformCommand::Toggle, PlatformCommand::Stop)
+    }
+}
+
+/// A bundle for a [`PlatformController`].
+#[derive(Bundle, Default)]
+pub struct PlatformControllerBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub controller: PlatformController,
+    pub junction: Junction,
+    pub consumer: SignalConsumer,
+    pub input: PipeInput,
+    pub iid: Iid,
+}
+
+impl LdtkEntity for PlatformControllerBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let platform_iid = entity_instance
+            .get_maybe_entity_ref_field("Platform")
+            .ok() // may not exist
+            .and_then(|r| r.as_ref())
+            .map(|r| r.entity_iid.clone());
+
+        let command = |field: &str, default: PlatformCommand| {
+            entity_instance
+                .get_maybe_enum_field(field)
+                .ok() // may not exist
+                .and_then(|command| command.as_deref())
+                .map(|command| PlatformCommand::from_name(command).expect("valid command"))
+                .unwrap_or(default)
+        };
+
+        let default = PlatformController::default();
+
+        PlatformControllerBundle {
+            controller: PlatformController {
+                platform_iid,
+                ..PlatformController::new(
+                    command("Friendly", default.friendly),
+                    command("Hostile", default.hostile),
+                )
+            },
+            input: PipeInput::from_field(entity_instance, layer_instance, "Input"),
+            iid: Iid::from(entity_instance),
+            ..Default::default()
+        }
+    }
+}
+
+fn upgrade_controller_targets(
+    mut controller_query: Query<&mut PlatformController>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for mut controller in controller_query.iter_mut() {
+        let Some(iid_request) = &controller.platform_iid else {
+            continue;
+        };
+
+        let found = iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == *iid_request)
+            .map(|(e, _)| e);
+
+        if let Some(found) = found {
+            controller.platform = Some(found);
+            controller.platform_iid = None;
+        }
+    }
+}
+
+fn command_platforms(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    controller_query: Query<&PlatformController>,
+    signal_query: Query<&Signal>,
+    mut platform_query: Query<&mut MovingPlatform>,
+) {
+    for ev in signal_events.iter() {
+        let Ok(controller) = controller_query.get(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+
+        let Some(mut platform) = controller
+            .platform
+            .and_then(|platform| platform_query.get_mut(platform).ok())
+        else {
+            continue;
+        };
+
+        let command = controller.command(signal.data.hostility);
+
+        command.apply(&mut platform);
+
+        crate::game_debug!(
+            Interactions,
+            "controller {:?} sent {:?} to {:?}",
+            ev.receiver,
+            command,
+            controller.platform
+        );
+    }
+}
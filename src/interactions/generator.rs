@@ -2,9 +2,14 @@
 
 use bevy::prelude::*;
 
-use super::{InteractionSystem, Signal, SignalEvent};
+use bevy_ecs_ldtk::{ldtk::ldtk_fields::LdtkFields as _, EntityInstance};
 
-use crate::projectile::prefab::{CreateProjectile, ProjectilePrefab};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{InteractionSystem, Signal, SignalData, SignalEvent};
+
+use crate::projectile::prefab::{CreateProjectile, ProjectilePrefab, Trajectory};
 
 /// Generator plugin.
 pub struct GeneratorPlugin;
@@ -25,21 +30,158 @@ pub struct Generator {
     pub location: Vec3,
     /// The projectile prefab.
     pub prefab: ProjectilePrefab,
+    /// How much the phase of each projectile is ahead of the last one, in
+    /// radians.
+    ///
+    /// This staggers projectiles that sway on a
+    /// [`SineWave`](crate::projectile::SineWave) into a wave train.
+    pub phase_step: f32,
+    /// The path projectiles take once they leave the generator.
+    pub trajectory: Trajectory,
+    /// How often the generator may fire.
+    pub limit: GeneratorLimit,
+    next_phase: f32,
+    cooldown: f32,
+    queue: VecDeque<SignalData>,
+}
+
+impl Generator {
+    /// Creates a new `Generator`.
+    pub fn new(prefab: ProjectilePrefab, location: Vec3) -> Generator {
+        Generator {
+            location,
+            prefab,
+            phase_step: 0.,
+            trajectory: Trajectory::default(),
+            limit: GeneratorLimit::default(),
+            next_phase: 0.,
+            cooldown: 0.,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Sets the phase step. See [`Generator::phase_step`].
+    pub fn with_phase_step(self, phase_step: f32) -> Generator {
+        Generator { phase_step, ..self }
+    }
+
+    /// Sets the trajectory. See [`Generator::trajectory`].
+    pub fn with_trajectory(self, trajectory: Trajectory) -> Generator {
+        Generator { trajectory, ..self }
+    }
+
+    /// Sets the limit. See [`Generator::limit`].
+    pub fn with_limit(self, limit: GeneratorLimit) -> Generator {
+        Generator { limit, ..self }
+    }
+
+    /// Checks if the generator is waiting out its cooldown.
+    pub fn cooling_down(&self) -> bool {
+        self.cooldown > 0.
+    }
+
+    /// The signals waiting for the generator to cool down.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// Keeps signals that come together from firing a [`Generator`] all at once.
+///
+/// After firing, a generator waits out its [`GeneratorLimit::cooldown`].
+/// Signals that reach it in the meantime wait in a queue and fire one per
+/// cooldown, up to [`GeneratorLimit::queue`] of them; the rest are dropped.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
+pub struct GeneratorLimit {
+    /// How long the generator waits after firing.
+    pub cooldown: Duration,
+    /// How many signals can wait for the cooldown.
+    pub queue: usize,
+}
+
+impl GeneratorLimit {
+    /// Reads the `Cooldown` and `Queue` fields of an LDtk entity.
+    ///
+    /// Entities without them fire for every signal.
+    pub fn from_entity_instance(inst: &EntityInstance) -> GeneratorLimit {
+        let cooldown = inst
+            .get_maybe_float_field("Cooldown")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or_default();
+        let queue = inst
+            .get_maybe_int_field("Queue")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or_default();
+
+        GeneratorLimit {
+            cooldown: Duration::from_secs_f32(cooldown.max(0.)),
+            queue: queue.max(0) as usize,
+        }
+    }
+}
+
+/// How much the phase of each projectile a [`Generator`] fires is ahead of
+/// the last one, in radians. See [`Generator::phase_step`].
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
+pub struct PhaseStep(pub f32);
+
+impl PhaseStep {
+    /// Reads the `PhaseStep` field of an LDtk entity, in degrees.
+    ///
+    /// Entities without it fire every projectile in phase.
+    pub fn from_entity_instance(inst: &EntityInstance) -> PhaseStep {
+        let degrees = inst
+            .get_maybe_float_field("PhaseStep")
+            .ok()
+            .copied()
+            .flatten()
+            .unwrap_or_default();
+
+        PhaseStep(degrees.to_radians())
+    }
 }
 
 fn generate_projectile(
     mut commands: Commands,
-    generator_query: Query<(&GlobalTransform, &Generator)>,
+    mut generator_query: Query<(Entity, &GlobalTransform, &mut Generator)>,
     mut signal_events: EventReader<SignalEvent>,
     signal_query: Query<&Signal>,
+    time: Res<Time>,
 ) {
+    for (_, transform, mut generator) in generator_query.iter_mut() {
+        // do not trip change detection
+        if !generator.cooling_down() {
+            continue;
+        }
+
+        generator.cooldown -= time.delta_seconds();
+
+        // fire what waited out the cooldown
+        while !generator.cooling_down() {
+            let Some(data) = generator.queue.pop_front() else {
+                break;
+            };
+
+            generator.cooldown += generator.limit.cooldown.as_secs_f32();
+            fire(&mut commands, transform, &mut generator, &data);
+        }
+
+        if !generator.cooling_down() {
+            generator.cooldown = 0.;
+        }
+    }
+
     for ev in signal_events.iter() {
         // do not produce projectiles for accepting
         if ev.sender == ev.receiver {
             continue;
         }
 
-        let Ok((transform, generator)) = generator_query.get(ev.receiver) else {
+        let Ok((entity, transform, mut generator)) = generator_query.get_mut(ev.receiver) else {
             continue;
         };
 
@@ -47,16 +189,38 @@ fn generate_projectile(
             continue;
         };
 
-        let mut location = transform.translation() + generator.location;
+        if !generator.cooling_down() {
+            generator.cooldown = generator.limit.cooldown.as_secs_f32();
+            fire(&mut commands, transform, &mut generator, &signal.data);
+        } else if generator.queue.len() < generator.limit.queue {
+            generator.queue.push_back(signal.data.clone());
+        } else {
+            crate::game_debug!(Interactions, "generator {:?} dropped a signal", entity);
+        }
+    }
+}
 
-        // set so that it appears above the tilemap
-        // idk tihs number is really arbitrary
-        location.z = 30.;
+fn fire(
+    commands: &mut Commands,
+    transform: &GlobalTransform,
+    generator: &mut Generator,
+    data: &SignalData,
+) {
+    let mut location = transform.translation() + generator.location;
 
-        // create a new projectile
-        commands.add(
-            CreateProjectile::new(generator.prefab.clone(), location)
-                .hostility(signal.data.hostility.clone()),
-        );
-    }
+    // set so that it appears above the tilemap
+    // idk tihs number is really arbitrary
+    location.z = 30.;
+
+    let phase = generator.next_phase;
+    generator.next_phase = (phase + generator.phase_step) % std::f32::consts::TAU;
+
+    // create a new projectile
+    commands.add(
+        CreateProjectile::new(generator.prefab.clone(), location)
+            .hostility(data.hostility.clone())
+            .strength(data.strength)
+            .phase(phase)
+            .trajectory(generator.trajectory),
+    );
 }
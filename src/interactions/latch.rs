@@ -0,0 +1,206 @@
+//! Latches for the pipe network.
+//!
+//! A [`Latch`] flips on or off with every signal that reaches it, and keeps
+//! whatever it is hooked up to powered for as long as it is on: its targets
+//! are activated every frame, and a signal goes down its other pipes every
+//! [`Latch::interval`]. Switching it off sends its targets a
+//! [`DeactivateEvent`], so platforms go back and doors close.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use super::{InteractionSystem, Junction, Signal, SignalConsumer, SignalData, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::Hostility;
+use crate::level::Iid;
+use crate::platform::{ActivateEvent, DeactivateEvent};
+
+/// Latch plugin.
+pub struct LatchPlugin;
+
+impl Plugin for LatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Latch>()
+            .add_systems(Update, upgrade_latch_targets)
+            .add_systems(
+                Update,
+                (toggle_latches, power_from_latches)
+                    .chain()
+                    .run_if(any_with_component::<Latch>())
+                    .after(InteractionSystem::TravelSignal),
+            );
+    }
+}
+
+/// A bundle for a [`Latch`].
+#[derive(Bundle, Clone, Debug)]
+pub struct LatchBundle {
+    pub latch: Latch,
+    pub consumer: SignalConsumer,
+}
+
+impl LatchBundle {
+    /// Creates a new `LatchBundle`.
+    pub fn new(latch: Latch) -> LatchBundle {
+        LatchBundle {
+            latch,
+            consumer: SignalConsumer,
+        }
+    }
+}
+
+/// Holds an on or off state that every signal that reaches it flips.
+#[derive(Clone, Component, Debug, Reflect)]
+pub struct Latch {
+    /// Whether the latch is on.
+    pub on: bool,
+    /// How often a signal goes down each pipe while the latch is on.
+    pub interval: Duration,
+    /// The entities activated every frame while the latch is on, and
+    /// deactivated when it is switched off.
+    pub activates: Vec<Entity>,
+    /// The pipe the signal that last flipped the latch came in from.
+    ///
+    /// Nothing is sent back up it, or the latch would power whatever flipped
+    /// it.
+    pub input: Option<Entity>,
+    activates_iid: Vec<String>,
+    timer: Timer,
+}
+
+impl Latch {
+    /// The interval of a latch if the level doesn't say.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Creates a new `Latch` that starts off.
+    pub fn new(interval: Duration) -> Latch {
+        Latch {
+            on: false,
+            interval,
+            activates: Vec::new(),
+            input: None,
+            activates_iid: Vec::new(),
+            timer: Timer::new(interval, TimerMode::Repeating),
+        }
+    }
+
+    /// Sets the targets by their LDtk iids, to be found once they are
+    /// spawned.
+    pub fn with_activates_iid(self, activates_iid: Vec<String>) -> Latch {
+        Latch {
+            activates_iid,
+            ..self
+        }
+    }
+
+    /// Flips the latch from a signal that came in from `sender`.
+    pub fn toggle(&mut self, sender: Entity) {
+        self.on = !self.on;
+        self.input = Some(sender);
+
+        // power comes on straight away
+        self.timer = Timer::new(self.interval, TimerMode::Repeating);
+        self.timer.set_elapsed(self.interval);
+    }
+}
+
+impl Default for Latch {
+    fn default() -> Latch {
+        Latch::new(Latch::DEFAULT_INTERVAL)
+    }
+}
+
+fn upgrade_latch_targets(mut latch_query: Query<&mut Latch>, iid_query: Query<(Entity, &Iid)>) {
+    for mut latch in latch_query.iter_mut() {
+        if latch.activates_iid.is_empty() {
+            continue;
+        }
+
+        let activates_iid = std::mem::take(&mut latch.activates_iid);
+
+        for iid_request in activates_iid {
+            let found = iid_query
+                .iter()
+                .find(|(_, iid)| iid.0 == iid_request)
+                .map(|(e, _)| e);
+
+            match found {
+                Some(found) => latch.activates.push(found),
+                None => latch.activates_iid.push(iid_request),
+            }
+        }
+    }
+}
+
+fn toggle_latches(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut deactivate_events: EventWriter<DeactivateEvent>,
+    mut latch_query: Query<&mut Latch>,
+) {
+    for ev in signal_events.iter() {
+        let Ok(mut latch) = latch_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+
+        latch.toggle(ev.sender);
+
+        if !latch.on {
+            for &target in latch.activates.iter() {
+                deactivate_events.send(DeactivateEvent(target));
+            }
+        }
+
+        crate::game_debug!(
+            Interactions,
+            "latch {:?} {}",
+            ev.receiver,
+            if latch.on { "on" } else { "off" }
+        );
+    }
+}
+
+fn power_from_latches(
+    mut commands: Commands,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut latch_query: Query<(Entity, &mut Latch, &Junction)>,
+    time: Res<Time>,
+) {
+    for (entity, mut latch, junction) in latch_query.iter_mut() {
+        // do not trip change detection
+        if !latch.on {
+            continue;
+        }
+
+        for &target in latch.activates.iter() {
+            activate_events.send(ActivateEvent(target));
+        }
+
+        latch.timer.tick(time.delta());
+
+        if latch.timer.times_finished_this_tick() == 0 {
+            continue;
+        }
+
+        for pipe in junction
+            .pipes
+            .iter()
+            .filter(|pipe| Some(pipe.receiver) != latch.input)
+        {
+            commands.spawn((
+                SpatialBundle::default(),
+                Signal {
+                    data: SignalData::new(Hostility::Friendly),
+                    source: entity,
+                    destination: Some(pipe.receiver),
+                    position: 0.,
+                    speed: pipe.speed,
+                },
+            ));
+        }
+    }
+}
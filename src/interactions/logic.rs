@@ -0,0 +1,269 @@
+//! Logic gates for the pipe network.
+//!
+//! A [`Gate`] sits on a junction and takes in every signal that reaches it.
+//! Signals arriving through different pipes within the gate's window are its
+//! inputs; when they satisfy the gate, it sends one signal out through its
+//! output pipe.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use super::{InteractionSystem, Junction, Pipe, Signal, SignalConsumer, SignalData, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::Hostility;
+
+/// Logic gate plugin.
+pub struct LogicPlugin;
+
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            evaluate_gates
+                .run_if(any_with_component::<Gate>())
+                .after(InteractionSystem::TravelSignal),
+        );
+    }
+}
+
+/// A bundle for a [`Gate`].
+#[derive(Bundle, Clone, Debug)]
+pub struct GateBundle {
+    pub gate: Gate,
+    pub consumer: SignalConsumer,
+}
+
+impl GateBundle {
+    /// Creates a new `GateBundle`.
+    pub fn new(gate: Gate) -> GateBundle {
+        GateBundle {
+            gate,
+            consumer: SignalConsumer,
+        }
+    }
+}
+
+/// The condition of a [`Gate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    /// Fires once every input has a signal.
+    #[default]
+    And,
+    /// Fires as soon as any input has a signal. Signals on the other inputs
+    /// within the window are swallowed.
+    Or,
+    /// Fires at the end of the window if no input had a signal.
+    ///
+    /// The window of a `Not` gate is always running, so a gate with nothing
+    /// coming in fires once every window.
+    Not,
+    /// Fires at the end of the window if exactly one input had a signal.
+    Xor,
+}
+
+impl GateKind {
+    /// Finds a gate kind by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<GateKind> {
+        match name {
+            "And" => Some(GateKind::And),
+            "Or" => Some(GateKind::Or),
+            "Not" => Some(GateKind::Not),
+            "Xor" => Some(GateKind::Xor),
+            _ => None,
+        }
+    }
+}
+
+/// A logic gate on a junction.
+///
+/// Every pipe out of the junction is an input, except the one in the
+/// direction of [`Gate::output`].
+#[derive(Clone, Component, Debug)]
+pub struct Gate {
+    /// What the gate checks for.
+    pub kind: GateKind,
+    /// How long signals on the inputs count for, starting from the first one.
+    pub window: Duration,
+    /// The direction of the output pipe from the junction.
+    pub output: Vec2,
+    inputs: Vec<(Entity, SignalData)>,
+    opened: Option<f32>,
+    fired: bool,
+}
+
+impl Gate {
+    /// The window of a gate if the level doesn't say.
+    pub const DEFAULT_WINDOW: Duration = Duration::from_millis(500);
+
+    /// Creates a new `Gate` with an output pipe in a direction.
+    pub fn new(kind: GateKind, output: Vec2) -> Gate {
+        Gate {
+            kind,
+            window: Gate::DEFAULT_WINDOW,
+            output,
+            inputs: Vec::new(),
+            opened: None,
+            fired: false,
+        }
+    }
+
+    /// Sets the window. See [`Gate::window`].
+    pub fn with_window(self, window: Duration) -> Gate {
+        Gate { window, ..self }
+    }
+
+    /// Takes a signal on the input from `sender`, at a time in seconds.
+    ///
+    /// A second signal on the same input within the window replaces the
+    /// first.
+    pub fn receive(&mut self, sender: Entity, data: SignalData, now: f32) {
+        self.inputs.retain(|(input, _)| *input != sender);
+        self.inputs.push((sender, data));
+        self.opened.get_or_insert(now);
+    }
+
+    /// Checks the gate at a time in seconds, with some number of input pipes.
+    ///
+    /// Returns the signal to send out, if the gate fires.
+    pub fn evaluate(&mut self, input_count: usize, now: f32) -> Option<SignalData> {
+        // not gates are always listening
+        if self.kind == GateKind::Not {
+            self.opened.get_or_insert(now);
+        }
+
+        let opened = self.opened?;
+
+        let closed = now - opened >= self.window.as_secs_f32();
+
+        let fired = match self.kind {
+            _ if self.fired => None,
+            GateKind::And if self.inputs.len() >= input_count.max(1) => Some(self.combined()),
+            GateKind::Or => self.inputs.first().map(|(_, data)| data.clone()),
+            GateKind::Not if closed && self.inputs.is_empty() => {
+                Some(SignalData::new(Hostility::Friendly))
+            }
+            GateKind::Xor if closed && self.inputs.len() == 1 => {
+                self.inputs.first().map(|(_, data)| data.clone())
+            }
+            _ => None,
+        };
+
+        self.fired |= fired.is_some();
+
+        // or gates swallow the rest of their window after firing
+        if closed || (self.fired && self.kind != GateKind::Or) {
+            self.inputs.clear();
+            self.opened = None;
+            self.fired = false;
+        }
+
+        fired
+    }
+
+    /// The signal made from every input at once.
+    fn combined(&self) -> SignalData {
+        let hostility = self
+            .inputs
+            .last()
+            .map(|(_, data)| data.hostility)
+            .unwrap_or_default();
+
+        // the output is no fresher than its freshest input, so a loop through
+        // a gate still dies out
+        SignalData {
+            strength: self.inputs.iter().map(|(_, data)| data.strength).sum(),
+            energy: self
+                .inputs
+                .iter()
+                .map(|(_, data)| data.energy)
+                .max()
+                .unwrap_or(SignalData::ENERGY),
+            ..SignalData::new(hostility)
+        }
+    }
+}
+
+/// Finds the pipe out of a junction that goes the closest to a direction.
+fn pipe_toward<'a>(
+    junction: &'a Junction,
+    position: Vec2,
+    direction: Vec2,
+    transform_query: &Query<&GlobalTransform>,
+) -> Option<&'a Pipe> {
+    junction
+        .pipes
+        .iter()
+        .filter_map(|pipe| {
+            let other = transform_query.get(pipe.receiver).ok()?;
+            let offset = (other.translation().truncate() - position).try_normalize()?;
+
+            Some((pipe, offset.dot(direction)))
+        })
+        .filter(|(_, alignment)| *alignment > 0.5)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(pipe, _)| pipe)
+}
+
+fn evaluate_gates(
+    mut commands: Commands,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut gate_query: Query<(Entity, &mut Gate, &Junction, &GlobalTransform)>,
+    signal_query: Query<&Signal>,
+    transform_query: Query<&GlobalTransform>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for ev in signal_events.iter() {
+        let Ok((_, mut gate, junction, transform)) = gate_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+
+        // signals coming back up the output are lost
+        let position = transform.translation().truncate();
+
+        let output = pipe_toward(junction, position, gate.output, &transform_query);
+
+        if output.map(|pipe| pipe.receiver) == Some(ev.sender) {
+            continue;
+        }
+
+        gate.receive(ev.sender, signal.data.clone(), now);
+    }
+
+    for (entity, mut gate, junction, transform) in gate_query.iter_mut() {
+        let position = transform.translation().truncate();
+
+        let Some(output) = pipe_toward(junction, position, gate.output, &transform_query) else {
+            continue;
+        };
+
+        let input_count = junction.pipes.len().saturating_sub(1);
+
+        let Some(data) = gate.evaluate(input_count, now) else {
+            continue;
+        };
+
+        crate::game_debug!(Interactions, "{:?} gate {:?} fired", gate.kind, entity);
+
+        commands.spawn((
+            SpatialBundle::default(),
+            Signal {
+                data,
+                source: entity,
+                destination: Some(output.receiver),
+                position: 0.,
+                speed: output.speed,
+            },
+        ));
+    }
+}
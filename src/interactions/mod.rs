@@ -1,14 +1,32 @@
 //! How nodes can communicate with each other.
 
 pub mod acceptor;
+pub mod budget;
+pub mod counter;
+pub mod echo;
+pub mod elevator;
 pub mod generator;
+pub mod latch;
+pub mod logic;
+pub mod network;
+#[cfg(feature = "devtools")]
+pub mod recorder;
+pub mod rhythm;
+pub mod sequencer;
+pub mod timing;
+pub mod trigger;
 pub mod visual;
+pub mod wireless;
 
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
+use crate::despawn::{DespawnQueue, DespawnReason};
 use crate::enemy::Hostility;
+use crate::level::pipe::Direction;
+use crate::rng::GameRng;
 
+pub use network::PipeNetworkBuilder;
 pub use visual::Buldge;
 
 /// All interaction plugins.
@@ -16,11 +34,27 @@ pub struct InteractionPlugins;
 
 impl PluginGroup for InteractionPlugins {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>()
+        let group = PluginGroupBuilder::start::<Self>()
             .add(PipePlugin)
             .add(acceptor::AcceptorPlugin)
+            .add(budget::SignalBudgetPlugin)
+            .add(counter::CounterPlugin)
+            .add(echo::EchoPlugin)
+            .add(elevator::ElevatorPlugin)
             .add(generator::GeneratorPlugin)
+            .add(latch::LatchPlugin)
+            .add(logic::LogicPlugin)
+            .add(rhythm::RhythmPlugin)
+            .add(sequencer::SequencerPlugin)
+            .add(timing::TimingPlugin)
+            .add(trigger::TriggerPlugin)
             .add(visual::VisualSignalPlugin)
+            .add(wireless::WirelessPlugin);
+
+        #[cfg(feature = "devtools")]
+        let group = group.add(recorder::SignalRecorderPlugin);
+
+        group
     }
 }
 
@@ -48,6 +82,10 @@ pub struct Signal {
 }
 
 impl Signal {
+    /// How far signals go in a second, in pipes, unless the pipe says
+    /// otherwise. See [`Pipe::speed`].
+    pub const SPEED: f32 = 8.;
+
     /// Creates a fresh signal starting from a junction.
     pub fn at(data: SignalData, source: Entity) -> Signal {
         Signal {
@@ -67,6 +105,31 @@ pub struct SignalData {
     ///
     /// Affects how projectiles on the other end are produced.
     pub hostility: Hostility,
+    /// The strength of the signal, where `1.` is a signal made from a single
+    /// projectile.
+    ///
+    /// Signals that meet in a pipe merge into one stronger signal, which
+    /// produces bigger projectiles on the other end.
+    pub strength: f32,
+    /// How many more junctions the signal can go through before it dies out.
+    ///
+    /// This keeps signals from going around a loop of pipes forever. Copies
+    /// of a signal keep what energy it had left.
+    pub energy: u32,
+}
+
+impl SignalData {
+    /// How many junctions a new signal can go through.
+    pub const ENERGY: u32 = 512;
+
+    /// Creates a new `SignalData` of normal strength.
+    pub fn new(hostility: Hostility) -> SignalData {
+        SignalData {
+            hostility,
+            strength: 1.,
+            energy: SignalData::ENERGY,
+        }
+    }
 }
 
 /// An event that is fired when a signal moves from an entity.
@@ -87,6 +150,40 @@ pub struct SignalEvent {
     pub overfill: f32,
 }
 
+/// Takes in the signals that reach a junction instead of passing them on.
+///
+/// Whatever else is on the junction, like a [`Gate`](logic::Gate), decides
+/// what to do with them.
+#[derive(Clone, Component, Debug, Default)]
+pub struct SignalConsumer;
+
+/// Lets signals through a junction in one direction only.
+///
+/// Signals coming in against [`Valve::allowed`] are lost, and signals going
+/// through are never sent back against it.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Valve {
+    /// The direction signals may travel through the junction.
+    pub allowed: Direction,
+}
+
+impl Valve {
+    /// Checks if a signal may travel between two points.
+    pub fn allows(&self, from: Vec2, to: Vec2) -> bool {
+        (to - from).dot(self.allowed.axis()) >= -f32::EPSILON
+    }
+}
+
+/// Only lets signals of one hostility through a junction.
+///
+/// Signals of the other hostility are lost.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Filter(pub Hostility);
+
+/// Flips the hostility of every signal that goes through a junction.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Converter;
+
 /// Pipe plugin.
 pub struct PipePlugin;
 
@@ -100,7 +197,10 @@ impl Plugin for PipePlugin {
             )
             .add_systems(
                 Update,
-                signal_travel.in_set(InteractionSystem::TravelSignal),
+                (merge_signals, signal_travel)
+                    .chain()
+                    .distributive_run_if(any_with_component::<Signal>())
+                    .in_set(InteractionSystem::TravelSignal),
             );
         //.add_systems(Update, debug_draw_pipes);
     }
@@ -114,9 +214,21 @@ impl Plugin for PipePlugin {
 pub struct Junction {
     /// The paths the pipe can take from the origin of this entity.
     pub pipes: Vec<Pipe>,
+    /// Which of the pipes out of the junction a signal goes down.
+    pub routing: RoutingMode,
+    next: usize,
 }
 
 impl Junction {
+    /// Creates a new `Junction` without pipes that routes signals a certain
+    /// way.
+    pub fn with_routing(routing: RoutingMode) -> Junction {
+        Junction {
+            routing,
+            ..Default::default()
+        }
+    }
+
     /// Clears all the current pipes.
     pub fn clear(&mut self) {
         self.pipes.clear();
@@ -126,6 +238,93 @@ impl Junction {
     pub fn push_pipe(&mut self, receiver: Entity) {
         self.pipes.push(Pipe::new(receiver))
     }
+
+    /// Picks which pipes out of the junction a signal goes down, from the
+    /// pipes it could go down.
+    ///
+    /// `direction` finds which way a pipe leaves the junction, for
+    /// [`RoutingMode::Weighted`].
+    pub fn route(
+        &mut self,
+        mut outputs: Vec<Pipe>,
+        direction: impl Fn(&Pipe) -> Option<Direction>,
+        rng: &mut GameRng,
+    ) -> Vec<Pipe> {
+        if outputs.len() < 2 {
+            return outputs;
+        }
+
+        let index = match &self.routing {
+            RoutingMode::Broadcast => return outputs,
+            RoutingMode::RoundRobin => {
+                let index = self.next % outputs.len();
+                self.next = self.next.wrapping_add(1);
+                index
+            }
+            RoutingMode::Random => rng.range_u32(0, outputs.len() as u32) as usize,
+            RoutingMode::Weighted(weights) => {
+                let weights = outputs
+                    .iter()
+                    .map(|pipe| {
+                        direction(pipe)
+                            .map(|direction| weights[direction as usize].max(0.))
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>();
+                let total = weights.iter().sum::<f32>();
+
+                if total > 0. {
+                    let mut pick = rng.next_f32() * total;
+
+                    weights
+                        .iter()
+                        .position(|&weight| {
+                            pick -= weight;
+                            pick < 0.
+                        })
+                        // rounding may leave a sliver past the last pipe
+                        .unwrap_or(weights.len() - 1)
+                } else {
+                    rng.range_u32(0, outputs.len() as u32) as usize
+                }
+            }
+        };
+
+        vec![outputs.swap_remove(index)]
+    }
+}
+
+/// How a [`Junction`] with more than one way out picks which a signal goes
+/// down.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub enum RoutingMode {
+    /// Every pipe gets a copy of the signal.
+    #[default]
+    Broadcast,
+    /// Each signal goes down the next pipe in turn.
+    RoundRobin,
+    /// Each signal goes down one pipe, picked at random.
+    Random,
+    /// Each signal goes down one pipe, picked at random with the chances
+    /// weighted by which way the pipe leaves the junction.
+    ///
+    /// The weights are in the order of [`Direction`]: right, up, left and
+    /// down. If every way out has no weight, any of them is picked.
+    Weighted([f32; 4]),
+}
+
+impl RoutingMode {
+    /// Finds a routing mode by its name, as in LDtk, with the weights for
+    /// [`RoutingMode::Weighted`].
+    pub fn from_name(name: &str, weights: [f32; 4]) -> Option<RoutingMode> {
+        match name {
+            "Broadcast" => Some(RoutingMode::Broadcast),
+            "RoundRobin" => Some(RoutingMode::RoundRobin),
+            "Random" => Some(RoutingMode::Random),
+            "Weighted" => Some(RoutingMode::Weighted(weights)),
+            _ => None,
+        }
+    }
 }
 
 /// A single pipe.
@@ -133,45 +332,192 @@ impl Junction {
 pub struct Pipe {
     /// The entity at the other end of the pipe.
     pub receiver: Entity,
+    /// How far signals go in a second through this pipe, in pipes.
+    pub speed: f32,
+    /// The shape of the pipe in the junction it leaves from.
+    pub start: PipeBend,
+    /// The shape of the pipe in the junction it goes to.
+    pub end: PipeBend,
 }
 
 impl Pipe {
-    /// Creates a new pipe with [`Pipe::size`] initialized to all ones.
+    /// Creates a new, straight pipe that signals go through at
+    /// [`Signal::SPEED`].
     pub fn new(receiver: Entity) -> Pipe {
-        Pipe { receiver }
+        Pipe {
+            receiver,
+            speed: Signal::SPEED,
+            start: PipeBend::Straight,
+            end: PipeBend::Straight,
+        }
+    }
+
+    /// Sets the speed. See [`Pipe::speed`].
+    pub fn with_speed(self, speed: f32) -> Pipe {
+        Pipe { speed, ..self }
     }
+
+    /// Sets the shape of both ends. See [`Pipe::start`] and [`Pipe::end`].
+    pub fn with_bends(self, start: PipeBend, end: PipeBend) -> Pipe {
+        Pipe { start, end, ..self }
+    }
+
+    /// Finds where a signal is along the pipe, from the centers of the
+    /// junctions at either end.
+    ///
+    /// The first half of the pipe is in the tile it leaves from and the
+    /// second half is in the tile it goes to. Each half is a straight line,
+    /// or a quarter of a circle around a corner.
+    pub fn point(&self, start: Vec2, end: Vec2, position: f32) -> Vec2 {
+        let half = (end - start) / 2.;
+
+        if position < 0.5 {
+            let t = position * 2.;
+
+            match self.start {
+                PipeBend::Straight => start + half * t,
+                PipeBend::Corner(other) => {
+                    // the second half of the turn into this pipe
+                    let entry = other.axis() * half.length();
+                    corner_point(start, entry, half, 0.5 + t / 2.)
+                }
+            }
+        } else {
+            let t = position * 2. - 1.;
+
+            match self.end {
+                PipeBend::Straight => end - half * (1. - t),
+                PipeBend::Corner(other) => {
+                    // the first half of the turn out of this pipe
+                    let entry = -half;
+                    let exit = other.axis() * half.length();
+                    corner_point(end, entry, exit, t / 2.)
+                }
+            }
+        }
+    }
+}
+
+/// The shape of one end of a [`Pipe`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum PipeBend {
+    /// The pipe goes straight to the center of the tile.
+    #[default]
+    Straight,
+    /// The tile is a corner, and signals curve around it to or from its
+    /// other way out, in this direction.
+    Corner(Direction),
+}
+
+/// Finds a point on a quarter of a circle around a corner tile.
+///
+/// `entry` and `exit` go from the center of the tile to the middle of the
+/// sides the curve starts and ends on, and `t` is how far along the curve the
+/// point is, from `0.` to `1.`.
+fn corner_point(center: Vec2, entry: Vec2, exit: Vec2, t: f32) -> Vec2 {
+    let angle = t * std::f32::consts::FRAC_PI_2;
+    let corner = center + entry + exit;
+
+    corner - exit * angle.cos() - entry * angle.sin()
 }
 
 fn handle_signal_events(
     mut commands: Commands,
+    mut despawn_queue: ResMut<DespawnQueue>,
     mut signal_events: EventReader<SignalEvent>,
     mut signal_query: Query<&mut Signal>,
-    junction_query: Query<&Junction>,
+    mut junction_query: Query<
+        (
+            &mut Junction,
+            Option<&Valve>,
+            Option<&Filter>,
+            Option<&Converter>,
+        ),
+        Without<SignalConsumer>,
+    >,
+    transform_query: Query<&GlobalTransform>,
+    mut rng: ResMut<GameRng>,
+    mut budget: ResMut<budget::SignalBudget>,
 ) {
+    let position = |entity: Entity| {
+        transform_query
+            .get(entity)
+            .map(|transform| transform.translation().truncate())
+            .ok()
+    };
+
     for ev in signal_events.iter() {
         let Ok(mut signal) = signal_query.get_mut(ev.signal) else {
             continue;
         };
 
-        let Ok(junction) = junction_query.get(ev.receiver) else {
+        let Ok((mut junction, valve, filter, converter)) = junction_query.get_mut(ev.receiver)
+        else {
             continue;
         };
 
+        let here = position(ev.receiver);
+
+        // valves only let signals through one way
+        let allows = |from: Option<Vec2>, to: Option<Vec2>| match (valve, from, to) {
+            (Some(valve), Some(from), Some(to)) => valve.allows(from, to),
+            _ => true,
+        };
+
+        if !allows(position(ev.sender), here) {
+            despawn_queue.push(ev.signal, DespawnReason::Other("valve"));
+            continue;
+        }
+
+        if filter.is_some_and(|filter| filter.0 != signal.data.hostility) {
+            despawn_queue.push(ev.signal, DespawnReason::Other("filter"));
+            continue;
+        }
+
+        if converter.is_some() {
+            signal.data.hostility = signal.data.hostility.opposite();
+        }
+
+        // signals going around a loop die out eventually
+        if signal.data.energy == 0 {
+            budget.exhausted += 1;
+            despawn_queue.push(ev.signal, DespawnReason::Other("exhausted"));
+            continue;
+        }
+
+        signal.data.energy -= 1;
+
         // move signal and maybe duplicate
-        let mut outputs = junction
+        let outputs = junction
             .pipes
             .iter()
-            .filter(|pipe| pipe.receiver != ev.sender);
+            .filter(|pipe| pipe.receiver != ev.sender)
+            .filter(|pipe| allows(here, position(pipe.receiver)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let direction = |pipe: &Pipe| {
+            here.zip(position(pipe.receiver))
+                .map(|(here, there)| Direction::nearest(there - here))
+        };
+
+        // do not trip change detection
+        let mut outputs = if junction.routing == RoutingMode::Broadcast {
+            outputs
+        } else {
+            junction.route(outputs, direction, &mut rng)
+        }
+        .into_iter();
 
         // move signal to first output
         if let Some(output) = outputs.next() {
             signal.source = ev.receiver;
             signal.destination = Some(output.receiver);
-            signal.speed = 8.; // TODO
+            signal.speed = output.speed;
             signal.position = ev.overfill;
         } else {
             // destroy signal
-            commands.entity(ev.signal).despawn_recursive();
+            despawn_queue.push(ev.signal, DespawnReason::Consumed);
             continue;
         }
 
@@ -184,19 +530,83 @@ fn handle_signal_events(
                     source: ev.receiver,
                     destination: Some(output.receiver),
                     position: ev.overfill,
-                    speed: 8., // TODO
+                    speed: output.speed,
                 },
             ));
         }
     }
 }
 
+/// How close signals in the same pipe must be to merge, as a fraction of the
+/// pipe.
+const SIGNAL_MERGE_DISTANCE: f32 = 0.25;
+
+fn merge_signals(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signals_query: Query<(Entity, &mut Signal)>,
+) {
+    use std::collections::HashMap;
+
+    // group signals travelling the same pipe
+    let mut pipes: HashMap<(Entity, Entity, Hostility), Vec<(Entity, f32)>> = HashMap::new();
+
+    for (entity, signal) in signals_query.iter() {
+        let Some(destination) = signal.destination else {
+            continue;
+        };
+
+        pipes
+            .entry((signal.source, destination, signal.data.hostility))
+            .or_default()
+            .push((entity, signal.position));
+    }
+
+    for mut signals in pipes.into_values() {
+        if signals.len() < 2 {
+            continue;
+        }
+
+        // leading signals first
+        signals.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let (mut leader, mut leader_position) = signals[0];
+
+        for &(entity, position) in &signals[1..] {
+            if leader_position - position > SIGNAL_MERGE_DISTANCE {
+                leader = entity;
+                leader_position = position;
+                continue;
+            }
+
+            // fold the trailing signal into the leader
+            let Ok((_, trailing)) = signals_query.get(entity) else {
+                continue;
+            };
+            let strength = trailing.data.strength;
+            let energy = trailing.data.energy;
+
+            if let Ok((_, mut signal)) = signals_query.get_mut(leader) {
+                signal.data.strength += strength;
+                signal.data.energy = signal.data.energy.max(energy);
+            }
+
+            despawn_queue.push(entity, DespawnReason::Consumed);
+        }
+    }
+}
+
 fn signal_travel(
     mut signals_query: Query<(Entity, &mut Signal)>,
     mut signal_events: EventWriter<SignalEvent>,
+    despawn_queue: Res<DespawnQueue>,
     time: Res<Time>,
 ) {
     for (signal_entity, mut signal) in signals_query.iter_mut() {
+        // merged or finished signals linger until the queue is applied
+        if despawn_queue.contains(signal_entity) {
+            continue;
+        }
+
         if let Some(dest) = signal.destination {
             // move signal forward
             signal.position += signal.speed * time.delta_seconds();
@@ -0,0 +1,268 @@
+//! Building pipe networks in code.
+//!
+//! Levels get their pipes from the `Pipes` layer in LDtk. A
+//! [`PipeNetworkBuilder`] spawns the same [`Junction`] graph without a level,
+//! for tests and generated levels.
+
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::*;
+
+use super::Junction;
+
+/// Builds a graph of [`Junction`]s.
+///
+/// Nodes are referred to by the order they were added in, starting at `0`.
+/// ```ignore
+/// let nodes = PipeNetworkBuilder::new()
+///     .node(Vec2::new(0., 0.))
+///     .node(Vec2::new(16., 0.))
+///     .node(Vec2::new(32., 0.))
+///     .connect(0, 1)
+///     .connect(1, 2)
+///     .insert(2, Acceptor::default())
+///     .spawn(&mut commands);
+/// ```
+#[derive(Default)]
+pub struct PipeNetworkBuilder {
+    nodes: Vec<PipeNode>,
+    pipes: Vec<(usize, usize)>,
+}
+
+struct PipeNode {
+    position: Vec2,
+    inserts: Vec<Box<dyn FnOnce(&mut EntityMut) + Send>>,
+}
+
+impl PipeNetworkBuilder {
+    /// Creates a new, empty `PipeNetworkBuilder`.
+    pub fn new() -> PipeNetworkBuilder {
+        PipeNetworkBuilder::default()
+    }
+
+    /// Adds a node at a position in the world.
+    pub fn node(mut self, position: Vec2) -> PipeNetworkBuilder {
+        self.nodes.push(PipeNode {
+            position,
+            inserts: Vec::new(),
+        });
+        self
+    }
+
+    /// Connects two nodes with a pipe both ways, like neighboring tiles in
+    /// the `Pipes` layer.
+    ///
+    /// If either node doesn't exist, this is a bug; it panics in debug builds
+    /// and leaves the pipe out otherwise.
+    pub fn connect(mut self, a: usize, b: usize) -> PipeNetworkBuilder {
+        let missing = [a, b].into_iter().find(|&node| node >= self.nodes.len());
+
+        if let Some(node) = missing {
+            debug_assert!(false, "node {} does not exist", node);
+            crate::game_warn!(Interactions, "cannot connect missing node {}", node);
+            return self;
+        }
+
+        self.pipes.push((a, b));
+        self
+    }
+
+    /// Connects each node to the next in a line.
+    ///
+    /// Pipes to nodes that don't exist are left out, like in
+    /// [`PipeNetworkBuilder::connect`].
+    pub fn chain(self, nodes: impl IntoIterator<Item = usize>) -> PipeNetworkBuilder {
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+
+        nodes
+            .windows(2)
+            .fold(self, |builder, pair| builder.connect(pair[0], pair[1]))
+    }
+
+    /// Adds components to a node, like an
+    /// [`AcceptorBundle`](super::acceptor::AcceptorBundle) or a
+    /// [`Generator`](super::generator::Generator).
+    ///
+    /// If the node doesn't exist, this is a bug; it panics in debug builds and
+    /// leaves the components out otherwise.
+    pub fn insert(mut self, node: usize, bundle: impl Bundle) -> PipeNetworkBuilder {
+        let Some(pipe_node) = self.nodes.get_mut(node) else {
+            debug_assert!(false, "node {} does not exist", node);
+            crate::game_warn!(Interactions, "cannot insert into missing node {}", node);
+            return self;
+        };
+
+        pipe_node
+            .inserts
+            .push(Box::new(move |entity: &mut EntityMut| {
+                entity.insert(bundle);
+            }));
+        self
+    }
+
+    /// The number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Checks if there are no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Spawns the network, returning the entity of each node in order.
+    ///
+    /// The entities are reserved now, but don't have any components until the
+    /// commands are applied.
+    pub fn spawn(self, commands: &mut Commands) -> Vec<Entity> {
+        let entities = self
+            .nodes
+            .iter()
+            .map(|_| commands.spawn_empty().id())
+            .collect::<Vec<_>>();
+
+        let nodes = entities.clone();
+        commands.add(move |world: &mut World| self.insert_into(world, &nodes));
+
+        entities
+    }
+
+    /// Spawns the network directly into a [`World`], returning the entity of
+    /// each node in order.
+    pub fn spawn_in_world(self, world: &mut World) -> Vec<Entity> {
+        let entities = self
+            .nodes
+            .iter()
+            .map(|_| world.spawn_empty().id())
+            .collect::<Vec<_>>();
+
+        self.insert_into(world, &entities);
+
+        entities
+    }
+
+    fn insert_into(self, world: &mut World, entities: &[Entity]) {
+        let mut junctions = vec![Junction::default(); entities.len()];
+
+        for (a, b) in self.pipes {
+            junctions[a].push_pipe(entities[b]);
+            junctions[b].push_pipe(entities[a]);
+        }
+
+        for ((node, junction), &entity) in self.nodes.into_iter().zip(junctions).zip(entities) {
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+
+            entity.insert((
+                SpatialBundle::from_transform(Transform::from_translation(
+                    node.position.extend(0.),
+                )),
+                junction,
+            ));
+
+            for insert in node.inserts {
+                insert(&mut entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::interactions::Signal;
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn receivers(world: &World, entity: Entity) -> Vec<Entity> {
+        world
+            .get::<Junction>(entity)
+            .unwrap()
+            .pipes
+            .iter()
+            .map(|pipe| pipe.receiver)
+            .collect()
+    }
+
+    #[test]
+    fn line_matches_pipes_layer() {
+        let mut world = World::new();
+
+        // like three tiles side by side in the `Pipes` layer
+        let nodes = PipeNetworkBuilder::new()
+            .node(Vec2::new(0., 0.))
+            .node(Vec2::new(16., 0.))
+            .node(Vec2::new(32., 0.))
+            .chain([0, 1, 2])
+            .spawn_in_world(&mut world);
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(receivers(&world, nodes[0]), vec![nodes[1]]);
+        assert_eq!(receivers(&world, nodes[1]), vec![nodes[0], nodes[2]]);
+        assert_eq!(receivers(&world, nodes[2]), vec![nodes[1]]);
+
+        for &node in nodes.iter() {
+            for pipe in world.get::<Junction>(node).unwrap().pipes.iter() {
+                assert_eq!(pipe.speed, Signal::SPEED);
+            }
+        }
+
+        assert_eq!(
+            world.get::<Transform>(nodes[1]).unwrap().translation,
+            Vec3::new(16., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn branch_matches_pipes_layer() {
+        let mut world = World::new();
+
+        // a T with the stem going down from the middle
+        let nodes = PipeNetworkBuilder::new()
+            .node(Vec2::new(0., 0.))
+            .node(Vec2::new(16., 0.))
+            .node(Vec2::new(32., 0.))
+            .node(Vec2::new(16., -16.))
+            .chain([0, 1, 2])
+            .connect(1, 3)
+            .spawn_in_world(&mut world);
+
+        assert_eq!(
+            receivers(&world, nodes[1]),
+            vec![nodes[0], nodes[2], nodes[3]]
+        );
+        assert_eq!(receivers(&world, nodes[3]), vec![nodes[1]]);
+    }
+
+    #[test]
+    fn insert_adds_components() {
+        let mut world = World::new();
+
+        let nodes = PipeNetworkBuilder::new()
+            .node(Vec2::ZERO)
+            .node(Vec2::X)
+            .connect(0, 1)
+            .insert(1, Marker)
+            .spawn_in_world(&mut world);
+
+        assert!(world.get::<Marker>(nodes[0]).is_none());
+        assert!(world.get::<Marker>(nodes[1]).is_some());
+        assert!(world.get::<Junction>(nodes[1]).is_some());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "node 3 does not exist")]
+    fn insert_missing_node_panics_in_debug() {
+        let _ = PipeNetworkBuilder::new().node(Vec2::ZERO).insert(3, Marker);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "node 1 does not exist")]
+    fn connect_missing_node_panics_in_debug() {
+        let _ = PipeNetworkBuilder::new().node(Vec2::ZERO).connect(0, 1);
+    }
+}
@@ -0,0 +1,268 @@
+//! Recording where signals go.
+//!
+//! Only built with the `devtools` feature. `F9` starts and stops recording
+//! every [`SignalEvent`] into a [`SignalTrace`] for the current level, and
+//! `F10` dumps the traces to `debug/`, as a DOT graph and as JSON, one of each
+//! per level. The graph has an edge for every pipe a signal went through,
+//! labeled with how many signals went through it, so the routing of a puzzle
+//! can be checked against what it was meant to do.
+
+use bevy::prelude::*;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+use super::{InteractionSystem, Signal, SignalEvent};
+use crate::level::CurrentLevel;
+use crate::telemetry::json_string;
+
+/// Signal recording plugin.
+pub struct SignalRecorderPlugin;
+
+impl Plugin for SignalRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SignalRecorder>().add_systems(
+            Update,
+            (
+                toggle_signal_recording,
+                record_signal_events
+                    .run_if(|recorder: Res<SignalRecorder>| recorder.recording)
+                    .after(InteractionSystem::TravelSignal),
+                export_signal_traces.run_if(|keyboard: Res<Input<KeyCode>>| {
+                    keyboard.just_pressed(SignalRecorder::EXPORT_KEY)
+                }),
+            ),
+        );
+    }
+}
+
+/// Signal recording state.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct SignalRecorder {
+    /// Whether signal events are being recorded.
+    pub recording: bool,
+    traces: HashMap<String, SignalTrace>,
+}
+
+impl SignalRecorder {
+    /// The key that toggles [`SignalRecorder::recording`].
+    pub const RECORD_KEY: KeyCode = KeyCode::F9;
+    /// The key that exports the traces.
+    pub const EXPORT_KEY: KeyCode = KeyCode::F10;
+    /// Where exported traces go.
+    pub const EXPORT_DIR: &str = "debug";
+
+    /// The trace of a level, by identifier.
+    pub fn trace(&self, level: &str) -> Option<&SignalTrace> {
+        self.traces.get(level)
+    }
+
+    /// Throws away everything recorded so far.
+    pub fn clear(&mut self) {
+        self.traces.clear();
+    }
+}
+
+/// Every signal event recorded in a level.
+#[derive(Clone, Debug, Default)]
+pub struct SignalTrace {
+    /// The events, in the order they happened.
+    pub entries: Vec<SignalTraceEntry>,
+    names: HashMap<Entity, String>,
+}
+
+/// A single recorded [`SignalEvent`].
+#[derive(Clone, Debug)]
+pub struct SignalTraceEntry {
+    /// When the event happened, in seconds since startup.
+    pub time: f32,
+    /// The sender.
+    pub sender: Entity,
+    /// The receiver.
+    pub receiver: Entity,
+    /// The signal.
+    pub signal: Entity,
+    /// The strength of the signal, if it still existed.
+    pub strength: Option<f32>,
+    /// The hostility of the signal, if it still existed.
+    pub hostility: Option<String>,
+}
+
+impl SignalTrace {
+    /// The name of a node in the trace.
+    pub fn name(&self, entity: Entity) -> String {
+        self.names
+            .get(&entity)
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", entity))
+    }
+
+    /// How many signals went from each sender to each receiver.
+    pub fn edges(&self) -> BTreeMap<(Entity, Entity), u32> {
+        let mut edges = BTreeMap::new();
+
+        for entry in &self.entries {
+            *edges.entry((entry.sender, entry.receiver)).or_default() += 1;
+        }
+
+        edges
+    }
+
+    /// Writes the trace as a DOT graph.
+    pub fn to_dot(&self, level: &str) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "digraph {} {{", dot_string(level));
+
+        let mut nodes: Vec<Entity> = self
+            .entries
+            .iter()
+            .flat_map(|entry| [entry.sender, entry.receiver])
+            .collect();
+        nodes.sort();
+        nodes.dedup();
+
+        for node in nodes {
+            let _ = writeln!(
+                out,
+                "    n{} [label={}];",
+                node.to_bits(),
+                dot_string(&self.name(node))
+            );
+        }
+
+        for ((sender, receiver), count) in self.edges() {
+            let _ = writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"];",
+                sender.to_bits(),
+                receiver.to_bits(),
+                count
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes the trace as JSON.
+    pub fn to_json(&self, level: &str) -> String {
+        let mut out = String::new();
+
+        let _ = write!(out, "{{\"level\":{},\"events\":[", json_string(level));
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(
+                out,
+                "{{\"time\":{},\"sender\":{},\"sender_name\":{},\"receiver\":{},\"receiver_name\":{},\"signal\":{}",
+                entry.time,
+                entry.sender.to_bits(),
+                json_string(&self.name(entry.sender)),
+                entry.receiver.to_bits(),
+                json_string(&self.name(entry.receiver)),
+                entry.signal.to_bits(),
+            );
+
+            if let Some(strength) = entry.strength {
+                let _ = write!(out, ",\"strength\":{}", strength);
+            }
+
+            if let Some(hostility) = &entry.hostility {
+                let _ = write!(out, ",\"hostility\":{}", json_string(hostility));
+            }
+
+            out.push('}');
+        }
+
+        out.push_str("]}\n");
+        out
+    }
+}
+
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn toggle_signal_recording(keyboard: Res<Input<KeyCode>>, mut recorder: ResMut<SignalRecorder>) {
+    if keyboard.just_pressed(SignalRecorder::RECORD_KEY) {
+        recorder.recording = !recorder.recording;
+
+        crate::game_info!(
+            Interactions,
+            "signal recording {}",
+            if recorder.recording {
+                "started"
+            } else {
+                "stopped"
+            }
+        );
+    }
+}
+
+fn record_signal_events(
+    mut recorder: ResMut<SignalRecorder>,
+    mut signal_events: EventReader<SignalEvent>,
+    signal_query: Query<&Signal>,
+    name_query: Query<&Name>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+) {
+    let trace = recorder.traces.entry(current_level.0.clone()).or_default();
+
+    for ev in signal_events.iter() {
+        let signal = signal_query.get(ev.signal).ok();
+
+        for entity in [ev.sender, ev.receiver] {
+            if let Ok(name) = name_query.get(entity) {
+                trace.names.insert(entity, name.as_str().to_owned());
+            }
+        }
+
+        trace.entries.push(SignalTraceEntry {
+            time: time.elapsed_seconds(),
+            sender: ev.sender,
+            receiver: ev.receiver,
+            signal: ev.signal,
+            strength: signal.map(|s| s.data.strength),
+            hostility: signal.map(|s| format!("{:?}", s.data.hostility)),
+        });
+    }
+}
+
+fn export_signal_traces(recorder: Res<SignalRecorder>) {
+    if let Err(err) = std::fs::create_dir_all(SignalRecorder::EXPORT_DIR) {
+        crate::game_warn!(
+            Interactions,
+            "cannot create {}: {}",
+            SignalRecorder::EXPORT_DIR,
+            err
+        );
+        return;
+    }
+
+    for (level, trace) in recorder.traces.iter() {
+        for (extension, contents) in [("dot", trace.to_dot(level)), ("json", trace.to_json(level))]
+        {
+            let path = format!(
+                "{}/signals-{}.{}",
+                SignalRecorder::EXPORT_DIR,
+                level,
+                extension
+            );
+
+            match std::fs::write(&path, contents) {
+                Ok(()) => crate::game_info!(
+                    Interactions,
+                    "exported signals to {} ({} events)",
+                    path,
+                    trace.entries.len()
+                ),
+                Err(err) => crate::game_warn!(Interactions, "cannot export {}: {}", path, err),
+            }
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Doors that open to a rhythm.
+//!
+//! A [`RhythmDoor`] listens for projectiles hitting its face and for hits on
+//! the [drums](crate::drum::Drum) it is linked to. Each hit has to land on the
+//! beat of the [`BeatClock`], the right number of beats after the one before
+//! it; a light on the door comes on for every hit in the pattern. Miss a beat
+//! and the lights go out. Finish the pattern and the door opens.
+//!
+//! A closed door flashes on every beat, so the player can see when to hit it.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use crate::beat::BeatClock;
+use crate::drum::{DrumHitEvent, DrumSystem};
+use crate::level::Iid;
+use crate::physics;
+use crate::platform::ActivateEvent;
+use crate::projectile::{HitEvent, ProjectileSystem};
+use crate::settings::AccessibilitySettings;
+
+/// Rhythm door plugin.
+pub struct RhythmPlugin;
+
+impl Plugin for RhythmPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<RhythmDoorBundle>("RhythmDoor")
+            .add_systems(Update, (setup_rhythm_doors, upgrade_rhythm_door_targets))
+            .add_systems(
+                Update,
+                (listen_for_rhythm, update_rhythm_lights, pulse_rhythm_doors)
+                    .chain()
+                    .run_if(any_with_component::<RhythmDoor>())
+                    .after(ProjectileSystem::Event)
+                    .after(DrumSystem::Hit),
+            );
+    }
+}
+
+/// A door that opens when it is hit in a rhythm.
+///
+/// The pattern is the number of beats between each hit and the one before
+/// it, so `[1, 1, 2]` is short, short, long: four hits, the last one two
+/// beats after the third.
+#[derive(Clone, Component, Debug)]
+pub struct RhythmDoor {
+    /// The beats between each hit.
+    pub pattern: Vec<u32>,
+    /// The drums whose hits count as hits on the door.
+    pub listens: Vec<Entity>,
+    /// The entity activated when the door opens.
+    pub activates: Option<Entity>,
+    listens_iid: Vec<String>,
+    activates_iid: Option<String>,
+    hits: usize,
+    last_beat: u64,
+    open: bool,
+}
+
+impl RhythmDoor {
+    /// The pattern of a door if the level doesn't say; short, short, long.
+    pub const DEFAULT_PATTERN: [u32; 3] = [1, 1, 2];
+    /// The color of a closed door.
+    pub const COLOR: Color = Color::rgb(0.45, 0.35, 0.6);
+    /// The color a closed door flashes on the beat.
+    pub const BEAT: Color = Color::rgb(0.75, 0.65, 0.95);
+    /// The color of a light for a hit that landed.
+    pub const LIT: Color = Color::rgb(1., 0.85, 0.3);
+    /// The color of a light for a hit still to come.
+    pub const UNLIT: Color = Color::rgba(0., 0., 0., 0.4);
+
+    /// Creates a new, closed `RhythmDoor` with a pattern.
+    pub fn new(pattern: Vec<u32>) -> RhythmDoor {
+        RhythmDoor {
+            pattern,
+            listens: Vec::new(),
+            activates: None,
+            listens_iid: Vec::new(),
+            activates_iid: None,
+            hits: 0,
+            last_beat: 0,
+            open: false,
+        }
+    }
+
+    /// How many hits it takes to open the door.
+    pub fn hits_needed(&self) -> usize {
+        self.pattern.len() + 1
+    }
+
+    /// How many hits of the pattern have landed so far.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Checks if the door is open.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Takes a hit that landed on a beat.
+    ///
+    /// Returns `true` if the hit finished the pattern.
+    pub fn hit(&mut self, beat: u64) -> bool {
+        if self.open {
+            return false;
+        }
+
+        let expected = self
+            .pattern
+            .get(self.hits.wrapping_sub(1))
+            .map(|&beats| self.last_beat + beats as u64);
+
+        self.hits = match expected {
+            Some(expected) if expected == beat => self.hits + 1,
+            // a second hit on the same beat doesn't count for anything
+            Some(_) if beat == self.last_beat => return false,
+            // any hit on the beat can start the pattern over
+            _ => 1,
+        };
+        self.last_beat = beat;
+
+        self.open = self.hits >= self.hits_needed();
+        self.open
+    }
+
+    /// Checks if the next hit of the pattern is already late.
+    pub fn is_late(&self, beat: u64) -> bool {
+        self.pattern
+            .get(self.hits.wrapping_sub(1))
+            .map(|&beats| beat > self.last_beat + beats as u64)
+            .unwrap_or_default()
+    }
+
+    /// Forgets the hits so far.
+    pub fn miss(&mut self) {
+        if !self.open {
+            self.hits = 0;
+        }
+    }
+}
+
+impl Default for RhythmDoor {
+    fn default() -> RhythmDoor {
+        RhythmDoor::new(RhythmDoor::DEFAULT_PATTERN.to_vec())
+    }
+}
+
+/// A light on a [`RhythmDoor`] for one of the hits of its pattern.
+#[derive(Clone, Component, Debug)]
+pub struct RhythmLight(usize);
+
+/// A bundle for a [`RhythmDoor`].
+#[derive(Bundle)]
+pub struct RhythmDoorBundle {
+    pub sprite: SpriteBundle,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub door: RhythmDoor,
+}
+
+impl RhythmDoorBundle {
+    /// Creates a new `RhythmDoorBundle` of a size.
+    pub fn new(door: RhythmDoor, size: Vec2) -> RhythmDoorBundle {
+        RhythmDoorBundle {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: RhythmDoor::COLOR,
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            collision_groups: CollisionGroups::new(physics::COLLISION_GROUP_SOLID, Group::all()),
+            door,
+        }
+    }
+}
+
+impl LdtkEntity for RhythmDoorBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let pattern = entity_instance
+            .get_maybe_ints_field("Pattern")
+            .map(|pattern| {
+                pattern
+                    .iter()
+                    .flatten()
+                    .map(|&beats| beats.max(1) as u32)
+                    .collect::<Vec<_>>()
+            })
+            .ok()
+            .filter(|pattern| !pattern.is_empty())
+            .unwrap_or_else(|| RhythmDoor::DEFAULT_PATTERN.to_vec());
+
+        let listens_iid = entity_instance
+            .get_maybe_entity_refs_field("Listens")
+            .map(|refs| {
+                refs.iter()
+                    .flatten()
+                    .map(|r| r.entity_iid.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let activates_iid = entity_instance
+            .get_maybe_entity_ref_field("Activates")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        RhythmDoorBundle::new(
+            RhythmDoor {
+                listens_iid,
+                activates_iid,
+                ..RhythmDoor::new(pattern)
+            },
+            size,
+        )
+    }
+}
+
+fn setup_rhythm_doors(
+    mut commands: Commands,
+    door_query: Query<(Entity, &RhythmDoor, &Sprite), Added<RhythmDoor>>,
+) {
+    for (entity, door, sprite) in door_query.iter() {
+        let height = sprite.custom_size.map(|size| size.y).unwrap_or(16.);
+
+        // a column of lights down the face of the door
+        let spacing = 4.;
+        let top = (door.hits_needed() as f32 - 1.) * spacing / 2.;
+
+        commands.entity(entity).with_children(|parent| {
+            for i in 0..door.hits_needed() {
+                let y = (top - i as f32 * spacing).clamp(-height / 2., height / 2.);
+
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: RhythmDoor::UNLIT,
+                            custom_size: Some(Vec2::splat(2.)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0., y, 0.1),
+                        ..Default::default()
+                    },
+                    RhythmLight(i),
+                ));
+            }
+        });
+    }
+}
+
+fn upgrade_rhythm_door_targets(
+    mut door_query: Query<&mut RhythmDoor>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for mut door in door_query.iter_mut() {
+        if door.listens_iid.is_empty() && door.activates_iid.is_none() {
+            continue;
+        }
+
+        let find = |iid_request: &str| {
+            iid_query
+                .iter()
+                .find(|(_, iid)| iid.0 == iid_request)
+                .map(|(e, _)| e)
+        };
+
+        let listens_iid = std::mem::take(&mut door.listens_iid);
+
+        for iid_request in listens_iid {
+            match find(&iid_request) {
+                Some(found) => door.listens.push(found),
+                None => door.listens_iid.push(iid_request),
+            }
+        }
+
+        if let Some(found) = door.activates_iid.as_deref().and_then(find) {
+            door.activates = Some(found);
+            door.activates_iid = None;
+        }
+    }
+}
+
+fn listen_for_rhythm(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitEvent>,
+    mut drum_hit_events: EventReader<DrumHitEvent>,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut door_query: Query<(Entity, &mut RhythmDoor)>,
+    clock: Res<BeatClock>,
+) {
+    let beat = clock.nearest_beat();
+    let on_beat = clock.judge().on_beat();
+
+    let hit_doors = hit_events.iter().map(|ev| ev.entity).collect::<Vec<_>>();
+    let hit_drums = drum_hit_events.iter().map(|ev| ev.drum).collect::<Vec<_>>();
+
+    for (entity, mut door) in door_query.iter_mut() {
+        if door.is_open() {
+            continue;
+        }
+
+        let hit =
+            hit_doors.contains(&entity) || door.listens.iter().any(|drum| hit_drums.contains(drum));
+
+        if !hit {
+            // do not trip change detection
+            if door.is_late(beat) {
+                door.miss();
+            }
+
+            continue;
+        }
+
+        if !on_beat {
+            if door.hits() > 0 {
+                door.miss();
+            }
+
+            continue;
+        }
+
+        if !door.hit(beat) {
+            continue;
+        }
+
+        crate::game_info!(Interactions, "rhythm door {:?} opened", entity);
+
+        commands.entity(entity).insert(ColliderDisabled);
+
+        if let Some(target) = door.activates {
+            activate_events.send(ActivateEvent(target));
+        }
+    }
+}
+
+fn update_rhythm_lights(
+    mut door_query: Query<(&RhythmDoor, &mut Sprite, &Children), Changed<RhythmDoor>>,
+    mut light_query: Query<(&RhythmLight, &mut Sprite), Without<RhythmDoor>>,
+) {
+    for (door, mut sprite, children) in door_query.iter_mut() {
+        // open doors fade out of the way; closed doors are left to
+        // `pulse_rhythm_doors`
+        let color = RhythmDoor::COLOR.with_a(0.25);
+
+        if door.is_open() && sprite.color != color {
+            sprite.color = color;
+        }
+
+        let mut lights = light_query.iter_many_mut(children);
+
+        while let Some((light, mut sprite)) = lights.fetch_next() {
+            let color = if light.0 < door.hits() {
+                RhythmDoor::LIT
+            } else {
+                RhythmDoor::UNLIT
+            };
+
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+fn pulse_rhythm_doors(
+    mut door_query: Query<(&RhythmDoor, &mut Sprite)>,
+    accessibility: Res<AccessibilitySettings>,
+    clock: Res<BeatClock>,
+) {
+    // flash on the beat, then fade back
+    let flash = 1. - clock.phase();
+    let t = accessibility.pulse(flash * flash);
+
+    let [r, g, b, _] = RhythmDoor::BEAT.as_rgba_f32();
+    let [br, bg, bb, _] = RhythmDoor::COLOR.as_rgba_f32();
+
+    let color = Color::rgb(br + (r - br) * t, bg + (g - bg) * t, bb + (b - bb) * t);
+
+    for (door, mut sprite) in door_query.iter_mut() {
+        // do not trip change detection
+        if !door.is_open() && sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Activations in a sequence.
+//!
+//! An [`ActivationSequencer`] waits for an [`ActivateEvent`] of its own, or a
+//! signal down the pipe it is hooked up to, and then activates each of its
+//! targets in turn with a delay between each, like platforms sliding out one
+//! after another.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::time::Duration;
+
+use super::{InteractionSystem, Junction, SignalConsumer, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::level::{pipe::PipeInput, Iid};
+use crate::platform::ActivateEvent;
+
+/// Sequencer plugin.
+pub struct SequencerPlugin;
+
+impl Plugin for SequencerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ActivationSequencerBundle>("ActivationSequencer")
+            .add_systems(Update, upgrade_sequencer_targets)
+            .add_systems(
+                Update,
+                (start_sequencers, run_sequencers)
+                    .chain()
+                    .run_if(any_with_component::<ActivationSequencer>())
+                    .after(InteractionSystem::TravelSignal),
+            );
+    }
+}
+
+/// Activates a list of targets one after another.
+///
+/// Starting a sequencer that is already going does nothing.
+#[derive(Clone, Component, Debug, Default)]
+pub struct ActivationSequencer {
+    /// The targets, in the order they are activated.
+    pub targets: Vec<Entity>,
+    /// The wait before each target is activated, from when the one before it
+    /// was, or from the start for the first.
+    ///
+    /// Targets past the end of the list wait as long as the last one, or
+    /// [`ActivationSequencer::DEFAULT_DELAY`] if it is empty.
+    pub delays: Vec<Duration>,
+    /// Every target of the level, in order, with the entity it was found as.
+    targets_iid: Vec<(String, Option<Entity>)>,
+    step: Option<(usize, Timer)>,
+}
+
+impl ActivationSequencer {
+    /// The wait between activations if the level doesn't say.
+    pub const DEFAULT_DELAY: Duration = Duration::from_millis(250);
+
+    /// Creates a new `ActivationSequencer` with some delays, to be given
+    /// targets.
+    pub fn new(delays: Vec<Duration>) -> ActivationSequencer {
+        ActivationSequencer {
+            delays,
+            ..Default::default()
+        }
+    }
+
+    /// The wait before a target.
+    pub fn delay(&self, index: usize) -> Duration {
+        self.delays
+            .get(index)
+            .or(self.delays.last())
+            .copied()
+            .unwrap_or(ActivationSequencer::DEFAULT_DELAY)
+    }
+
+    /// The iids of targets that haven't been found yet.
+    pub fn unresolved(&self) -> impl Iterator<Item = &str> {
+        self.targets_iid
+            .iter()
+            .filter(|(_, found)| found.is_none())
+            .map(|(iid, _)| iid.as_str())
+    }
+
+    /// Checks if the sequence is going.
+    pub fn is_running(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Starts the sequence from the first target.
+    pub fn start(&mut self) {
+        if self.is_running() || self.targets.is_empty() {
+            return;
+        }
+
+        self.step = Some((0, Timer::new(self.delay(0), TimerMode::Once)));
+    }
+
+    /// Moves the sequence along, returning the targets that are activated.
+    pub fn tick(&mut self, delta: Duration) -> Vec<Entity> {
+        let mut activated = Vec::new();
+        let mut delta = delta;
+
+        while let Some((index, timer)) = &mut self.step {
+            let index = *index;
+            let left = timer.remaining();
+
+            timer.tick(delta);
+
+            if !timer.finished() {
+                break;
+            }
+
+            // the rest of the frame counts toward the next target
+            delta = delta.saturating_sub(left);
+
+            activated.extend(self.targets.get(index).copied());
+
+            let next = index + 1;
+
+            self.step = if next < self.targets.len() {
+                Some((next, Timer::new(self.delay(next), TimerMode::Once)))
+            } else {
+                None
+            };
+        }
+
+        activated
+    }
+}
+
+/// A bundle for an [`ActivationSequencer`].
+#[derive(Bundle, Default)]
+pub struct ActivationSequencerBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub sequencer: ActivationSequencer,
+    pub junction: Junction,
+    pub consumer: SignalConsumer,
+    pub input: PipeInput,
+    pub iid: Iid,
+}
+
+impl LdtkEntity for ActivationSequencerBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let targets_iid = entity_instance
+            .get_maybe_entity_refs_field("Targets")
+            .map(|refs| {
+                refs.iter()
+                    .flatten()
+                    .map(|r| (r.entity_iid.clone(), None))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let delays = entity_instance
+            .get_maybe_floats_field("Delays")
+            .map(|delays| {
+                delays
+                    .iter()
+                    .flatten()
+                    .map(|&delay| Duration::from_secs_f32(delay.max(0.)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        ActivationSequencerBundle {
+            sequencer: ActivationSequencer {
+                targets_iid,
+                ..ActivationSequencer::new(delays)
+            },
+            input: PipeInput::from_field(entity_instance, layer_instance, "Input"),
+            iid: Iid::from(entity_instance),
+            ..Default::default()
+        }
+    }
+}
+
+fn upgrade_sequencer_targets(
+    mut sequencer_query: Query<&mut ActivationSequencer>,
+    iid_query: Query<(Entity, &Iid)>,
+    added_iid_query: Query<(Entity, &Iid), Added<Iid>>,
+) {
+    for mut sequencer in sequencer_query.iter_mut() {
+        if sequencer.targets_iid.is_empty() {
+            continue;
+        }
+
+        // a new sequencer looks through everything, and after that only
+        // through what was spawned since
+        let iids = if sequencer.is_added() {
+            iid_query.iter().collect::<Vec<_>>()
+        } else {
+            added_iid_query.iter().collect::<Vec<_>>()
+        };
+
+        let found = sequencer
+            .targets_iid
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, found))| found.is_none())
+            .filter_map(|(index, (iid_request, _))| {
+                iids.iter()
+                    .find(|(_, iid)| iid.0 == *iid_request)
+                    .map(|&(e, _)| (index, e))
+            })
+            .collect::<Vec<_>>();
+
+        // do not trip change detection
+        if found.is_empty() {
+            continue;
+        }
+
+        let sequencer = &mut *sequencer;
+
+        for (index, e) in found {
+            sequencer.targets_iid[index].1 = Some(e);
+        }
+
+        // targets that are still missing don't hold up the rest, and the
+        // sequence keeps the order of the level
+        sequencer.targets = sequencer
+            .targets_iid
+            .iter()
+            .filter_map(|(_, found)| *found)
+            .collect();
+
+        if sequencer.unresolved().next().is_none() {
+            sequencer.targets_iid.clear();
+        }
+    }
+}
+
+fn start_sequencers(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut activate_events: EventReader<ActivateEvent>,
+    mut sequencer_query: Query<&mut ActivationSequencer>,
+) {
+    let signalled = signal_events.iter().filter_map(|ev| {
+        sequencer_query.contains(ev.receiver).then(|| {
+            despawn_queue.push(ev.signal, DespawnReason::Consumed);
+            ev.receiver
+        })
+    });
+
+    let sequencers = signalled
+        .chain(activate_events.iter().map(|ev| ev.0))
+        .collect::<Vec<_>>();
+
+    for entity in sequencers {
+        let Ok(mut sequencer) = sequencer_query.get_mut(entity) else {
+            continue;
+        };
+
+        if !sequencer.is_running() {
+            crate::game_debug!(Interactions, "sequencer {:?} started", entity);
+
+            let unresolved = sequencer.unresolved().collect::<Vec<_>>();
+
+            if !unresolved.is_empty() {
+                crate::game_warn!(
+                    Interactions,
+                    "sequencer {:?} started without targets {:?}",
+                    entity,
+                    unresolved
+                );
+            }
+
+            sequencer.start();
+        }
+    }
+}
+
+fn run_sequencers(
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut sequencer_query: Query<&mut ActivationSequencer>,
+    time: Res<Time>,
+) {
+    for mut sequencer in sequencer_query.iter_mut() {
+        // do not trip change detection
+        if !sequencer.is_running() {
+            continue;
+        }
+
+        for target in sequencer.tick(time.delta()) {
+            activate_events.send(ActivateEvent(target));
+        }
+    }
+}
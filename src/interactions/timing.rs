@@ -0,0 +1,205 @@
+//! Timing nodes for the pipe network.
+//!
+//! A [`Delay`] holds onto the signals that reach it for a while before
+//! sending them on, and a [`Pulse`] sends out a signal of its own every so
+//! often.
+
+use bevy::prelude::*;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::{InteractionSystem, Junction, Signal, SignalConsumer, SignalData, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::Hostility;
+
+/// Timing node plugin.
+pub struct TimingPlugin;
+
+impl Plugin for TimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (hold_delayed_signals, release_delayed_signals)
+                .chain()
+                .run_if(any_with_component::<Delay>())
+                .after(InteractionSystem::TravelSignal),
+        )
+        .add_systems(
+            Update,
+            fire_pulses
+                .run_if(any_with_component::<Pulse>())
+                .after(InteractionSystem::TravelSignal),
+        );
+    }
+}
+
+/// A bundle for a [`Delay`].
+#[derive(Bundle, Clone, Debug)]
+pub struct DelayBundle {
+    pub delay: Delay,
+    pub consumer: SignalConsumer,
+}
+
+impl DelayBundle {
+    /// Creates a new `DelayBundle`.
+    pub fn new(delay: Delay) -> DelayBundle {
+        DelayBundle {
+            delay,
+            consumer: SignalConsumer,
+        }
+    }
+}
+
+/// Holds every signal that reaches a junction for some time, then sends it on
+/// the way it was going.
+#[derive(Clone, Component, Debug)]
+pub struct Delay {
+    /// How long signals are held for.
+    pub time: Duration,
+    held: VecDeque<(f32, Entity, SignalData)>,
+}
+
+impl Delay {
+    /// The time of a delay if the level doesn't say.
+    pub const DEFAULT_TIME: Duration = Duration::from_secs(1);
+
+    /// Creates a new `Delay`.
+    pub fn new(time: Duration) -> Delay {
+        Delay {
+            time,
+            held: VecDeque::new(),
+        }
+    }
+
+    /// Holds a signal from `sender` that came in at a time in seconds.
+    pub fn hold(&mut self, sender: Entity, data: SignalData, now: f32) {
+        self.held
+            .push_back((now + self.time.as_secs_f32(), sender, data));
+    }
+
+    /// Lets go of the next signal that is due at a time in seconds, and the
+    /// pipe it came in from.
+    pub fn release(&mut self, now: f32) -> Option<(Entity, SignalData)> {
+        if self.held.front()?.0 > now {
+            return None;
+        }
+
+        self.held
+            .pop_front()
+            .map(|(_, sender, data)| (sender, data))
+    }
+}
+
+impl Default for Delay {
+    fn default() -> Delay {
+        Delay::new(Delay::DEFAULT_TIME)
+    }
+}
+
+/// Sends a signal out of every pipe of a junction on a repeating timer.
+#[derive(Clone, Component, Debug)]
+pub struct Pulse {
+    /// The signal sent out.
+    pub data: SignalData,
+    timer: Timer,
+}
+
+impl Pulse {
+    /// The interval of a pulse if the level doesn't say.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// Creates a new, friendly `Pulse` that fires every `interval`.
+    pub fn new(interval: Duration) -> Pulse {
+        Pulse {
+            data: SignalData::new(Hostility::Friendly),
+            timer: Timer::new(interval, TimerMode::Repeating),
+        }
+    }
+}
+
+impl Default for Pulse {
+    fn default() -> Pulse {
+        Pulse::new(Pulse::DEFAULT_INTERVAL)
+    }
+}
+
+/// Sends a signal from a junction down each of its pipes, except the one to
+/// `except`.
+fn send_out(
+    commands: &mut Commands,
+    entity: Entity,
+    junction: &Junction,
+    except: Option<Entity>,
+    data: &SignalData,
+) {
+    for pipe in junction
+        .pipes
+        .iter()
+        .filter(|pipe| Some(pipe.receiver) != except)
+    {
+        commands.spawn((
+            SpatialBundle::default(),
+            Signal {
+                data: data.clone(),
+                source: entity,
+                destination: Some(pipe.receiver),
+                position: 0.,
+                speed: pipe.speed,
+            },
+        ));
+    }
+}
+
+fn hold_delayed_signals(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut delay_query: Query<&mut Delay>,
+    signal_query: Query<&Signal>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for ev in signal_events.iter() {
+        let Ok(mut delay) = delay_query.get_mut(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        delay.hold(ev.sender, signal.data.clone(), now);
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+    }
+}
+
+fn release_delayed_signals(
+    mut commands: Commands,
+    mut delay_query: Query<(Entity, &mut Delay, &Junction)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    for (entity, mut delay, junction) in delay_query.iter_mut() {
+        while let Some((sender, data)) = delay.release(now) {
+            send_out(&mut commands, entity, junction, Some(sender), &data);
+        }
+    }
+}
+
+fn fire_pulses(
+    mut commands: Commands,
+    mut pulse_query: Query<(Entity, &mut Pulse, &Junction)>,
+    time: Res<Time>,
+) {
+    for (entity, mut pulse, junction) in pulse_query.iter_mut() {
+        pulse.timer.tick(time.delta());
+
+        for _ in 0..pulse.timer.times_finished_this_tick() {
+            send_out(&mut commands, entity, junction, None, &pulse.data);
+        }
+    }
+}
@@ -0,0 +1,704 @@
+//! Triggers in the world, like pressure plates.
+//!
+//! A [`PressurePlate`] is on while it is stood on and a [`Lever`] is flipped
+//! on and off by the player or by shooting it. While either is on, it
+//! activates its target every frame, and sends a signal down the pipe it is
+//! hooked up to every [`Trigger::INTERVAL`], so it can drive doors and
+//! platforms from across the level.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{Junction, Signal, SignalData};
+
+use crate::enemy::Hostility;
+use crate::level::{pipe::PipeInput, Iid};
+use crate::physics;
+use crate::platform::ActivateEvent;
+use crate::player::controller::{Controller, ControllerSystem};
+use crate::projectile::{ContactBehavior, Heavy, HitEvent, ProjectileSystem};
+
+/// Trigger plugin.
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PressurePlateBundle>("PressurePlate")
+            .register_ldtk_entity::<LeverBundle>("Lever")
+            .add_systems(
+                Update,
+                (
+                    create_plate_visual,
+                    create_lever_visual,
+                    upgrade_plate_targets,
+                    upgrade_lever_targets,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    weigh_pressure_plates,
+                    animate_pressure_plates.run_if(any_with_component::<PressurePlate>()),
+                )
+                    .chain()
+                    .in_set(TriggerSystem::Weigh),
+            )
+            .add_systems(
+                Update,
+                press_plates_with_projectiles
+                    .run_if(any_with_component::<Heavy>())
+                    .after(ProjectileSystem::Event)
+                    .before(TriggerSystem::Weigh),
+            )
+            .add_systems(
+                Update,
+                toggle_levers
+                    .run_if(any_with_component::<Lever>())
+                    .after(ControllerSystem::ScanInput)
+                    .after(ProjectileSystem::Event)
+                    .in_set(TriggerSystem::Weigh),
+            )
+            .add_systems(
+                Update,
+                (power_from_plates, power_from_levers, animate_levers)
+                    .in_set(TriggerSystem::Power)
+                    .after(TriggerSystem::Weigh),
+            );
+    }
+}
+
+/// Trigger systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum TriggerSystem {
+    /// Pressure plates are weighed and levers are flipped.
+    Weigh,
+    /// Triggers that are on activate their targets and send signals.
+    Power,
+}
+
+/// The power a trigger puts out while it is on.
+#[derive(Clone, Debug)]
+pub struct Trigger {
+    timer: Timer,
+}
+
+impl Trigger {
+    /// How often a signal goes down the pipe of a trigger while it is on.
+    pub const INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Starts the signals over, so one goes out straight away.
+    fn restart(&mut self) {
+        self.timer.reset();
+        self.timer.set_elapsed(Trigger::INTERVAL);
+    }
+
+    /// Activates the target and sends signals down every pipe when they are
+    /// due.
+    fn power(
+        &mut self,
+        commands: &mut Commands,
+        activate_events: &mut EventWriter<ActivateEvent>,
+        entity: Entity,
+        activates: Option<Entity>,
+        junction: Option<&Junction>,
+        delta: Duration,
+    ) {
+        if let Some(target) = activates {
+            activate_events.send(ActivateEvent(target));
+        }
+
+        self.timer.tick(delta);
+
+        if self.timer.times_finished_this_tick() == 0 {
+            return;
+        }
+
+        for pipe in junction.iter().flat_map(|junction| junction.pipes.iter()) {
+            commands.spawn((
+                SpatialBundle::default(),
+                Signal {
+                    data: SignalData::new(Hostility::Friendly),
+                    source: entity,
+                    destination: Some(pipe.receiver),
+                    position: 0.,
+                    speed: pipe.speed,
+                },
+            ));
+        }
+    }
+}
+
+impl Default for Trigger {
+    fn default() -> Trigger {
+        Trigger {
+            timer: Timer::new(Trigger::INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// A bundle for a [`PressurePlate`].
+#[derive(Bundle)]
+pub struct PressurePlateBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub contact_force_threshold: ContactForceEventThreshold,
+    pub pressure_plate: PressurePlate,
+    pub junction: Junction,
+    pub output: PipeInput,
+}
+
+impl Default for PressurePlateBundle {
+    fn default() -> PressurePlateBundle {
+        PressurePlateBundle {
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            computed_visibility: ComputedVisibility::default(),
+            collider: Collider::cuboid(7., 2.),
+            collision_groups: CollisionGroups::new(physics::COLLISION_GROUP_SOLID, Group::all()),
+            active_events: ActiveEvents::CONTACT_FORCE_EVENTS,
+            // report every force
+            contact_force_threshold: ContactForceEventThreshold(0.),
+            pressure_plate: PressurePlate::default(),
+            junction: Junction::default(),
+            output: PipeInput::default(),
+        }
+    }
+}
+
+impl LdtkEntity for PressurePlateBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let activate_ref = entity_instance
+            .get_maybe_entity_ref_field("Activates")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        let required_weight = entity_instance
+            .get_float_field("Weight")
+            .ok()
+            .copied()
+            .unwrap_or(PressurePlate::default().required_weight);
+
+        PressurePlateBundle {
+            pressure_plate: PressurePlate {
+                required_weight,
+                activates_iid: activate_ref,
+                ..Default::default()
+            },
+            output: PipeInput::from_field(entity_instance, layer_instance, "Output"),
+            ..Default::default()
+        }
+    }
+}
+
+/// A plate that is pressed down while enough weight rests on it.
+///
+/// Sends an [`ActivateEvent`] to its target every frame while it is pressed,
+/// and signals down its pipe. See [`Trigger`].
+#[derive(Clone, Component, Debug)]
+pub struct PressurePlate {
+    /// The weight required to press the plate.
+    ///
+    /// Weight is measured in the physics engine's mass units; the player
+    /// weighs a little more than `0.5`.
+    pub required_weight: f32,
+    /// The entity activated when the plate is pressed.
+    pub activates: Option<Entity>,
+    activates_iid: Option<String>,
+    weight: f32,
+    pressed: bool,
+    depression: f32,
+    trigger: Trigger,
+}
+
+impl PressurePlate {
+    /// The weight currently resting on the plate.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Checks if the plate is pressed.
+    pub fn pressed(&self) -> bool {
+        self.pressed
+    }
+}
+
+impl Default for PressurePlate {
+    fn default() -> PressurePlate {
+        PressurePlate {
+            required_weight: 0.5,
+            activates: None,
+            activates_iid: None,
+            weight: 0.,
+            pressed: false,
+            depression: 0.,
+            trigger: Trigger::default(),
+        }
+    }
+}
+
+/// The visual part of a [`PressurePlate`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct PressurePlateVisual;
+
+/// A bundle for a [`Lever`].
+#[derive(Bundle)]
+pub struct LeverBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub sensor: Sensor,
+    pub contact_behavior: ContactBehavior,
+    pub lever: Lever,
+    pub junction: Junction,
+    pub output: PipeInput,
+}
+
+impl Default for LeverBundle {
+    fn default() -> LeverBundle {
+        LeverBundle {
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            computed_visibility: ComputedVisibility::default(),
+            collider: Collider::cuboid(4., 6.),
+            // caught by projectiles of either side, and nothing else
+            collision_groups: CollisionGroups::new(
+                physics::COLLISION_GROUP_FRIENDLY | physics::COLLISION_GROUP_HOSTILE,
+                physics::COLLISION_GROUP_PROJECTILE,
+            ),
+            sensor: Sensor,
+            contact_behavior: ContactBehavior::Absorb,
+            lever: Lever::default(),
+            junction: Junction::default(),
+            output: PipeInput::default(),
+        }
+    }
+}
+
+impl LdtkEntity for LeverBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let activate_ref = entity_instance
+            .get_maybe_entity_ref_field("Activates")
+            .ok() // may not exist
+            .and_then(|a| a.as_ref())
+            .map(|a| a.entity_iid.clone());
+
+        let on = entity_instance
+            .get_bool_field("On")
+            .ok()
+            .copied()
+            .unwrap_or_default();
+
+        LeverBundle {
+            lever: Lever {
+                activates_iid: activate_ref,
+                ..Lever::new(on)
+            },
+            output: PipeInput::from_field(entity_instance, layer_instance, "Output"),
+            ..Default::default()
+        }
+    }
+}
+
+/// A lever that the player flips on and off, by pressing the interact button
+/// next to it or by shooting it.
+///
+/// Sends an [`ActivateEvent`] to its target every frame while it is on, and
+/// signals down its pipe. See [`Trigger`].
+#[derive(Clone, Component, Debug)]
+pub struct Lever {
+    /// Whether the lever is on.
+    pub on: bool,
+    /// The entity activated while the lever is on.
+    pub activates: Option<Entity>,
+    activates_iid: Option<String>,
+    trigger: Trigger,
+}
+
+impl Lever {
+    /// How close the player has to be to flip a lever, in world units.
+    pub const REACH: f32 = 16.;
+    /// How far the handle of a lever leans over, in radians.
+    pub const LEAN: f32 = 0.6;
+
+    /// Creates a new `Lever`.
+    pub fn new(on: bool) -> Lever {
+        let mut trigger = Trigger::default();
+        trigger.restart();
+
+        Lever {
+            on,
+            activates: None,
+            activates_iid: None,
+            trigger,
+        }
+    }
+
+    /// Flips the lever.
+    pub fn toggle(&mut self) {
+        self.on = !self.on;
+        self.trigger.restart();
+    }
+}
+
+impl Default for Lever {
+    fn default() -> Lever {
+        Lever::new(false)
+    }
+}
+
+/// The handle of a [`Lever`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct LeverVisual;
+
+fn create_plate_visual(
+    mut commands: Commands,
+    new_plates_query: Query<Entity, Added<PressurePlate>>,
+) {
+    for entity in new_plates_query.iter() {
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.55, 0.55, 0.6),
+                        custom_size: Some(Vec2::new(14., 4.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                PressurePlateVisual,
+            ))
+            .set_parent(entity);
+    }
+}
+
+fn create_lever_visual(
+    mut commands: Commands,
+    new_levers_query: Query<(Entity, &Lever), Added<Lever>>,
+) {
+    for (entity, lever) in new_levers_query.iter() {
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.55, 0.55, 0.6),
+                        custom_size: Some(Vec2::new(2., 10.)),
+                        // turn around the bottom
+                        anchor: bevy::sprite::Anchor::BottomCenter,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0., -5., 0.)
+                        .with_rotation(Quat::from_rotation_z(lever_lean(lever))),
+                    ..Default::default()
+                },
+                LeverVisual,
+            ))
+            .set_parent(entity);
+    }
+}
+
+fn lever_lean(lever: &Lever) -> f32 {
+    // leans right when on
+    if lever.on {
+        -Lever::LEAN
+    } else {
+        Lever::LEAN
+    }
+}
+
+fn upgrade_plate_targets(
+    mut plate_query: Query<&mut PressurePlate>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    for mut plate in plate_query.iter_mut() {
+        let Some(iid_request) = &plate.activates_iid else {
+            continue;
+        };
+
+        let found = iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == *iid_request)
+            .map(|(e, _)| e);
+
+        if let Some(found) = found {
+            plate.activates = Some(found);
+            plate.activates_iid = None;
+        }
+    }
+}
+
+fn upgrade_lever_targets(mut lever_query: Query<&mut Lever>, iid_query: Query<(Entity, &Iid)>) {
+    for mut lever in lever_query.iter_mut() {
+        let Some(iid_request) = &lever.activates_iid else {
+            continue;
+        };
+
+        let found = iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == *iid_request)
+            .map(|(e, _)| e);
+
+        if let Some(found) = found {
+            lever.activates = Some(found);
+            lever.activates_iid = None;
+        }
+    }
+}
+
+fn weigh_pressure_plates(
+    mut plate_query: Query<(Entity, &mut PressurePlate)>,
+    mut contact_force_events: EventReader<ContactForceEvent>,
+    physics_config: Res<RapierConfiguration>,
+    physics: Res<RapierContext>,
+) {
+    // gravity in physics units
+    let gravity = physics_config.gravity / physics.physics_scale();
+
+    if gravity.length_squared() <= f32::EPSILON {
+        return;
+    }
+
+    let mut weights: HashMap<Entity, f32> = HashMap::new();
+
+    for ev in contact_force_events.iter() {
+        let plate = if plate_query.contains(ev.collider1) {
+            ev.collider1
+        } else if plate_query.contains(ev.collider2) {
+            ev.collider2
+        } else {
+            continue;
+        };
+
+        // only the force along gravity counts as weight
+        let force = ev.total_force.dot(gravity.normalize()).abs();
+
+        *weights.entry(plate).or_default() += force / gravity.length();
+    }
+
+    for (entity, mut plate) in plate_query.iter_mut() {
+        let weight = weights.get(&entity).copied().unwrap_or_default();
+
+        // do not trip change detection
+        if plate.weight != weight {
+            plate.weight = weight;
+        }
+
+        let pressed = weight >= plate.required_weight;
+
+        if pressed != plate.pressed {
+            plate.pressed = pressed;
+
+            if pressed {
+                plate.trigger.restart();
+            }
+        }
+    }
+}
+
+fn press_plates_with_projectiles(
+    mut hit_events: EventReader<HitEvent>,
+    mut plate_query: Query<&mut PressurePlate>,
+    heavy_query: Query<(), With<Heavy>>,
+    mut activate_events: EventWriter<ActivateEvent>,
+) {
+    for ev in hit_events.iter() {
+        if !heavy_query.contains(ev.projectile) {
+            continue;
+        }
+
+        let Ok(mut plate) = plate_query.get_mut(ev.entity) else {
+            continue;
+        };
+
+        // slam the plate down, it springs back on its own
+        plate.depression = 1.;
+
+        if let Some(target) = plate.activates {
+            activate_events.send(ActivateEvent(target));
+        }
+    }
+}
+
+fn animate_pressure_plates(
+    mut plate_query: Query<(&Children, &mut PressurePlate)>,
+    mut visual_query: Query<&mut Transform, With<PressurePlateVisual>>,
+    time: Res<Time>,
+) {
+    for (children, mut plate) in plate_query.iter_mut() {
+        let target = if plate.pressed { 1. } else { 0. };
+        let step = 8. * time.delta_seconds();
+
+        let depression = plate.depression + (target - plate.depression).clamp(-step, step);
+
+        if depression == plate.depression {
+            continue;
+        }
+
+        plate.depression = depression;
+
+        let mut visuals = visual_query.iter_many_mut(children);
+
+        while let Some(mut transform) = visuals.fetch_next() {
+            // sink into the ground
+            transform.translation.y = -3. * depression;
+        }
+    }
+}
+
+fn toggle_levers(
+    mut hit_events: EventReader<HitEvent>,
+    mut lever_query: Query<(Entity, &GlobalTransform, &mut Lever)>,
+    controller_query: Query<(&GlobalTransform, &Controller)>,
+) {
+    let shot = hit_events
+        .iter()
+        .filter(|ev| lever_query.contains(ev.entity))
+        .map(|ev| ev.entity)
+        .collect::<Vec<_>>();
+
+    let mut flipped = Vec::new();
+
+    for (transform, controller) in controller_query.iter() {
+        if !controller.interact() {
+            continue;
+        }
+
+        // flip the closest lever in reach
+        let position = transform.translation().truncate();
+
+        let closest = lever_query
+            .iter()
+            .map(|(entity, transform, _)| {
+                (
+                    entity,
+                    transform.translation().truncate().distance(position),
+                )
+            })
+            .filter(|(_, dist)| *dist <= Lever::REACH)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        flipped.extend(closest.map(|(entity, _)| entity));
+    }
+
+    // a lever hit by two things at once flips once
+    flipped.extend(shot);
+    flipped.sort();
+    flipped.dedup();
+
+    for entity in flipped {
+        let Ok((_, _, mut lever)) = lever_query.get_mut(entity) else {
+            continue;
+        };
+
+        lever.toggle();
+
+        crate::game_debug!(
+            Interactions,
+            "lever {:?} {}",
+            entity,
+            if lever.on { "on" } else { "off" }
+        );
+    }
+}
+
+fn power_from_plates(
+    mut commands: Commands,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut plate_query: Query<(Entity, &mut PressurePlate, Option<&Junction>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut plate, junction) in plate_query.iter_mut() {
+        // do not trip change detection
+        if !plate.pressed {
+            continue;
+        }
+
+        let activates = plate.activates;
+
+        plate.trigger.power(
+            &mut commands,
+            &mut activate_events,
+            entity,
+            activates,
+            junction,
+            time.delta(),
+        );
+    }
+}
+
+fn power_from_levers(
+    mut commands: Commands,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut lever_query: Query<(Entity, &mut Lever, Option<&Junction>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut lever, junction) in lever_query.iter_mut() {
+        // do not trip change detection
+        if !lever.on {
+            continue;
+        }
+
+        let activates = lever.activates;
+
+        lever.trigger.power(
+            &mut commands,
+            &mut activate_events,
+            entity,
+            activates,
+            junction,
+            time.delta(),
+        );
+    }
+}
+
+fn animate_levers(
+    lever_query: Query<(&Children, &Lever), Changed<Lever>>,
+    mut visual_query: Query<&mut Transform, With<LeverVisual>>,
+) {
+    for (children, lever) in lever_query.iter() {
+        let mut visuals = visual_query.iter_many_mut(children);
+
+        let rotation = Quat::from_rotation_z(lever_lean(lever));
+
+        while let Some(mut transform) = visuals.fetch_next() {
+            // do not trip change detection
+            if transform.rotation != rotation {
+                transform.rotation = rotation;
+            }
+        }
+    }
+}
@@ -3,11 +3,17 @@
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
-use super::{InteractionSystem, Signal};
+use bevy_ecs_tilemap::{
+    map::{TilemapGridSize, TilemapId},
+    tiles::{TileColor, TilePos, TileStorage},
+};
+
+use super::{InteractionSystem, Junction, Signal};
 
 use std::borrow::Cow;
 use std::sync::Arc;
 
+use crate::settings::AccessibilitySettings;
 use crate::{GameAssets, GameState};
 
 /// Adds visuals to signals.
@@ -21,11 +27,51 @@ impl Plugin for VisualSignalPlugin {
         )
         .add_systems(
             Update,
-            update_signal_transform.after(InteractionSystem::TravelSignal),
-        );
+            update_signal_transform
+                .run_if(any_with_component::<Signal>())
+                .after(InteractionSystem::TravelSignal),
+        )
+        .add_systems(
+            Update,
+            (
+                (light_pipe_tiles, tint_signal_glow).run_if(any_with_component::<Signal>()),
+                fade_pipe_glow.run_if(any_with_component::<PipeGlow>()),
+            )
+                .chain()
+                .after(update_signal_transform),
+        )
+        .add_systems(Update, apply_pipe_tints.after(fade_pipe_glow));
     }
 }
 
+/// A soft light that travels with a signal.
+#[derive(Clone, Component, Debug, Default)]
+pub struct SignalGlow;
+
+/// A pipe tile lit up by a signal passing through it.
+///
+/// The light fades over [`PipeGlow::FADE`].
+#[derive(Clone, Component, Debug)]
+pub struct PipeGlow {
+    /// The color of the light.
+    pub color: Color,
+    /// How bright the light is, from `0.` to `1.`.
+    pub intensity: f32,
+}
+
+impl PipeGlow {
+    /// How long it takes for a tile to go dark again, in seconds.
+    pub const FADE: f32 = 0.4;
+    /// How much of the light's color is mixed into the tile at full
+    /// intensity.
+    pub const STRENGTH: f32 = 0.6;
+}
+
+/// The color of a pipe tile while it isn't lit up by a [`PipeGlow`], like
+/// the red or blue of an intake that only takes one hostility.
+#[derive(Clone, Component, Debug)]
+pub struct PipeTint(pub Color);
+
 /// Defines how a signal will visually "buldge" while travelling through pipes,
 /// as an array of floats, where 1 is normal size and 0 is hidden.
 #[derive(Clone, Component, Debug)]
@@ -106,10 +152,10 @@ impl<'w, 's> BuldgeQuery<'w, 's> {
 
 fn create_signal_visual(
     mut commands: Commands,
-    new_signal_query: Query<Entity, Added<Signal>>,
+    new_signal_query: Query<(Entity, &Signal), Added<Signal>>,
     assets: Res<GameAssets>,
 ) {
-    for entity in new_signal_query.iter() {
+    for (entity, signal) in new_signal_query.iter() {
         // create buldge matte
         commands
             .spawn(SpriteBundle {
@@ -117,12 +163,32 @@ fn create_signal_visual(
                 ..Default::default()
             })
             .set_parent(entity);
+
+        // create glow behind the matte
+        let mut color = signal.data.hostility.color();
+        color.set_a(0.3);
+
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(14.)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0., 0., -0.1),
+                    ..Default::default()
+                },
+                SignalGlow,
+            ))
+            .set_parent(entity);
     }
 }
 
 fn update_signal_transform(
     transforms: Query<&GlobalTransform>,
     mut signals_query: Query<(&mut Transform, &Signal)>,
+    junctions_query: Query<&Junction>,
     buldges: BuldgeQuery,
     // for testing
     //mut gizmos: Gizmos,
@@ -143,16 +209,130 @@ fn update_signal_transform(
         let start = source.translation().truncate();
         let end = dest.translation().truncate();
 
-        // lerp
-        let position = start.lerp(end, signal.position);
+        // follow the shape of the pipe, if there is one
+        let pipe = junctions_query
+            .get(signal.source)
+            .ok()
+            .and_then(|junction| junction.pipes.iter().find(|p| p.receiver == destination));
+
+        let position = match pipe {
+            Some(pipe) => pipe.point(start, end, signal.position),
+            None => start.lerp(end, signal.position),
+        };
 
         transform.translation = position.extend(30.);
 
         // find scale
         let scale = buldges.at(signal.source, destination, signal.position.min(0.999999));
 
+        // stronger signals are bigger
+        let scale = scale * signal.data.strength.sqrt().min(2.);
+
         transform.scale = Vec3::splat(scale);
 
         //gizmos.circle(transform.translation, Vec3::Z, scale * 4., Color::BLUE);
     }
 }
+
+fn tint_signal_glow(
+    signals_query: Query<(&Signal, &Children)>,
+    mut glow_query: Query<&mut Sprite, With<SignalGlow>>,
+) {
+    for (signal, children) in signals_query.iter() {
+        // converters can change a signal's hostility on the way
+        let mut color = signal.data.hostility.color();
+        color.set_a(0.3);
+
+        let mut glows = glow_query.iter_many_mut(children);
+
+        while let Some(mut sprite) = glows.fetch_next() {
+            // do not trip change detection
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+fn light_pipe_tiles(
+    mut commands: Commands,
+    signals_query: Query<(&Transform, &Signal)>,
+    tilemap_id_query: Query<&TilemapId>,
+    layers_query: Query<(&TileStorage, &TilemapGridSize, &GlobalTransform)>,
+    mut glow_query: Query<&mut PipeGlow>,
+) {
+    for (transform, signal) in signals_query.iter() {
+        if signal.destination.is_none() {
+            continue;
+        }
+
+        // signals travel between tiles on the same layer
+        let Ok(tilemap_id) = tilemap_id_query.get(signal.source) else {
+            continue;
+        };
+
+        let Ok((tiles, grid_size, layer_transform)) = layers_query.get(tilemap_id.0) else {
+            continue;
+        };
+
+        let local = layer_transform
+            .affine()
+            .inverse()
+            .transform_point3(transform.translation)
+            .truncate();
+        let pos = (local / Vec2::new(grid_size.x, grid_size.y)).round();
+
+        if pos.x < 0. || pos.y < 0. {
+            continue;
+        }
+
+        let Some(tile) = tiles.get(&TilePos::new(pos.x as u32, pos.y as u32)) else {
+            continue;
+        };
+
+        let color = signal.data.hostility.color();
+
+        if let Ok(mut glow) = glow_query.get_mut(tile) {
+            glow.color = color;
+            glow.intensity = 1.;
+        } else {
+            commands.entity(tile).insert(PipeGlow {
+                color,
+                intensity: 1.,
+            });
+        }
+    }
+}
+
+fn fade_pipe_glow(
+    mut commands: Commands,
+    mut glow_query: Query<(Entity, &mut PipeGlow, &mut TileColor, Option<&PipeTint>)>,
+    accessibility: Res<AccessibilitySettings>,
+    time: Res<Time>,
+) {
+    for (entity, mut glow, mut tile_color, tint) in glow_query.iter_mut() {
+        let base = tint.map(|tint| tint.0).unwrap_or(Color::WHITE);
+
+        glow.intensity -= time.delta_seconds() / PipeGlow::FADE;
+
+        if glow.intensity <= 0. {
+            tile_color.0 = base;
+            commands.entity(entity).remove::<PipeGlow>();
+            continue;
+        }
+
+        let t = glow.intensity * accessibility.pulse(PipeGlow::STRENGTH);
+        let [r, g, b, _] = glow.color.as_rgba_f32();
+        let [br, bg, bb, _] = base.as_rgba_f32();
+
+        tile_color.0 = Color::rgb(br + (r - br) * t, bg + (g - bg) * t, bb + (b - bb) * t);
+    }
+}
+
+fn apply_pipe_tints(
+    mut tint_query: Query<(&PipeTint, &mut TileColor), (Changed<PipeTint>, Without<PipeGlow>)>,
+) {
+    for (tint, mut tile_color) in tint_query.iter_mut() {
+        tile_color.0 = tint.0;
+    }
+}
@@ -0,0 +1,186 @@
+//! Signals sent without pipes.
+//!
+//! A [`Transmitter`] takes in the signals that reach it and sends them to
+//! every [`Receiver`] on the same [`ChannelId`], wherever they are, which then
+//! send them on down their own pipes. This hooks up rooms that are far apart,
+//! or in different levels, as long as the level of the receiver is loaded.
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+
+use super::{InteractionSystem, Signal, SignalConsumer, SignalData, SignalEvent};
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+
+/// Wireless plugin.
+pub struct WirelessPlugin;
+
+impl Plugin for WirelessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Channels>()
+            .add_event::<WirelessEvent>()
+            .add_systems(Update, register_receivers.before(receive_signals))
+            .add_systems(
+                Update,
+                (transmit_signals, receive_signals)
+                    .chain()
+                    .run_if(any_with_component::<Transmitter>())
+                    .after(InteractionSystem::TravelSignal),
+            );
+    }
+}
+
+/// A channel that transmitters and receivers are tuned to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub struct ChannelId(pub u32);
+
+/// Sends the signals that reach a junction to every [`Receiver`] on its
+/// channel.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Transmitter(pub ChannelId);
+
+/// Sends the signals from every [`Transmitter`] on its channel down the pipes
+/// of a junction.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Receiver(pub ChannelId);
+
+/// A bundle for a [`Transmitter`].
+#[derive(Bundle, Clone, Debug)]
+pub struct TransmitterBundle {
+    pub transmitter: Transmitter,
+    pub consumer: SignalConsumer,
+}
+
+impl TransmitterBundle {
+    /// Creates a new `TransmitterBundle`.
+    pub fn new(channel: ChannelId) -> TransmitterBundle {
+        TransmitterBundle {
+            transmitter: Transmitter(channel),
+            consumer: SignalConsumer,
+        }
+    }
+}
+
+/// Every [`Receiver`], by channel.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct Channels {
+    receivers: HashMap<ChannelId, Vec<Entity>>,
+}
+
+impl Channels {
+    /// The receivers on a channel.
+    pub fn receivers(&self, channel: ChannelId) -> &[Entity] {
+        self.receivers
+            .get(&channel)
+            .map(|receivers| receivers.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Adds a receiver to a channel.
+    pub fn register(&mut self, channel: ChannelId, receiver: Entity) {
+        let receivers = self.receivers.entry(channel).or_default();
+
+        if !receivers.contains(&receiver) {
+            receivers.push(receiver);
+        }
+    }
+
+    /// Takes a receiver off every channel it is on.
+    pub fn unregister(&mut self, receiver: Entity) {
+        for receivers in self.receivers.values_mut() {
+            receivers.retain(|&e| e != receiver);
+        }
+
+        self.receivers.retain(|_, receivers| !receivers.is_empty());
+    }
+}
+
+/// A signal went out on a channel.
+#[derive(Debug, Event)]
+pub struct WirelessEvent {
+    /// The transmitter that sent the signal.
+    pub transmitter: Entity,
+    /// The channel of the transmitter.
+    pub channel: ChannelId,
+    /// The signal.
+    pub data: SignalData,
+}
+
+fn register_receivers(
+    mut channels: ResMut<Channels>,
+    receiver_query: Query<(Entity, &Receiver), Changed<Receiver>>,
+    mut removed: RemovedComponents<Receiver>,
+) {
+    for entity in removed.iter() {
+        channels.unregister(entity);
+    }
+
+    for (entity, receiver) in receiver_query.iter() {
+        // the channel may have changed
+        channels.unregister(entity);
+        channels.register(receiver.0, entity);
+    }
+}
+
+fn transmit_signals(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut wireless_events: EventWriter<WirelessEvent>,
+    transmitter_query: Query<&Transmitter>,
+    signal_query: Query<&Signal>,
+) {
+    for ev in signal_events.iter() {
+        let Ok(transmitter) = transmitter_query.get(ev.receiver) else {
+            continue;
+        };
+
+        let Ok(signal) = signal_query.get(ev.signal) else {
+            continue;
+        };
+
+        despawn_queue.push(ev.signal, DespawnReason::Consumed);
+
+        wireless_events.send(WirelessEvent {
+            transmitter: ev.receiver,
+            channel: transmitter.0,
+            data: signal.data.clone(),
+        });
+    }
+}
+
+fn receive_signals(
+    mut commands: Commands,
+    mut wireless_events: EventReader<WirelessEvent>,
+    mut signal_events: EventWriter<SignalEvent>,
+    channels: Res<Channels>,
+) {
+    for ev in wireless_events.iter() {
+        let receivers = channels.receivers(ev.channel);
+
+        if receivers.is_empty() {
+            crate::game_debug!(
+                Interactions,
+                "transmitter {:?} has no receivers on {:?}",
+                ev.transmitter,
+                ev.channel
+            );
+            continue;
+        }
+
+        for &receiver in receivers {
+            let signal = commands
+                .spawn((
+                    SpatialBundle::default(),
+                    Signal::at(ev.data.clone(), receiver),
+                ))
+                .id();
+            signal_events.send(SignalEvent {
+                receiver,
+                sender: receiver,
+                signal,
+                overfill: 0.,
+            });
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Debug names for entities spawned at runtime.
+//!
+//! With the `devtools` feature, projectiles, signals, effects and generated
+//! colliders get a [`Name`] like `QuarterNote #12` as they are spawned, so
+//! they can be told apart in the inspector and in logs. Without it, nothing
+//! is named and this costs nothing.
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+
+/// Debug label plugin.
+pub struct DebugLabelPlugin;
+
+impl Plugin for DebugLabelPlugin {
+    #[allow(unused_variables)]
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "devtools")]
+        {
+            use crate::fx::{absorb::GhostProjectile, particles::Particle};
+            use crate::interactions::Signal;
+            use crate::level::{collision::CreatedCollider, Ground, Spikes};
+            use crate::projectile::{beam::Beam, explosion::Explosion};
+
+            app.init_resource::<DebugLabels>().add_systems(
+                PostUpdate,
+                (
+                    label_added::<Signal>("Signal"),
+                    label_added::<GhostProjectile>("Ghost"),
+                    label_added::<Beam>("Beam"),
+                    label_added::<Explosion>("Explosion"),
+                    label_added::<Particle>("Particle"),
+                    label_added::<CreatedCollider<Ground>>("GroundCollider"),
+                    label_added::<CreatedCollider<Spikes>>("SpikesCollider"),
+                ),
+            );
+        }
+    }
+}
+
+/// Counts how many of each kind of entity have been labeled.
+#[derive(Debug, Default, Resource)]
+pub struct DebugLabels {
+    counts: HashMap<&'static str, u32>,
+}
+
+impl DebugLabels {
+    /// Makes a new name for an entity of a kind.
+    pub fn next(&mut self, kind: &'static str) -> Name {
+        let count = self.counts.entry(kind).or_default();
+        *count += 1;
+
+        Name::new(format!("{} #{}", kind, count))
+    }
+}
+
+/// Names an entity, if [`DebugLabels`] are being kept.
+///
+/// This replaces any name the entity already has, so reused entities are
+/// named after what they are now.
+pub fn label(world: &mut World, entity: Entity, kind: &'static str) {
+    let Some(mut labels) = world.get_resource_mut::<DebugLabels>() else {
+        return;
+    };
+
+    let name = labels.next(kind);
+
+    if let Some(mut entity) = world.get_entity_mut(entity) {
+        entity.insert(name);
+    }
+}
+
+#[cfg(feature = "devtools")]
+fn label_added<T: Component>(
+    kind: &'static str,
+) -> impl FnMut(Commands, Query<Entity, (Added<T>, Without<Name>)>, ResMut<DebugLabels>) {
+    move |mut commands, added_query, mut labels| {
+        for entity in added_query.iter() {
+            commands.entity(entity).insert(labels.next(kind));
+        }
+    }
+}
@@ -1,5 +1,9 @@
 //! Merges level collision using a simple map.
+//!
+//! Colliders are built per [`CHUNK_SIZE`] chunk of a map, so changing a tile
+//! only rebuilds the colliders of its chunk.
 
+use bevy::ecs::query::Has;
 use bevy::prelude::*;
 use bevy_ecs_tilemap::{
     map::{TilemapSize, TilemapTileSize},
@@ -7,9 +11,11 @@ use bevy_ecs_tilemap::{
 };
 use bevy_rapier2d::prelude::*;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
 
+use super::seam::Welded;
+use crate::camera::PlayerCamera;
 use crate::physics;
 
 /// A plugin for a single map of collision.
@@ -39,10 +45,29 @@ where
         app.add_systems(
             Update,
             build_collision::<T>.in_set(LevelCollisionSystem::BuildCollision),
+        )
+        .add_systems(
+            Update,
+            activate_chunks::<T>
+                .run_if(resource_exists::<ChunkActivation>())
+                .after(LevelCollisionSystem::BuildCollision),
         );
     }
 }
 
+/// How many tiles wide and tall a chunk of colliders is.
+pub const CHUNK_SIZE: u32 = 16;
+
+/// Disables the colliders of chunks far away from every camera.
+///
+/// Chunks are all active if this resource does not exist.
+#[derive(Clone, Debug, Resource)]
+pub struct ChunkActivation {
+    /// How far the center of a chunk can be from a camera before it is
+    /// disabled, in world units.
+    pub radius: f32,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
 pub enum LevelCollisionSystem {
     /// Systems that actually build the collision
@@ -59,6 +84,7 @@ where
     T: Send + Sync + 'static,
 {
     map: Vec<bool>,
+    dirty: HashSet<UVec2>,
     _marker: PhantomData<T>,
 }
 
@@ -70,6 +96,7 @@ where
     pub fn new(map_size: &TilemapSize) -> CollisionMap<T> {
         CollisionMap::<T> {
             map: (0..map_size.count()).map(|_| false).collect(),
+            dirty: HashSet::new(),
             _marker: PhantomData,
         }
     }
@@ -86,8 +113,27 @@ where
     }
 
     /// Puts a bool in the map.
+    ///
+    /// The chunk of the tile is rebuilt if the tile changed.
     pub fn put(&mut self, map_size: &TilemapSize, pos: impl Into<TilePos>, flag: bool) {
-        self.map[pos.into().to_index(map_size)] = flag;
+        let pos = pos.into();
+        let tile = &mut self.map[pos.to_index(map_size)];
+
+        if *tile != flag {
+            *tile = flag;
+            self.dirty.insert(UVec2::new(pos.x, pos.y) / CHUNK_SIZE);
+        }
+    }
+
+    /// Marks every chunk of the map to be rebuilt.
+    pub fn rebuild(&mut self, map_size: &TilemapSize) {
+        let chunks = (UVec2::new(map_size.x, map_size.y) + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        for y in 0..chunks.y {
+            for x in 0..chunks.x {
+                self.dirty.insert(UVec2::new(x, y));
+            }
+        }
     }
 }
 
@@ -98,18 +144,35 @@ pub struct CreatedCollider<T>
 where
     T: Send + Sync + 'static,
 {
+    chunk: UVec2,
     _marker: PhantomData<T>,
 }
 
-impl<T> Default for CreatedCollider<T>
+impl<T> CreatedCollider<T>
 where
     T: Send + Sync + 'static,
 {
-    fn default() -> CreatedCollider<T> {
+    /// Creates a new `CreatedCollider` for a chunk.
+    pub fn new(chunk: UVec2) -> CreatedCollider<T> {
         CreatedCollider::<T> {
+            chunk,
             _marker: PhantomData,
         }
     }
+
+    /// The chunk the collider was built for, in chunks.
+    pub fn chunk(&self) -> UVec2 {
+        self.chunk
+    }
+}
+
+impl<T> Default for CreatedCollider<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn default() -> CreatedCollider<T> {
+        CreatedCollider::new(UVec2::ZERO)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
@@ -127,41 +190,99 @@ struct Rect {
 
 fn build_collision<T>(
     mut commands: Commands,
-    layer_query: Query<
-        (&Parent, &TilemapSize, &TilemapTileSize, &CollisionMap<T>),
+    mut layer_query: Query<
+        (
+            &Parent,
+            &TilemapSize,
+            &TilemapTileSize,
+            &mut CollisionMap<T>,
+        ),
         Changed<CollisionMap<T>>,
     >,
-    created_colliders: Query<(Entity, &Parent), With<CreatedCollider<T>>>,
+    created_colliders: Query<(Entity, &Parent, &CreatedCollider<T>)>,
 ) where
     T: Send + Sync + 'static,
 {
-    layer_query.for_each(|(parent, map_size, tile_size, collision_map)| {
+    for (parent, map_size, tile_size, mut collision_map) in layer_query.iter_mut() {
+        if collision_map.dirty.is_empty() {
+            continue;
+        }
+
+        // taking the dirty chunks should not count as a change
+        let dirty = std::mem::take(&mut collision_map.bypass_change_detection().dirty);
+
         // clear created colliders
-        for (collider_entity, collider_parent) in created_colliders.iter() {
-            if collider_parent.get() == parent.get() {
+        for (collider_entity, collider_parent, created) in created_colliders.iter() {
+            if collider_parent.get() == parent.get() && dirty.contains(&created.chunk) {
                 commands.entity(collider_entity).despawn_recursive()
             }
         }
 
-        let colliders = create_colliders_for(
-            parent.get(),
-            &mut commands,
-            map_size,
-            tile_size,
-            collision_map,
-        );
+        for chunk in dirty {
+            let colliders = create_colliders_for(
+                parent.get(),
+                &mut commands,
+                chunk,
+                map_size,
+                tile_size,
+                &collision_map,
+            );
 
-        for entity in colliders {
-            commands
-                .entity(entity)
-                .insert(CreatedCollider::<T>::default());
+            for entity in colliders {
+                commands
+                    .entity(entity)
+                    .insert(CreatedCollider::<T>::new(chunk));
+            }
         }
-    })
+    }
+}
+
+fn activate_chunks<T>(
+    mut commands: Commands,
+    activation: Res<ChunkActivation>,
+    layer_query: Query<(&Parent, &TilemapTileSize), With<CollisionMap<T>>>,
+    // welded colliders are disabled for good
+    collider_query: Query<
+        (Entity, &Parent, &CreatedCollider<T>, Has<ColliderDisabled>),
+        Without<Welded>,
+    >,
+    transform_query: Query<&GlobalTransform>,
+    camera_query: Query<&GlobalTransform, With<PlayerCamera>>,
+) where
+    T: Send + Sync + 'static,
+{
+    let tile_sizes: HashMap<Entity, &TilemapTileSize> = layer_query
+        .iter()
+        .map(|(parent, tile_size)| (parent.get(), tile_size))
+        .collect();
+
+    for (entity, parent, created, disabled) in collider_query.iter() {
+        let (Some(tile_size), Ok(level)) = (
+            tile_sizes.get(&parent.get()),
+            transform_query.get(parent.get()),
+        ) else {
+            continue;
+        };
+
+        let chunk_size = Vec2::new(tile_size.x, tile_size.y) * CHUNK_SIZE as f32;
+        let center = level.translation().truncate() + (created.chunk.as_vec2() + 0.5) * chunk_size;
+
+        let active = camera_query
+            .iter()
+            .any(|camera| camera.translation().truncate().distance(center) <= activation.radius);
+
+        if active && disabled {
+            commands.entity(entity).remove::<ColliderDisabled>();
+        } else if !active && !disabled {
+            commands.entity(entity).insert(ColliderDisabled);
+        }
+    }
 }
 
 fn create_colliders_for<T>(
     parent_entity: Entity,
     commands: &mut Commands,
+    chunk: UVec2,
     map_size: &TilemapSize,
     tile_size: &TilemapTileSize,
     map: &CollisionMap<T>,
@@ -169,17 +290,20 @@ fn create_colliders_for<T>(
 where
     T: Send + Sync + 'static,
 {
+    let start = chunk * CHUNK_SIZE;
+    let end = (start + CHUNK_SIZE).min(UVec2::new(map_size.x, map_size.y));
+
     let mut plates: Vec<Vec<Plate>> = Vec::new();
 
     // sort by y
-    for y in 0..map_size.y {
+    for y in start.y..end.y {
         let mut current_layer = Vec::new();
         let mut plate_start: Option<u32> = None;
 
         // extra empty column so the algorithm "finishes" plates that touch the
-        // right edge.
-        for x in 0..map_size.x + 1 {
-            let solid = map.get(map_size, UVec2::new(x, y));
+        // right edge of the chunk.
+        for x in start.x..end.x + 1 {
+            let solid = x < end.x && map.get(map_size, UVec2::new(x, y));
 
             match (plate_start, solid) {
                 (Some(s), false) => {
@@ -200,7 +324,7 @@ where
         plates.push(current_layer);
     }
 
-    build_rects(plates)
+    build_rects(plates, start.y)
         .into_iter()
         .map(|rect| {
             commands
@@ -225,7 +349,7 @@ where
         .collect()
 }
 
-fn build_rects(mut plates: Vec<Vec<Plate>>) -> Vec<Rect> {
+fn build_rects(mut plates: Vec<Vec<Plate>>, y_offset: u32) -> Vec<Rect> {
     let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
     let mut prev_row = Vec::new();
     let mut finished_rects = Vec::new();
@@ -247,8 +371,8 @@ fn build_rects(mut plates: Vec<Vec<Plate>>) -> Vec<Rect> {
                 .entry(plate.clone())
                 .and_modify(|e| e.top += 1)
                 .or_insert(Rect {
-                    bottom: y as u32,
-                    top: y as u32,
+                    bottom: y as u32 + y_offset,
+                    top: y as u32 + y_offset,
                     left: plate.left,
                     right: plate.right,
                 });
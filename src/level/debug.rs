@@ -0,0 +1,306 @@
+//! Debug views of level collision.
+//!
+//! Only built with the `devtools` feature. `F3` draws the [`CollisionMap`]s and the
+//! colliders built from them over the level, and `F4` exports the same to a
+//! PNG in `debug/`, one per level. Cells that are solid but not covered by a
+//! collider (or the other way around) mean the int-grid and the generated
+//! colliders disagree.
+
+use bevy::prelude::*;
+
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_ecs_tilemap::map::{TilemapSize, TilemapTileSize};
+use bevy_rapier2d::prelude::*;
+
+use super::collision::{CollisionMap, CreatedCollider};
+use super::{Ground, Spikes};
+
+/// Collision debugging plugin.
+pub struct CollisionDebugPlugin;
+
+impl Plugin for CollisionDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollisionDebug>().add_systems(
+            Update,
+            (
+                toggle_collision_overlay,
+                draw_collision_overlay.run_if(|debug: Res<CollisionDebug>| debug.overlay),
+                export_collision.run_if(|keyboard: Res<Input<KeyCode>>| {
+                    keyboard.just_pressed(CollisionDebug::EXPORT_KEY)
+                }),
+            ),
+        );
+    }
+}
+
+/// Collision debugging state.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct CollisionDebug {
+    /// Whether collision is drawn over the level.
+    pub overlay: bool,
+}
+
+impl CollisionDebug {
+    /// The key that toggles [`CollisionDebug::overlay`].
+    pub const OVERLAY_KEY: KeyCode = KeyCode::F3;
+    /// The key that exports collision images.
+    pub const EXPORT_KEY: KeyCode = KeyCode::F4;
+    /// How many pixels wide a tile is in exported images.
+    pub const EXPORT_SCALE: u32 = 4;
+    /// Where exported images go.
+    pub const EXPORT_DIR: &str = "debug";
+}
+
+/// A collider rect in tile coordinates, inclusive, `y` going up.
+#[derive(Clone, Copy, Debug)]
+struct TileRect {
+    left: i32,
+    right: i32,
+    bottom: i32,
+    top: i32,
+}
+
+impl TileRect {
+    fn from_collider(
+        transform: &Transform,
+        collider: &Collider,
+        tile_size: &TilemapTileSize,
+    ) -> Option<TileRect> {
+        let half_extents = collider.as_cuboid()?.half_extents();
+        let min = transform.translation.truncate() - half_extents;
+        let max = transform.translation.truncate() + half_extents;
+
+        Some(TileRect {
+            left: (min.x / tile_size.x).round() as i32,
+            right: (max.x / tile_size.x).round() as i32 - 1,
+            bottom: (min.y / tile_size.y).round() as i32,
+            top: (max.y / tile_size.y).round() as i32 - 1,
+        })
+    }
+}
+
+const GROUND_COLOR: [u8; 4] = [160, 160, 160, 255];
+const SPIKES_COLOR: [u8; 4] = [220, 40, 40, 255];
+const GROUND_COLLIDER_COLOR: [u8; 4] = [40, 220, 80, 255];
+const SPIKES_COLLIDER_COLOR: [u8; 4] = [240, 200, 40, 255];
+
+fn rgba(color: [u8; 4]) -> Color {
+    Color::rgba_u8(color[0], color[1], color[2], color[3])
+}
+
+fn toggle_collision_overlay(keyboard: Res<Input<KeyCode>>, mut debug: ResMut<CollisionDebug>) {
+    if keyboard.just_pressed(CollisionDebug::OVERLAY_KEY) {
+        debug.overlay = !debug.overlay;
+    }
+}
+
+fn draw_collision_overlay(
+    ground_query: Query<(
+        &Parent,
+        &TilemapSize,
+        &TilemapTileSize,
+        &CollisionMap<Ground>,
+    )>,
+    spikes_query: Query<(
+        &Parent,
+        &TilemapSize,
+        &TilemapTileSize,
+        &CollisionMap<Spikes>,
+    )>,
+    ground_colliders: Query<(&GlobalTransform, &Collider), With<CreatedCollider<Ground>>>,
+    spikes_colliders: Query<(&GlobalTransform, &Collider), With<CreatedCollider<Spikes>>>,
+    transform_query: Query<&GlobalTransform>,
+    mut gizmos: Gizmos,
+) {
+    let mut draw_cells = |parent: &Parent,
+                          map_size: &TilemapSize,
+                          tile_size: &TilemapTileSize,
+                          solid: &dyn Fn(UVec2) -> bool,
+                          color: Color| {
+        let Ok(origin) = transform_query.get(parent.get()) else {
+            return;
+        };
+
+        let origin = origin.translation().truncate();
+        let size = Vec2::new(tile_size.x, tile_size.y);
+
+        for y in 0..map_size.y {
+            for x in 0..map_size.x {
+                if solid(UVec2::new(x, y)) {
+                    let center = origin + (Vec2::new(x as f32, y as f32) + 0.5) * size;
+                    gizmos.rect_2d(center, 0., size * 0.5, color);
+                }
+            }
+        }
+    };
+
+    for (parent, map_size, tile_size, map) in ground_query.iter() {
+        draw_cells(
+            parent,
+            map_size,
+            tile_size,
+            &|pos| map.get(map_size, pos),
+            rgba(GROUND_COLOR),
+        );
+    }
+
+    for (parent, map_size, tile_size, map) in spikes_query.iter() {
+        draw_cells(
+            parent,
+            map_size,
+            tile_size,
+            &|pos| map.get(map_size, pos),
+            rgba(SPIKES_COLOR),
+        );
+    }
+
+    for (colliders, color) in [
+        (&ground_colliders, GROUND_COLLIDER_COLOR),
+        (&spikes_colliders, SPIKES_COLLIDER_COLOR),
+    ] {
+        for (transform, collider) in colliders.iter() {
+            let Some(cuboid) = collider.as_cuboid() else {
+                continue;
+            };
+
+            gizmos.rect_2d(
+                transform.translation().truncate(),
+                0.,
+                cuboid.half_extents() * 2.,
+                rgba(color),
+            );
+        }
+    }
+}
+
+fn export_collision(
+    ground_query: Query<(
+        &Parent,
+        &TilemapSize,
+        &TilemapTileSize,
+        &CollisionMap<Ground>,
+    )>,
+    spikes_query: Query<(&Parent, &CollisionMap<Spikes>)>,
+    ground_colliders: Query<(&Parent, &Transform, &Collider), With<CreatedCollider<Ground>>>,
+    spikes_colliders: Query<(&Parent, &Transform, &Collider), With<CreatedCollider<Spikes>>>,
+    name_query: Query<&Name>,
+) {
+    if let Err(err) = std::fs::create_dir_all(CollisionDebug::EXPORT_DIR) {
+        crate::game_warn!(
+            Level,
+            "cannot create {}: {}",
+            CollisionDebug::EXPORT_DIR,
+            err
+        );
+        return;
+    }
+
+    for (parent, map_size, tile_size, ground) in ground_query.iter() {
+        let level = parent.get();
+        let scale = CollisionDebug::EXPORT_SCALE;
+        let width = map_size.x * scale;
+        let height = map_size.y * scale;
+
+        let mut data = vec![0u8; (width * height * 4) as usize];
+
+        // images go down, tiles go up
+        let mut put = |x: u32, y: u32, color: [u8; 4]| {
+            if x < width && y < height {
+                let i = (((height - 1 - y) * width + x) * 4) as usize;
+                data[i..i + 4].copy_from_slice(&color);
+            }
+        };
+
+        let spikes = spikes_query
+            .iter()
+            .find(|(p, _)| p.get() == level)
+            .map(|(_, map)| map);
+
+        for y in 0..map_size.y {
+            for x in 0..map_size.x {
+                let pos = UVec2::new(x, y);
+
+                let color = if ground.get(map_size, pos) {
+                    GROUND_COLOR
+                } else if spikes.is_some_and(|s| s.get(map_size, pos)) {
+                    SPIKES_COLOR
+                } else {
+                    continue;
+                };
+
+                for py in 0..scale {
+                    for px in 0..scale {
+                        put(x * scale + px, y * scale + py, color);
+                    }
+                }
+            }
+        }
+
+        let mut colliders = 0;
+
+        for (colliders_query, color) in [
+            (&ground_colliders, GROUND_COLLIDER_COLOR),
+            (&spikes_colliders, SPIKES_COLLIDER_COLOR),
+        ] {
+            for (collider_parent, transform, collider) in colliders_query.iter() {
+                if collider_parent.get() != level {
+                    continue;
+                }
+
+                let Some(rect) = TileRect::from_collider(transform, collider, tile_size) else {
+                    continue;
+                };
+
+                colliders += 1;
+
+                // outline the rect along the edges of its cells
+                let left = (rect.left.max(0) as u32) * scale;
+                let right = ((rect.right + 1).max(0) as u32) * scale - 1;
+                let bottom = (rect.bottom.max(0) as u32) * scale;
+                let top = ((rect.top + 1).max(0) as u32) * scale - 1;
+
+                for x in left..=right {
+                    put(x, bottom, color);
+                    put(x, top, color);
+                }
+
+                for y in bottom..=top {
+                    put(left, y, color);
+                    put(right, y, color);
+                }
+            }
+        }
+
+        let name = name_query
+            .get(level)
+            .map(|name| name.as_str().to_owned())
+            .unwrap_or_else(|_| format!("{}", level.index()));
+        let path = format!("{}/collision-{}.png", CollisionDebug::EXPORT_DIR, name);
+
+        let image = Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        );
+
+        let result = image
+            .try_into_dynamic()
+            .map_err(|err| err.to_string())
+            .and_then(|image| image.save(&path).map_err(|err| err.to_string()));
+
+        match result {
+            Ok(()) => crate::game_info!(
+                Level,
+                "exported collision to {} ({} colliders)",
+                path,
+                colliders
+            ),
+            Err(err) => crate::game_warn!(Level, "cannot export {}: {}", path, err),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Doors that open for signals.
+//!
+//! A [`Door`] is solid until it gets a signal down the pipe it is hooked up
+//! to, or an [`ActivateEvent`] from a plate or anything else. Then it slides
+//! open to let things through, and may close again after a while or when it
+//! gets a [`DeactivateEvent`].
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::time::Duration;
+
+use super::pipe::PipeInput;
+use super::Iid;
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::interactions::{InteractionSystem, Junction, SignalConsumer, SignalEvent};
+use crate::physics;
+use crate::platform::{ActivateEvent, DeactivateEvent};
+
+/// Door plugin.
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<DoorBundle>("Door").add_systems(
+            Update,
+            (open_doors, close_doors, animate_doors)
+                .chain()
+                .run_if(any_with_component::<Door>())
+                .after(InteractionSystem::TravelSignal),
+        );
+    }
+}
+
+/// A door that is solid until it is opened by a signal or an
+/// [`ActivateEvent`].
+#[derive(Clone, Component, Debug)]
+pub struct Door {
+    /// How long the door stays open before closing again, if it ever does.
+    ///
+    /// Every signal or activation while the door is open starts the wait
+    /// over.
+    pub closes_after: Option<Duration>,
+    size: Vec2,
+    open: bool,
+    timer: Timer,
+    openness: f32,
+}
+
+impl Door {
+    /// The color of a door.
+    pub const COLOR: Color = Color::rgb(0.35, 0.4, 0.5);
+    /// How long it takes for a door to slide open or shut, in seconds.
+    pub const SLIDE: f32 = 0.25;
+
+    /// Creates a new, closed `Door` of a size that stays open once it is
+    /// opened.
+    pub fn new(size: Vec2) -> Door {
+        Door {
+            closes_after: None,
+            size,
+            open: false,
+            timer: Timer::default(),
+            openness: 0.,
+        }
+    }
+
+    /// Sets how long the door stays open. See [`Door::closes_after`].
+    pub fn with_closes_after(self, closes_after: Duration) -> Door {
+        Door {
+            closes_after: Some(closes_after),
+            ..self
+        }
+    }
+
+    /// Checks if the door is open.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens the door, or keeps it open for longer.
+    pub fn open(&mut self) {
+        self.open = true;
+
+        if let Some(closes_after) = self.closes_after {
+            self.timer = Timer::new(closes_after, TimerMode::Once);
+        }
+    }
+
+    /// Closes the door.
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+}
+
+/// A bundle for a [`Door`].
+#[derive(Bundle)]
+pub struct DoorBundle {
+    pub sprite: SpriteBundle,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub door: Door,
+    pub junction: Junction,
+    pub consumer: SignalConsumer,
+    pub input: PipeInput,
+    pub iid: Iid,
+}
+
+impl DoorBundle {
+    /// Creates a new `DoorBundle`.
+    pub fn new(door: Door) -> DoorBundle {
+        let size = door.size;
+
+        DoorBundle {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: Door::COLOR,
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            collision_groups: CollisionGroups::new(physics::COLLISION_GROUP_SOLID, Group::all()),
+            door,
+            junction: Junction::default(),
+            consumer: SignalConsumer,
+            input: PipeInput::default(),
+            iid: Iid::default(),
+        }
+    }
+}
+
+impl LdtkEntity for DoorBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        let mut door = Door::new(size);
+
+        if let Some(closes) = entity_instance
+            .get_maybe_float_field("Closes")
+            .ok()
+            .copied()
+            .flatten()
+        {
+            door = door.with_closes_after(Duration::from_secs_f32(closes.max(0.)));
+        }
+
+        DoorBundle {
+            input: PipeInput::from_field(entity_instance, layer_instance, "Input"),
+            iid: Iid::from(entity_instance),
+            ..DoorBundle::new(door)
+        }
+    }
+}
+
+fn open_doors(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut activate_events: EventReader<ActivateEvent>,
+    mut door_query: Query<&mut Door>,
+) {
+    let signalled = signal_events.iter().filter_map(|ev| {
+        door_query.contains(ev.receiver).then(|| {
+            despawn_queue.push(ev.signal, DespawnReason::Consumed);
+            ev.receiver
+        })
+    });
+
+    let doors = signalled
+        .chain(activate_events.iter().map(|ev| ev.0))
+        .collect::<Vec<_>>();
+
+    for entity in doors {
+        let Ok(mut door) = door_query.get_mut(entity) else {
+            continue;
+        };
+
+        if !door.is_open() {
+            crate::game_debug!(Level, "door {:?} opened", entity);
+        }
+
+        door.open();
+    }
+}
+
+fn close_doors(
+    mut deactivate_events: EventReader<DeactivateEvent>,
+    mut door_query: Query<(Entity, &mut Door)>,
+    time: Res<Time>,
+) {
+    for ev in deactivate_events.iter() {
+        let Ok((entity, mut door)) = door_query.get_mut(ev.0) else {
+            continue;
+        };
+
+        if door.is_open() {
+            crate::game_debug!(Level, "door {:?} closed", entity);
+        }
+
+        door.close();
+    }
+
+    for (entity, mut door) in door_query.iter_mut() {
+        if !door.open || door.closes_after.is_none() {
+            continue;
+        }
+
+        door.timer.tick(time.delta());
+
+        if door.timer.finished() {
+            door.open = false;
+
+            crate::game_debug!(Level, "door {:?} closed", entity);
+        }
+    }
+}
+
+fn animate_doors(
+    mut commands: Commands,
+    mut door_query: Query<(
+        Entity,
+        &mut Door,
+        &mut Sprite,
+        &mut Collider,
+        Has<ColliderDisabled>,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, mut door, mut sprite, mut collider, disabled) in door_query.iter_mut() {
+        let target = if door.open { 1. } else { 0. };
+        let step = time.delta_seconds() / Door::SLIDE;
+
+        let openness = door.openness + (target - door.openness).clamp(-step, step);
+
+        if openness != door.openness {
+            door.openness = openness;
+
+            // draw in from the top and bottom, and only block what is drawn
+            // so a closing door doesn't shut on anything in the doorway
+            let size = Vec2::new(door.size.x, door.size.y * (1. - openness));
+
+            sprite.custom_size = Some(size);
+            *collider = Collider::cuboid(size.x / 2., size.y / 2.);
+        }
+
+        // passable as soon as it starts to open, and solid again as soon as
+        // it starts to close
+        if door.open && !disabled {
+            commands.entity(entity).insert(ColliderDisabled);
+        } else if !door.open && disabled {
+            commands.entity(entity).remove::<ColliderDisabled>();
+        }
+    }
+}
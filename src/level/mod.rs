@@ -1,7 +1,14 @@
 //! Level stuff.
 
 pub mod collision;
+#[cfg(feature = "devtools")]
+pub mod debug;
+pub mod door;
 pub mod pipe;
+pub mod procgen;
+pub mod seam;
+pub mod streaming;
+pub mod surface;
 
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
@@ -11,8 +18,9 @@ use bevy_rapier2d::prelude::*;
 use std::collections::HashMap;
 
 use collision::{CollisionMap, CreatedCollider, LevelCollisionPlugin, LevelCollisionSystem};
+use surface::Surface;
 
-use crate::enemy::{Enemy, Hostility};
+use crate::combat::{DamageKind, Hazard};
 use crate::physics;
 
 pub struct LevelPlugin;
@@ -21,6 +29,10 @@ impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(LevelCollisionPlugin::<Ground>::default())
             .add_plugins(LevelCollisionPlugin::<Spikes>::default())
+            .add_plugins(door::DoorPlugin)
+            .add_plugins(seam::SeamWeldPlugin)
+            .add_plugins(procgen::BonusRoomPlugin)
+            .add_plugins(streaming::LevelStreamingPlugin)
             .add_systems(
                 Update,
                 update_collision_map::<Ground>.before(LevelCollisionSystem::BuildCollision),
@@ -29,7 +41,11 @@ impl Plugin for LevelPlugin {
                 Update,
                 update_collision_map::<Spikes>.before(LevelCollisionSystem::BuildCollision),
             )
+            .add_systems(Update, surface::update_surface_map)
             .add_systems(Update, make_spikes_deadly);
+
+        #[cfg(feature = "devtools")]
+        app.add_plugins(debug::CollisionDebugPlugin);
     }
 
     fn finish(&self, app: &mut App) {
@@ -38,6 +54,14 @@ impl Plugin for LevelPlugin {
     }
 }
 
+/// The identifier of the level the player is in.
+///
+/// This level and its neighbors are spawned by [`streaming`]. It stands in for
+/// `bevy_ecs_ldtk`'s `LevelSelection`, which would spawn every neighbor in the
+/// same frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Resource)]
+pub struct CurrentLevel(pub String);
+
 /// A component that identifies an entity by its instance identifier.
 #[derive(Clone, Component, Debug, Default)]
 pub struct Iid(pub String);
@@ -53,6 +77,8 @@ impl From<&EntityInstance> for Iid {
 pub struct CollisionBundle {
     #[with(initial_collision)]
     collider: Collision,
+    #[with(Surface::from_int_grid_cell)]
+    surface: Surface,
 }
 
 fn initial_collision(i: IntGridCell) -> Collision {
@@ -60,6 +86,8 @@ fn initial_collision(i: IntGridCell) -> Collision {
         1 => Collision::Solid,
         2 => Collision::Solid,
         4 => Collision::Spikes,
+        // surface materials
+        5..=7 => Collision::Solid,
         _ => Collision::Vacant,
     }
 }
@@ -137,10 +165,12 @@ fn make_spikes_deadly(
         commands
             .entity(entity)
             .insert(CollisionGroups::new(
-                physics::COLLISION_GROUP_SOLID | physics::COLLISION_GROUP_HOSTILE,
+                physics::COLLISION_GROUP_SOLID,
                 Group::all(),
             ))
-            .insert(Hostility::Hostile)
-            .insert(Enemy::invincible());
+            .insert(Hazard {
+                kind: DamageKind::Spikes,
+                ..Default::default()
+            });
     }
 }
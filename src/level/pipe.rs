@@ -4,21 +4,31 @@ use bevy::prelude::*;
 use bevy::transform::TransformSystem;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::{
-    map::{TilemapId, TilemapSize},
+    map::{TilemapGridSize, TilemapId, TilemapSize},
     tiles::{TileBundle, TilePos, TileStorage, TileTextureIndex},
 };
 use bevy_rapier2d::prelude::*;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::identity;
+use std::time::Duration;
 
+use crate::enemy::Hostility;
 use crate::interactions::{
     acceptor::{Acceptor, AcceptorBundle},
-    generator::Generator,
-    Buldge, Junction,
+    counter::{Counter, CounterBundle},
+    echo::Echo,
+    generator::{Generator, GeneratorLimit, PhaseStep},
+    latch::{Latch, LatchBundle},
+    logic::{Gate, GateBundle, GateKind},
+    timing::{Delay, DelayBundle, Pulse},
+    visual::PipeTint,
+    wireless::{ChannelId, Receiver, TransmitterBundle},
+    Buldge, Converter, Filter, Junction, Pipe, PipeBend, RoutingMode, Signal, Valve,
 };
 use crate::physics;
-use crate::projectile::prefab::ProjectilePrefab;
+use crate::platform::MovingPlatform;
+use crate::projectile::prefab::{ProjectilePrefab, Trajectory};
 
 /// Creates pipes from LDTK levels.
 ///
@@ -34,6 +44,12 @@ impl Plugin for LevelPipePlugin {
                     .in_set(LevelPipeSystem::MergePipes)
                     .before(TransformSystem::TransformPropagate),
             )
+            .add_systems(
+                PostUpdate,
+                (route_platforms_along_pipes, connect_pipe_inputs)
+                    .after(build_pipe_network)
+                    .in_set(LevelPipeSystem::MergePipes),
+            )
             .add_systems(
                 PostUpdate,
                 create_pipe_segment_collision.after(LevelPipeSystem::MergePipes),
@@ -78,6 +94,12 @@ pub struct PipeEntityBundle {
     grid_coords: GridCoords,
     #[with(PipeEntity::from_entity_instance)]
     pipe_entity: PipeEntity,
+    #[with(Trajectory::from_entity_instance)]
+    trajectory: Trajectory,
+    #[with(GeneratorLimit::from_entity_instance)]
+    limit: GeneratorLimit,
+    #[with(PhaseStep::from_entity_instance)]
+    phase_step: PhaseStep,
 }
 
 /// A pipe entity that will give the corresponding tile in the `Pipes` layer
@@ -89,11 +111,82 @@ pub enum PipeEntity {
     /// A vertical chute.
     ///
     /// * `direction`: direction of exiting projectiles.
-    ChuteVertical(f32),
+    /// * `accepts`: the only hostility of projectile taken in, if any. See
+    ///   [`Acceptor::accepts`].
+    ChuteVertical(f32, Option<Hostility>),
     /// A horizontal chute.
     ///
     /// * `direction`: direction of exiting projectiles.
-    ChuteHorizontal(f32),
+    /// * `accepts`: the only hostility of projectile taken in, if any. See
+    ///   [`Acceptor::accepts`].
+    ChuteHorizontal(f32, Option<Hostility>),
+    /// A logic gate.
+    ///
+    /// See [`Gate`].
+    Gate {
+        kind: GateKind,
+        output: Direction,
+        window: Option<f32>,
+    },
+    /// Counts signals.
+    ///
+    /// See [`Counter`].
+    Counter {
+        required: u32,
+        timeout: Option<f32>,
+        activates: Option<String>,
+    },
+    /// Flips on and off with each signal, and powers things while on.
+    ///
+    /// See [`Latch`].
+    Latch {
+        interval: Option<f32>,
+        activates: Vec<String>,
+    },
+    /// Holds signals for some seconds.
+    ///
+    /// See [`Delay`].
+    Delay(Option<f32>),
+    /// Sends a signal every some seconds.
+    ///
+    /// See [`Pulse`].
+    Pulse(Option<f32>),
+    /// An echo.
+    ///
+    /// See [`Echo`].
+    Echo {
+        delay: Option<f32>,
+        falloff: Option<f32>,
+    },
+    /// A one-way valve.
+    ///
+    /// See [`Valve`].
+    Valve(Direction),
+    /// Only lets signals of a hostility through.
+    ///
+    /// See [`Filter`].
+    Filter(Hostility),
+    /// Flips the hostility of signals.
+    ///
+    /// See [`Converter`].
+    Converter,
+    /// Picks which way signals go out of the tile.
+    ///
+    /// See [`RoutingMode`].
+    Router(RoutingMode),
+    /// Sends signals to receivers on a channel.
+    ///
+    /// See [`Transmitter`](crate::interactions::wireless::Transmitter).
+    Transmitter(ChannelId),
+    /// Sends signals from transmitters on a channel down its pipes.
+    ///
+    /// See [`Receiver`].
+    Receiver(ChannelId),
+    /// Changes how fast signals go through the tile, without changing what
+    /// it looks like.
+    ///
+    /// See [`PipeSpeed`].
+    Speed(f32),
 }
 
 impl PipeEntity {
@@ -107,32 +200,178 @@ impl PipeEntity {
             "PipeChuteVertical" => {
                 let direction = inst.get_float_field("Direction").expect("valid direction");
 
-                PipeEntity::ChuteVertical(*direction)
+                PipeEntity::ChuteVertical(*direction, chute_accepts(inst))
             }
             "PipeChuteHorizontal" => {
                 let direction = inst.get_float_field("Direction").expect("valid direction");
 
-                PipeEntity::ChuteHorizontal(*direction)
+                PipeEntity::ChuteHorizontal(*direction, chute_accepts(inst))
             }
             "PipeExitRight" => PipeEntity::Exit(Direction::Right),
+            "PipeGate" => {
+                let kind = inst.get_enum_field("Gate").expect("valid gate");
+                let output = inst.get_enum_field("Output").expect("valid output");
+                let window = inst.get_maybe_float_field("Window").ok().copied().flatten();
+
+                PipeEntity::Gate {
+                    kind: GateKind::from_name(kind).expect("valid gate"),
+                    output: Direction::from_name(output).expect("valid output"),
+                    window,
+                }
+            }
+            "PipeCounter" => {
+                let required = inst
+                    .get_maybe_int_field("Required")
+                    .ok()
+                    .copied()
+                    .flatten()
+                    .map(|required| required.max(1) as u32)
+                    .unwrap_or(Counter::default().required);
+                let timeout = inst
+                    .get_maybe_float_field("Timeout")
+                    .ok()
+                    .copied()
+                    .flatten();
+                let activates = inst
+                    .get_maybe_entity_ref_field("Activates")
+                    .ok() // may not exist
+                    .and_then(|a| a.as_ref())
+                    .map(|a| a.entity_iid.clone());
+
+                PipeEntity::Counter {
+                    required,
+                    timeout,
+                    activates,
+                }
+            }
+            "PipeLatch" => {
+                let interval = inst
+                    .get_maybe_float_field("Interval")
+                    .ok()
+                    .copied()
+                    .flatten();
+                let activates = inst
+                    .get_maybe_entity_refs_field("Activates")
+                    .map(|refs| {
+                        refs.iter()
+                            .flatten()
+                            .map(|r| r.entity_iid.clone())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                PipeEntity::Latch {
+                    interval,
+                    activates,
+                }
+            }
+            "PipeDelay" => {
+                PipeEntity::Delay(inst.get_maybe_float_field("Time").ok().copied().flatten())
+            }
+            "PipePulse" => PipeEntity::Pulse(
+                inst.get_maybe_float_field("Interval")
+                    .ok()
+                    .copied()
+                    .flatten(),
+            ),
+            "PipeEcho" => {
+                let delay = inst.get_maybe_float_field("Delay").ok().copied().flatten();
+                let falloff = inst
+                    .get_maybe_float_field("Falloff")
+                    .ok()
+                    .copied()
+                    .flatten();
+
+                PipeEntity::Echo { delay, falloff }
+            }
+            "PipeValve" => {
+                let allowed = inst.get_enum_field("Allowed").expect("valid direction");
+
+                PipeEntity::Valve(Direction::from_name(allowed).expect("valid direction"))
+            }
+            "PipeFilter" => {
+                let hostility = inst.get_enum_field("Hostility").expect("valid hostility");
+
+                PipeEntity::Filter(Hostility::from_name(hostility).expect("valid hostility"))
+            }
+            "PipeConverter" => PipeEntity::Converter,
+            "PipeRouter" => {
+                let routing = inst.get_enum_field("Routing").expect("valid routing");
+
+                // right, up, left and down, with missing weights counting as 1
+                let mut weights = [1.; 4];
+
+                if let Ok(fields) = inst.get_maybe_floats_field("Weights") {
+                    for (weight, field) in weights.iter_mut().zip(fields) {
+                        if let Some(field) = field {
+                            *weight = field.max(0.);
+                        }
+                    }
+                }
+
+                PipeEntity::Router(RoutingMode::from_name(routing, weights).expect("valid routing"))
+            }
+            "PipeTransmitter" => {
+                let channel = inst.get_int_field("Channel").expect("valid channel");
+
+                PipeEntity::Transmitter(ChannelId((*channel).max(0) as u32))
+            }
+            "PipeReceiver" => {
+                let channel = inst.get_int_field("Channel").expect("valid channel");
+
+                PipeEntity::Receiver(ChannelId((*channel).max(0) as u32))
+            }
+            "PipeSpeed" => {
+                let speed = inst.get_float_field("Speed").expect("valid speed");
+
+                PipeEntity::Speed(*speed)
+            }
             _ => panic!("invalid identifier"),
         }
     }
 
     /// Gets the texture index of the tileset (`pipes.png`).
+    ///
+    /// # Panics
+    /// Panics for [`PipeEntity::Speed`], which keeps the tile it is on.
     pub fn texture_index(&self) -> u32 {
         match self {
             PipeEntity::Exit(Direction::Left) => 0,
             PipeEntity::Exit(Direction::Right) => 6,
-            PipeEntity::ChuteVertical(_) => 10,
-            PipeEntity::ChuteHorizontal(_) => 4, // TODO: random chutes
+            PipeEntity::ChuteVertical(..) => 10,
+            PipeEntity::ChuteHorizontal(..) => 4, // TODO: random chutes
+            PipeEntity::Gate { .. } => 9,
+            PipeEntity::Echo { .. } => 11,
+            PipeEntity::Delay(_) => 16,
+            PipeEntity::Pulse(_) => 17,
+            PipeEntity::Counter { .. } => 18,
+            PipeEntity::Filter(Hostility::Friendly) => 21,
+            PipeEntity::Filter(Hostility::Hostile) => 22,
+            PipeEntity::Converter => 23,
+            PipeEntity::Latch { .. } => 27,
+            PipeEntity::Router(_) => 28,
+            PipeEntity::Transmitter(_) => 29,
+            PipeEntity::Receiver(_) => 7,
+            // arrows pointing the way signals go
+            PipeEntity::Valve(Direction::Right) => 12,
+            PipeEntity::Valve(Direction::Up) => 13,
+            PipeEntity::Valve(Direction::Left) => 14,
+            PipeEntity::Valve(Direction::Down) => 15,
             _ => todo!(),
         }
     }
 }
 
+/// Reads the `Accepts` field of a chute.
+fn chute_accepts(inst: &EntityInstance) -> Option<Hostility> {
+    inst.get_maybe_enum_field("Accepts")
+        .ok() // may not exist
+        .and_then(|accepts| accepts.as_deref())
+        .map(|accepts| Hostility::from_name(accepts).expect("valid hostility"))
+}
+
 /// A direction.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
 pub enum Direction {
     #[default]
     Right,
@@ -142,6 +381,50 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Every direction, in the order of the neighbors of a tile.
+    pub const ALL: [Direction; 4] = [
+        Direction::Right,
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+    ];
+
+    /// Finds a direction by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "Right" => Some(Direction::Right),
+            "Up" => Some(Direction::Up),
+            "Left" => Some(Direction::Left),
+            "Down" => Some(Direction::Down),
+            _ => None,
+        }
+    }
+
+    /// Finds the direction closest to a vector.
+    pub fn nearest(v: Vec2) -> Direction {
+        if v.x.abs() >= v.y.abs() {
+            if v.x >= 0. {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if v.y >= 0. {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+
+    /// Gets the direction the other way.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Left => Direction::Right,
+            Direction::Down => Direction::Up,
+        }
+    }
+
     /// Gets the vector of the direction.
     pub fn axis(self) -> Vec2 {
         match self {
@@ -153,6 +436,24 @@ impl Direction {
     }
 }
 
+/// Scales how fast signals go through a tile of the pipes layer.
+///
+/// A pipe between two tiles takes the average of both ends, so a signal
+/// speeds up or slows down over the pipe into or out of the tile.
+#[derive(Clone, Copy, Component, Debug, PartialEq)]
+pub struct PipeSpeed(pub f32);
+
+impl PipeSpeed {
+    /// How much faster signals go through chutes.
+    pub const CHUTE: PipeSpeed = PipeSpeed(1.5);
+}
+
+impl Default for PipeSpeed {
+    fn default() -> PipeSpeed {
+        PipeSpeed(1.)
+    }
+}
+
 /// A pipe segment.
 #[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Hash)]
 pub enum PipeSegment {
@@ -163,6 +464,44 @@ pub enum PipeSegment {
     Red,
 }
 
+/// Marks a [`MovingPlatform`] whose waypoints were found by following pipes.
+#[derive(Clone, Component, Debug, Default)]
+pub struct PipeRouted;
+
+/// Hooks an entity that isn't on the `Pipes` layer, like a
+/// [`Door`](super::door::Door), up to a tile on the `Pipes` layer of its level,
+/// so signals can reach it.
+///
+/// The entity needs a [`Junction`] of its own.
+#[derive(Clone, Component, Debug, Default)]
+pub struct PipeInput {
+    /// The tile that feeds the entity signals.
+    pub tile: Option<TilePos>,
+}
+
+impl PipeInput {
+    /// Reads a point field of an [`EntityInstance`] as the tile, if it is set.
+    pub fn from_field(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        field: &str,
+    ) -> PipeInput {
+        let tile = entity_instance
+            .get_maybe_point_field(field)
+            .ok()
+            .copied()
+            .flatten()
+            .and_then(|point| {
+                // ldtk y coordinates go down
+                let y = layer_instance.c_hei - 1 - point.y;
+
+                (point.x >= 0 && y >= 0).then(|| TilePos::new(point.x as u32, y as u32))
+            });
+
+        PipeInput { tile }
+    }
+}
+
 /// Marker trait for the pipes layer.
 #[derive(Clone, Component, Debug, Default)]
 pub struct PipesLayer;
@@ -180,11 +519,25 @@ fn mark_pipes_layer(
 
 fn merge_pipes_down(
     mut commands: Commands,
-    new_pipes_query: Query<(Entity, &GridCoords, &PipeEntity, &Parent)>,
+    new_pipes_query: Query<(
+        Entity,
+        &GridCoords,
+        &PipeEntity,
+        Option<&Trajectory>,
+        Option<&GeneratorLimit>,
+        Option<&PhaseStep>,
+        &Parent,
+    )>,
     levels_query: Query<&Children>,
     mut layers_query: Query<(Entity, &mut TileStorage), With<PipesLayer>>,
 ) {
-    for (new_pipe_entity, grid_coords, pipe_entity, parent) in new_pipes_query.iter() {
+    for (new_pipe_entity, grid_coords, pipe_entity, trajectory, limit, phase_step, parent) in
+        new_pipes_query.iter()
+    {
+        let trajectory = trajectory.copied().unwrap_or_default();
+        let limit = limit.copied().unwrap_or_default();
+        let phase_step = phase_step.copied().unwrap_or_default();
+
         let Ok(level_children) = levels_query.get(parent.get()) else {
             continue;
         };
@@ -196,6 +549,23 @@ fn merge_pipes_down(
         if let Some((layer_entity, mut pipes_layer)) = layers.fetch_next() {
             // find tile in grid
             let pos = TilePos::new(grid_coords.x as u32, grid_coords.y as u32);
+
+            // speeds only make sense on a pipe that is already there, and a
+            // stopped pipe would hold signals forever
+            if let PipeEntity::Speed(speed) = pipe_entity {
+                match pipes_layer.get(&pos) {
+                    Some(entity) => {
+                        commands.entity(entity).insert(PipeSpeed(speed.max(0.1)));
+                    }
+                    None => crate::game_warn!(Level, "pipe speed at {:?} has no pipe", pos),
+                }
+
+                commands
+                    .entity(new_pipe_entity)
+                    .remove::<(PipeEntity, Trajectory, GeneratorLimit, PhaseStep)>();
+                continue;
+            }
+
             let entity = match pipes_layer.get(&pos) {
                 Some(entity) => entity,
                 None => {
@@ -217,39 +587,55 @@ fn merge_pipes_down(
 
             // add exciting stuff
             match pipe_entity {
-                PipeEntity::ChuteVertical(dir) => {
+                PipeEntity::ChuteVertical(dir, accepts) => {
+                    if let Some(accepts) = accepts {
+                        commands.entity(entity).insert(PipeTint(accepts.color()));
+                    }
+
                     commands.entity(entity).insert((
                         AcceptorBundle {
                             collider: Collider::cuboid(6., 8.),
-                            acceptor: Acceptor,
+                            acceptor: Acceptor { accepts: *accepts },
                         },
-                        Generator {
-                            prefab: ProjectilePrefab::QuarterNote {
+                        Generator::new(
+                            ProjectilePrefab::QuarterNote {
                                 // TODO: magic number
                                 initial_velocity: Vec2::new(*dir, 0.) * 128.,
                             },
-                            location: Vec3::new(9f32.copysign(*dir), 0., 0.),
-                        },
+                            Vec3::new(9f32.copysign(*dir), 0., 0.),
+                        )
+                        .with_trajectory(trajectory)
+                        .with_limit(limit)
+                        .with_phase_step(phase_step.0),
                         Name::new("ChuteVertical"),
                         Junction::default(),
+                        PipeSpeed::CHUTE,
                         Buldge::no_cover(),
                     ));
                 }
-                PipeEntity::ChuteHorizontal(dir) => {
+                PipeEntity::ChuteHorizontal(dir, accepts) => {
+                    if let Some(accepts) = accepts {
+                        commands.entity(entity).insert(PipeTint(accepts.color()));
+                    }
+
                     commands.entity(entity).insert((
                         AcceptorBundle {
                             collider: Collider::cuboid(8., 6.),
-                            acceptor: Acceptor,
+                            acceptor: Acceptor { accepts: *accepts },
                         },
-                        Generator {
-                            prefab: ProjectilePrefab::QuarterNote {
+                        Generator::new(
+                            ProjectilePrefab::QuarterNote {
                                 // TODO: magic number
                                 initial_velocity: Vec2::new(0., *dir) * 128.,
                             },
-                            location: Vec3::new(0., 9f32.copysign(*dir), 0.),
-                        },
+                            Vec3::new(0., 9f32.copysign(*dir), 0.),
+                        )
+                        .with_trajectory(trajectory)
+                        .with_limit(limit)
+                        .with_phase_step(phase_step.0),
                         Name::new("ChuteHorizontal"),
                         Junction::default(),
+                        PipeSpeed::CHUTE,
                         Buldge::no_cover(),
                     ));
                 }
@@ -261,22 +647,168 @@ fn merge_pipes_down(
                     };
 
                     commands.entity(entity).insert((
-                        Generator {
-                            prefab: ProjectilePrefab::BeamNote {
+                        Generator::new(
+                            ProjectilePrefab::BeamNote {
                                 // TODO: magic number
                                 initial_direction: direction.axis().x * 32.,
                             },
                             location,
-                        },
+                        )
+                        .with_trajectory(trajectory)
+                        .with_limit(limit)
+                        .with_phase_step(phase_step.0),
                         Name::new("Exit"),
                         Junction::default(),
                         Buldge::no_cover(),
                     ));
                 }
+                PipeEntity::Gate {
+                    kind,
+                    output,
+                    window,
+                } => {
+                    let mut gate = Gate::new(*kind, output.axis());
+
+                    if let Some(window) = window {
+                        gate = gate.with_window(Duration::from_secs_f32(window.max(0.)));
+                    }
+
+                    commands.entity(entity).insert((
+                        GateBundle::new(gate),
+                        Name::new("Gate"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Counter {
+                    required,
+                    timeout,
+                    activates,
+                } => {
+                    let mut counter = Counter::new(*required);
+
+                    if let Some(timeout) = timeout {
+                        counter = counter.with_timeout(Duration::from_secs_f32(timeout.max(0.)));
+                    }
+
+                    if let Some(activates) = activates {
+                        counter = counter.with_activates_iid(activates.clone());
+                    }
+
+                    commands.entity(entity).insert((
+                        CounterBundle::new(counter),
+                        Name::new("Counter"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Latch {
+                    interval,
+                    activates,
+                } => {
+                    let latch = interval
+                        .map(|interval| Latch::new(Duration::from_secs_f32(interval.max(0.1))))
+                        .unwrap_or_default()
+                        .with_activates_iid(activates.clone());
+
+                    commands.entity(entity).insert((
+                        LatchBundle::new(latch),
+                        Name::new("Latch"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Delay(time) => {
+                    let delay = time
+                        .map(|time| Delay::new(Duration::from_secs_f32(time.max(0.))))
+                        .unwrap_or_default();
+
+                    commands.entity(entity).insert((
+                        DelayBundle::new(delay),
+                        Name::new("Delay"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Pulse(interval) => {
+                    let pulse = interval
+                        .map(|interval| Pulse::new(Duration::from_secs_f32(interval.max(0.1))))
+                        .unwrap_or_default();
+
+                    commands.entity(entity).insert((
+                        pulse,
+                        Name::new("Pulse"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Echo { delay, falloff } => {
+                    let mut echo = Echo::default();
+
+                    if let Some(delay) = delay {
+                        echo.delay = Duration::from_secs_f32(delay.max(0.));
+                    }
+
+                    if let Some(falloff) = falloff {
+                        echo = echo.with_falloff(falloff.clamp(0., 1.));
+                    }
+
+                    commands.entity(entity).insert((
+                        echo,
+                        Name::new("Echo"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Valve(allowed) => {
+                    commands.entity(entity).insert((
+                        Valve { allowed: *allowed },
+                        Name::new("Valve"),
+                        Junction::default(),
+                    ));
+                }
+                PipeEntity::Filter(hostility) => {
+                    commands.entity(entity).insert((
+                        Filter(*hostility),
+                        Name::new("Filter"),
+                        Junction::default(),
+                    ));
+                }
+                PipeEntity::Converter => {
+                    commands.entity(entity).insert((
+                        Converter,
+                        Name::new("Converter"),
+                        Junction::default(),
+                    ));
+                }
+                PipeEntity::Router(routing) => {
+                    commands
+                        .entity(entity)
+                        .insert((Name::new("Router"), Junction::with_routing(routing.clone())));
+                }
+                PipeEntity::Transmitter(channel) => {
+                    commands.entity(entity).insert((
+                        TransmitterBundle::new(*channel),
+                        Name::new("Transmitter"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Receiver(channel) => {
+                    commands.entity(entity).insert((
+                        Receiver(*channel),
+                        Name::new("Receiver"),
+                        Junction::default(),
+                        Buldge::no_cover(),
+                    ));
+                }
+                PipeEntity::Speed(_) => unreachable!(),
             }
 
             // delete old pipeentity
-            commands.entity(new_pipe_entity).remove::<PipeEntity>();
+            commands
+                .entity(new_pipe_entity)
+                .remove::<(PipeEntity, Trajectory, GeneratorLimit, PhaseStep)>();
         }
     }
 }
@@ -288,10 +820,10 @@ fn create_pipe_segment_collision(
 ) {
     for (entity, tile_idx) in pipe_segment_query.iter() {
         let collider = match tile_idx.0 {
-            // straight horizontal tubes
-            1..=3 => Collider::cuboid(8., 6.),
-            // straight vertical tubes
-            8 => Collider::cuboid(6., 8.),
+            // straight horizontal tubes and valves
+            1..=3 | 12 | 14 => Collider::cuboid(8., 6.),
+            // straight vertical tubes and valves
+            8 | 13 | 15 => Collider::cuboid(6., 8.),
             // elbows (this is cheating)
             19 | 20 | 25 | 26 => Collider::cuboid(6., 6.),
             _ => continue,
@@ -311,6 +843,7 @@ fn build_pipe_network(
     mut param_set: ParamSet<(Query<&mut Junction>, Query<&Parent, Changed<Junction>>)>,
     //mut junctions_query: Query<&mut Junction>,
     colors_query: Query<&PipeSegment>,
+    speeds_query: Query<&PipeSpeed>,
     //added_junctions: Query<&Parent, Added<Junction>>,
     layers_query: Query<&TileStorage, With<PipesLayer>>,
 ) {
@@ -330,15 +863,24 @@ fn build_pipe_network(
             for x in 0..tiles.size.x {
                 let pos = TilePos::new(x, y);
 
-                build_junction(&mut param_set.p0(), &colors_query, tiles, pos);
+                build_junction(
+                    &mut param_set.p0(),
+                    &colors_query,
+                    &speeds_query,
+                    tiles,
+                    pos,
+                );
             }
         }
+
+        bend_pipes(&mut param_set.p0(), tiles);
     }
 }
 
 fn build_junction(
     junctions_query: &mut Query<&mut Junction>,
     colors_query: &Query<&PipeSegment>,
+    speeds_query: &Query<&PipeSpeed>,
     tiles: &TileStorage,
     pos: TilePos,
 ) {
@@ -347,6 +889,7 @@ fn build_junction(
     };
 
     let color = colors_query.get(tile_entity).ok();
+    let speed = speeds_query.get(tile_entity).copied().unwrap_or_default();
 
     if let Ok(mut junction) = junctions_query.get_mut(tile_entity) {
         junction.clear();
@@ -373,7 +916,79 @@ fn build_junction(
                 continue;
             };
 
-            junction.push_pipe(neighbor_entity);
+            let neighbor_speed = speeds_query
+                .get(neighbor_entity)
+                .copied()
+                .unwrap_or_default();
+
+            junction.pipes.push(
+                Pipe::new(neighbor_entity)
+                    .with_speed(Signal::SPEED * (speed.0 + neighbor_speed.0) / 2.),
+            );
+        }
+    }
+}
+
+/// Finds the corner tiles of a layer and curves the pipes through them.
+fn bend_pipes(junctions_query: &mut Query<&mut Junction>, tiles: &TileStorage) {
+    // the ways out of every tile, with the tile each one goes to
+    let mut exits = HashMap::new();
+
+    for y in 0..tiles.size.y {
+        for x in 0..tiles.size.x {
+            let pos = TilePos::new(x, y);
+
+            let Some(tile_entity) = tiles.get(&pos) else {
+                continue;
+            };
+
+            let Ok(junction) = junctions_query.get(tile_entity) else {
+                continue;
+            };
+
+            let tile_exits = neighbor_positions(&tiles.size, &pos)
+                .into_iter()
+                .zip(Direction::ALL)
+                .filter_map(|(neighbor_pos, direction)| {
+                    let neighbor_entity = tiles.get(&neighbor_pos?)?;
+
+                    junction
+                        .pipes
+                        .iter()
+                        .any(|pipe| pipe.receiver == neighbor_entity)
+                        .then_some((neighbor_entity, direction))
+                })
+                .collect::<Vec<_>>();
+
+            exits.insert(tile_entity, tile_exits);
+        }
+    }
+
+    // a corner has two ways out that aren't opposite each other
+    let corner = |tile_entity: Entity, exit: Direction| -> PipeBend {
+        match exits.get(&tile_entity).map(|exits| exits.as_slice()) {
+            Some(&[(_, a), (_, b)]) if a != b.opposite() => {
+                PipeBend::Corner(if a == exit { b } else { a })
+            }
+            _ => PipeBend::Straight,
+        }
+    };
+
+    for (&tile_entity, tile_exits) in exits.iter() {
+        let Ok(mut junction) = junctions_query.get_mut(tile_entity) else {
+            continue;
+        };
+
+        for pipe in junction.pipes.iter_mut() {
+            let Some(&(_, direction)) = tile_exits
+                .iter()
+                .find(|(neighbor_entity, _)| *neighbor_entity == pipe.receiver)
+            else {
+                continue;
+            };
+
+            pipe.start = corner(tile_entity, direction);
+            pipe.end = corner(pipe.receiver, direction.opposite());
         }
     }
 }
@@ -394,3 +1009,179 @@ fn neighbor_positions(size: &TilemapSize, pos: &TilePos) -> [Option<TilePos>; 4]
         }
     })
 }
+
+fn connect_pipe_inputs(
+    mut junctions_query: Query<&mut Junction>,
+    input_query: Query<(Entity, &PipeInput, &Parent)>,
+    levels_query: Query<&Children>,
+    layers_query: Query<&TileStorage, With<PipesLayer>>,
+) {
+    for (entity, input, parent) in input_query.iter() {
+        let Some(pos) = input.tile else {
+            continue;
+        };
+
+        let Ok(level_children) = levels_query.get(parent.get()) else {
+            continue;
+        };
+
+        let Some(tile) = layers_query
+            .iter_many(level_children)
+            .next()
+            .and_then(|tiles| tiles.get(&pos))
+        else {
+            continue;
+        };
+
+        // the pipes are built again every frame, so the link to the entity
+        // has to be put back every frame
+        if let Ok(mut junction) = junctions_query.get_mut(tile) {
+            if !junction.pipes.iter().any(|pipe| pipe.receiver == entity) {
+                junction.push_pipe(entity);
+            }
+        }
+
+        if let Ok(mut junction) = junctions_query.get_mut(entity) {
+            if !junction.pipes.iter().any(|pipe| pipe.receiver == tile) {
+                junction.clear();
+                junction.push_pipe(tile);
+            }
+        }
+    }
+}
+
+fn route_platforms_along_pipes(
+    mut commands: Commands,
+    mut platforms_query: Query<(Entity, &Parent, &mut MovingPlatform), Without<PipeRouted>>,
+    parent_query: Query<&Parent>,
+    levels_query: Query<&Children>,
+    layers_query: Query<(&TileStorage, &TilemapGridSize, &Transform), With<PipesLayer>>,
+    transform_query: Query<&Transform>,
+    junctions_query: Query<&Junction>,
+    tile_pos_query: Query<&TilePos>,
+) {
+    for (platform_entity, parent, mut platform) in platforms_query.iter_mut() {
+        if !platform.follow_pipes {
+            continue;
+        }
+
+        let Ok(level) = parent_query.get(parent.get()) else {
+            continue;
+        };
+
+        let Ok(level_children) = levels_query.get(level.get()) else {
+            continue;
+        };
+
+        // skip if pipes layer has not been marked yet
+        let Some((tiles, grid_size, pipes_transform)) =
+            layers_query.iter_many(level_children).next()
+        else {
+            continue;
+        };
+
+        let Ok(entity_layer_transform) = transform_query.get(parent.get()) else {
+            continue;
+        };
+
+        // platform locations are local to the entity layer, which is a sibling
+        // of the pipes layer
+        let to_pipes =
+            pipes_transform.compute_affine().inverse() * entity_layer_transform.compute_affine();
+        let from_pipes = to_pipes.inverse();
+
+        let grid_size = Vec2::new(grid_size.x, grid_size.y);
+
+        let tile_at = |location: Vec2| {
+            let local = to_pipes.transform_point3(location.extend(0.)).truncate();
+            let pos = (local / grid_size).round();
+
+            if pos.x < 0. || pos.y < 0. {
+                return None;
+            }
+
+            tiles.get(&TilePos::new(pos.x as u32, pos.y as u32))
+        };
+
+        let (Some(start), Some(end)) = (
+            tile_at(platform.start_location),
+            tile_at(platform.end_location),
+        ) else {
+            continue;
+        };
+
+        // the network may not be built yet, so try again later
+        let Some(route) = find_pipe_route(&junctions_query, start, end) else {
+            continue;
+        };
+
+        let points = tile_pos_query
+            .iter_many(&route)
+            .map(|pos| {
+                let local = Vec2::new(pos.x as f32, pos.y as f32) * grid_size;
+                from_pipes.transform_point3(local.extend(0.)).truncate()
+            })
+            .collect::<Vec<_>>();
+
+        // keep the platform's offset from the pipes
+        let offset = points
+            .first()
+            .map(|first| platform.start_location - *first)
+            .unwrap_or_default();
+
+        platform.waypoints = points
+            .windows(3)
+            .filter(|w| {
+                // only keep corners
+                let a = (w[1] - w[0]).normalize_or_zero();
+                let b = (w[2] - w[1]).normalize_or_zero();
+
+                a.dot(b) < 0.99
+            })
+            .map(|w| w[1] + offset)
+            .collect();
+
+        commands.entity(platform_entity).insert(PipeRouted);
+    }
+}
+
+/// Finds the shortest chain of connected pipes between two tiles.
+fn find_pipe_route(
+    junctions_query: &Query<&Junction>,
+    start: Entity,
+    end: Entity,
+) -> Option<Vec<Entity>> {
+    let mut came_from: HashMap<Entity, Entity> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    came_from.insert(start, start);
+
+    while let Some(current) = queue.pop_front() {
+        if current == end {
+            // walk back to the start
+            let mut route = vec![end];
+            let mut current = end;
+
+            while current != start {
+                current = came_from[&current];
+                route.push(current);
+            }
+
+            route.reverse();
+            return Some(route);
+        }
+
+        let Ok(junction) = junctions_query.get(current) else {
+            continue;
+        };
+
+        for pipe in &junction.pipes {
+            if !came_from.contains_key(&pipe.receiver) {
+                came_from.insert(pipe.receiver, current);
+                queue.push_back(pipe.receiver);
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,396 @@
+//! Bonus rooms generated at runtime.
+//!
+//! A level named [`BonusRoomSettings::level`] is left empty in LDtk. When it
+//! spawns, its `Ground` layer is filled with a floor, walls and a few
+//! platforms with points [`Pickup`]s over them, all picked by the [`GameRng`].
+//! Each platform can be jumped to from the one under it, and no collectible is
+//! stuck inside a platform.
+//! Sending a [`GenerateBonusRoom`] throws the room away and makes a new one,
+//! for endless practice and score attack.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::map::{TilemapSize, TilemapTileSize};
+
+use std::ops::Range;
+
+use super::collision::{CollisionMap, LevelCollisionSystem};
+use super::Ground;
+use crate::pickup::{Pickup, PickupBundle};
+use crate::rng::GameRng;
+
+/// Bonus room plugin.
+pub struct BonusRoomPlugin;
+
+impl Plugin for BonusRoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BonusRoomSettings>()
+            .add_event::<GenerateBonusRoom>()
+            .add_systems(
+                Update,
+                (
+                    mark_bonus_rooms,
+                    generate_bonus_rooms.before(LevelCollisionSystem::BuildCollision),
+                ),
+            );
+    }
+}
+
+/// How bonus rooms are generated.
+#[derive(Clone, Debug, Resource)]
+pub struct BonusRoomSettings {
+    /// The identifier of the empty level rooms are generated in.
+    pub level: String,
+    /// How wide platforms are, in tiles.
+    pub platform_width: Range<u32>,
+    /// How far apart platforms are vertically, in tiles.
+    pub platform_gap: Range<u32>,
+    /// How far a platform can be to the side of the one under it, from edge
+    /// to edge, in tiles.
+    pub platform_reach: u32,
+    /// How many collectibles go over each platform.
+    pub collectibles_per_platform: u32,
+    /// How many points a collectible is worth.
+    pub collectible_points: u64,
+}
+
+impl Default for BonusRoomSettings {
+    fn default() -> BonusRoomSettings {
+        BonusRoomSettings {
+            level: "BonusRoom".into(),
+            platform_width: 4..10,
+            // the player jumps a little over six tiles
+            platform_gap: 3..6,
+            platform_reach: 6,
+            collectibles_per_platform: 2,
+            collectible_points: 50,
+        }
+    }
+}
+
+/// Throws away every bonus room and generates a new one.
+#[derive(Clone, Debug, Default, Event)]
+pub struct GenerateBonusRoom;
+
+/// Marks the `Ground` layer of a bonus room.
+#[derive(Clone, Component, Debug, Default)]
+pub struct BonusRoom;
+
+/// Marks something spawned for a bonus room, that goes away when the room is
+/// generated again.
+#[derive(Clone, Component, Debug, Default)]
+pub struct BonusRoomPiece;
+
+/// The layout of a bonus room, in tiles, `y` going up.
+#[derive(Clone, Debug)]
+pub struct BonusRoomLayout {
+    /// The size of the room.
+    pub size: UVec2,
+    /// The platforms, from the bottom up.
+    pub platforms: Vec<BonusPlatform>,
+    /// Where collectibles go.
+    pub collectibles: Vec<UVec2>,
+    solid: Vec<bool>,
+}
+
+/// A platform in a [`BonusRoomLayout`], in tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BonusPlatform {
+    /// The leftmost tile.
+    pub left: u32,
+    /// How many tiles wide the platform is.
+    pub width: u32,
+    /// The row the platform is in.
+    pub y: u32,
+}
+
+impl BonusPlatform {
+    /// The column just past the right end.
+    pub fn right(&self) -> u32 {
+        self.left + self.width
+    }
+
+    /// How many tiles there are between this platform and another one to the
+    /// side, or `0` if they overlap horizontally.
+    pub fn horizontal_gap(&self, other: &BonusPlatform) -> u32 {
+        self.left
+            .saturating_sub(other.right())
+            .max(other.left.saturating_sub(self.right()))
+    }
+}
+
+impl BonusRoomLayout {
+    /// Generates a new layout.
+    pub fn generate(
+        rng: &mut GameRng,
+        size: UVec2,
+        settings: &BonusRoomSettings,
+    ) -> BonusRoomLayout {
+        let mut layout = BonusRoomLayout {
+            size,
+            platforms: Vec::new(),
+            collectibles: Vec::new(),
+            solid: vec![false; (size.x * size.y) as usize],
+        };
+
+        // too small for anything but walls
+        if size.x < 3 || size.y < 3 {
+            layout.solid.fill(true);
+            return layout;
+        }
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                if x == 0 || y == 0 || x == size.x - 1 || y == size.y - 1 {
+                    layout.set(UVec2::new(x, y));
+                }
+            }
+        }
+
+        let inner_width = size.x - 2;
+        let width = &settings.platform_width;
+        // collectibles float two tiles over a platform, so the next one up
+        // has to be at least three tiles higher to not swallow them
+        let min_gap = settings.platform_gap.start.max(3);
+        let max_gap = settings.platform_gap.end.max(min_gap + 1);
+
+        // walk up from the floor, which reaches everywhere
+        let mut below = BonusPlatform {
+            left: 1,
+            width: inner_width,
+            y: 0,
+        };
+
+        loop {
+            let y = below.y + rng.range_u32(min_gap, max_gap);
+
+            // leave room to stand under the ceiling
+            if y + 3 >= size.y {
+                break;
+            }
+
+            let width = rng.range_u32(width.start, width.end).clamp(1, inner_width);
+
+            // close enough to the side of the platform below to jump to
+            let reach = settings.platform_reach;
+            let min_left = below.left.saturating_sub(width + reach).max(1);
+            let max_left = (below.right() + reach).min(size.x - width - 1);
+            let left = rng.range_u32(min_left, max_left + 1);
+
+            let platform = BonusPlatform { left, width, y };
+
+            for x in platform.left..platform.right() {
+                layout.set(UVec2::new(x, y));
+            }
+
+            let count = settings.collectibles_per_platform;
+
+            for i in 0..count {
+                let x = left + (i + 1) * width / (count + 1);
+                let pos = UVec2::new(x, y + 2);
+
+                if !layout.solid(pos) {
+                    layout.collectibles.push(pos);
+                }
+            }
+
+            layout.platforms.push(platform);
+            below = platform;
+        }
+
+        layout
+    }
+
+    /// Checks if a tile is solid.
+    pub fn solid(&self, pos: UVec2) -> bool {
+        pos.x < self.size.x
+            && pos.y < self.size.y
+            && self.solid[(pos.y * self.size.x + pos.x) as usize]
+    }
+
+    fn set(&mut self, pos: UVec2) {
+        self.solid[(pos.y * self.size.x + pos.x) as usize] = true;
+    }
+}
+
+fn mark_bonus_rooms(
+    mut commands: Commands,
+    new_layers_query: Query<(Entity, &LayerMetadata, &Parent), Added<LayerMetadata>>,
+    levels_query: Query<&Handle<LdtkLevel>>,
+    levels: Res<Assets<LdtkLevel>>,
+    settings: Res<BonusRoomSettings>,
+) {
+    for (entity, layer, parent) in new_layers_query.iter() {
+        if layer.identifier != "Ground" {
+            continue;
+        }
+
+        let Some(level) = levels_query
+            .get(parent.get())
+            .ok()
+            .and_then(|level| levels.get(level))
+        else {
+            continue;
+        };
+
+        if level.level.identifier == settings.level {
+            commands.entity(entity).insert(BonusRoom);
+        }
+    }
+}
+
+fn generate_bonus_rooms(
+    mut commands: Commands,
+    mut generate_events: EventReader<GenerateBonusRoom>,
+    mut room_query: Query<
+        (
+            Entity,
+            &Parent,
+            &TilemapSize,
+            &TilemapTileSize,
+            Option<&mut CollisionMap<Ground>>,
+        ),
+        With<BonusRoom>,
+    >,
+    added_query: Query<(), Added<BonusRoom>>,
+    pieces_query: Query<Entity, With<BonusRoomPiece>>,
+    mut rng: ResMut<GameRng>,
+    settings: Res<BonusRoomSettings>,
+) {
+    // new rooms are generated right away
+    if generate_events.iter().count() == 0 && added_query.is_empty() {
+        return;
+    }
+
+    for entity in pieces_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (entity, parent, map_size, tile_size, collision_map) in room_query.iter_mut() {
+        let size = UVec2::new(map_size.x, map_size.y);
+        let layout = BonusRoomLayout::generate(&mut rng, size, &settings);
+
+        let mut new_map = None;
+        let collision_map = match collision_map {
+            Some(map) => map.into_inner(),
+            None => new_map.insert(CollisionMap::<Ground>::new(map_size)),
+        };
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let pos = UVec2::new(x, y);
+                collision_map.put(map_size, pos, layout.solid(pos));
+            }
+        }
+
+        if let Some(map) = new_map {
+            commands.entity(entity).insert(map);
+        }
+
+        let tile = Vec2::new(tile_size.x, tile_size.y);
+
+        commands.entity(parent.get()).with_children(|level| {
+            // one sprite for each run of solid tiles in a row
+            for y in 0..size.y {
+                let mut x = 0;
+
+                while x < size.x {
+                    if !layout.solid(UVec2::new(x, y)) {
+                        x += 1;
+                        continue;
+                    }
+
+                    let start = x;
+
+                    while x < size.x && layout.solid(UVec2::new(x, y)) {
+                        x += 1;
+                    }
+
+                    let run = Vec2::new((x - start) as f32, 1.) * tile;
+                    let corner = Vec2::new(start as f32, y as f32) * tile;
+
+                    level.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgb(0.35, 0.33, 0.45),
+                                custom_size: Some(run),
+                                ..Default::default()
+                            },
+                            transform: Transform::from_translation((corner + run / 2.).extend(0.)),
+                            ..Default::default()
+                        },
+                        BonusRoomPiece,
+                    ));
+                }
+            }
+
+            for pos in &layout.collectibles {
+                let center = (pos.as_vec2() + 0.5) * tile;
+
+                level.spawn((
+                    PickupBundle::new(
+                        Pickup::Points(settings.collectible_points),
+                        center.extend(1.),
+                    ),
+                    BonusRoomPiece,
+                ));
+            }
+        });
+
+        crate::game_info!(
+            Level,
+            "generated bonus room with {} collectibles",
+            layout.collectibles.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bonus_rooms_are_reachable() {
+        let settings = BonusRoomSettings::default();
+        let size = UVec2::new(34, 22);
+
+        for seed in 0..64 {
+            let mut rng = GameRng::new(seed);
+            let layout = BonusRoomLayout::generate(&mut rng, size, &settings);
+
+            assert!(!layout.platforms.is_empty(), "seed {}", seed);
+
+            let floor = BonusPlatform {
+                left: 1,
+                width: size.x - 2,
+                y: 0,
+            };
+
+            for (below, platform) in std::iter::once(&floor)
+                .chain(&layout.platforms)
+                .zip(&layout.platforms)
+            {
+                assert!(
+                    platform.left >= 1 && platform.right() < size.x,
+                    "seed {}",
+                    seed
+                );
+                assert!(
+                    platform.y - below.y < settings.platform_gap.end,
+                    "seed {}",
+                    seed
+                );
+                assert!(
+                    platform.horizontal_gap(below) <= settings.platform_reach,
+                    "seed {}",
+                    seed
+                );
+            }
+
+            for pos in &layout.collectibles {
+                assert!(!layout.solid(*pos), "seed {}", seed);
+                assert!(layout.solid(*pos - UVec2::Y * 2), "seed {}", seed);
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Welds ground colliders together across seams.
+//!
+//! Colliders are built per level and per [`CHUNK_SIZE`] chunk, so a floor
+//! running across a level border or a chunk border is made of two colliders
+//! that meet edge to edge. The player's rounded collider can catch on the
+//! corners where they meet. Colliders that share a whole edge are disabled
+//! and replaced by one [`SeamWeld`] covering both.
+//!
+//! When colliders are built or removed, only they and the welds and colliders
+//! next to them are welded again. Welds are children of a level, so they go
+//! away with it.
+//!
+//! [`CHUNK_SIZE`]: super::collision::CHUNK_SIZE
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use super::collision::{CreatedCollider, LevelCollisionSystem};
+use super::Ground;
+use crate::physics;
+
+/// Seam welding plugin.
+pub struct SeamWeldPlugin;
+
+impl Plugin for SeamWeldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            weld_seams.after(LevelCollisionSystem::BuildCollision),
+        );
+    }
+}
+
+/// A collider made of [`CreatedCollider`]s welded together.
+#[derive(Clone, Component, Debug)]
+pub struct SeamWeld {
+    /// The colliders that were welded.
+    pub sources: Vec<Entity>,
+}
+
+/// Marks a [`CreatedCollider`] that is disabled because it was welded into a
+/// [`SeamWeld`].
+#[derive(Clone, Component, Debug)]
+pub struct Welded(pub Entity);
+
+/// How far apart two edges can be and still be the same edge.
+const EPSILON: f32 = 0.01;
+
+#[derive(Clone, Debug)]
+struct WeldRect {
+    min: Vec2,
+    max: Vec2,
+    /// The level the weld is parented to.
+    level: Entity,
+    sources: Vec<Entity>,
+}
+
+impl WeldRect {
+    /// Welds two rects together if they share a whole edge.
+    fn weld(&self, other: &WeldRect) -> Option<WeldRect> {
+        let close = |a: f32, b: f32| (a - b).abs() < EPSILON;
+
+        let same_rows = close(self.min.y, other.min.y) && close(self.max.y, other.max.y);
+        let same_columns = close(self.min.x, other.min.x) && close(self.max.x, other.max.x);
+
+        let side_by_side =
+            same_rows && (close(self.max.x, other.min.x) || close(other.max.x, self.min.x));
+        let stacked =
+            same_columns && (close(self.max.y, other.min.y) || close(other.max.y, self.min.y));
+
+        if !side_by_side && !stacked {
+            return None;
+        }
+
+        Some(WeldRect {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            level: self.level,
+            sources: self
+                .sources
+                .iter()
+                .chain(other.sources.iter())
+                .copied()
+                .collect(),
+        })
+    }
+
+    /// Checks if two rects overlap or touch, even at a corner.
+    fn touches(&self, other: &WeldRect) -> bool {
+        self.min.x <= other.max.x + EPSILON
+            && other.min.x <= self.max.x + EPSILON
+            && self.min.y <= other.max.y + EPSILON
+            && other.min.y <= self.max.y + EPSILON
+    }
+}
+
+fn weld_seams(
+    mut commands: Commands,
+    added_query: Query<Entity, Added<CreatedCollider<Ground>>>,
+    mut removed: RemovedComponents<CreatedCollider<Ground>>,
+    collider_query: Query<
+        (Entity, &Parent, &Transform, &Collider, Option<&Welded>),
+        With<CreatedCollider<Ground>>,
+    >,
+    weld_query: Query<(Entity, &SeamWeld)>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    let removed = removed.iter().collect::<Vec<_>>();
+
+    // only when the level collision changes
+    if added_query.is_empty() && removed.is_empty() {
+        return;
+    }
+
+    let rect_of = |entity: Entity| {
+        let (_, parent, transform, collider, _) = collider_query.get(entity).ok()?;
+        let half_extents = collider.as_cuboid()?.half_extents();
+        let level = transform_query.get(parent.get()).ok()?;
+        let center = level.translation().truncate() + transform.translation.truncate();
+
+        Some(WeldRect {
+            min: center - half_extents,
+            max: center + half_extents,
+            level: parent.get(),
+            sources: vec![entity],
+        })
+    };
+
+    // welds that lost a collider fall apart
+    let mut broken = weld_query
+        .iter()
+        .filter(|(_, weld)| weld.sources.iter().any(|source| removed.contains(source)))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    // new colliders, and colliders whose weld fell apart or went away with
+    // its level, are welded again
+    let mut dirty = added_query.iter().collect::<Vec<_>>();
+
+    dirty.extend(
+        collider_query
+            .iter()
+            .filter(|(_, _, _, _, welded)| match welded {
+                Some(Welded(weld)) => broken.contains(weld) || !weld_query.contains(*weld),
+                None => false,
+            })
+            .map(|(entity, ..)| entity),
+    );
+
+    let dirty_rects = dirty
+        .iter()
+        .filter_map(|&entity| rect_of(entity))
+        .collect::<Vec<_>>();
+    let near_dirty = |rect: &WeldRect| dirty_rects.iter().any(|dirty| dirty.touches(rect));
+
+    // so do the neighbors, which might weld with them now
+    for (entity, weld) in weld_query.iter() {
+        if broken.contains(&entity) {
+            continue;
+        }
+
+        let near = weld
+            .sources
+            .iter()
+            .filter_map(|&source| rect_of(source))
+            .any(|rect| near_dirty(&rect));
+
+        if near {
+            broken.push(entity);
+            dirty.extend(weld.sources.iter().copied());
+        }
+    }
+
+    let neighbors = collider_query
+        .iter()
+        .filter(|(entity, _, _, _, welded)| welded.is_none() && !dirty.contains(entity))
+        .filter(|(entity, ..)| rect_of(*entity).is_some_and(|rect| near_dirty(&rect)))
+        .map(|(entity, ..)| entity)
+        .collect::<Vec<_>>();
+
+    dirty.extend(neighbors);
+
+    dirty.sort();
+    dirty.dedup();
+
+    for entity in broken {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for &entity in &dirty {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(Welded, ColliderDisabled)>();
+        }
+    }
+
+    let mut rects: Vec<WeldRect> = dirty.iter().filter_map(|&entity| rect_of(entity)).collect();
+
+    // weld until nothing else fits
+    let mut welded = true;
+
+    while welded {
+        welded = false;
+
+        let mut i = 0;
+
+        while i < rects.len() {
+            let mut j = i + 1;
+
+            while j < rects.len() {
+                if let Some(rect) = rects[i].weld(&rects[j]) {
+                    rects[i] = rect;
+                    rects.swap_remove(j);
+                    welded = true;
+                    j = i + 1;
+                } else {
+                    j += 1;
+                }
+            }
+
+            i += 1;
+        }
+    }
+
+    for rect in rects.into_iter().filter(|rect| rect.sources.len() > 1) {
+        let Ok(level) = transform_query.get(rect.level) else {
+            continue;
+        };
+
+        let half_extents = (rect.max - rect.min) / 2.;
+        let center = rect.min + half_extents - level.translation().truncate();
+
+        // parented to the level so it goes away with it
+        let weld = commands
+            .spawn((
+                Collider::cuboid(half_extents.x, half_extents.y),
+                RigidBody::Fixed,
+                Friction::new(1.0),
+                TransformBundle::from_transform(Transform::from_translation(center.extend(0.))),
+                CollisionGroups::new(physics::COLLISION_GROUP_SOLID, Group::all()),
+            ))
+            .set_parent(rect.level)
+            .id();
+
+        for &source in &rect.sources {
+            commands
+                .entity(source)
+                .insert((Welded(weld), ColliderDisabled));
+        }
+
+        commands.entity(weld).insert(SeamWeld {
+            sources: rect.sources,
+        });
+    }
+}
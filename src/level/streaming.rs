@@ -0,0 +1,188 @@
+//! Loading levels over multiple frames.
+//!
+//! Instead of letting `bevy_ecs_ldtk` spawn every neighbor of the
+//! [`CurrentLevel`] the frame it changes, the neighbors are added to the
+//! world's [`LevelSet`] a few at a time. Each frame gets a budget of roughly
+//! [`LevelStreaming::entity_budget`] entities, and the neighbors closest to
+//! the player go first, so the level the player is walking into is usually
+//! there long before they cross into it.
+//!
+//! `bevy_ecs_ldtk` spawns a level whole, so a single level bigger than the
+//! budget still spawns in one frame; it just gets that frame to itself.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{ldtk::Level, LdtkAsset, LdtkLevel, LevelSet};
+
+use std::collections::HashSet;
+
+use super::CurrentLevel;
+use crate::player::LocalPlayer;
+
+/// Level streaming plugin.
+pub struct LevelStreamingPlugin;
+
+impl Plugin for LevelStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStreaming>().add_systems(
+            Update,
+            stream_levels.run_if(resource_exists::<CurrentLevel>()),
+        );
+    }
+}
+
+/// Settings for loading levels.
+#[derive(Clone, Debug, Resource)]
+pub struct LevelStreaming {
+    /// Roughly how many entities can be spawned for neighboring levels in a
+    /// single frame.
+    pub entity_budget: usize,
+    /// Whether the neighbors of the current level are loaded at all.
+    pub load_neighbors: bool,
+}
+
+impl Default for LevelStreaming {
+    fn default() -> LevelStreaming {
+        LevelStreaming {
+            entity_budget: 2048,
+            load_neighbors: true,
+        }
+    }
+}
+
+impl LevelStreaming {
+    /// Estimates how many entities spawning a level takes.
+    ///
+    /// Returns `None` if the level's layers aren't loaded yet.
+    pub fn cost(level: &Level, ldtk: &LdtkAsset, levels: &Assets<LdtkLevel>) -> Option<usize> {
+        // levels saved in separate files only have their layers in the level
+        // asset
+        let layers = match &level.layer_instances {
+            Some(layers) => layers,
+            None => ldtk
+                .level_map
+                .get(&level.iid)
+                .and_then(|handle| levels.get(handle))
+                .and_then(|level| level.level.layer_instances.as_ref())?,
+        };
+
+        let cost = layers
+            .iter()
+            .map(|layer| {
+                layer.grid_tiles.len()
+                    + layer.auto_layer_tiles.len()
+                    + layer.entity_instances.len()
+                    + layer.int_grid_csv.iter().filter(|v| **v != 0).count()
+            })
+            .sum::<usize>();
+
+        // the level and its layers
+        Some(cost + layers.len() + 1)
+    }
+}
+
+fn stream_levels(
+    streaming: Res<LevelStreaming>,
+    current_level: Res<CurrentLevel>,
+    mut world_query: Query<(&Handle<LdtkAsset>, &mut LevelSet)>,
+    player_query: Query<&GlobalTransform, With<LocalPlayer>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    levels: Res<Assets<LdtkLevel>>,
+) {
+    let player = player_query
+        .get_single()
+        .ok()
+        .map(|t| t.translation().truncate());
+
+    for (ldtk_handle, mut level_set) in world_query.iter_mut() {
+        let Some(ldtk) = ldtk_assets.get(ldtk_handle) else {
+            continue;
+        };
+
+        let Some(current) = ldtk
+            .iter_levels()
+            .find(|level| level.identifier == current_level.0)
+        else {
+            crate::game_warn!(Level, "no level named {}", current_level.0);
+            continue;
+        };
+
+        let mut wanted = HashSet::new();
+        wanted.insert(current.iid.as_str());
+
+        if streaming.load_neighbors {
+            wanted.extend(current.neighbours.iter().map(|n| n.level_iid.as_str()));
+        }
+
+        // unload levels that are too far away; do not trip change detection
+        if level_set
+            .iids
+            .iter()
+            .any(|iid| !wanted.contains(iid.as_str()))
+        {
+            level_set.iids.retain(|iid| wanted.contains(iid.as_str()));
+        }
+
+        // the current level never waits
+        if !level_set.iids.contains(&current.iid) {
+            level_set.iids.insert(current.iid.clone());
+
+            crate::game_debug!(Level, "loading {}", current.identifier);
+        }
+
+        let mut missing = ldtk
+            .iter_levels()
+            .filter(|level| {
+                wanted.contains(level.iid.as_str()) && !level_set.iids.contains(&level.iid)
+            })
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            continue;
+        }
+
+        if let Some(player) = player {
+            missing.sort_by(|a, b| {
+                distance_to_level(a, player).total_cmp(&distance_to_level(b, player))
+            });
+        }
+
+        let mut spent = 0usize;
+
+        for level in missing {
+            // the layers of a level in a separate file may still be loading
+            let Some(cost) = LevelStreaming::cost(level, ldtk, &levels) else {
+                continue;
+            };
+
+            // at least one level goes every frame, even if it's over budget
+            if spent > 0 && spent + cost > streaming.entity_budget {
+                break;
+            }
+
+            spent += cost;
+            level_set.iids.insert(level.iid.clone());
+
+            crate::game_debug!(
+                Level,
+                "loading neighbor {} (~{} entities)",
+                level.identifier,
+                cost
+            );
+        }
+    }
+}
+
+/// The squared distance from a point to the closest point of a level, in world
+/// coordinates.
+fn distance_to_level(level: &Level, point: Vec2) -> f32 {
+    // levels are placed like `bevy_ecs_ldtk` places them with world
+    // translation
+    let min = Vec2::new(
+        level.world_x as f32,
+        -level.world_y as f32 - level.px_hei as f32,
+    );
+    let max = min + Vec2::new(level.px_wid as f32, level.px_hei as f32);
+
+    point.distance_squared(point.clamp(min, max))
+}
@@ -0,0 +1,170 @@
+//! Surface materials of level tiles.
+//!
+//! Each ground tile has a [`Surface`], kept in a [`SurfaceMap`] next to the
+//! [`CollisionMap`](super::collision::CollisionMap) of its layer. Footsteps
+//! and dust look the surface up with [`Surfaces`].
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::{
+    map::{TilemapSize, TilemapTileSize},
+    tiles::TilePos,
+};
+
+use std::collections::HashMap;
+
+/// What a tile is made of.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Surface {
+    /// Plain ground.
+    #[default]
+    Stone,
+    Metal,
+    Wood,
+    /// The skin of a drum, or anything like it.
+    Membrane,
+}
+
+impl Surface {
+    /// Gets the surface of an int grid cell.
+    pub fn from_int_grid_cell(i: IntGridCell) -> Surface {
+        match i.value {
+            5 => Surface::Metal,
+            6 => Surface::Wood,
+            7 => Surface::Membrane,
+            _ => Surface::Stone,
+        }
+    }
+
+    /// The playback speed of footsteps on the surface.
+    pub fn pitch(self) -> f32 {
+        match self {
+            Surface::Stone => 1.,
+            Surface::Metal => 1.5,
+            Surface::Wood => 0.8,
+            Surface::Membrane => 0.6,
+        }
+    }
+
+    /// The volume of footsteps on the surface.
+    pub fn volume(self) -> f32 {
+        match self {
+            Surface::Stone => 0.3,
+            Surface::Metal => 0.5,
+            Surface::Wood => 0.4,
+            Surface::Membrane => 0.6,
+        }
+    }
+
+    /// The color of dust kicked up from the surface.
+    pub fn dust_color(self) -> Color {
+        match self {
+            Surface::Stone => Color::rgb(0.75, 0.79, 0.86),
+            Surface::Metal => Color::rgb(0.55, 0.61, 0.71),
+            Surface::Wood => Color::rgb(0.72, 0.44, 0.31),
+            Surface::Membrane => Color::rgb(0.89, 0.65, 0.45),
+        }
+    }
+}
+
+/// The [`Surface`]s of a tile layer.
+#[derive(Clone, Component, Debug, Default)]
+pub struct SurfaceMap {
+    map: Vec<Option<Surface>>,
+}
+
+impl SurfaceMap {
+    /// Creates a new surface map.
+    pub fn new(map_size: &TilemapSize) -> SurfaceMap {
+        SurfaceMap {
+            map: vec![None; map_size.count()],
+        }
+    }
+
+    /// Gets a surface from the map, if the tile has one.
+    pub fn get(&self, map_size: &TilemapSize, pos: impl Into<TilePos>) -> Option<Surface> {
+        let pos = pos.into();
+
+        if pos.within_map_bounds(map_size) {
+            self.map[pos.to_index(map_size)]
+        } else {
+            None
+        }
+    }
+
+    /// Puts a surface in the map.
+    pub fn put(&mut self, map_size: &TilemapSize, pos: impl Into<TilePos>, surface: Surface) {
+        self.map[pos.into().to_index(map_size)] = Some(surface);
+    }
+}
+
+/// Looks up the [`Surface`] at a point in the world.
+#[derive(SystemParam)]
+pub struct Surfaces<'w, 's> {
+    layer_query: Query<
+        'w,
+        's,
+        (
+            &'static GlobalTransform,
+            &'static TilemapSize,
+            &'static TilemapTileSize,
+            &'static SurfaceMap,
+        ),
+    >,
+}
+
+impl<'w, 's> Surfaces<'w, 's> {
+    /// Gets the surface of the tile under a point, if there is a tile there.
+    pub fn at(&self, point: Vec2) -> Option<Surface> {
+        for (transform, map_size, tile_size, surface_map) in self.layer_query.iter() {
+            let local = transform
+                .affine()
+                .inverse()
+                .transform_point3(point.extend(0.))
+                .truncate();
+            let tile = (local / Vec2::new(tile_size.x, tile_size.y)).floor();
+
+            if tile.x < 0. || tile.y < 0. {
+                continue;
+            }
+
+            let pos = TilePos::new(tile.x as u32, tile.y as u32);
+
+            // layers of other levels, or layers without a tile here
+            if let Some(surface) = surface_map.get(map_size, pos) {
+                return Some(surface);
+            }
+        }
+
+        None
+    }
+}
+
+pub(super) fn update_surface_map(
+    mut commands: Commands,
+    surface_query: Query<(&Surface, &TilePos, &Parent), Changed<Surface>>,
+    mut layer_query: Query<(&TilemapSize, Option<&mut SurfaceMap>)>,
+) {
+    let mut new_surface_maps: HashMap<Entity, SurfaceMap> = HashMap::new();
+
+    for (surface, pos, parent) in surface_query.iter() {
+        let Ok((map_size, mut surface_map)) = layer_query.get_mut(parent.get()) else {
+            continue;
+        };
+
+        let surface_map = if let Some(s) = surface_map.as_mut() {
+            &mut *s
+        } else {
+            new_surface_maps
+                .entry(parent.get())
+                .or_insert_with(|| SurfaceMap::new(map_size))
+        };
+
+        surface_map.put(map_size, *pos, *surface);
+    }
+
+    for (entity, surface_map) in new_surface_maps {
+        commands.entity(entity).insert(surface_map);
+    }
+}
@@ -1,14 +1,35 @@
 //! `tothe` library.
 
+pub mod animation;
+pub mod arena;
+pub mod atlas;
+pub mod audio;
+pub mod beat;
 pub mod camera;
+pub mod carry;
+pub mod combat;
+pub mod despawn;
 pub mod drum;
+pub mod dummy;
 pub mod enemy;
+#[cfg(feature = "devtools")]
+pub mod frame_step;
+pub mod fx;
 pub mod interactions;
+pub mod label;
 pub mod level;
+pub mod logging;
 pub mod physics;
+pub mod pickup;
 pub mod platform;
 pub mod player;
 pub mod projectile;
+pub mod rng;
+pub mod save;
+pub mod score;
+pub mod settings;
+pub mod telemetry;
+pub mod tutorial;
 pub mod ui;
 
 use bevy::prelude::*;
@@ -23,6 +44,7 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<GameState>()
+            .add_state::<GameMode>()
             .add_plugins((
                 camera::CameraPlugin,
                 camera::hint::CameraHintPlugin,
@@ -44,12 +66,59 @@ impl Plugin for GamePlugin {
                 enemy::EnemyPlugin,
                 enemy::prefab::EnemyPrefabPlugin,
                 drum::DrumPlugin,
+                audio::SoundPlugin,
+                camera::zoom::CameraZoomPlugin,
+                logging::LoggingPlugin,
+                carry::CarryPlugin,
+                projectile::explosion::ExplosionPlugin,
+                fx::FxPlugins,
+                atlas::AtlasRegistryPlugin,
+                projectile::budget::ProjectileBudgetPlugin,
+                projectile::beam::BeamPlugin,
+                rng::RngPlugin,
+                projectile::split::SplitPlugin,
+            ))
+            .add_plugins((
+                score::ScorePlugin,
+                ui::kill_feed::KillFeedPlugin,
+                projectile::turret::TurretPlugin,
+                ui::indicator::OffscreenIndicatorPlugin,
+                projectile::knockback::KnockbackPlugin,
+                player::footsteps::FootstepPlugin,
+                player::charge_shot::ChargeShotPlugin,
+                combat::CombatPlugin,
+                despawn::DespawnPlugin,
+                label::DebugLabelPlugin,
+                enemy::ai::EnemyAiPlugin,
+                enemy::shield::ShieldPlugin,
+                enemy::boss::BossPlugin,
+                arena::ArenaPlugin,
+                ui::wave::WaveAnnouncementPlugin,
+            ))
+            .add_plugins((
+                enemy::spawner::EnemySpawnerPlugin,
+                pickup::PickupPlugin,
+                settings::SettingsPlugin,
+                beat::BeatPlugin,
+                ui::calibration::CalibrationPlugin,
+                dummy::DummyPlugin,
+                animation::AnimationPlugin,
+                telemetry::TelemetryPlugin,
+                camera::resolution::AdaptiveResolutionPlugin,
+                tutorial::TutorialPlugin,
             ))
             .add_loading_state(
-                LoadingState::new(GameState::AssetLoading).continue_to_state(GameState::InGame),
+                LoadingState::new(GameState::AssetLoading)
+                    .continue_to_state(GameState::Calibration),
             )
             .add_collection_to_loading_state::<_, GameAssets>(GameState::AssetLoading)
             .add_systems(OnEnter(GameState::InGame), spawn_world);
+
+        #[cfg(feature = "devtools")]
+        app.add_plugins((
+            frame_step::FrameStepPlugin,
+            player::latency::InputLatencyPlugin,
+        ));
     }
 }
 
@@ -61,8 +130,9 @@ pub struct GameAssets {
     #[asset(texture_atlas(tile_size_x = 16., tile_size_y = 16., columns = 3, rows = 2))]
     #[asset(path = "world/platform.png")]
     pub platform_atlas: Handle<TextureAtlas>,
-    #[asset(path = "world/drum.png")]
-    pub drum_image: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 48., tile_size_y = 32., columns = 3, rows = 1))]
+    #[asset(path = "world/drum_sheet.png")]
+    pub drum_sheet: Handle<TextureAtlas>,
     #[asset(texture_atlas(tile_size_x = 16., tile_size_y = 16., columns = 2, rows = 1))]
     #[asset(path = "player/player.png")]
     pub player_sheet: Handle<TextureAtlas>,
@@ -72,6 +142,9 @@ pub struct GameAssets {
     #[asset(texture_atlas(tile_size_x = 32., tile_size_y = 24., columns = 7, rows = 1))]
     #[asset(path = "enemy/howard/howard.png")]
     pub enemy_howard: Handle<TextureAtlas>,
+    #[asset(texture_atlas(tile_size_x = 16., tile_size_y = 16., columns = 4, rows = 1))]
+    #[asset(path = "enemy/wisp/wisp.png")]
+    pub enemy_wisp: Handle<TextureAtlas>,
     #[asset(path = "signal/signal_matte.png")]
     pub signal_matte: Handle<Image>,
     #[asset(path = "signal/signal_mask.png")]
@@ -84,6 +157,21 @@ pub struct GameAssets {
     pub conceal: Handle<Image>,
     #[asset(path = "player/conceal_wedge.png")]
     pub conceal_wedge: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 4., tile_size_y = 6., columns = 12, rows = 1))]
+    #[asset(path = "ui/digits.png")]
+    pub ui_digits: Handle<TextureAtlas>,
+    #[asset(path = "sound/ratchet.wav")]
+    pub sound_ratchet: Handle<AudioSource>,
+    #[asset(path = "sound/bounce.wav")]
+    pub sound_bounce: Handle<AudioSource>,
+    #[asset(path = "sound/footstep.wav")]
+    pub sound_footstep: Handle<AudioSource>,
+    #[asset(path = "sound/platform_loop.wav")]
+    pub sound_platform_loop: Handle<AudioSource>,
+    #[asset(path = "sound/platform_chime.wav")]
+    pub sound_platform_chime: Handle<AudioSource>,
+    #[asset(path = "sound/platform_clunk.wav")]
+    pub sound_platform_clunk: Handle<AudioSource>,
 }
 
 /// Game state.
@@ -91,9 +179,26 @@ pub struct GameAssets {
 pub enum GameState {
     #[default]
     AssetLoading,
+    /// The latency calibration screen, which goes straight to the game if the
+    /// player has already calibrated.
+    ///
+    /// See [`ui::calibration`].
+    Calibration,
     InGame,
 }
 
+/// What the player is playing.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum GameMode {
+    /// The levels, in order.
+    #[default]
+    Story,
+    /// Waves of enemies in a single level.
+    ///
+    /// See [`arena`].
+    Arena,
+}
+
 /// The main world.
 #[derive(Clone, Component, Default, Debug)]
 pub struct GameWorld;
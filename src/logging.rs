@@ -0,0 +1,336 @@
+//! Logging with per-category verbosity.
+//!
+//! Gameplay code should log through [`game_info!`], [`game_debug!`] and
+//! friends instead of `bevy::log` directly, so noisy categories can be
+//! silenced. Verbosity is loaded from `settings/dev.log.ron` and can be changed at
+//! runtime through the [`LogSettings`] resource.
+//!
+//! [`game_info!`]: crate::game_info
+//! [`game_debug!`]: crate::game_debug
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Logging plugin.
+pub struct LoggingPlugin;
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LogSettings>()
+            .add_asset_loader(LogSettingsLoader)
+            .init_resource::<LogSettings>()
+            .add_systems(Startup, load_log_settings)
+            .add_systems(
+                PreUpdate,
+                (apply_loaded_log_settings, sync_verbosity).chain(),
+            );
+
+        #[cfg(feature = "devtools")]
+        {
+            use bevy_inspector_egui::bevy_egui::EguiPlugin;
+
+            if !app.is_plugin_added::<EguiPlugin>() {
+                app.add_plugins(EguiPlugin);
+            }
+
+            app.add_systems(Update, log_settings_ui);
+        }
+    }
+}
+
+/// A category of log messages, usually one per gameplay module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogCategory {
+    Audio,
+    Camera,
+    Despawn,
+    Enemy,
+    Interactions,
+    Level,
+    Platform,
+    Player,
+    Projectile,
+    Settings,
+    Telemetry,
+}
+
+impl LogCategory {
+    /// All of the categories.
+    pub const ALL: [LogCategory; 11] = [
+        LogCategory::Audio,
+        LogCategory::Camera,
+        LogCategory::Despawn,
+        LogCategory::Enemy,
+        LogCategory::Interactions,
+        LogCategory::Level,
+        LogCategory::Platform,
+        LogCategory::Player,
+        LogCategory::Projectile,
+        LogCategory::Settings,
+        LogCategory::Telemetry,
+    ];
+
+    /// The name of the category.
+    pub const fn name(self) -> &'static str {
+        match self {
+            LogCategory::Audio => "audio",
+            LogCategory::Camera => "camera",
+            LogCategory::Despawn => "despawn",
+            LogCategory::Enemy => "enemy",
+            LogCategory::Interactions => "interactions",
+            LogCategory::Level => "level",
+            LogCategory::Platform => "platform",
+            LogCategory::Player => "player",
+            LogCategory::Projectile => "projectile",
+            LogCategory::Settings => "settings",
+            LogCategory::Telemetry => "telemetry",
+        }
+    }
+}
+
+impl fmt::Display for LogCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How much a category logs.
+///
+/// Each verbosity includes the messages of the verbosities before it.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[repr(u8)]
+pub enum Verbosity {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    #[default]
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl Verbosity {
+    /// All of the verbosities.
+    pub const ALL: [Verbosity; 6] = [
+        Verbosity::Off,
+        Verbosity::Error,
+        Verbosity::Warn,
+        Verbosity::Info,
+        Verbosity::Debug,
+        Verbosity::Trace,
+    ];
+
+    fn from_u8(v: u8) -> Verbosity {
+        Verbosity::ALL
+            .get(v as usize)
+            .copied()
+            .unwrap_or(Verbosity::Trace)
+    }
+}
+
+static VERBOSITY: [AtomicU8; LogCategory::ALL.len()] =
+    [const { AtomicU8::new(Verbosity::Info as u8) }; LogCategory::ALL.len()];
+
+/// Checks if a message of a category and verbosity should be logged.
+///
+/// This is used by the logging macros, and reflects the last synced
+/// [`LogSettings`].
+pub fn enabled(category: LogCategory, verbosity: Verbosity) -> bool {
+    let current = Verbosity::from_u8(VERBOSITY[category as usize].load(Ordering::Relaxed));
+
+    verbosity != Verbosity::Off && verbosity <= current
+}
+
+/// Verbosity settings for each [`LogCategory`].
+///
+/// Categories not in the map log at [`LogSettings::default_verbosity`].
+#[derive(Clone, Debug, Resource, Serialize, Deserialize, TypeUuid, TypePath)]
+#[uuid = "7f0a5f8e-6f2c-4b4e-9a55-1f3d6c1b2e40"]
+pub struct LogSettings {
+    /// The verbosity of unlisted categories.
+    #[serde(default)]
+    pub default_verbosity: Verbosity,
+    /// Verbosity overrides per category.
+    #[serde(default)]
+    pub categories: HashMap<LogCategory, Verbosity>,
+}
+
+impl LogSettings {
+    /// Gets the verbosity of a category.
+    pub fn verbosity(&self, category: LogCategory) -> Verbosity {
+        self.categories
+            .get(&category)
+            .copied()
+            .unwrap_or(self.default_verbosity)
+    }
+
+    /// Sets the verbosity of a category.
+    pub fn set_verbosity(&mut self, category: LogCategory, verbosity: Verbosity) {
+        self.categories.insert(category, verbosity);
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> LogSettings {
+        LogSettings {
+            default_verbosity: Verbosity::Info,
+            categories: HashMap::new(),
+        }
+    }
+}
+
+/// Loads [`LogSettings`] from `.log.ron` files.
+#[derive(Default)]
+pub struct LogSettingsLoader;
+
+impl AssetLoader for LogSettingsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let settings: LogSettings = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(settings));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["log.ron"]
+    }
+}
+
+/// The handle of the dev settings file.
+#[derive(Resource)]
+struct LogSettingsHandle(Handle<LogSettings>);
+
+/// Logs at the info verbosity in a [`LogCategory`].
+#[macro_export]
+macro_rules! game_info {
+    ($category:ident, $($arg:tt)+) => {
+        if $crate::logging::enabled(
+            $crate::logging::LogCategory::$category,
+            $crate::logging::Verbosity::Info,
+        ) {
+            ::bevy::log::info!(
+                "[{}] {}",
+                $crate::logging::LogCategory::$category,
+                format_args!($($arg)+)
+            );
+        }
+    };
+}
+
+/// Logs at the debug verbosity in a [`LogCategory`].
+#[macro_export]
+macro_rules! game_debug {
+    ($category:ident, $($arg:tt)+) => {
+        if $crate::logging::enabled(
+            $crate::logging::LogCategory::$category,
+            $crate::logging::Verbosity::Debug,
+        ) {
+            // bevy filters out debug by default, so this is logged as info
+            ::bevy::log::info!(
+                "[{}] {}",
+                $crate::logging::LogCategory::$category,
+                format_args!($($arg)+)
+            );
+        }
+    };
+}
+
+/// Logs at the warn verbosity in a [`LogCategory`].
+#[macro_export]
+macro_rules! game_warn {
+    ($category:ident, $($arg:tt)+) => {
+        if $crate::logging::enabled(
+            $crate::logging::LogCategory::$category,
+            $crate::logging::Verbosity::Warn,
+        ) {
+            ::bevy::log::warn!(
+                "[{}] {}",
+                $crate::logging::LogCategory::$category,
+                format_args!($($arg)+)
+            );
+        }
+    };
+}
+
+fn load_log_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LogSettingsHandle(asset_server.load("settings/dev.log.ron")));
+}
+
+fn apply_loaded_log_settings(
+    mut asset_events: EventReader<AssetEvent<LogSettings>>,
+    mut log_settings: ResMut<LogSettings>,
+    handle: Option<Res<LogSettingsHandle>>,
+    assets: Res<Assets<LogSettings>>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    for ev in asset_events.iter() {
+        match ev {
+            AssetEvent::Created { handle: h } | AssetEvent::Modified { handle: h }
+                if *h == handle.0 =>
+            {
+                if let Some(settings) = assets.get(h) {
+                    *log_settings = settings.clone();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn sync_verbosity(log_settings: Res<LogSettings>) {
+    if !log_settings.is_changed() {
+        return;
+    }
+
+    for category in LogCategory::ALL {
+        VERBOSITY[category as usize]
+            .store(log_settings.verbosity(category) as u8, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "devtools")]
+fn log_settings_ui(
+    mut contexts: bevy_inspector_egui::bevy_egui::EguiContexts,
+    mut log_settings: ResMut<LogSettings>,
+) {
+    use bevy_inspector_egui::egui;
+
+    egui::Window::new("Logging")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for category in LogCategory::ALL {
+                let mut verbosity = log_settings.verbosity(category);
+
+                egui::ComboBox::from_label(category.name())
+                    .selected_text(format!("{:?}", verbosity))
+                    .show_ui(ui, |ui| {
+                        for v in Verbosity::ALL {
+                            ui.selectable_value(&mut verbosity, v, format!("{:?}", v));
+                        }
+                    });
+
+                // do not trip change detection
+                if verbosity != log_settings.verbosity(category) {
+                    log_settings.set_verbosity(category, verbosity);
+                }
+            }
+        });
+}
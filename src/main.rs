@@ -7,6 +7,7 @@ use bevy_ecs_ldtk::prelude::*;
 
 use bevy_rapier2d::prelude::*;
 
+use tothe::level::CurrentLevel;
 use tothe::GamePlugin;
 
 fn main() {
@@ -43,7 +44,7 @@ fn main() {
         //.add_plugins(RapierDebugRenderPlugin::default())
         //.add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::new())
         .add_plugins(GamePlugin)
-        .insert_resource(LevelSelection::Identifier("Level_0".into()))
+        .insert_resource(CurrentLevel("Level_0".into()))
         .insert_resource(RapierConfiguration {
             // good arcade gravity
             gravity: Vec2::new(0., -9.81 * 72.),
@@ -51,7 +52,8 @@ fn main() {
         })
         .insert_resource(LdtkSettings {
             level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
-                load_level_neighbors: true,
+                // neighbors are loaded over multiple frames by the game
+                load_level_neighbors: false,
             },
             ..Default::default()
         })
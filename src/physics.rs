@@ -15,6 +15,13 @@ pub const COLLISION_GROUP_HOSTILE: Group = Group::GROUP_3;
 pub const COLLISION_GROUP_PROJECTILE: Group = Group::GROUP_4;
 /// Collision for triggers.
 pub const COLLISION_GROUP_TRIGGER: Group = Group::GROUP_5;
+/// Collision for hostile projectiles that friendly projectiles can shoot down.
+pub const COLLISION_GROUP_INTERCEPTABLE: Group = Group::GROUP_6;
+/// Collision for debris that only rests on solids.
+pub const COLLISION_GROUP_DEBRIS: Group = Group::GROUP_7;
+/// Collision for friendly projectiles, which can shoot down interceptable
+/// ones.
+pub const COLLISION_GROUP_INTERCEPTOR: Group = Group::GROUP_8;
 
 /// Physics plugin.
 pub struct PhysicsPlugin;
@@ -22,15 +29,18 @@ pub struct PhysicsPlugin;
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            FixedUpdate,
-            check_grounded.in_set(PhysicsSet::CheckGrounded),
+            PostUpdate,
+            check_grounded
+                .in_set(PhysicsSet::CheckGrounded)
+                .before(bevy_rapier2d::plugin::PhysicsSet::SyncBackend),
         );
     }
 }
 
 #[derive(Clone, Debug, SystemSet, Hash, PartialEq, Eq)]
 pub enum PhysicsSet {
-    /// [`Grounded`] components are updated in this set.
+    /// [`Grounded`] components are updated in this set, in `PostUpdate`
+    /// before the physics step, from the contacts of the last one.
     CheckGrounded,
 }
 
@@ -0,0 +1,335 @@
+//! Things lying around to be picked up.
+//!
+//! A [`Pickup`] is placed in LDtk or dropped by an enemy (see
+//! [`DropOnDeath`](crate::enemy::DropOnDeath)). Anything with a
+//! [`PickupCollector`] pulls in the pickups around it that it can use and
+//! collects them when it touches them, sending a [`PickupEvent`] for counters
+//! and the like.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use crate::combat::Health;
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::physics;
+use crate::projectile::prefab::ProjectilePrefab;
+use crate::projectile::spawner::{Charge, Spawner};
+use crate::projectile::split::SplitOnAbsorb;
+use crate::score::{Score, ScoreEvent};
+
+/// Pickup plugin.
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PickupBundle>("Pickup")
+            .add_event::<PickupEvent>()
+            .add_systems(
+                Update,
+                (attract_pickups, collect_pickups)
+                    .chain()
+                    .in_set(PickupSystem::Collect)
+                    .run_if(any_with_component::<Pickup>()),
+            );
+    }
+}
+
+/// Pickup systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum PickupSystem {
+    /// Pickups are pulled in and collected, and [`PickupEvent`]s are sent.
+    Collect,
+}
+
+/// Something that does something for whoever collects it, then goes away.
+#[derive(Clone, Component, Debug, PartialEq)]
+pub enum Pickup {
+    /// Heals the collector's [`Health`] by some amount.
+    Health(f32),
+    /// Fills the collector's [`Charge`].
+    Charge,
+    /// Gives the player points, before the combo multiplier.
+    Points(u64),
+    /// Makes the notes the collector's [`Spawner`] fires burst into some
+    /// number of smaller notes when they are absorbed.
+    Split(u32),
+}
+
+impl Pickup {
+    /// Reads a pickup from the fields of an LDtk entity.
+    ///
+    /// `Kind` is one of `Health`, `Charge`, `Points` or `Split`, and `Amount`
+    /// is how much health or how many points it gives, or how many notes shots
+    /// split into. Without a `Kind`, it is worth
+    /// points.
+    pub fn from_entity_instance(entity_instance: &EntityInstance) -> Pickup {
+        let amount = entity_instance
+            .get_maybe_float_field("Amount")
+            .ok()
+            .copied()
+            .flatten();
+
+        let kind = entity_instance
+            .get_maybe_enum_field("Kind")
+            .ok()
+            .and_then(|kind| kind.as_deref());
+
+        Pickup::from_fields(kind, amount)
+    }
+
+    /// Makes a pickup from the name of its kind and its amount, as they are
+    /// written in LDtk.
+    pub fn from_fields(kind: Option<&str>, amount: Option<f32>) -> Pickup {
+        match kind {
+            Some("Health") => Pickup::Health(amount.unwrap_or(1.)),
+            Some("Charge") => Pickup::Charge,
+            Some("Split") => Pickup::Split(amount.map(|count| count.max(1.) as u32).unwrap_or(3)),
+            Some("Points") | None => {
+                Pickup::Points(amount.map(|points| points.max(0.) as u64).unwrap_or(50))
+            }
+            Some(other) => {
+                crate::game_warn!(Level, "unknown pickup {}", other);
+                Pickup::Points(0)
+            }
+        }
+    }
+
+    /// Checks if a collector with or without [`Health`], [`Charge`] and a
+    /// [`Spawner`] has any use for the pickup.
+    pub fn usable_by(&self, health: bool, charge: bool, spawner: bool) -> bool {
+        match self {
+            Pickup::Health(_) => health,
+            Pickup::Charge => charge,
+            Pickup::Points(_) => true,
+            Pickup::Split(_) => spawner,
+        }
+    }
+
+    /// The color a pickup is drawn in.
+    pub fn color(&self) -> Color {
+        match self {
+            Pickup::Health(_) => Color::rgb(1., 0.35, 0.4),
+            Pickup::Charge => Color::rgb(0.4, 0.75, 1.),
+            Pickup::Points(_) => Color::rgb(1., 0.85, 0.3),
+            Pickup::Split(_) => Color::rgb(0.7, 0.5, 1.),
+        }
+    }
+}
+
+impl Default for Pickup {
+    fn default() -> Pickup {
+        Pickup::Points(50)
+    }
+}
+
+/// A [`Pickup`] bundle.
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pub sprite: SpriteBundle,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub active_events: ActiveEvents,
+    pub collision_groups: CollisionGroups,
+    pub pickup: Pickup,
+}
+
+impl PickupBundle {
+    /// Creates a new `PickupBundle`.
+    pub fn new(pickup: Pickup, translation: Vec3) -> PickupBundle {
+        PickupBundle {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: pickup.color(),
+                    custom_size: Some(Vec2::splat(4.)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(translation),
+                ..Default::default()
+            },
+            collider: Collider::ball(3.),
+            sensor: Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            collision_groups: CollisionGroups::new(
+                physics::COLLISION_GROUP_TRIGGER,
+                physics::COLLISION_GROUP_FRIENDLY,
+            ),
+            pickup,
+        }
+    }
+}
+
+impl Default for PickupBundle {
+    fn default() -> PickupBundle {
+        PickupBundle::new(Pickup::default(), Vec3::ZERO)
+    }
+}
+
+impl LdtkEntity for PickupBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        // the transform is set by bevy_ecs_ldtk
+        PickupBundle::new(Pickup::from_entity_instance(entity_instance), Vec3::ZERO)
+    }
+}
+
+/// Lets something collect [`Pickup`]s by touching them.
+#[derive(Clone, Component, Debug)]
+pub struct PickupCollector {
+    /// How close pickups must be to be pulled in, in world units.
+    pub magnet_radius: f32,
+    /// How fast pickups are pulled in.
+    pub magnet_speed: f32,
+}
+
+impl Default for PickupCollector {
+    fn default() -> PickupCollector {
+        PickupCollector {
+            magnet_radius: 24.,
+            magnet_speed: 160.,
+        }
+    }
+}
+
+/// Something was picked up.
+#[derive(Clone, Debug, Event)]
+pub struct PickupEvent {
+    /// Who picked it up.
+    pub collector: Entity,
+    /// What was picked up.
+    pub pickup: Pickup,
+    /// Where it was picked up.
+    pub position: Vec2,
+}
+
+fn attract_pickups(
+    mut pickup_query: Query<(&GlobalTransform, &mut Transform, &Pickup)>,
+    collector_query: Query<(
+        &GlobalTransform,
+        &PickupCollector,
+        Has<Health>,
+        Has<Charge>,
+        Has<Spawner>,
+    )>,
+    time: Res<Time>,
+) {
+    for (global_transform, mut transform, pickup) in pickup_query.iter_mut() {
+        let position = global_transform.translation().truncate();
+
+        let closest = collector_query
+            .iter()
+            .filter(|(_, _, health, charge, spawner)| pickup.usable_by(*health, *charge, *spawner))
+            .map(|(t, collector, ..)| (t.translation().truncate() - position, collector))
+            .filter(|(offset, collector)| offset.length() <= collector.magnet_radius)
+            .min_by(|(a, _), (b, _)| a.length_squared().total_cmp(&b.length_squared()));
+
+        let Some((offset, collector)) = closest else {
+            continue;
+        };
+
+        // levels are only ever translated, so a world offset is a local one
+        let step = (collector.magnet_speed * time.delta_seconds()).min(offset.length());
+
+        transform.translation += (offset.normalize_or_zero() * step).extend(0.);
+    }
+}
+
+fn collect_pickups(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut pickup_events: EventWriter<PickupEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut score: ResMut<Score>,
+    pickup_query: Query<(&GlobalTransform, &Pickup)>,
+    mut collector_query: Query<
+        (
+            Option<&mut Health>,
+            Option<&mut Charge>,
+            Option<&mut Spawner>,
+        ),
+        With<PickupCollector>,
+    >,
+) {
+    for ev in collision_events.iter() {
+        let CollisionEvent::Started(e1, e2, _) = *ev else {
+            continue;
+        };
+
+        for (pickup, collector) in [(e1, e2), (e2, e1)] {
+            if despawn_queue.contains(pickup) {
+                continue;
+            }
+
+            let Ok((transform, item)) = pickup_query.get(pickup) else {
+                continue;
+            };
+
+            let Ok((health, charge, spawner)) = collector_query.get_mut(collector) else {
+                continue;
+            };
+
+            let position = transform.translation().truncate();
+
+            // a pickup the collector can't use stays where it is
+            match item {
+                Pickup::Health(amount) => {
+                    let Some(mut health) = health else {
+                        continue;
+                    };
+
+                    health.heal(*amount);
+                }
+                Pickup::Charge => {
+                    let Some(mut charge) = charge else {
+                        continue;
+                    };
+
+                    charge.fill();
+                }
+                Pickup::Split(count) => {
+                    let Some(mut spawner) = spawner else {
+                        continue;
+                    };
+
+                    spawner.split = Some(SplitOnAbsorb {
+                        prefab: ProjectilePrefab::QuarterRest {
+                            initial_velocity: Vec2::ZERO,
+                        },
+                        count: *count,
+                        spread: std::f32::consts::FRAC_PI_2,
+                    });
+                }
+                Pickup::Points(points) => {
+                    let points = score.award(*points);
+
+                    score_events.send(ScoreEvent {
+                        points,
+                        combo: score.combo(),
+                        position: Some(position),
+                        kill: None,
+                    });
+                }
+            }
+
+            pickup_events.send(PickupEvent {
+                collector,
+                pickup: item.clone(),
+                position,
+            });
+
+            despawn_queue.push(pickup, DespawnReason::Other("collected"));
+        }
+    }
+}
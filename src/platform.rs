@@ -14,7 +14,13 @@ use bevy_ecs_ldtk::{
     EntityInstance,
 };
 
+use std::time::Duration;
+
+use crate::audio::PlaySound;
+use crate::fx::particles::{ParticleSpec, SpawnParticles};
 use crate::level::Iid;
+use crate::physics;
+use crate::projectile::{HitEvent, ProjectileSystem};
 use crate::{GameAssets, GameState};
 
 /// Platform plugin.
@@ -23,22 +29,43 @@ pub struct MovingPlatformPlugin;
 impl Plugin for MovingPlatformPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ActivateEvent>()
+            .add_event::<DeactivateEvent>()
+            .add_event::<PlatformStateEvent>()
             .register_type::<MovingPlatform>()
             .register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform")
             .add_systems(
                 Update,
                 update_platform_width
                     .run_if(in_state(GameState::InGame))
+                    .run_if(any_with_component::<PlatformWidth>())
                     .in_set(PlatformSystem::UpdateWidth),
             )
             .add_systems(
                 Update,
-                animate_platform_gear.in_set(PlatformSystem::AnimateGear),
+                animate_platform_gear
+                    .run_if(any_with_component::<MovingPlatform>())
+                    .in_set(PlatformSystem::AnimateGear),
             )
             .add_systems(Update, listen_for_activation)
+            .add_systems(
+                Update,
+                jam_platforms
+                    .run_if(in_state(GameState::InGame))
+                    .in_set(PlatformSystem::Jam)
+                    .after(ProjectileSystem::Event),
+            )
+            .add_systems(
+                Update,
+                update_jammed_platforms
+                    .run_if(any_with_component::<Jammed>())
+                    .after(PlatformSystem::Jam)
+                    .before(PlatformSystem::AnimateGear),
+            )
             .add_systems(
                 FixedUpdate,
-                move_platform.in_set(PlatformSystem::MovePlatform),
+                move_platform
+                    .run_if(any_with_component::<MovingPlatform>())
+                    .in_set(PlatformSystem::MovePlatform),
             );
     }
 }
@@ -49,14 +76,58 @@ pub enum PlatformSystem {
     UpdateWidth,
     /// Updates the gear.
     AnimateGear,
+    /// Jams platforms whose gears were hit.
+    Jam,
     /// Actually moves the platform.
     MovePlatform,
 }
 
+/// What a [`MovingPlatform`] is doing.
+///
+/// Whenever this changes, a [`PlatformStateEvent`] is sent, so sounds and
+/// effects can react to platforms starting, arriving and jamming.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PlatformState {
+    /// Sitting at its target.
+    #[default]
+    Idle,
+    /// On the way to its target.
+    Moving,
+    /// Held in place by a projectile in its gear.
+    ///
+    /// See [`Jammed`].
+    Jammed,
+}
+
+/// Sent when the [`PlatformState`] of a platform changes.
+#[derive(Clone, Debug, Event)]
+pub struct PlatformStateEvent {
+    /// The platform.
+    pub entity: Entity,
+    /// What the platform was doing.
+    pub from: PlatformState,
+    /// What the platform is doing now.
+    pub to: PlatformState,
+}
+
+impl PlatformStateEvent {
+    /// Checks if the platform just got to its target.
+    pub fn arrived(&self) -> bool {
+        self.from == PlatformState::Moving && self.to == PlatformState::Idle
+    }
+}
+
 /// An event for activating stuff (mostly platforms).
 #[derive(Event)]
 pub struct ActivateEvent(pub Entity);
 
+/// An event for turning off stuff that an [`ActivateEvent`] turned on, like
+/// when a latch is switched off.
+///
+/// Platforms go back to their start location and doors close.
+#[derive(Event)]
+pub struct DeactivateEvent(pub Entity);
+
 /// A bundle for a moving platform
 ///
 /// Scaling this horizontally will tile it in a special way.
@@ -69,6 +140,7 @@ pub struct MovingPlatformBundle {
     pub collider: Collider,
     pub rigidbody: RigidBody,
     pub moving_platform: MovingPlatform,
+    pub state: PlatformState,
     pub platform_width: PlatformWidth,
     pub accumulated_distance: AccumulatedDistance,
     pub iid: Iid,
@@ -84,6 +156,7 @@ impl Default for MovingPlatformBundle {
             collider: Collider::cuboid(24., 8.),
             rigidbody: RigidBody::KinematicPositionBased,
             moving_platform: Default::default(),
+            state: Default::default(),
             platform_width: PlatformWidth(0),
             accumulated_distance: Default::default(),
             iid: Default::default(),
@@ -131,9 +204,18 @@ impl LdtkEntity for MovingPlatformBundle {
             .clone()
             .map(|e| e as usize);
 
+        let follow_pipes = entity_instance
+            .get_bool_field("FollowPipes")
+            .ok() // may not exist
+            .copied()
+            .unwrap_or(false);
+
         MovingPlatformBundle {
             iid: entity_instance.into(),
-            moving_platform: MovingPlatform::new(start_position, end_position, gear_position),
+            moving_platform: MovingPlatform {
+                follow_pipes,
+                ..MovingPlatform::new(start_position, end_position, gear_position)
+            },
             ..Default::default()
         }
     }
@@ -151,6 +233,14 @@ pub struct MovingPlatform {
     pub start_location: Vec2,
     /// The target location of the moving platform in local space.
     pub end_location: Vec2,
+    /// Corners the platform passes through between the start and end
+    /// locations, in local space.
+    pub waypoints: Vec<Vec2>,
+    /// Whether the waypoints should be found by following the pipes from the
+    /// start to the end location.
+    ///
+    /// See [`crate::level::pipe`].
+    pub follow_pipes: bool,
     /// Target location in between the start and final destination. Must be a
     /// value between `0.` and `1.`.
     pub lerp: f32,
@@ -158,6 +248,8 @@ pub struct MovingPlatform {
     pub gear_location: Option<usize>,
     /// The phase of the gear.
     pub gear_phase: usize,
+    /// How far along the path the platform is, in world units.
+    progress: f32,
 }
 
 impl MovingPlatform {
@@ -175,6 +267,70 @@ impl MovingPlatform {
         }
     }
 
+    /// Iterates over all the points of the platform's path, including the
+    /// start and end locations.
+    pub fn path(&self) -> impl Iterator<Item = Vec2> + '_ {
+        std::iter::once(self.start_location)
+            .chain(self.waypoints.iter().copied())
+            .chain(std::iter::once(self.end_location))
+    }
+
+    /// The total length of the platform's path.
+    pub fn path_length(&self) -> f32 {
+        self.path()
+            .zip(self.path().skip(1))
+            .map(|(a, b)| a.distance(b))
+            .sum()
+    }
+
+    /// Finds the point a distance along the platform's path.
+    pub fn point_along_path(&self, mut distance: f32) -> Vec2 {
+        for (a, b) in self.path().zip(self.path().skip(1)) {
+            let length = a.distance(b);
+
+            if distance <= length {
+                return a.lerp(b, distance / length.max(f32::EPSILON));
+            }
+
+            distance -= length;
+        }
+
+        self.end_location
+    }
+
+    /// Sends the platform to its end location.
+    pub fn go_to_end(&mut self) {
+        self.lerp = 1.;
+    }
+
+    /// Sends the platform back to its start location.
+    pub fn return_to_start(&mut self) {
+        self.lerp = 0.;
+    }
+
+    /// Turns the platform around, or sends it to the far end if it is sitting
+    /// still.
+    pub fn toggle(&mut self) {
+        let target = self.lerp * self.path_length();
+
+        let to_end = if target == self.progress {
+            self.lerp < 0.5
+        } else {
+            target < self.progress
+        };
+
+        self.lerp = if to_end { 1. } else { 0. };
+    }
+
+    /// Stops the platform where it is.
+    pub fn stop(&mut self) {
+        let length = self.path_length();
+
+        if length > 0. {
+            self.lerp = (self.progress / length).clamp(0., 1.);
+        }
+    }
+
     fn gear_sprite_index(&self) -> usize {
         3 + self.gear_phase % 3
     }
@@ -186,9 +342,12 @@ impl Default for MovingPlatform {
             speed: 160.,
             start_location: Vec2::default(),
             end_location: Vec2::default(),
+            waypoints: Vec::new(),
+            follow_pipes: false,
             lerp: 0.,
             gear_location: None,
             gear_phase: 0,
+            progress: 0.,
         }
     }
 }
@@ -204,8 +363,46 @@ pub struct PlatformWidth(usize);
 #[derive(Clone, Component, Debug)]
 struct PlatformGear;
 
+/// A platform that was jammed by a projectile hitting its gear.
+///
+/// The platform will not move until the timer finishes.
+#[derive(Clone, Component, Debug)]
+pub struct Jammed(Timer);
+
+impl Jammed {
+    /// Creates a new `Jammed`.
+    pub fn new(duration: Duration) -> Jammed {
+        Jammed(Timer::new(duration, TimerMode::Once))
+    }
+}
+
+impl Default for Jammed {
+    fn default() -> Jammed {
+        Jammed::new(Duration::from_millis(1500))
+    }
+}
+
+/// Changes the state of a platform, sending a [`PlatformStateEvent`] if it is
+/// different.
+fn set_platform_state(
+    entity: Entity,
+    state: &mut Mut<PlatformState>,
+    to: PlatformState,
+    state_events: &mut EventWriter<PlatformStateEvent>,
+) {
+    // do not trip change detection
+    if **state == to {
+        return;
+    }
+
+    let from = std::mem::replace(&mut **state, to);
+
+    state_events.send(PlatformStateEvent { entity, from, to });
+}
+
 fn listen_for_activation(
     mut activation_events: EventReader<ActivateEvent>,
+    mut deactivation_events: EventReader<DeactivateEvent>,
     mut platforms_query: Query<&mut MovingPlatform>,
 ) {
     for ev in activation_events.iter() {
@@ -215,6 +412,16 @@ fn listen_for_activation(
 
         platform.lerp = 1.;
     }
+
+    // after activations, so turning off wins over an activation from the
+    // same frame
+    for ev in deactivation_events.iter() {
+        let Ok(mut platform) = platforms_query.get_mut(ev.0) else {
+            continue;
+        };
+
+        platform.return_to_start();
+    }
 }
 
 fn update_platform_width(
@@ -284,7 +491,16 @@ fn update_platform_width(
             entity.set_parent(platform_entity);
 
             if gear {
-                entity.insert(PlatformGear);
+                // gear is a sensor so projectiles can jam the platform
+                entity.insert((
+                    PlatformGear,
+                    Collider::cuboid(6., 6.),
+                    Sensor,
+                    CollisionGroups::new(
+                        physics::COLLISION_GROUP_SOLID,
+                        physics::COLLISION_GROUP_PROJECTILE,
+                    ),
+                ));
             }
         }
     }
@@ -303,27 +519,115 @@ fn animate_platform_gear(
     }
 }
 
+fn jam_platforms(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitEvent>,
+    mut play_sound: EventWriter<PlaySound>,
+    mut state_events: EventWriter<PlatformStateEvent>,
+    gear_query: Query<(&Parent, &GlobalTransform), With<PlatformGear>>,
+    mut platforms_query: Query<(&mut MovingPlatform, &mut PlatformState, Option<&mut Jammed>)>,
+    assets: Res<GameAssets>,
+) {
+    for ev in hit_events.iter() {
+        let Ok((parent, gear_transform)) = gear_query.get(ev.entity) else {
+            continue;
+        };
+
+        let Ok((mut platform, mut state, jammed)) = platforms_query.get_mut(parent.get()) else {
+            continue;
+        };
+
+        // kick the gear back a notch so it looks stuck
+        platform.gear_phase += 2;
+
+        match jammed {
+            // jamming a jammed platform just holds it longer
+            Some(mut jammed) => jammed.0.reset(),
+            None => {
+                commands.entity(parent.get()).insert(Jammed::default());
+            }
+        }
+
+        set_platform_state(
+            parent.get(),
+            &mut state,
+            PlatformState::Jammed,
+            &mut state_events,
+        );
+
+        // sparks
+        let mut location = gear_transform.translation();
+        location.z = 100.;
+
+        commands.add(SpawnParticles::new(
+            ParticleSpec::atlas(
+                assets.projectile_sheet.clone(),
+                18..20,
+                Duration::from_millis(100),
+                Color::rgb(1., 0.85, 0.3),
+            ),
+            location,
+        ));
+
+        play_sound.send(PlaySound::new(assets.sound_ratchet.clone()));
+    }
+}
+
+fn update_jammed_platforms(
+    mut commands: Commands,
+    mut state_events: EventWriter<PlatformStateEvent>,
+    mut platforms_query: Query<(Entity, &mut Jammed, &mut PlatformState)>,
+    time: Res<Time>,
+) {
+    for (entity, mut jammed, mut state) in platforms_query.iter_mut() {
+        jammed.0.tick(time.delta());
+
+        if jammed.0.finished() {
+            commands.entity(entity).remove::<Jammed>();
+
+            // `move_platform` picks it back up if it still has somewhere to go
+            set_platform_state(entity, &mut state, PlatformState::Idle, &mut state_events);
+        }
+    }
+}
+
 fn move_platform(
-    mut platforms_query: Query<(
-        &mut Transform,
-        &mut MovingPlatform,
-        &mut AccumulatedDistance,
-    )>,
+    mut state_events: EventWriter<PlatformStateEvent>,
+    mut platforms_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut MovingPlatform,
+            &mut AccumulatedDistance,
+            &mut PlatformState,
+        ),
+        Without<Jammed>,
+    >,
     time: Res<FixedTime>,
 ) {
-    for (mut transform, mut platform, mut acc) in platforms_query.iter_mut() {
-        let mut current = transform.translation.truncate();
-        let target = platform
-            .start_location
-            .lerp(platform.end_location, platform.lerp);
-
-        let dist = move_toward(
-            &mut current,
-            target,
-            platform.speed * time.period.as_secs_f32(),
-        );
+    for (entity, mut transform, mut platform, mut acc, mut state) in platforms_query.iter_mut() {
+        let target = platform.lerp * platform.path_length();
+        let max_movement = platform.speed * time.period.as_secs_f32();
+
+        let movement = (target - platform.progress).clamp(-max_movement, max_movement);
+        let dist = movement.abs();
+
+        // land exactly on the target so the platform settles
+        if dist < max_movement {
+            platform.progress = target;
+        } else {
+            platform.progress += movement;
+        }
 
-        transform.translation = current.extend(2.);
+        let to = if dist > 0. {
+            PlatformState::Moving
+        } else {
+            PlatformState::Idle
+        };
+
+        set_platform_state(entity, &mut state, to, &mut state_events);
+
+        transform.translation = platform.point_along_path(platform.progress).extend(2.);
 
         acc.0 += dist;
 
@@ -334,15 +638,3 @@ fn move_platform(
         platform.gear_phase += phase_change as usize;
     }
 }
-
-fn move_toward(current: &mut Vec2, target: Vec2, max_movement: f32) -> f32 {
-    let difference = target - *current;
-
-    if difference.length_squared() > max_movement * max_movement {
-        *current += difference.normalize() * max_movement;
-        max_movement
-    } else {
-        *current = target;
-        difference.length()
-    }
-}
@@ -0,0 +1,128 @@
+//! Charged shots.
+//!
+//! Tapping shoot fires a normal shot through the player's
+//! [`Spawner`](crate::projectile::spawner::Spawner). Holding it charges up a
+//! bigger shot that fires on release.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use super::controller::{Controller, ControllerOptions, ControllerSystem};
+use crate::enemy::Hostility;
+use crate::projectile::prefab::{CreateProjectile, ProjectilePrefab};
+
+/// Charge shot plugin.
+pub struct ChargeShotPlugin;
+
+impl Plugin for ChargeShotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            charge_shots
+                .run_if(any_with_component::<ChargeShot>())
+                .after(ControllerSystem::ScanInput),
+        );
+    }
+}
+
+/// How charged up a [`ChargeShot`] is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChargeTier {
+    /// Not charged; only the normal shot fires.
+    #[default]
+    None,
+    /// A pair of eighth notes.
+    Eighth,
+    /// A whole note that pierces and presses switches.
+    Whole,
+}
+
+impl ChargeTier {
+    /// The prefab fired at this tier, if any, going in a direction.
+    ///
+    /// `speed` is the speed of a normal shot; charged shots are slower.
+    pub fn prefab(self, direction: Vec2, speed: f32) -> Option<ProjectilePrefab> {
+        match self {
+            ChargeTier::None => None,
+            ChargeTier::Eighth => Some(ProjectilePrefab::EighthNotes {
+                initial_velocity: direction * speed * 0.75,
+            }),
+            ChargeTier::Whole => Some(ProjectilePrefab::WholeNote {
+                initial_velocity: direction * speed * 0.5,
+            }),
+        }
+    }
+}
+
+/// Lets a player charge up a shot by holding the shoot button.
+#[derive(Clone, Component, Debug)]
+pub struct ChargeShot {
+    /// How long the button must be held for each tier after
+    /// [`ChargeTier::None`].
+    pub tiers: [Duration; 2],
+    held: Duration,
+}
+
+impl ChargeShot {
+    /// The tier the shot would fire at if released now.
+    pub fn tier(&self) -> ChargeTier {
+        if self.held >= self.tiers[1] {
+            ChargeTier::Whole
+        } else if self.held >= self.tiers[0] {
+            ChargeTier::Eighth
+        } else {
+            ChargeTier::None
+        }
+    }
+}
+
+impl Default for ChargeShot {
+    fn default() -> ChargeShot {
+        ChargeShot {
+            tiers: [Duration::from_millis(500), Duration::from_millis(1200)],
+            held: Duration::ZERO,
+        }
+    }
+}
+
+fn charge_shots(
+    mut commands: Commands,
+    mut query: Query<(
+        &GlobalTransform,
+        &Controller,
+        &ControllerOptions,
+        &mut ChargeShot,
+        Option<&Hostility>,
+    )>,
+    time: Res<Time>,
+) {
+    for (transform, controller, options, mut charge_shot, hostility) in query.iter_mut() {
+        if !options.enabled {
+            charge_shot.held = Duration::ZERO;
+            continue;
+        }
+
+        if controller.shoot_held() {
+            charge_shot.held += time.delta();
+            continue;
+        }
+
+        if charge_shot.held == Duration::ZERO {
+            continue;
+        }
+
+        let prefab = charge_shot
+            .tier()
+            .prefab(controller.shoot_dir(), options.projectile_speed);
+
+        charge_shot.held = Duration::ZERO;
+
+        if let Some(prefab) = prefab {
+            commands.add(
+                CreateProjectile::new(prefab, transform.translation())
+                    .hostility(hostility.copied().unwrap_or_default()),
+            );
+        }
+    }
+}
@@ -1,11 +1,17 @@
 //! Player physics controller.
+//!
+//! Input is scanned in `PreUpdate`, as soon as bevy has read it for the frame,
+//! and movement is applied in `PostUpdate` right before the physics step, so
+//! every step sees the input of the frame it runs in exactly once.
 
 use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent};
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 
 use bevy_rapier2d::prelude::*;
 
 use crate::camera::{cursor::CursorWorldPosition, PlayerCamera};
+use crate::carry::Carrier;
 use crate::physics::{Grounded, PhysicsSet};
 use crate::projectile::spawner::{SpawnProjectile, Spawner, SpawnerSystem};
 
@@ -21,26 +27,30 @@ impl Plugin for ControllerPlugin {
             enable_physics_for_controller,
         )
         .add_systems(
-            Update,
-            tick_coyote_jump_timer.before(ControllerSystem::Apply),
-        )
-        .add_systems(
-            Update,
-            detect_gamepad.in_set(ControllerSystem::DetectGamepad),
+            PreUpdate,
+            detect_gamepad
+                .in_set(ControllerSystem::DetectGamepad)
+                .after(InputSystem),
         )
         .add_systems(
-            Update,
+            PreUpdate,
             (clear_controller, scan_input)
                 .chain()
-                .in_set(ControllerSystem::ScanInput),
+                .in_set(ControllerSystem::ScanInput)
+                .after(ControllerSystem::DetectGamepad),
         )
         .add_systems(
-            Update,
-            (apply_projectiles, apply_movement)
+            PostUpdate,
+            (tick_coyote_jump_timer, apply_movement)
                 .chain()
                 .in_set(ControllerSystem::Apply)
-                .after(ControllerSystem::ScanInput)
                 .after(PhysicsSet::CheckGrounded)
+                .before(bevy_rapier2d::plugin::PhysicsSet::SyncBackend),
+        )
+        .add_systems(
+            Update,
+            apply_projectiles
+                .in_set(ControllerSystem::Apply)
                 .before(SpawnerSystem::Spawn),
         );
     }
@@ -48,8 +58,12 @@ impl Plugin for ControllerPlugin {
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, SystemSet)]
 pub enum ControllerSystem {
+    /// Gamepads are given to controllers, in `PreUpdate`.
     DetectGamepad,
+    /// Input is read into [`Controller`]s, in `PreUpdate`.
     ScanInput,
+    /// Controllers fire in `Update` and move the player in `PostUpdate`,
+    /// before the physics step.
     Apply,
 }
 
@@ -103,7 +117,9 @@ pub struct Controller {
     jump: bool,
     jump_buffer: Timer,
     shoot: bool,
+    shoot_held: bool,
     shoot_dir: Vec2,
+    interact: bool,
 }
 
 impl Controller {
@@ -112,6 +128,26 @@ impl Controller {
         self.shoot_dir
     }
 
+    /// Checks if the player is holding the shoot button.
+    pub fn shoot_held(&self) -> bool {
+        self.shoot_held
+    }
+
+    /// Checks if the player pressed the interact button this frame.
+    pub fn interact(&self) -> bool {
+        self.interact
+    }
+
+    /// Checks if the player pressed the shoot button this frame.
+    pub fn shoot(&self) -> bool {
+        self.shoot
+    }
+
+    /// Checks if the player pressed the jump button this frame.
+    pub fn jump(&self) -> bool {
+        self.jump
+    }
+
     /// Sets the jump timer.
     ///
     /// This happens when the user presses a button to jump. Instead of a dumb
@@ -125,6 +161,17 @@ impl Controller {
     pub fn buffered_jump(&self) -> bool {
         !self.jump_buffer.finished()
     }
+
+    /// Lets go of everything, as if nothing was pressed this frame.
+    ///
+    /// The jump buffer and aim are kept.
+    pub fn release(&mut self) {
+        self.jump = false;
+        self.x_movement = 0.0;
+        self.shoot = false;
+        self.shoot_held = false;
+        self.interact = false;
+    }
 }
 
 impl Default for Controller {
@@ -134,7 +181,9 @@ impl Default for Controller {
             jump: false,
             jump_buffer: Timer::default(),
             shoot: false,
+            shoot_held: false,
             shoot_dir: Vec2::X,
+            interact: false,
         }
     }
 }
@@ -226,7 +275,12 @@ fn detect_gamepad(
                     if use_gamepad.0.is_none() {
                         // add gamepad
                         use_gamepad.0 = Some(ev.gamepad);
-                        bevy::log::info!("connected gamepad {:?} to player {:?}", ev.gamepad, name);
+                        crate::game_info!(
+                            Player,
+                            "connected gamepad {:?} to player {:?}",
+                            ev.gamepad,
+                            name
+                        );
                     }
                 }
             }
@@ -236,7 +290,7 @@ fn detect_gamepad(
                     if use_gamepad.0 == Some(ev.gamepad) {
                         // add gamepad
                         use_gamepad.0 = None;
-                        bevy::log::info!("connected gamepad from player {:?}", name);
+                        crate::game_info!(Player, "disconnected gamepad from player {:?}", name);
                     }
                 }
             }
@@ -305,12 +359,27 @@ fn scan_input(
 
         // shoot button
         controller.shoot |= mouse.just_pressed(MouseButton::Left);
+        controller.shoot_held |= mouse.pressed(MouseButton::Left);
 
         if let Some(gamepad) = gamepad {
             controller.shoot |= gamepad_button.just_pressed(GamepadButton {
                 gamepad,
                 button_type: GamepadButtonType::RightTrigger,
             });
+            controller.shoot_held |= gamepad_button.pressed(GamepadButton {
+                gamepad,
+                button_type: GamepadButtonType::RightTrigger,
+            });
+        }
+
+        // interact button
+        controller.interact |= keyboard.just_pressed(KeyCode::E);
+
+        if let Some(gamepad) = gamepad {
+            controller.interact |= gamepad_button.just_pressed(GamepadButton {
+                gamepad,
+                button_type: GamepadButtonType::West,
+            });
         }
 
         // aim
@@ -344,9 +413,7 @@ fn scan_input(
 fn clear_controller(mut query: Query<&mut Controller>, time: Res<Time>) {
     for mut controller in query.iter_mut() {
         controller.jump_buffer.tick(time.delta());
-        controller.jump = false;
-        controller.x_movement = 0.0;
-        controller.shoot = false;
+        controller.release();
     }
 }
 
@@ -374,10 +441,12 @@ fn apply_movement(
         &Grounded,
         &mut CoyoteJump,
         &mut Velocity,
+        Option<&Carrier>,
     )>,
     physics_options: Res<RapierConfiguration>,
 ) {
-    for (controller, options, grounded, mut coyote_jump, mut velocity) in query.iter_mut() {
+    for (controller, options, grounded, mut coyote_jump, mut velocity, carrier) in query.iter_mut()
+    {
         if !options.enabled {
             continue;
         }
@@ -388,6 +457,10 @@ fn apply_movement(
             ..
         } = *options;
 
+        // carrying things slows the player down
+        let burden = carrier.map(|c| c.burden()).unwrap_or(1.);
+        let max_speed = max_speed * burden;
+
         move_toward(
             &mut velocity.linvel.x,
             controller.x_movement * max_speed,
@@ -400,7 +473,8 @@ fn apply_movement(
         // apply jump
         if jump {
             coyote_jump.lock();
-            velocity.linvel.y = options.initial_jump_velocity(physics_options.gravity.y);
+            velocity.linvel.y =
+                options.initial_jump_velocity(physics_options.gravity.y) * burden.sqrt();
         }
     }
 }
@@ -0,0 +1,103 @@
+//! Footsteps and landings.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use crate::audio::PlaySound;
+use crate::fx::particles::{ParticleSpec, SpawnParticles};
+use crate::level::surface::{Surface, Surfaces};
+use crate::physics::Grounded;
+use crate::{GameAssets, GameState};
+
+/// Footstep plugin.
+pub struct FootstepPlugin;
+
+impl Plugin for FootstepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            play_footsteps
+                .run_if(in_state(GameState::InGame))
+                .run_if(any_with_component::<Footsteps>()),
+        );
+    }
+}
+
+/// Plays a sound and kicks up dust for each step an entity takes on the
+/// ground, depending on the [`Surface`] under it.
+#[derive(Clone, Component, Debug)]
+pub struct Footsteps {
+    /// How far apart steps are.
+    pub stride: f32,
+    /// How far below the entity its feet are.
+    pub foot_offset: f32,
+    travelled: f32,
+    fall_speed: f32,
+}
+
+impl Default for Footsteps {
+    fn default() -> Footsteps {
+        Footsteps {
+            stride: 12.,
+            foot_offset: 4.,
+            travelled: 0.,
+            fall_speed: 0.,
+        }
+    }
+}
+
+/// How fast an entity must fall to kick up dust when it lands.
+const LANDING_DUST_SPEED: f32 = 96.;
+
+fn play_footsteps(
+    mut commands: Commands,
+    mut query: Query<(&GlobalTransform, &Velocity, Ref<Grounded>, &mut Footsteps)>,
+    mut play_sound_events: EventWriter<PlaySound>,
+    surfaces: Surfaces,
+    assets: Res<GameAssets>,
+    time: Res<Time>,
+) {
+    for (transform, velocity, grounded, mut footsteps) in query.iter_mut() {
+        if !grounded.is_grounded() {
+            footsteps.fall_speed = footsteps.fall_speed.max(-velocity.linvel.y);
+            footsteps.travelled = 0.;
+            continue;
+        }
+
+        let feet = transform.translation().truncate() - Vec2::Y * footsteps.foot_offset;
+        let surface = surfaces.at(feet).unwrap_or_default();
+
+        if grounded.is_changed() && !grounded.is_added() {
+            let fall_speed = std::mem::take(&mut footsteps.fall_speed);
+
+            play_sound_events.send(step_sound(&assets, surface).volume(surface.volume() * 1.5));
+
+            if fall_speed >= LANDING_DUST_SPEED {
+                commands.add(dust(feet, surface).count(5));
+            }
+
+            continue;
+        }
+
+        footsteps.travelled += velocity.linvel.x.abs() * time.delta_seconds();
+
+        if footsteps.travelled >= footsteps.stride {
+            footsteps.travelled %= footsteps.stride;
+
+            play_sound_events.send(step_sound(&assets, surface));
+            commands.add(dust(feet, surface).count(2));
+        }
+    }
+}
+
+fn dust(feet: Vec2, surface: Surface) -> SpawnParticles {
+    // above the tilemap, below projectiles
+    SpawnParticles::new(ParticleSpec::dust(surface.dust_color()), feet.extend(50.))
+}
+
+fn step_sound(assets: &GameAssets, surface: Surface) -> PlaySound {
+    PlaySound::new(assets.sound_footstep.clone())
+        .volume(surface.volume())
+        .speed(surface.pitch())
+}
@@ -0,0 +1,186 @@
+//! Measuring input latency.
+//!
+//! Only built with the `devtools` feature. `F2` shows the "Input Latency"
+//! window, which times every jump and shot of the [`LocalPlayer`] from the
+//! start of the frame it was pressed in to the physics step that moved the
+//! player, and how many frames late that step was.
+
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use bevy_rapier2d::prelude::*;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::controller::{Controller, ControllerSystem};
+use super::LocalPlayer;
+
+/// Input latency plugin.
+pub struct InputLatencyPlugin;
+
+impl Plugin for InputLatencyPlugin {
+    fn build(&self, app: &mut App) {
+        use bevy_inspector_egui::bevy_egui::EguiPlugin;
+
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<InputLatency>()
+            .add_systems(First, start_latency_frame)
+            .add_systems(
+                PreUpdate,
+                capture_presses.after(ControllerSystem::ScanInput),
+            )
+            .add_systems(
+                PostUpdate,
+                measure_presses.after(PhysicsSet::StepSimulation),
+            )
+            .add_systems(Update, (toggle_latency_window, latency_window));
+    }
+}
+
+/// Input latency measurements.
+#[derive(Clone, Debug, Resource)]
+pub struct InputLatency {
+    /// Whether the window is shown.
+    pub visible: bool,
+    samples: VecDeque<LatencySample>,
+    pending: Option<(Instant, u64)>,
+    frame_start: Option<Instant>,
+    frame: u64,
+}
+
+/// A single measured press.
+#[derive(Clone, Copy, Debug)]
+pub struct LatencySample {
+    /// The time from the start of the frame to the physics step.
+    pub latency: Duration,
+    /// How many frames after the press the physics step was.
+    pub frames: u64,
+}
+
+impl InputLatency {
+    /// The key that shows and hides the window.
+    pub const TOGGLE_KEY: KeyCode = KeyCode::F2;
+    /// How many samples are kept.
+    pub const MAX_SAMPLES: usize = 60;
+
+    /// The samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &LatencySample> {
+        self.samples.iter()
+    }
+
+    /// The average latency of the samples.
+    pub fn average(&self) -> Option<Duration> {
+        let total = self.samples.iter().map(|s| s.latency).sum::<Duration>();
+
+        (!self.samples.is_empty()).then(|| total / self.samples.len() as u32)
+    }
+
+    /// The worst latency of the samples.
+    pub fn worst(&self) -> Option<Duration> {
+        self.samples.iter().map(|s| s.latency).max()
+    }
+}
+
+impl Default for InputLatency {
+    fn default() -> InputLatency {
+        InputLatency {
+            visible: false,
+            samples: VecDeque::new(),
+            pending: None,
+            frame_start: None,
+            frame: 0,
+        }
+    }
+}
+
+fn start_latency_frame(mut latency: ResMut<InputLatency>) {
+    latency.frame_start = Some(Instant::now());
+    latency.frame += 1;
+}
+
+fn capture_presses(
+    mut latency: ResMut<InputLatency>,
+    controller_query: Query<&Controller, With<LocalPlayer>>,
+) {
+    // keep the oldest press until it is measured
+    if latency.pending.is_some() {
+        return;
+    }
+
+    let pressed = controller_query
+        .iter()
+        .any(|controller| controller.jump() || controller.shoot());
+
+    if let (true, Some(frame_start)) = (pressed, latency.frame_start) {
+        latency.pending = Some((frame_start, latency.frame));
+    }
+}
+
+fn measure_presses(
+    mut latency: ResMut<InputLatency>,
+    controller_query: Query<&Controller, With<LocalPlayer>>,
+) {
+    let Some((pressed_at, frame)) = latency.pending else {
+        return;
+    };
+
+    // jumps are measured once the physics tick has seen them
+    if controller_query.iter().any(|controller| controller.jump()) {
+        return;
+    }
+
+    latency.pending = None;
+
+    let sample = LatencySample {
+        latency: pressed_at.elapsed(),
+        frames: latency.frame - frame,
+    };
+
+    latency.samples.push_back(sample);
+
+    while latency.samples.len() > InputLatency::MAX_SAMPLES {
+        latency.samples.pop_front();
+    }
+}
+
+fn toggle_latency_window(keyboard: Res<Input<KeyCode>>, mut latency: ResMut<InputLatency>) {
+    if keyboard.just_pressed(InputLatency::TOGGLE_KEY) {
+        latency.visible = !latency.visible;
+    }
+}
+
+fn latency_window(
+    mut contexts: bevy_inspector_egui::bevy_egui::EguiContexts,
+    latency: Res<InputLatency>,
+) {
+    use bevy_inspector_egui::egui;
+
+    if !latency.visible {
+        return;
+    }
+
+    let ms = |d: Option<Duration>| {
+        d.map(|d| format!("{:.2} ms", d.as_secs_f64() * 1000.))
+            .unwrap_or_else(|| "-".into())
+    };
+
+    egui::Window::new("Input Latency").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!(
+            "last {}",
+            ms(latency.samples.back().map(|s| s.latency))
+        ));
+        ui.label(format!("average {}", ms(latency.average())));
+        ui.label(format!("worst {}", ms(latency.worst())));
+
+        let late = latency.samples.iter().filter(|s| s.frames > 0).count();
+        ui.label(format!(
+            "{} of {} presses a frame late or more",
+            late,
+            latency.samples.len()
+        ));
+    });
+}
@@ -1,6 +1,10 @@
 //! Player things.
 
+pub mod charge_shot;
 pub mod controller;
+pub mod footsteps;
+#[cfg(feature = "devtools")]
+pub mod latency;
 pub mod respawn;
 
 use bevy::prelude::*;
@@ -10,12 +14,19 @@ use bevy_rapier2d::prelude::*;
 use std::time::Duration;
 
 use crate::{
+    carry::Carrier,
+    combat::{CombatSystem, DamageEvent},
+    despawn::{DespawnQueue, DespawnReason},
+    enemy::Hostility,
+    fx::particles::{ParticleSpec, SpawnParticles},
     physics::{self, Grounded},
+    pickup::PickupCollector,
     projectile::spawner::{Charge, Spawner},
-    enemy::Hostility,
     GameAssets, GameState,
 };
+use charge_shot::ChargeShot;
 use controller::{ControllerBundle, ControllerOptions, CoyoteJump, UseGamepad};
+use footsteps::Footsteps;
 use respawn::{Respawn, RespawnSystem, WorldRespawn};
 
 /// A player plugin.
@@ -27,7 +38,15 @@ impl Plugin for PlayerPlugin {
             .add_systems(
                 Update,
                 detect_player_death
-                    .after(RespawnSystem::Respawn),
+                    .after(RespawnSystem::Respawn)
+                    .after(CombatSystem::Damage),
+            )
+            .add_systems(
+                Update,
+                (
+                    finish_player_deaths.run_if(any_with_component::<PlayerDeath>()),
+                    expire_corpses.run_if(any_with_component::<Corpse>()),
+                ),
             );
     }
 }
@@ -39,6 +58,33 @@ impl Plugin for PlayerPlugin {
 #[derive(Clone, Component, Default, Debug)]
 pub struct LocalPlayer;
 
+/// A player that is dying.
+///
+/// The player is hidden and locked out of their controller while their sprite
+/// tumbles away as a [`Corpse`]. When the timer is done, the [`WorldRespawn`]
+/// curtain starts to fall.
+#[derive(Clone, Component, Debug)]
+pub struct PlayerDeath(Timer);
+
+impl PlayerDeath {
+    /// How long the corpse tumbles before the curtain starts to fall.
+    pub const DELAY: Duration = Duration::from_millis(250);
+    /// How many notes the player pops into.
+    pub const PARTICLES: u32 = 8;
+}
+
+impl Default for PlayerDeath {
+    fn default() -> PlayerDeath {
+        PlayerDeath(Timer::new(PlayerDeath::DELAY, TimerMode::Once))
+    }
+}
+
+/// The tumbling sprite of a dead player.
+///
+/// It goes away once the curtain has closed over it.
+#[derive(Clone, Component, Debug)]
+pub struct Corpse(Timer);
+
 /// A startup system that spawns a default player in.
 fn spawn_player(mut commands: Commands, assets: Res<GameAssets>) {
     commands
@@ -79,6 +125,10 @@ fn spawn_player(mut commands: Commands, assets: Res<GameAssets>) {
         .insert((
             Hostility::Friendly,
             ActiveEvents::COLLISION_EVENTS,
+            Carrier::default(),
+            Footsteps::default(),
+            ChargeShot::default(),
+            PickupCollector::default(),
         ))
         .with_children(|parent| {
             parent.spawn((SpriteSheetBundle {
@@ -91,38 +141,106 @@ fn spawn_player(mut commands: Commands, assets: Res<GameAssets>) {
 }
 
 fn detect_player_death(
-    mut collision_events: EventReader<CollisionEvent>,
-    mut player_query: Query<(&mut Visibility, &mut ControllerOptions), With<LocalPlayer>>,
-    mut world_respawn: ResMut<WorldRespawn>,
-    subject_query: Query<&Hostility>,
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut player_query: Query<
+        (&GlobalTransform, &mut Visibility, &mut ControllerOptions),
+        (With<LocalPlayer>, Without<PlayerDeath>),
+    >,
+    source_query: Query<&GlobalTransform>,
+    world_respawn: Res<WorldRespawn>,
+    assets: Res<GameAssets>,
 ) {
-    for ev in collision_events.iter() {
-        let CollisionEvent::Started(c1, c2, _) = ev else {
+    for ev in damage_events.iter() {
+        let Ok((transform, mut player_visibility, mut controller)) =
+            player_query.get_mut(ev.target)
+        else {
             continue;
         };
 
-        // find player
-        let ((mut player_visibility, mut controller), subject) = {
-            if let Ok(player) = player_query.get_mut(*c1) {
-                (player, *c2)
-            } else if let Ok(player) = player_query.get_mut(*c2) {
-                (player, *c1)
-            } else {
-                continue;
-            }
-        };
+        // kill player
+        *player_visibility = Visibility::Hidden;
+        controller.enabled = false;
+        commands.entity(ev.target).insert(PlayerDeath::default());
 
-        // find subject
-        let Ok(subject_hostility) = subject_query.get(subject) else {
-            continue;
-        };
+        let position = transform.translation();
 
-        if *subject_hostility == Hostility::Hostile {
-            // kill player
-            *player_visibility = Visibility::Hidden;
-            controller.enabled = false;
+        // tumble away from whatever did it
+        let direction = ev
+            .source
+            .and_then(|source| source_query.get(source).ok())
+            .map(|source| (position.x - source.translation().x).signum())
+            .filter(|direction| *direction != 0.)
+            .unwrap_or(1.);
+
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: assets.player_sheet.clone(),
+                sprite: TextureAtlasSprite::new(0),
+                transform: Transform::from_translation(position + Vec3::new(0., 4., 0.)),
+                ..Default::default()
+            },
+            RigidBody::Dynamic,
+            Collider::ball(3.),
+            CollisionGroups::new(
+                physics::COLLISION_GROUP_DEBRIS,
+                physics::COLLISION_GROUP_SOLID,
+            ),
+            Velocity {
+                linvel: Vec2::new(direction * 48., 160.),
+                angvel: direction * -12.,
+            },
+            Corpse(Timer::new(
+                PlayerDeath::DELAY + world_respawn.duration,
+                TimerMode::Once,
+            )),
+        ));
+
+        let color = Hostility::Friendly.color();
+        let spec = ParticleSpec::atlas(
+            assets.projectile_sheet.clone(),
+            0..1,
+            Duration::from_millis(500),
+            color,
+        )
+        .lifetime(0.3..0.6)
+        .velocity(Vec2::new(-64., 16.), Vec2::new(64., 96.))
+        .gravity(240.)
+        .scale(0.5)
+        .fade_to(color.with_a(0.));
+
+        commands.add(SpawnParticles::new(spec, position).count(PlayerDeath::PARTICLES));
+
+        crate::game_info!(Player, "player died");
+    }
+}
+
+fn finish_player_deaths(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut PlayerDeath)>,
+    mut world_respawn: ResMut<WorldRespawn>,
+    time: Res<Time>,
+) {
+    for (entity, mut death) in player_query.iter_mut() {
+        death.0.tick(time.delta());
+
+        if death.0.finished() {
+            commands.entity(entity).remove::<PlayerDeath>();
             world_respawn.start_respawn();
         }
     }
 }
 
+fn expire_corpses(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut corpse_query: Query<(Entity, &mut Corpse)>,
+    time: Res<Time>,
+) {
+    for (entity, mut corpse) in corpse_query.iter_mut() {
+        corpse.0.tick(time.delta());
+
+        if corpse.0.finished() && !despawn_queue.contains(entity) {
+            despawn_queue.push(entity, DespawnReason::Expired);
+        }
+    }
+}
@@ -3,7 +3,7 @@
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
-use bevy_ecs_ldtk::{app::LdtkEntityAppExt, LdtkEntity, LdtkLevel, LevelSelection};
+use bevy_ecs_ldtk::{app::LdtkEntityAppExt, LdtkEntity, LdtkLevel};
 
 use std::collections::HashMap;
 use std::time::Duration;
@@ -11,6 +11,9 @@ use std::time::Duration;
 use super::{LocalPlayer, controller::ControllerOptions};
 
 use crate::{GameState, GameAssets, spawn_world};
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::{Enemy, EnemyDiedEvent, EnemySystem, RespawnPolicy};
+use crate::level::{CurrentLevel, Iid};
 
 pub struct RespawnPlugin;
 
@@ -18,18 +21,23 @@ impl Plugin for RespawnPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CheckpointMap>()
             .init_resource::<WorldRespawn>()
-            .add_systems(
-                Update,
-                world_respawn
-                    .run_if(in_state(GameState::InGame)),
-            )
+            .init_resource::<FallenEnemies>()
+            .add_systems(Update, world_respawn.run_if(in_state(GameState::InGame)))
             .add_systems(
                 Update,
                 respawn
                     .run_if(in_state(GameState::InGame))
                     .in_set(RespawnSystem::Respawn),
             )
-            .add_systems(Update, update_checkpoints);
+            .add_systems(Update, update_checkpoints)
+            .add_systems(
+                Update,
+                (
+                    record_fallen_enemies.after(EnemySystem::Despawn),
+                    checkpoint_fallen_enemies.run_if(resource_changed::<CurrentLevel>()),
+                    keep_fallen_enemies_dead,
+                ),
+            );
     }
 
     fn finish(&self, app: &mut App) {
@@ -107,6 +115,71 @@ impl Default for WorldRespawn {
     }
 }
 
+/// Enemies placed in levels that were killed and stay dead, by their instance
+/// identifiers.
+///
+/// The world is spawned again from the level data when the player respawns,
+/// which brings back every enemy where it started. Enemies found here are
+/// despawned again as they are spawned, as their [`RespawnPolicy`] says.
+#[derive(Clone, Default, Debug, Resource)]
+pub struct FallenEnemies {
+    map: HashMap<String, FallenEnemy>,
+}
+
+/// An enemy in [`FallenEnemies`].
+#[derive(Clone, Debug)]
+pub struct FallenEnemy {
+    /// What brings the enemy back.
+    pub policy: RespawnPolicy,
+    /// Whether the player has reached a checkpoint since the enemy was killed.
+    pub checkpointed: bool,
+}
+
+impl FallenEnemies {
+    /// Gets a fallen enemy by its instance identifier.
+    pub fn get(&self, iid: &str) -> Option<&FallenEnemy> {
+        self.map.get(iid)
+    }
+
+    /// Checks if an enemy stays dead.
+    pub fn stays_dead(&self, iid: &str) -> bool {
+        self.map.contains_key(iid)
+    }
+
+    /// Records that an enemy was killed.
+    ///
+    /// Enemies that always come back aren't kept.
+    pub fn fall(&mut self, iid: impl Into<String>, policy: RespawnPolicy) {
+        if policy == RespawnPolicy::Always {
+            return;
+        }
+
+        self.map.insert(
+            iid.into(),
+            FallenEnemy {
+                policy,
+                checkpointed: false,
+            },
+        );
+    }
+
+    /// Marks every fallen enemy as killed before the current checkpoint.
+    pub fn checkpoint(&mut self) {
+        for fallen in self.map.values_mut() {
+            fallen.checkpointed = true;
+        }
+    }
+
+    /// Forgets the enemies that come back when the world respawns.
+    pub fn restore(&mut self) {
+        self.map.retain(|_, fallen| match fallen.policy {
+            RespawnPolicy::Never => true,
+            RespawnPolicy::OnCheckpoint => fallen.checkpointed,
+            RespawnPolicy::Always => false,
+        });
+    }
+}
+
 /// A marker component for a checkpoint, where a player will respawn when they
 /// die.
 #[derive(Clone, Component, Default, Debug)]
@@ -122,21 +195,16 @@ pub struct CheckpointMap {
 #[derive(SystemParam)]
 pub struct CurrentCheckpoint<'w, 's> {
     checkpoints: Res<'w, CheckpointMap>,
-    level_selection: Res<'w, LevelSelection>,
+    current_level: Res<'w, CurrentLevel>,
     checkpoint_query: Query<'w, 's, &'static GlobalTransform, With<Checkpoint>>,
 }
 
 impl<'w, 's> CurrentCheckpoint<'w, 's> {
     /// Gets the current checkpoint's transform.
     pub fn position(&self) -> Option<&GlobalTransform> {
-        let level = match &*self.level_selection {
-            LevelSelection::Identifier(level) => level,
-            _ => todo!("no support for other level selections"),
-        };
-
         self.checkpoints
             .map
-            .get(level)
+            .get(&self.current_level.0)
             .and_then(|c| self.checkpoint_query.get(*c).ok())
     }
 }
@@ -168,9 +236,38 @@ fn update_checkpoints(
     }
 }
 
+fn record_fallen_enemies(
+    mut fallen_enemies: ResMut<FallenEnemies>,
+    mut died_events: EventReader<EnemyDiedEvent>,
+) {
+    for event in died_events.iter() {
+        if let Some(iid) = &event.iid {
+            fallen_enemies.fall(iid.clone(), event.respawn);
+        }
+    }
+}
+
+fn checkpoint_fallen_enemies(mut fallen_enemies: ResMut<FallenEnemies>) {
+    fallen_enemies.checkpoint();
+}
+
+fn keep_fallen_enemies_dead(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    fallen_enemies: Res<FallenEnemies>,
+    enemy_query: Query<(Entity, &Iid), Added<Enemy>>,
+) {
+    for (entity, iid) in enemy_query.iter() {
+        if fallen_enemies.stays_dead(&iid.0) {
+            despawn_queue.push(entity, DespawnReason::Other("stays dead"));
+        }
+    }
+}
+
 fn world_respawn(
     mut commands: Commands,
+    mut despawn_queue: ResMut<DespawnQueue>,
     mut world_respawn: ResMut<WorldRespawn>,
+    mut fallen_enemies: ResMut<FallenEnemies>,
     game_world_query: Query<Entity, With<crate::GameWorld>>,
     mut curtain_query: Query<&mut crate::ui::Curtain>,
     mut respawn_timer_query: Query<&mut Respawn, With<LocalPlayer>>,
@@ -192,9 +289,12 @@ fn world_respawn(
     if world_respawn.timer.finished() {
         // try to respawn world
         for entity in game_world_query.iter() {
-            commands.entity(entity).despawn_recursive();
+            despawn_queue.push(entity, DespawnReason::Respawn);
         }
 
+        // bring back enemies killed since the last checkpoint
+        fallen_enemies.restore();
+
         spawn_world(commands, assets);
 
         world_respawn.finished = true;
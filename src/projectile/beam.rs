@@ -0,0 +1,126 @@
+//! Hitscan beams.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::time::Duration;
+
+use super::{ContactBehavior, HitEvent, ProjectileSystem};
+use crate::despawn::{DespawnQueue, DespawnReason};
+use crate::enemy::Hostility;
+
+/// Beam plugin.
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            fire_beams
+                .in_set(ProjectileSystem::Event)
+                .run_if(any_with_component::<Beam>()),
+        )
+        .add_systems(
+            Update,
+            fade_beams
+                .after(ProjectileSystem::Despawn)
+                .run_if(any_with_component::<Beam>()),
+        );
+    }
+}
+
+/// A beam.
+///
+/// Beams hit the first thing in their path the frame they are created, then
+/// fade away. Like an [`Explosion`](super::explosion::Explosion), a beam is
+/// the `projectile` of the [`HitEvent`] it sends, but it does not have a
+/// [`Projectile`](super::Projectile) component.
+#[derive(Clone, Component, Debug)]
+pub struct Beam {
+    /// The direction of the beam.
+    pub direction: Vec2,
+    /// How far the beam goes if it does not hit anything, in world units.
+    pub range: f32,
+    /// The width of the beam.
+    pub width: f32,
+    timer: Timer,
+}
+
+impl Beam {
+    /// How long a beam is visible for.
+    pub const FADE_DURATION: Duration = Duration::from_millis(150);
+
+    /// Creates a new `Beam`.
+    pub fn new(direction: Vec2, range: f32) -> Beam {
+        Beam {
+            direction: direction.normalize_or_zero(),
+            range,
+            width: 2.,
+            timer: Timer::new(Beam::FADE_DURATION, TimerMode::Once),
+        }
+    }
+}
+
+fn fire_beams(
+    mut beam_query: Query<(Entity, &mut Transform, &mut Sprite, &Beam, &Hostility), Added<Beam>>,
+    velocity_query: Query<&Velocity>,
+    mut hit_events: EventWriter<HitEvent>,
+    physics: Res<RapierContext>,
+) {
+    for (entity, mut transform, mut sprite, beam, hostility) in beam_query.iter_mut() {
+        let origin = transform.translation.truncate();
+
+        let filter = QueryFilter::new().groups(hostility.collision_groups_projectile());
+
+        let hit = physics.cast_ray_and_get_normal(origin, beam.direction, beam.range, true, filter);
+
+        let length = match hit {
+            Some((hit, intersection)) => {
+                let entity_velocity = velocity_query
+                    .get(hit)
+                    .map(|v| v.linvel)
+                    .unwrap_or_default();
+
+                hit_events.send(HitEvent {
+                    projectile: entity,
+                    entity: hit,
+                    result: ContactBehavior::Absorb,
+                    point: Some(intersection.point),
+                    // ray normals face the ray origin
+                    normal: Some(intersection.normal),
+                    relative_velocity: -entity_velocity,
+                });
+
+                intersection.toi
+            }
+            None => beam.range,
+        };
+
+        // stretch the sprite from the origin to the hit point
+        let rot = beam.direction.y.atan2(beam.direction.x);
+
+        transform.translation += (beam.direction * length / 2.).extend(0.);
+        transform.rotation = Quat::from_axis_angle(Vec3::Z, rot);
+        sprite.custom_size = Some(Vec2::new(length, beam.width));
+    }
+}
+
+fn fade_beams(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut beam_query: Query<(Entity, &mut Sprite, &mut Beam)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut beam) in beam_query.iter_mut() {
+        beam.timer.tick(time.delta());
+
+        if beam.timer.finished() {
+            despawn_queue.push(entity, DespawnReason::Expired);
+            continue;
+        }
+
+        if let Some(size) = sprite.custom_size.as_mut() {
+            size.y = beam.width * beam.timer.percent_left();
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Keeps the number of live projectiles in check.
+//!
+//! Generators in neighboring levels keep firing even when the player is
+//! nowhere near them. Projectiles that wander far outside of every spawned
+//! level are culled, and once there are more than
+//! [`ProjectileBudget::max_projectiles`] the oldest ones are despawned first.
+//!
+//! Thrown [`Carryable`]s are projectiles too, but they are puzzle pieces that
+//! are never pooled, so they are left alone.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::LdtkLevel;
+
+use std::collections::VecDeque;
+
+use super::{DespawnEvent, Projectile, ProjectileSystem};
+use crate::carry::Carryable;
+use crate::despawn::DespawnReason;
+
+/// Projectile budget plugin.
+pub struct ProjectileBudgetPlugin;
+
+impl Plugin for ProjectileBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProjectileBudget>().add_systems(
+            Update,
+            (track_projectile_age, cull_projectiles)
+                .chain()
+                .in_set(ProjectileSystem::Event),
+        );
+    }
+}
+
+/// Limits on live projectiles.
+#[derive(Debug, Resource)]
+pub struct ProjectileBudget {
+    /// The most projectiles that can be alive at once.
+    pub max_projectiles: usize,
+    /// How far outside of a level a projectile can go before it is culled, in
+    /// world units.
+    pub cull_margin: f32,
+    /// Live projectiles, oldest first.
+    order: VecDeque<Entity>,
+}
+
+impl ProjectileBudget {
+    /// The number of live projectiles.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Checks if there are no live projectiles.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl Default for ProjectileBudget {
+    fn default() -> ProjectileBudget {
+        ProjectileBudget {
+            max_projectiles: 256,
+            cull_margin: 64.,
+            order: VecDeque::new(),
+        }
+    }
+}
+
+fn track_projectile_age(
+    mut budget: ResMut<ProjectileBudget>,
+    projectile_query: Query<(), (With<Projectile>, Without<Carryable>)>,
+    new_projectile_query: Query<Entity, (Added<Projectile>, Without<Carryable>)>,
+) {
+    // pooled projectiles come back with a new `Projectile`, so they are new
+    // again
+    let new = new_projectile_query.iter().collect::<Vec<_>>();
+
+    budget
+        .order
+        .retain(|entity| projectile_query.contains(*entity) && !new.contains(entity));
+    budget.order.extend(new);
+}
+
+fn cull_projectiles(
+    budget: Res<ProjectileBudget>,
+    projectile_query: Query<(Entity, &GlobalTransform, &Projectile), Without<Carryable>>,
+    levels_query: Query<(&GlobalTransform, &Handle<LdtkLevel>)>,
+    levels: Res<Assets<LdtkLevel>>,
+    mut despawn_events: EventWriter<DespawnEvent>,
+) {
+    let bounds = levels_query
+        .iter()
+        .filter_map(|(t, level)| levels.get(level).map(|l| (t, l)))
+        .map(|(t, level)| {
+            let size = Vec2::new(level.level.px_wid as f32, level.level.px_hei as f32);
+
+            let min = t.transform_point(Vec3::ZERO).truncate();
+            let max = t.transform_point(size.extend(0.)).truncate();
+
+            Rect::from_corners(min, max).inset(budget.cull_margin)
+        })
+        .collect::<Vec<_>>();
+
+    // levels haven't loaded yet
+    if !bounds.is_empty() {
+        for (entity, transform, projectile) in projectile_query.iter() {
+            if projectile.absorbed {
+                continue;
+            }
+
+            let position = transform.translation().truncate();
+
+            if !bounds.iter().any(|rect| rect.contains(position)) {
+                despawn_events.send(DespawnEvent {
+                    projectile: entity,
+                    reason: DespawnReason::Other("out of bounds"),
+                });
+            }
+        }
+    }
+
+    // oldest first
+    let over = budget.order.len().saturating_sub(budget.max_projectiles);
+
+    for &entity in budget.order.iter().take(over) {
+        despawn_events.send(DespawnEvent {
+            projectile: entity,
+            reason: DespawnReason::Other("over budget"),
+        });
+    }
+}
@@ -0,0 +1,230 @@
+//! Explosive projectiles.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::time::Duration;
+
+use super::{
+    prefab::{CreateProjectile, ProjectilePrefab},
+    ContactBehavior, HitEvent, Projectile, ProjectileSystem,
+};
+use crate::enemy::Hostility;
+use crate::fx::particles::Particle;
+use crate::{GameAssets, GameState};
+
+/// Explosion plugin.
+pub struct ExplosionPlugin;
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        // the hits are sent with every other projectile hit, so everything
+        // that reacts to hits sees them the same frame
+        app.add_systems(
+            Update,
+            explode_projectiles
+                .run_if(in_state(GameState::InGame))
+                .in_set(ProjectileSystem::Event)
+                .after(super::set_absorb_flag),
+        );
+    }
+}
+
+/// Makes a projectile explode when it is absorbed.
+///
+/// Everything in the radius of the explosion is hit as if the explosion was a
+/// projectile of the same [`Hostility`], so enemies die and drums and
+/// acceptors react. [`ProjectilePrefab::Bomb`] is explosive.
+#[derive(Clone, Component, Debug)]
+pub struct Explosive {
+    /// The radius of the explosion in world units.
+    pub radius: f32,
+    /// A projectile created at the center of the explosion, if any.
+    pub prefab: Option<ProjectilePrefab>,
+}
+
+impl Default for Explosive {
+    fn default() -> Explosive {
+        Explosive {
+            radius: 24.,
+            prefab: None,
+        }
+    }
+}
+
+/// An explosion.
+///
+/// This is the `projectile` of the [`HitEvent`]s sent by an explosion. It
+/// does not have a [`Projectile`] component, and disappears on its own after
+/// its animation plays.
+#[derive(Clone, Component, Debug)]
+pub struct Explosion {
+    /// The radius of the explosion in world units.
+    pub radius: f32,
+}
+
+fn explode_projectiles(
+    mut commands: Commands,
+    projectile_query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Hostility,
+        &Projectile,
+        &Explosive,
+    )>,
+    hostility_query: Query<&Hostility>,
+    transform_query: Query<&GlobalTransform>,
+    mut hit_events: EventWriter<HitEvent>,
+    physics: Res<RapierContext>,
+    assets: Res<GameAssets>,
+) {
+    for (entity, transform, hostility, projectile, explosive) in projectile_query.iter() {
+        if !projectile.absorbed {
+            continue;
+        }
+
+        let center = transform.translation().truncate();
+
+        let explosion = commands
+            .spawn((
+                SpriteSheetBundle {
+                    texture_atlas: assets.projectile_sheet.clone(),
+                    sprite: TextureAtlasSprite {
+                        color: hostility.color(),
+                        ..TextureAtlasSprite::new(18)
+                    },
+                    // the residue sprite is about 8 units wide
+                    transform: Transform::from_translation(transform.translation())
+                        .with_scale(Vec3::splat(explosive.radius / 4.)),
+                    ..Default::default()
+                },
+                Particle::animated(18..20, Duration::from_millis(150)),
+                Explosion {
+                    radius: explosive.radius,
+                },
+                *hostility,
+            ))
+            .id();
+
+        let filter = QueryFilter::new()
+            .groups(hostility.collision_groups_projectile())
+            .exclude_collider(entity);
+
+        physics.intersections_with_shape(
+            center,
+            0.,
+            &Collider::ball(explosive.radius),
+            filter,
+            |hit| {
+                let hit_event = explosion_hit(
+                    explosion,
+                    center,
+                    *hostility,
+                    hit,
+                    hostility_query.get(hit).ok().copied(),
+                    transform_query
+                        .get(hit)
+                        .ok()
+                        .map(|t| t.translation().truncate()),
+                );
+
+                if let Some(hit_event) = hit_event {
+                    hit_events.send(hit_event);
+                }
+
+                true
+            },
+        );
+
+        if let Some(prefab) = &explosive.prefab {
+            commands.add(
+                CreateProjectile::new(prefab.clone(), transform.translation())
+                    .hostility(*hostility),
+            );
+        }
+    }
+}
+
+/// The hit an explosion makes on something in its radius, if any.
+///
+/// Like projectiles, explosions don't hit things of the same [`Hostility`].
+/// The normal points from the thing to the center of the explosion.
+fn explosion_hit(
+    explosion: Entity,
+    center: Vec2,
+    hostility: Hostility,
+    entity: Entity,
+    entity_hostility: Option<Hostility>,
+    entity_position: Option<Vec2>,
+) -> Option<HitEvent> {
+    if entity_hostility == Some(hostility) {
+        return None;
+    }
+
+    Some(HitEvent {
+        projectile: explosion,
+        entity,
+        result: ContactBehavior::Absorb,
+        point: None,
+        normal: entity_position.and_then(|position| (center - position).try_normalize()),
+        relative_velocity: Vec2::ZERO,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explosions_spare_their_own_side() {
+        let explosion = Entity::from_raw(1);
+        let enemy = Entity::from_raw(2);
+
+        let hit = explosion_hit(
+            explosion,
+            Vec2::ZERO,
+            Hostility::Friendly,
+            enemy,
+            Some(Hostility::Friendly),
+            Some(Vec2::X),
+        );
+
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn explosions_push_away_from_the_center() {
+        let explosion = Entity::from_raw(1);
+        let enemy = Entity::from_raw(2);
+
+        let hit = explosion_hit(
+            explosion,
+            Vec2::new(10., 0.),
+            Hostility::Friendly,
+            enemy,
+            Some(Hostility::Hostile),
+            Some(Vec2::new(10., -8.)),
+        )
+        .unwrap();
+
+        assert_eq!(hit.projectile, explosion);
+        assert_eq!(hit.entity, enemy);
+        assert_eq!(hit.result, ContactBehavior::Absorb);
+        assert_eq!(hit.normal, Some(Vec2::Y));
+
+        // walls and drums have no hostility, and something right in the
+        // middle has no way to point
+        let hit = explosion_hit(
+            explosion,
+            Vec2::ZERO,
+            Hostility::Hostile,
+            enemy,
+            None,
+            Some(Vec2::ZERO),
+        )
+        .unwrap();
+
+        assert_eq!(hit.normal, None);
+    }
+}
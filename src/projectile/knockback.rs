@@ -0,0 +1,99 @@
+//! Projectiles that push things around.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use super::{HitEvent, Projectile, ProjectileSystem};
+
+/// Knockback plugin.
+pub struct KnockbackPlugin;
+
+impl Plugin for KnockbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_knockback
+                .after(ProjectileSystem::Event)
+                .before(ProjectileSystem::Despawn),
+        );
+    }
+}
+
+/// Bodies at least this heavy are not knocked back.
+pub const KNOCKBACK_MAX_MASS: f32 = 64.;
+
+/// Pushes dynamic bodies a projectile hits along the way it was going.
+///
+/// The impulse is scaled by the [strength] of the projectile. Bodies with a
+/// [`KnockbackImmune`] marker, or that are heavier than
+/// [`KNOCKBACK_MAX_MASS`], don't budge.
+///
+/// [strength]: Projectile::strength
+#[derive(Clone, Component, Debug)]
+pub struct KnockbackOnHit {
+    /// The impulse applied to the body.
+    pub impulse: f32,
+}
+
+/// Marks a body that cannot be knocked back by a [`KnockbackOnHit`]
+/// projectile.
+#[derive(Clone, Component, Debug, Default)]
+pub struct KnockbackImmune;
+
+fn apply_knockback(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitEvent>,
+    projectile_query: Query<(&Projectile, &KnockbackOnHit)>,
+    mut body_query: Query<
+        (
+            &RigidBody,
+            Option<&ReadMassProperties>,
+            Option<&mut ExternalImpulse>,
+        ),
+        Without<KnockbackImmune>,
+    >,
+) {
+    for ev in hit_events.iter() {
+        let Ok((projectile, knockback)) = projectile_query.get(ev.projectile) else {
+            continue;
+        };
+
+        let Ok((rigidbody, mass, external_impulse)) = body_query.get_mut(ev.entity) else {
+            continue;
+        };
+
+        if *rigidbody != RigidBody::Dynamic {
+            continue;
+        }
+
+        if mass
+            .map(|m| m.0.mass >= KNOCKBACK_MAX_MASS)
+            .unwrap_or_default()
+        {
+            continue;
+        }
+
+        let direction = match ev.relative_velocity.try_normalize() {
+            Some(direction) => direction,
+            None => match projectile.incoming_velocity().try_normalize() {
+                Some(direction) => direction,
+                None => continue,
+            },
+        };
+
+        let impulse = direction * knockback.impulse * projectile.strength;
+
+        // rapier clears the impulse each step, so hits in the same frame add
+        // up
+        match external_impulse {
+            Some(mut external_impulse) => external_impulse.impulse += impulse,
+            None => {
+                commands.entity(ev.entity).insert(ExternalImpulse {
+                    impulse,
+                    torque_impulse: 0.,
+                });
+            }
+        }
+    }
+}
@@ -1,16 +1,26 @@
 //! Projectile things.
 
+pub mod beam;
+pub mod budget;
+pub mod explosion;
+pub mod knockback;
+pub mod pool;
 pub mod prefab;
 pub mod residue;
+pub mod split;
 pub mod spawner; // TODO: move to playe mod
+pub mod turret;
 
 use bevy::prelude::*;
 
 use bevy_rapier2d::prelude::*;
 
+use std::collections::HashMap;
 use std::time::Duration;
 
+use crate::despawn::DespawnReason;
 use crate::enemy::Hostility;
+use crate::fx::tint::{PushTint, TintStack};
 use crate::physics;
 
 /// Projectile plugin.
@@ -18,8 +28,11 @@ pub struct ProjectilePlugin;
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<HitEvent>()
+        app.init_resource::<pool::ProjectilePool>()
+            .add_event::<HitEvent>()
             .add_event::<DespawnEvent>()
+            .add_event::<InterceptEvent>()
+            .add_event::<BounceEvent>()
             .add_systems(
                 Update,
                 (
@@ -36,13 +49,30 @@ impl Plugin for ProjectilePlugin {
             )
             .add_systems(
                 Update,
-                (bounce_projectiles, animate_squish)
+                (
+                    bounce_projectiles,
+                    ricochet_projectiles,
+                    animate_squish.run_if(any_with_component::<Squish>()),
+                )
                     .in_set(ProjectileSystem::Bounce)
                     .after(ProjectileSystem::Event)
                     .before(ProjectileSystem::Despawn),
             )
-            .add_systems(FixedUpdate, projectile_sine_wave)
-            .add_systems(PostUpdate, (update_collision_groups, update_sprite_color));
+            .add_systems(
+                FixedUpdate,
+                projectile_sine_wave.run_if(any_with_component::<SineWave>()),
+            )
+            .add_systems(
+                PostUpdate,
+                record_incoming_velocity
+                    .run_if(any_with_component::<Projectile>())
+                    .before(PhysicsSet::SyncBackend),
+            )
+            .add_systems(
+                PostUpdate,
+                (update_collision_groups, update_sprite_color)
+                    .distributive_run_if(any_with_component::<Projectile>()),
+            );
     }
 }
 
@@ -92,7 +122,7 @@ impl Default for ProjectileBundle {
 }
 
 /// A single projectile.
-#[derive(Clone, Component, Debug, Default)]
+#[derive(Clone, Component, Debug)]
 pub struct Projectile {
     //pub initial_speed: f32,
     /// Whether the projectile is being absorbed this frame.
@@ -100,6 +130,32 @@ pub struct Projectile {
     /// Set this to false to prevent the projectile from being absorbed. This
     /// cannot prevent projectiles being killed from [`TimeToLive`].
     pub absorbed: bool,
+    /// How hard the projectile hits, where `1.` is a normal projectile.
+    ///
+    /// Projectiles made from strong signals are stronger.
+    pub strength: f32,
+    /// The velocity going into the last physics step.
+    incoming: Vec2,
+}
+
+impl Default for Projectile {
+    fn default() -> Projectile {
+        Projectile {
+            absorbed: false,
+            strength: 1.,
+            incoming: Vec2::ZERO,
+        }
+    }
+}
+
+impl Projectile {
+    /// The velocity of the projectile going into the last physics step.
+    ///
+    /// Unlike [`Velocity`], this has not been changed by any contacts the
+    /// projectile made.
+    pub fn incoming_velocity(&self) -> Vec2 {
+        self.incoming
+    }
 }
 
 /// Determines the despawn behavior of projectiles.
@@ -143,6 +199,27 @@ pub struct NoCollide;
 #[derive(Clone, Component, Debug, Default)]
 pub struct SolidProjectile;
 
+/// Lets friendly projectiles shoot down a hostile projectile.
+///
+/// Both projectiles are absorbed on contact and an [`InterceptEvent`] is sent
+/// instead of a [`HitEvent`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct Interceptable;
+
+/// Lets a projectile pass through enemies it kills.
+///
+/// The projectile is absorbed as usual once it runs out of pierces.
+#[derive(Clone, Component, Debug)]
+pub struct Pierce {
+    /// How many more enemies the projectile can pass through.
+    pub remaining: u32,
+}
+
+/// Makes a projectile heavy enough to press switches it hits, like
+/// [`PressurePlate`](crate::interactions::trigger::PressurePlate)s.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Heavy;
+
 /// Makes a projectile sway on a sine wave.
 #[derive(Clone, Component, Debug)]
 pub struct SineWave {
@@ -153,20 +230,48 @@ pub struct SineWave {
     pub period: f32,
     /// The amplitude of the wave in world coordinates.
     pub amp: f32,
+    /// Where on the wave the projectile starts, in radians.
+    ///
+    /// Projectiles with different phases make a wave train.
+    pub phase: f32,
+    /// How much the period changes each second, if at all.
+    pub period_ramp: Option<f32>,
 
     ticks: u32,
 }
 
 impl SineWave {
+    /// Sets the phase. See [`SineWave::phase`].
+    pub fn with_phase(self, phase: f32) -> SineWave {
+        SineWave { phase, ..self }
+    }
+
+    /// Sets the period ramp. See [`SineWave::period_ramp`].
+    pub fn with_period_ramp(self, period_ramp: f32) -> SineWave {
+        SineWave {
+            period_ramp: Some(period_ramp),
+            ..self
+        }
+    }
+
     /// The velocity of the current frame.
     pub fn velocity(&self, timestep: Duration) -> f32 {
         let SineWave {
-            period, amp, ticks, ..
+            period,
+            amp,
+            phase,
+            period_ramp,
+            ticks,
+            ..
         } = *self;
 
         let time = (timestep * ticks).as_secs_f32();
+        let ramp = period_ramp.unwrap_or_default();
+
+        // the derivative of `amp * sin(phase + period * t + ramp * t^2 / 2)`
+        let angle = phase + period * time + ramp * time * time / 2.;
 
-        amp * period * (time * period).cos()
+        amp * (period + ramp * time) * angle.cos()
     }
 }
 
@@ -176,6 +281,8 @@ impl Default for SineWave {
             axis: Vec2::Y,
             period: 1.,
             amp: 1.,
+            phase: 0.,
+            period_ramp: None,
             ticks: 0,
         }
     }
@@ -185,15 +292,56 @@ impl Default for SineWave {
 #[derive(Clone, Component, Debug, Default)]
 pub struct Bounce {
     height: Option<f32>,
+    count: u32,
+}
+
+impl Bounce {
+    /// How many times the projectile has bounced.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Makes a projectile reflect off of solids instead of being absorbed.
+///
+/// The projectile is absorbed as usual once it runs out of bounces.
+#[derive(Clone, Component, Debug)]
+pub struct Ricochet {
+    /// How many more times the projectile can reflect.
+    pub remaining: u32,
+    count: u32,
+}
+
+impl Ricochet {
+    /// Creates a new `Ricochet` with a bounce budget.
+    pub fn new(remaining: u32) -> Ricochet {
+        Ricochet {
+            remaining,
+            count: 0,
+        }
+    }
+
+    /// How many times the projectile has reflected.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
 }
 
 /// A component coupled with [`Bounce`] to make projectiles squish visually.
+///
+/// This goes on a child of the projectile, with the sprite as its own child.
+/// The child is turned so its local y runs along [`Squish::axis`], then
+/// squashed along it and stretched along the tangent, so the volume stays
+/// about the same. The sprite is turned back upright under it.
 #[derive(Clone, Component, Debug)]
 pub struct Squish {
     /// How fast the squish will return to normal size, per second.
     pub retention: f32,
     /// The current squish value.
     pub squish: f32,
+    /// The axis the sprite is squashed along, usually the normal of the last
+    /// impact.
+    pub axis: Vec2,
 }
 
 impl Default for Squish {
@@ -201,6 +349,7 @@ impl Default for Squish {
         Squish {
             retention: 1.,
             squish: 1.,
+            axis: Vec2::Y,
         }
     }
 }
@@ -218,6 +367,11 @@ impl TimeToLive {
     pub fn new(duration: Duration) -> TimeToLive {
         TimeToLive(Timer::new(duration, TimerMode::Once))
     }
+
+    /// Checks if the projectile has lived too long.
+    pub fn finished(&self) -> bool {
+        self.0.finished()
+    }
 }
 
 impl Default for TimeToLive {
@@ -238,13 +392,50 @@ pub struct HitEvent {
     pub entity: Entity,
     /// The result of the interaction.
     pub result: ContactBehavior,
+    /// The contact point in world space.
+    ///
+    /// This is `None` if the physics engine did not generate a contact, e.g.
+    /// when either collider is a sensor.
+    pub point: Option<Vec2>,
+    /// The contact normal, pointing from the entity towards the projectile.
+    ///
+    /// This is `None` if the physics engine did not generate a contact.
+    pub normal: Option<Vec2>,
+    /// The velocity of the projectile relative to the entity going into the
+    /// contact.
+    pub relative_velocity: Vec2,
 }
 
-/// A projectile has despawned after living for too long.
+/// A friendly projectile shot down an [`Interceptable`] projectile.
+#[derive(Debug, Event)]
+pub struct InterceptEvent {
+    /// The friendly projectile.
+    pub projectile: Entity,
+    /// The projectile that was shot down.
+    pub intercepted: Entity,
+    /// The contact point in world space, if any.
+    pub point: Option<Vec2>,
+}
+
+/// A projectile bounced off of something.
+///
+/// Sent for both [`Bounce`] and [`Ricochet`] projectiles.
+#[derive(Debug, Event)]
+pub struct BounceEvent {
+    /// The projectile.
+    pub projectile: Entity,
+    /// How many times the projectile has bounced, including this one.
+    pub count: u32,
+}
+
+/// A projectile has despawned after living for too long, or going over the
+/// [budget](budget::ProjectileBudget).
 #[derive(Debug, Event)]
 pub struct DespawnEvent {
     /// The projectile.
     pub projectile: Entity,
+    /// Why the projectile is going away.
+    pub reason: DespawnReason,
 }
 
 fn synchronize_your_death_watches_lads(
@@ -256,7 +447,10 @@ fn synchronize_your_death_watches_lads(
         time_to_live.0.tick(time.delta());
 
         if time_to_live.0.finished() {
-            despawn_events.send(DespawnEvent { projectile: entity });
+            despawn_events.send(DespawnEvent {
+                projectile: entity,
+                reason: DespawnReason::Expired,
+            });
         }
     }
 }
@@ -279,7 +473,9 @@ fn projectile_sine_wave(
 }
 
 fn bounce_projectiles(
+    mut hit_events: EventReader<HitEvent>,
     mut bounce_query: Query<(
+        Entity,
         &GlobalTransform,
         &Children,
         &mut Bounce,
@@ -288,9 +484,15 @@ fn bounce_projectiles(
         &GravityScale,
     )>,
     mut squish_query: Query<&mut Squish>,
+    mut bounce_events: EventWriter<BounceEvent>,
     physics_config: Res<RapierConfiguration>,
 ) {
-    for (transform, children, mut bounce, mut velocity, mut projectile, gravity_scale) in
+    let normals: HashMap<Entity, Vec2> = hit_events
+        .iter()
+        .filter_map(|ev| Some((ev.projectile, ev.normal?)))
+        .collect();
+
+    for (entity, transform, children, mut bounce, mut velocity, mut projectile, gravity_scale) in
         bounce_query.iter_mut()
     {
         if bounce.height.is_none() {
@@ -308,19 +510,98 @@ fn bounce_projectiles(
 
             velocity.linvel.y = vel;
 
+            bounce.count += 1;
+            bounce_events.send(BounceEvent {
+                projectile: entity,
+                count: bounce.count,
+            });
+
             // setup squish animation
             let mut children = squish_query.iter_many_mut(children);
 
             while let Some(mut squish) = children.fetch_next() {
                 squish.squish = 0.7;
+
+                if let Some(normal) = normals.get(&entity) {
+                    squish.axis = *normal;
+                }
             }
         }
     }
 }
 
-fn animate_squish(mut squish_query: Query<(&mut Transform, &mut Squish)>, time: Res<Time>) {
-    for (mut transform, mut squish) in squish_query.iter_mut() {
-        transform.scale.y = squish.squish;
+fn ricochet_projectiles(
+    mut hit_events: EventReader<HitEvent>,
+    mut ricochet_query: Query<(&mut Ricochet, &mut Velocity, &mut Transform, &mut Projectile)>,
+    collision_groups_query: Query<&CollisionGroups>,
+    mut bounce_events: EventWriter<BounceEvent>,
+) {
+    for ev in hit_events.iter() {
+        let Ok((mut ricochet, mut velocity, mut transform, mut projectile)) =
+            ricochet_query.get_mut(ev.projectile)
+        else {
+            continue;
+        };
+
+        let Some(normal) = ev.normal else {
+            continue;
+        };
+
+        // only reflect off of solids
+        let solid = collision_groups_query
+            .get(ev.entity)
+            .map(|groups| groups.memberships.contains(physics::COLLISION_GROUP_SOLID))
+            .unwrap_or_default();
+
+        if !solid || ricochet.remaining == 0 {
+            continue;
+        }
+
+        ricochet.remaining -= 1;
+        ricochet.count += 1;
+        projectile.absorbed = false;
+
+        bounce_events.send(BounceEvent {
+            projectile: ev.projectile,
+            count: ricochet.count,
+        });
+
+        // the physics step already ate the velocity along the normal, so
+        // reflect what the projectile had going in
+        let incoming = projectile.incoming;
+        velocity.linvel = incoming - 2. * incoming.dot(normal) * normal;
+        // reflect again off of another wall in the same frame
+        projectile.incoming = velocity.linvel;
+
+        let rot = velocity.linvel.y.atan2(velocity.linvel.x);
+        transform.rotation = Quat::from_axis_angle(Vec3::Z, rot);
+    }
+}
+
+fn record_incoming_velocity(mut projectile_query: Query<(&mut Projectile, &Velocity)>) {
+    for (mut projectile, velocity) in projectile_query.iter_mut() {
+        projectile.incoming = velocity.linvel;
+    }
+}
+
+fn animate_squish(
+    mut squish_query: Query<(&mut Transform, &mut Squish, Option<&Children>)>,
+    mut sprite_query: Query<&mut Transform, Without<Squish>>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut squish, children) in squish_query.iter_mut() {
+        let axis = squish.axis.try_normalize().unwrap_or(Vec2::Y);
+        let rotation = Quat::from_rotation_arc_2d(Vec2::Y, axis);
+
+        transform.rotation = rotation;
+        transform.scale = Vec3::new(1. / squish.squish, squish.squish, 1.);
+
+        // the sprite shouldn't turn with the axis, only squish
+        let mut sprites = sprite_query.iter_many_mut(children.into_iter().flatten());
+
+        while let Some(mut sprite_transform) = sprites.fetch_next() {
+            sprite_transform.rotation = rotation.inverse();
+        }
 
         squish.squish = (squish.squish + squish.retention * time.delta_seconds()).min(1.);
     }
@@ -328,61 +609,98 @@ fn animate_squish(mut squish_query: Query<(&mut Transform, &mut Squish)>, time:
 
 fn update_collision_groups(
     mut projectile_query: Query<
-        (&Hostility, &mut CollisionGroups, Option<&NoHurt>, Option<&NoCollide>, Option<&SolidProjectile>),
-        (With<Projectile>, Changed<Hostility>),
+        (
+            &Hostility,
+            &mut CollisionGroups,
+            Has<NoHurt>,
+            Has<NoCollide>,
+            Has<SolidProjectile>,
+            Has<Interceptable>,
+        ),
+        (
+            With<Projectile>,
+            Or<(Changed<Hostility>, Changed<Interceptable>)>,
+        ),
     >,
 ) {
-    for (hostility, mut collision_groups, no_hurt, no_collide, solid) in projectile_query.iter_mut() {
-        let no_hurt = no_hurt.is_some();
-        let no_collide = no_collide.is_some();
-
-        let solid = solid.is_some();
-
-        match *hostility {
-            Hostility::Friendly => {
-                *collision_groups = CollisionGroups::new(
-                    physics::COLLISION_GROUP_PROJECTILE,
-                    physics::COLLISION_GROUP_SOLID | physics::COLLISION_GROUP_HOSTILE,
-                );
-            }
-            Hostility::Hostile => {
-                *collision_groups = CollisionGroups::new(
-                    physics::COLLISION_GROUP_PROJECTILE,
-                    physics::COLLISION_GROUP_SOLID | physics::COLLISION_GROUP_FRIENDLY,
-                );
-            }
-        }
+    for (hostility, mut collision_groups, no_hurt, no_collide, solid, interceptable) in
+        projectile_query.iter_mut()
+    {
+        *collision_groups =
+            projectile_collision_groups(*hostility, no_hurt, no_collide, solid, interceptable);
+    }
+}
 
-        if no_hurt {
-            collision_groups.filters &= !physics::COLLISION_GROUP_FRIENDLY;
-            collision_groups.filters &= !physics::COLLISION_GROUP_HOSTILE;
-        }
+/// The collision groups of a projectile.
+fn projectile_collision_groups(
+    hostility: Hostility,
+    no_hurt: bool,
+    no_collide: bool,
+    solid: bool,
+    interceptable: bool,
+) -> CollisionGroups {
+    let mut collision_groups = match hostility {
+        Hostility::Friendly => CollisionGroups::new(
+            physics::COLLISION_GROUP_PROJECTILE,
+            physics::COLLISION_GROUP_SOLID | physics::COLLISION_GROUP_HOSTILE,
+        ),
+        Hostility::Hostile => CollisionGroups::new(
+            physics::COLLISION_GROUP_PROJECTILE,
+            physics::COLLISION_GROUP_SOLID | physics::COLLISION_GROUP_FRIENDLY,
+        ),
+    };
+
+    if no_hurt {
+        collision_groups.filters &= !physics::COLLISION_GROUP_FRIENDLY;
+        collision_groups.filters &= !physics::COLLISION_GROUP_HOSTILE;
+    }
 
-        if no_collide {
-            collision_groups.filters &= !physics::COLLISION_GROUP_SOLID;
-        }
+    if no_collide {
+        collision_groups.filters &= !physics::COLLISION_GROUP_SOLID;
+    }
 
-        if solid {
-            bevy::log::info!("solid boye");
-            collision_groups.memberships |= physics::COLLISION_GROUP_SOLID;
-            collision_groups.filters |= physics::COLLISION_GROUP_FRIENDLY;
-            collision_groups.filters |= physics::COLLISION_GROUP_HOSTILE;
+    // friendly projectiles can always shoot down interceptable ones, but
+    // interceptable ones pass through each other
+    match hostility {
+        Hostility::Friendly => {
+            collision_groups.memberships |= physics::COLLISION_GROUP_INTERCEPTOR;
+            collision_groups.filters |= physics::COLLISION_GROUP_INTERCEPTABLE;
         }
+        Hostility::Hostile if interceptable => {
+            collision_groups.memberships |= physics::COLLISION_GROUP_INTERCEPTABLE;
+            collision_groups.filters |= physics::COLLISION_GROUP_INTERCEPTOR;
+        }
+        Hostility::Hostile => (),
+    }
+
+    if solid {
+        crate::game_debug!(Projectile, "solid boye");
+        collision_groups.memberships |= physics::COLLISION_GROUP_SOLID;
+        collision_groups.filters |= physics::COLLISION_GROUP_FRIENDLY;
+        collision_groups.filters |= physics::COLLISION_GROUP_HOSTILE;
     }
+
+    collision_groups
 }
 
 fn update_sprite_color(
+    mut commands: Commands,
     projectile_query: Query<(Entity, &Hostility), (With<Projectile>, Changed<Hostility>)>,
     children_query: Query<&Children>,
-    mut texture_atlas_query: Query<&mut TextureAtlasSprite>,
+    sprite_query: Query<(), With<TextureAtlasSprite>>,
 ) {
     for (proj_entity, hostility) in projectile_query.iter() {
         for entity in children_query
             .iter_descendants(proj_entity)
             .chain(std::iter::once(proj_entity))
         {
-            if let Ok(mut sprite) = texture_atlas_query.get_mut(entity) {
-                sprite.color = hostility.color();
+            if sprite_query.contains(entity) {
+                commands.add(PushTint::new(
+                    entity,
+                    Hostility::TINT,
+                    TintStack::HOSTILITY,
+                    hostility.color(),
+                ));
             }
         }
     }
@@ -391,9 +709,13 @@ fn update_sprite_color(
 fn create_hit_events(
     mut collision_events: EventReader<CollisionEvent>,
     mut hit_events: EventWriter<HitEvent>,
-    projectile_query: Query<Entity, With<Projectile>>,
+    mut intercept_events: EventWriter<InterceptEvent>,
+    projectile_query: Query<&Projectile>,
+    interceptable_query: Query<(), With<Interceptable>>,
+    velocity_query: Query<&Velocity>,
     behavior_query: Query<&ContactBehavior>,
     hostility_query: Query<&Hostility>,
+    physics: Res<RapierContext>,
 ) {
     // technically this actually does nothing but copy data but it's nice to
     // have access to all of this easily
@@ -429,20 +751,76 @@ fn create_hit_events(
             }
         }
 
+        let contact = physics.contact_pair(c1, c2).and_then(|pair| {
+            let manifold = pair
+                .manifolds()
+                .find(|manifold| manifold.normal() != Vec2::ZERO)?;
+
+            // manifold normals point from the first collider to the second
+            let normal = if pair.collider1() == projectile {
+                -manifold.normal()
+            } else {
+                manifold.normal()
+            };
+
+            let point = manifold
+                .solver_contacts()
+                .next()
+                .map(|contact| contact.point() * physics.physics_scale());
+
+            Some((point, normal))
+        });
+
+        // projectiles shooting each other down
+        let intercepted = [(projectile, entity), (entity, projectile)]
+            .into_iter()
+            .find(|&(p, i)| {
+                projectile_query.contains(i)
+                    && interceptable_query.contains(i)
+                    && hostility_query.get(p).ok() == Some(&Hostility::Friendly)
+            });
+
+        if let Some((projectile, intercepted)) = intercepted {
+            intercept_events.send(InterceptEvent {
+                projectile,
+                intercepted,
+                point: contact.and_then(|(point, _)| point),
+            });
+            continue;
+        }
+
+        let projectile_velocity = projectile_query
+            .get(projectile)
+            .map(|p| p.incoming)
+            .unwrap_or_default();
+        let entity_velocity = velocity_query
+            .get(entity)
+            .map(|v| v.linvel)
+            .unwrap_or_default();
+
         hit_events.send(HitEvent {
             projectile,
             entity,
             result: projectile_behavior.and(entity_behavior),
+            point: contact.and_then(|(point, _)| point),
+            normal: contact.map(|(_, normal)| normal),
+            relative_velocity: projectile_velocity - entity_velocity,
         });
     }
 }
 
 fn set_absorb_flag(
     mut hit_events: EventReader<HitEvent>,
+    mut intercept_events: EventReader<InterceptEvent>,
     mut projectile_query: Query<&mut Projectile>,
 ) {
-    for ev in hit_events.iter() {
-        let Ok(mut projectile) = projectile_query.get_mut(ev.projectile) else {
+    let hit = hit_events.iter().map(|ev| ev.projectile);
+    let intercepted = intercept_events
+        .iter()
+        .flat_map(|ev| [ev.projectile, ev.intercepted]);
+
+    for entity in hit.chain(intercepted) {
+        let Ok(mut projectile) = projectile_query.get_mut(entity) else {
             continue;
         };
 
@@ -457,13 +835,42 @@ fn despawn_projectiles(
 ) {
     for (entity, proj) in projectile_query.iter() {
         if proj.absorbed {
-            commands.entity(entity).despawn_recursive();
+            commands.add(pool::ReleaseProjectile(entity));
         }
     }
 
     for ev in despawn_events.iter() {
-        if let Some(entity) = commands.get_entity(ev.projectile) {
-            entity.despawn_recursive();
-        }
+        commands.add(pool::ReleaseProjectile(ev.projectile));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collide(a: CollisionGroups, b: CollisionGroups) -> bool {
+        a.memberships.intersects(b.filters) && b.memberships.intersects(a.filters)
+    }
+
+    #[test]
+    fn friendly_projectiles_intercept() {
+        let friendly = projectile_collision_groups(Hostility::Friendly, false, false, false, false);
+        let interceptable =
+            projectile_collision_groups(Hostility::Hostile, false, false, false, true);
+        let hostile = projectile_collision_groups(Hostility::Hostile, false, false, false, false);
+
+        assert!(collide(friendly, interceptable));
+        assert!(!collide(friendly, hostile));
+        assert!(!collide(friendly, friendly));
+    }
+
+    #[test]
+    fn interceptable_projectiles_pass_through_each_other() {
+        let interceptable =
+            projectile_collision_groups(Hostility::Hostile, false, false, false, true);
+        let hostile = projectile_collision_groups(Hostility::Hostile, false, false, false, false);
+
+        assert!(!collide(interceptable, interceptable));
+        assert!(!collide(interceptable, hostile));
     }
 }
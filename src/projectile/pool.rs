@@ -0,0 +1,158 @@
+//! Recycles projectiles instead of despawning them.
+//!
+//! Projectiles created with [`CreateProjectile`] are returned to the
+//! [`ProjectilePool`] when they are absorbed or live too long, and the next
+//! projectile made from the same kind of prefab reuses the entity.
+//!
+//! [`CreateProjectile`]: super::prefab::CreateProjectile
+
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use std::collections::HashMap;
+use std::mem::Discriminant;
+
+use crate::despawn::{DespawnQueue, DespawnReason};
+
+use super::{
+    explosion::Explosive, knockback::KnockbackOnHit, prefab::ProjectilePrefab,
+    split::SplitOnAbsorb, Bounce, Heavy, Interceptable, NoCollide, NoHurt, Pierce, Projectile,
+    Ricochet, SineWave, SolidProjectile, TimeToLive,
+};
+
+/// Inactive projectiles waiting to be reused.
+#[derive(Debug, Resource)]
+pub struct ProjectilePool {
+    /// How many inactive projectiles are kept for each kind of prefab.
+    ///
+    /// Projectiles released past this are despawned as usual.
+    pub capacity: usize,
+    free: HashMap<Discriminant<ProjectilePrefab>, Vec<Entity>>,
+}
+
+impl ProjectilePool {
+    /// The number of inactive projectiles in the pool.
+    pub fn len(&self) -> usize {
+        self.free.values().map(Vec::len).sum()
+    }
+
+    /// Checks if the pool has no inactive projectiles.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ProjectilePool {
+    fn default() -> ProjectilePool {
+        ProjectilePool {
+            capacity: 64,
+            free: HashMap::new(),
+        }
+    }
+}
+
+/// Marks a projectile that goes back to the [`ProjectilePool`] instead of
+/// being despawned.
+#[derive(Clone, Component, Debug)]
+pub struct Pooled(Discriminant<ProjectilePrefab>);
+
+/// A command that returns a projectile to the [`ProjectilePool`].
+///
+/// Projectiles that did not come from the pool, or that do not fit in it, are
+/// despawned.
+pub struct ReleaseProjectile(pub Entity);
+
+impl Command for ReleaseProjectile {
+    fn apply(self, world: &mut World) {
+        let ReleaseProjectile(entity) = self;
+
+        // already going away, parking it would hand out a dead entity
+        if world.resource::<DespawnQueue>().contains(entity) {
+            return;
+        }
+
+        let Some(pooled) = world.get::<Pooled>(entity).cloned() else {
+            world
+                .resource_mut::<DespawnQueue>()
+                .push(entity, DespawnReason::Absorbed);
+            return;
+        };
+
+        // released twice in the same frame
+        if world.get::<Projectile>(entity).is_none() {
+            return;
+        }
+
+        let full = world
+            .get_resource::<ProjectilePool>()
+            .map(|pool| {
+                let free = pool.free.get(&pooled.0).map(Vec::len).unwrap_or_default();
+                free >= pool.capacity
+            })
+            .unwrap_or(true);
+
+        if full {
+            world
+                .resource_mut::<DespawnQueue>()
+                .push(entity, DespawnReason::Absorbed);
+            return;
+        }
+
+        // park it, the prefab will set everything back up
+        let mut entity_mut = world.entity_mut(entity);
+
+        entity_mut
+            .despawn_descendants()
+            .remove::<(
+                Projectile,
+                TimeToLive,
+                SineWave,
+                Bounce,
+                Ricochet,
+                Explosive,
+                Interceptable,
+                SplitOnAbsorb,
+                KnockbackOnHit,
+                Pierce,
+                Heavy,
+                NoHurt,
+                NoCollide,
+                SolidProjectile,
+                Velocity,
+            )>()
+            .insert((Visibility::Hidden, RigidBodyDisabled, ColliderDisabled));
+
+        world
+            .resource_mut::<ProjectilePool>()
+            .free
+            .entry(pooled.0)
+            .or_default()
+            .push(entity);
+    }
+}
+
+/// Takes an inactive projectile for a prefab from the pool, or spawns a new
+/// entity if there are none.
+pub(super) fn take(world: &mut World, prefab: &ProjectilePrefab) -> Entity {
+    let kind = std::mem::discriminant(prefab);
+
+    loop {
+        let reused = world
+            .get_resource_mut::<ProjectilePool>()
+            .and_then(|mut pool| pool.free.get_mut(&kind)?.pop());
+
+        match reused {
+            Some(entity) if world.get_entity(entity).is_some() => {
+                world
+                    .entity_mut(entity)
+                    .remove::<(RigidBodyDisabled, ColliderDisabled)>();
+                return entity;
+            }
+            // parked projectiles may have been despawned by something else
+            Some(_) => continue,
+            None => return world.spawn(Pooled(kind)).id(),
+        }
+    }
+}
@@ -3,11 +3,22 @@
 use bevy::ecs::system::Command;
 use bevy::prelude::*;
 
+use bevy_ecs_ldtk::prelude::*;
+
 use bevy_rapier2d::prelude::*;
 
-use super::{Bounce, NoHurt, NoCollide, SolidProjectile, Projectile, ProjectileBundle, SineWave, Squish, TimeToLive};
+use super::{Bounce, Heavy, Interceptable, NoHurt, NoCollide, Pierce, SolidProjectile, Projectile, ProjectileBundle, Ricochet, SineWave, Squish, TimeToLive};
+use super::pool;
+use super::beam::Beam;
+use super::explosion::Explosive;
+use super::knockback::KnockbackOnHit;
+use super::residue::{ResidueSpec, ResidueStyle, ResidueSurface};
+use super::split::SplitOnAbsorb;
+
+use std::time::Duration;
 
 use crate::enemy::Hostility;
+use crate::rng::GameRng;
 use crate::GameAssets;
 
 /// A projectile prefab.
@@ -22,23 +33,147 @@ pub enum ProjectilePrefab {
     /// A quarter note that sways up and down on a sine wave.
     QuarterNote { initial_velocity: Vec2 },
     /// A beam note that bouncess. If the direction is `0`, it will choose a
-    /// random direction to bounce into.
+    /// random direction to bounce into using the [`GameRng`].
     BeamNote { initial_direction: f32 },
     /// A beat is a wide note that serves as a platform.
     Beat { initial_velocity: Vec2 },
+    /// A rest that reflects off of walls a number of times before it is
+    /// absorbed.
+    Ricochet { initial_velocity: Vec2, bounces: u32 },
+    /// A hitscan beam that instantly hits the first thing in its path.
+    Beam { direction: Vec2, range: f32 },
+    /// A pair of eighth notes that split into two smaller notes when they hit
+    /// a wall or live too long.
+    EighthNotes { initial_velocity: Vec2 },
+    /// A big, slow whole note that passes through the first enemy it kills
+    /// and is heavy enough to press switches. A hostile one can be shot down.
+    WholeNote { initial_velocity: Vec2 },
+    /// A note that falls in an arc and explodes when it is absorbed.
+    Bomb { initial_velocity: Vec2 },
 }
 
 impl ProjectilePrefab {
+    /// The name of the prefab, for debugging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectilePrefab::QuarterRest { .. } => "QuarterRest",
+            ProjectilePrefab::QuarterNote { .. } => "QuarterNote",
+            ProjectilePrefab::BeamNote { .. } => "BeamNote",
+            ProjectilePrefab::Beat { .. } => "Beat",
+            ProjectilePrefab::Ricochet { .. } => "Ricochet",
+            ProjectilePrefab::Beam { .. } => "Beam",
+            ProjectilePrefab::EighthNotes { .. } => "EighthNotes",
+            ProjectilePrefab::WholeNote { .. } => "WholeNote",
+            ProjectilePrefab::Bomb { .. } => "Bomb",
+        }
+    }
+
+    /// Finds a prefab by its name, as in LDtk, going at a velocity.
+    ///
+    /// Beams can't be picked by name, as they need a range.
+    pub fn from_name(name: &str, initial_velocity: Vec2) -> Option<ProjectilePrefab> {
+        match name {
+            "QuarterRest" => Some(ProjectilePrefab::QuarterRest { initial_velocity }),
+            "QuarterNote" => Some(ProjectilePrefab::QuarterNote { initial_velocity }),
+            "BeamNote" => Some(ProjectilePrefab::BeamNote {
+                initial_direction: initial_velocity.x,
+            }),
+            "Beat" => Some(ProjectilePrefab::Beat { initial_velocity }),
+            "Ricochet" => Some(ProjectilePrefab::Ricochet {
+                initial_velocity,
+                bounces: 3,
+            }),
+            "EighthNotes" => Some(ProjectilePrefab::EighthNotes { initial_velocity }),
+            "WholeNote" => Some(ProjectilePrefab::WholeNote { initial_velocity }),
+            "Bomb" => Some(ProjectilePrefab::Bomb { initial_velocity }),
+            _ => None,
+        }
+    }
+
     /// Creates a new projectile in a world.
-    pub fn create(&self, world: &mut World, location: Vec3, hostility: Hostility) {
+    ///
+    /// This reuses a projectile from the [`ProjectilePool`](pool::ProjectilePool) if there is one.
+    pub fn create(&self, world: &mut World, location: Vec3, hostility: Hostility) -> Entity {
+        let entity = match self {
+            // beams are gone as soon as they fire, there is nothing to reuse
+            ProjectilePrefab::Beam { .. } => world.spawn_empty().id(),
+            _ => pool::take(world, self),
+        };
+
         world.resource_scope::<GameAssets, _>(|world, assets| {
-            self.create_inner(world, &*assets, location, hostility)
+            self.create_inner(world, entity, &*assets, location, hostility)
         });
+
+        // always inserted, so pooled projectiles don't keep an old one
+        if let Some(residue) = self.residue() {
+            world.entity_mut(entity).insert(residue);
+        }
+
+        entity
+    }
+
+    /// The residue the prefab leaves behind when it is absorbed.
+    ///
+    /// Beams leave none; they aren't projectiles.
+    pub fn residue(&self) -> Option<ResidueSpec> {
+        let frames = Duration::from_millis(100);
+
+        match self {
+            ProjectilePrefab::Beam { .. } => None,
+            // a flat beat splats against walls
+            ProjectilePrefab::Beat { .. } => Some(
+                ResidueSpec::new(
+                    ResidueStyle::new(18..20, frames)
+                        .scale(2.)
+                        .align_to_normal(),
+                )
+                .on(ResidueSurface::Enemy, Some(ResidueStyle::default())),
+            ),
+            ProjectilePrefab::Ricochet { .. } => Some(ResidueSpec::new(
+                ResidueStyle::new(18..20, frames).align_to_normal(),
+            )),
+            ProjectilePrefab::EighthNotes { .. } => Some(ResidueSpec::new(
+                ResidueStyle::new(18..20, frames).scale(1.5),
+            )),
+            // heavy enough to leave a mark, but slips right into acceptors
+            ProjectilePrefab::WholeNote { .. } => Some(
+                ResidueSpec::new(
+                    ResidueStyle::new(18..20, Duration::from_millis(150))
+                        .scale(2.)
+                        .align_to_normal(),
+                )
+                .on(ResidueSurface::Acceptor, None),
+            ),
+            _ => Some(ResidueSpec::default()),
+        }
+    }
+
+    /// Returns the same prefab going a different way.
+    ///
+    /// Prefabs that only go left or right take the horizontal part of the
+    /// velocity.
+    pub fn with_velocity(&self, velocity: Vec2) -> ProjectilePrefab {
+        let mut prefab = self.clone();
+
+        match &mut prefab {
+            ProjectilePrefab::QuarterRest { initial_velocity }
+            | ProjectilePrefab::QuarterNote { initial_velocity }
+            | ProjectilePrefab::Beat { initial_velocity }
+            | ProjectilePrefab::Ricochet { initial_velocity, .. }
+            | ProjectilePrefab::EighthNotes { initial_velocity }
+            | ProjectilePrefab::WholeNote { initial_velocity }
+            | ProjectilePrefab::Bomb { initial_velocity } => *initial_velocity = velocity,
+            ProjectilePrefab::BeamNote { initial_direction } => *initial_direction = velocity.x,
+            ProjectilePrefab::Beam { direction, .. } => *direction = velocity,
+        }
+
+        prefab
     }
 
     fn create_inner(
         &self,
         world: &mut World,
+        entity: Entity,
         assets: &GameAssets,
         mut location: Vec3,
         hostility: Hostility,
@@ -50,7 +185,7 @@ impl ProjectilePrefab {
             ProjectilePrefab::QuarterRest { initial_velocity } => {
                 let rot = initial_velocity.y.atan2(initial_velocity.x);
 
-                world.spawn((
+                world.entity_mut(entity).insert((
                     ProjectileBundle {
                         transform: Transform::from_translation(location)
                             * Transform::from_rotation(Quat::from_axis_angle(Vec3::Z, rot)),
@@ -86,7 +221,7 @@ impl ProjectilePrefab {
                 //   |   /
                 //  /____\
                 // /      \
-                world.spawn((
+                world.entity_mut(entity).insert((
                     ProjectileBundle {
                         transform: Transform::from_translation(location),
                         gravity_scale: GravityScale(0.),
@@ -115,8 +250,14 @@ impl ProjectilePrefab {
                 ));
             }
             ProjectilePrefab::BeamNote { initial_direction } => {
+                let linvel = {
+                    let mut rng = world.get_resource_mut::<GameRng>();
+                    beam_note_velocity(*initial_direction, rng.as_deref_mut())
+                };
+
                 world
-                    .spawn((
+                    .entity_mut(entity)
+                    .insert((
                         ProjectileBundle {
                             transform: Transform::from_translation(location),
                             gravity_scale: GravityScale(0.5),
@@ -128,29 +269,29 @@ impl ProjectilePrefab {
                             hostility,
                             ..Default::default()
                         },
-                        Velocity {
-                            linvel: Vec2::new(*initial_direction, 0.),
-                            angvel: 0.,
-                        },
+                        Velocity { linvel, angvel: 0. },
                         Bounce::default(),
                         LockedAxes::ROTATION_LOCKED,
                         VisibilityBundle::default(),
                         TimeToLive::default(),
                     ))
                     .with_children(|parent| {
-                        parent.spawn((
-                            SpriteSheetBundle {
-                                texture_atlas: assets.projectile_sheet.clone(),
-                                sprite: TextureAtlasSprite::new(1),
-                                ..Default::default()
-                            },
-                            hostility,
-                            Squish::default(),
-                        ));
+                        parent
+                            .spawn((SpatialBundle::default(), Squish::default()))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    SpriteSheetBundle {
+                                        texture_atlas: assets.projectile_sheet.clone(),
+                                        sprite: TextureAtlasSprite::new(1),
+                                        ..Default::default()
+                                    },
+                                    hostility,
+                                ));
+                            });
                     });
             }
             ProjectilePrefab::Beat { initial_velocity } => {
-                world.spawn((
+                world.entity_mut(entity).insert((
                     ProjectileBundle {
                         transform: Transform::from_translation(location),
                         gravity_scale: GravityScale(0.),
@@ -172,6 +313,255 @@ impl ProjectilePrefab {
                     TimeToLive::default(),
                 ));
             }
+            ProjectilePrefab::Ricochet { initial_velocity, bounces } => {
+                let rot = initial_velocity.y.atan2(initial_velocity.x);
+
+                world.entity_mut(entity).insert((
+                    ProjectileBundle {
+                        transform: Transform::from_translation(location)
+                            * Transform::from_rotation(Quat::from_axis_angle(Vec3::Z, rot)),
+                        gravity_scale: GravityScale(0.),
+                        projectile: Projectile::default(),
+                        collider: Collider::cuboid(2., 2.),
+                        hostility,
+                        ..Default::default()
+                    },
+                    Velocity {
+                        linvel: *initial_velocity,
+                        angvel: 0.,
+                    },
+                    Ricochet::new(*bounces),
+                    KnockbackOnHit { impulse: 8. },
+                    Friction::coefficient(0.),
+                    LockedAxes::ROTATION_LOCKED,
+                    assets.projectile_sheet.clone(),
+                    TextureAtlasSprite::new(0),
+                    VisibilityBundle::default(),
+                    TimeToLive::default(),
+                ));
+            }
+            ProjectilePrefab::Beam { direction, range } => {
+                world.entity_mut(entity).insert((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: hostility.color(),
+                            // sized when the beam fires
+                            custom_size: Some(Vec2::ZERO),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(location),
+                        ..Default::default()
+                    },
+                    Beam::new(*direction, *range),
+                    hostility,
+                ));
+            }
+            ProjectilePrefab::EighthNotes { initial_velocity } => {
+                world.entity_mut(entity).insert((
+                    ProjectileBundle {
+                        transform: Transform::from_translation(location),
+                        gravity_scale: GravityScale(0.),
+                        projectile: Projectile::default(),
+                        collider: Collider::cuboid(4., 4.),
+                        hostility,
+                        ..Default::default()
+                    },
+                    Velocity {
+                        linvel: *initial_velocity,
+                        angvel: 0.,
+                    },
+                    SplitOnAbsorb {
+                        prefab: ProjectilePrefab::QuarterNote {
+                            initial_velocity: *initial_velocity,
+                        },
+                        count: 2,
+                        spread: std::f32::consts::FRAC_PI_4,
+                    },
+                    LockedAxes::ROTATION_LOCKED,
+                    assets.projectile_sheet.clone(),
+                    TextureAtlasSprite::new(1),
+                    VisibilityBundle::default(),
+                    TimeToLive::new(Duration::from_secs(2)),
+                ));
+            }
+            ProjectilePrefab::WholeNote { initial_velocity } => {
+                world.entity_mut(entity).insert((
+                    ProjectileBundle {
+                        transform: Transform::from_translation(location),
+                        gravity_scale: GravityScale(0.),
+                        projectile: Projectile::default(),
+                        collider: Collider::cuboid(7., 4.),
+                        hostility,
+                        ..Default::default()
+                    },
+                    Velocity {
+                        linvel: *initial_velocity,
+                        angvel: 0.,
+                    },
+                    Pierce { remaining: 1 },
+                    Heavy,
+                    // big and slow enough to shoot down
+                    Interceptable,
+                    KnockbackOnHit { impulse: 24. },
+                    LockedAxes::ROTATION_LOCKED,
+                    assets.projectile_sheet.clone(),
+                    TextureAtlasSprite::new(6),
+                    VisibilityBundle::default(),
+                    TimeToLive::default(),
+                ));
+            }
+            ProjectilePrefab::Bomb { initial_velocity } => {
+                world.entity_mut(entity).insert((
+                    ProjectileBundle {
+                        transform: Transform::from_translation(location),
+                        gravity_scale: GravityScale(0.5),
+                        projectile: Projectile::default(),
+                        collider: Collider::ball(4.),
+                        hostility,
+                        ..Default::default()
+                    },
+                    Velocity {
+                        linvel: *initial_velocity,
+                        angvel: 0.,
+                    },
+                    Explosive::default(),
+                    LockedAxes::ROTATION_LOCKED,
+                    assets.projectile_sheet.clone(),
+                    TextureAtlasSprite::new(7),
+                    VisibilityBundle::default(),
+                    TimeToLive::default(),
+                ));
+            }
+        }
+    }
+}
+
+/// The speed of a [`ProjectilePrefab::BeamNote`] going in a random direction.
+pub const BEAM_NOTE_RANDOM_SPEED: f32 = 32.;
+
+/// The velocity a [`ProjectilePrefab::BeamNote`] starts with.
+///
+/// A direction of `0` picks left or right from `rng`, or right if there is
+/// none.
+fn beam_note_velocity(initial_direction: f32, rng: Option<&mut GameRng>) -> Vec2 {
+    if initial_direction == 0. {
+        let sign = rng.map(|rng| rng.sign()).unwrap_or(1.);
+
+        Vec2::new(sign * BEAM_NOTE_RANDOM_SPEED, 0.)
+    } else {
+        Vec2::new(initial_direction, 0.)
+    }
+}
+
+/// A path a projectile takes after it is created, overriding the one its
+/// prefab would give it.
+///
+/// This lets the same prefab play different roles in a level.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
+pub enum Trajectory {
+    /// Whatever the prefab does.
+    #[default]
+    Prefab,
+    /// A straight line, with no sway or gravity.
+    Straight,
+    /// A lob that arcs up and falls back down.
+    ///
+    /// * `gravity`: the gravity scale of the projectile.
+    /// * `lift`: the upward speed the projectile starts with.
+    Lob { gravity: f32, lift: f32 },
+    /// Sways side to side across the direction of travel. See [`SineWave`].
+    ///
+    /// * `period_ramp`: how much the period changes each second, if at all.
+    ///   See [`SineWave::period_ramp`].
+    Sine {
+        amp: f32,
+        period: f32,
+        period_ramp: Option<f32>,
+    },
+}
+
+impl Trajectory {
+    /// Creates a `Trajectory` from the `Trajectory` field of an
+    /// [`EntityInstance`], tuned by its optional `Gravity`, `Lift`,
+    /// `Amplitude`, `Period` and `PeriodRamp` fields.
+    ///
+    /// Entities without the field keep the prefab's trajectory.
+    pub fn from_entity_instance(inst: &EntityInstance) -> Trajectory {
+        let float = |name: &str, default: f32| {
+            inst.get_maybe_float_field(name)
+                .ok()
+                .copied()
+                .flatten()
+                .unwrap_or(default)
+        };
+
+        let Ok(Some(preset)) = inst.get_maybe_enum_field("Trajectory") else {
+            return Trajectory::Prefab;
+        };
+
+        match preset.as_ref() {
+            "Straight" => Trajectory::Straight,
+            "Lob" => Trajectory::Lob {
+                gravity: float("Gravity", 1.),
+                lift: float("Lift", 96.),
+            },
+            "Sine" => Trajectory::Sine {
+                amp: float("Amplitude", 2.),
+                period: float("Period", 16.),
+                period_ramp: inst
+                    .get_maybe_float_field("PeriodRamp")
+                    .ok()
+                    .copied()
+                    .flatten(),
+            },
+            _ => {
+                crate::game_warn!(Projectile, "unknown trajectory {}", preset);
+                Trajectory::Prefab
+            }
+        }
+    }
+
+    /// Puts a freshly created projectile on this trajectory.
+    fn apply(self, world: &mut World, entity: Entity) {
+        let mut entity = world.entity_mut(entity);
+
+        // hitscan projectiles don't travel
+        let Some(velocity) = entity.get::<Velocity>().map(|v| v.linvel) else {
+            return;
+        };
+
+        match self {
+            Trajectory::Prefab => (),
+            Trajectory::Straight => {
+                entity.remove::<SineWave>().insert(GravityScale(0.));
+            }
+            Trajectory::Lob { gravity, lift } => {
+                entity.remove::<SineWave>().insert(GravityScale(gravity));
+
+                if let Some(mut velocity) = entity.get_mut::<Velocity>() {
+                    velocity.linvel.y += lift;
+                }
+            }
+            Trajectory::Sine {
+                amp,
+                period,
+                period_ramp,
+            } => {
+                let normal = velocity.try_normalize().unwrap_or(Vec2::X);
+
+                let mut sine_wave = SineWave {
+                    axis: Vec2::new(normal.y, -normal.x),
+                    period,
+                    amp,
+                    ..Default::default()
+                };
+
+                if let Some(period_ramp) = period_ramp {
+                    sine_wave = sine_wave.with_period_ramp(period_ramp);
+                }
+
+                entity.insert((GravityScale(0.), sine_wave));
+            }
         }
     }
 }
@@ -181,6 +571,10 @@ pub struct CreateProjectile {
     prefab: ProjectilePrefab,
     location: Vec3,
     hostility: Hostility,
+    strength: f32,
+    phase: f32,
+    trajectory: Trajectory,
+    split: Option<SplitOnAbsorb>,
 }
 
 impl CreateProjectile {
@@ -190,6 +584,10 @@ impl CreateProjectile {
             prefab,
             location,
             hostility: Hostility::default(),
+            strength: 1.,
+            phase: 0.,
+            trajectory: Trajectory::default(),
+            split: None,
         }
     }
 
@@ -197,6 +595,33 @@ impl CreateProjectile {
     pub fn hostility(self, hostility: Hostility) -> CreateProjectile {
         CreateProjectile { hostility, ..self }
     }
+
+    /// Sets the strength.
+    ///
+    /// Stronger projectiles are bigger and faster. See
+    /// [`Projectile::strength`].
+    pub fn strength(self, strength: f32) -> CreateProjectile {
+        CreateProjectile { strength, ..self }
+    }
+
+    /// Sets the phase of projectiles that sway on a [`SineWave`].
+    pub fn phase(self, phase: f32) -> CreateProjectile {
+        CreateProjectile { phase, ..self }
+    }
+
+    /// Sets the trajectory.
+    pub fn trajectory(self, trajectory: Trajectory) -> CreateProjectile {
+        CreateProjectile { trajectory, ..self }
+    }
+
+    /// Makes the projectile split when it is absorbed, on top of anything the
+    /// prefab already does. See [`SplitOnAbsorb`].
+    pub fn split(self, split: SplitOnAbsorb) -> CreateProjectile {
+        CreateProjectile {
+            split: Some(split),
+            ..self
+        }
+    }
 }
 
 impl Command for CreateProjectile {
@@ -205,8 +630,100 @@ impl Command for CreateProjectile {
             prefab,
             location,
             hostility,
+            strength,
+            phase,
+            trajectory,
+            split,
         } = self;
 
-        prefab.create(world, location, hostility);
+        let entity = prefab.create(world, location, hostility);
+
+        if let Some(split) = split {
+            world.entity_mut(entity).insert(split);
+        }
+
+        // pooled projectiles are renamed after what they are now
+        crate::label::label(world, entity, prefab.name());
+
+        trajectory.apply(world, entity);
+
+        if phase != 0. {
+            if let Some(mut sine_wave) = world.get_mut::<SineWave>(entity) {
+                sine_wave.phase = phase;
+            }
+        }
+
+        if strength == 1. {
+            return;
+        }
+
+        // colliders are scaled with the transform
+        let size = strength.sqrt().clamp(0.5, 3.);
+        let speed = (1. + (strength - 1.) * 0.25).clamp(0.5, 2.);
+
+        let mut entity = world.entity_mut(entity);
+
+        if let Some(mut transform) = entity.get_mut::<Transform>() {
+            transform.scale *= Vec3::new(size, size, 1.);
+        }
+
+        if let Some(mut velocity) = entity.get_mut::<Velocity>() {
+            velocity.linvel *= speed;
+        }
+
+        if let Some(mut projectile) = entity.get_mut::<Projectile>() {
+            projectile.strength = strength;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beam_note_fixed_direction() {
+        let mut rng = GameRng::new(1);
+
+        assert_eq!(
+            beam_note_velocity(-24., Some(&mut rng)),
+            Vec2::new(-24., 0.)
+        );
+        assert_eq!(beam_note_velocity(24., None), Vec2::new(24., 0.));
+    }
+
+    #[test]
+    fn beam_note_random_direction() {
+        let mut left = false;
+        let mut right = false;
+
+        for seed in 0..32 {
+            let mut rng = GameRng::new(seed);
+            let velocity = beam_note_velocity(0., Some(&mut rng));
+
+            // only ever straight left or right, at the same speed
+            assert_eq!(velocity.y, 0.);
+            assert_eq!(velocity.x.abs(), BEAM_NOTE_RANDOM_SPEED);
+
+            left |= velocity.x < 0.;
+            right |= velocity.x > 0.;
+        }
+
+        assert!(left && right);
+
+        assert_eq!(
+            beam_note_velocity(0., None),
+            Vec2::new(BEAM_NOTE_RANDOM_SPEED, 0.)
+        );
+    }
+
+    #[test]
+    fn beam_note_same_seed_same_direction() {
+        for seed in 0..32 {
+            let first = beam_note_velocity(0., Some(&mut GameRng::new(seed)));
+            let second = beam_note_velocity(0., Some(&mut GameRng::new(seed)));
+
+            assert_eq!(first, second);
+        }
     }
 }
@@ -1,12 +1,21 @@
 //! Particle residual effects from projectiles.
+//!
+//! What a projectile leaves behind when it is absorbed is decided by its
+//! [`ResidueSpec`], and can depend on what it hit. Residue is spawned as
+//! [`fx::particles`](crate::fx::particles).
 
+use bevy::ecs::query::Has;
 use bevy::prelude::*;
 
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
 use std::ops::Range;
 use std::time::Duration;
 
-use super::{Projectile, ProjectileSystem};
-use crate::enemy::Hostility;
+use super::{HitEvent, Projectile, ProjectileSystem};
+use crate::enemy::{Enemy, Hostility};
+use crate::fx::particles::{ParticleSpec, SpawnParticles};
+use crate::interactions::acceptor::Acceptor;
 use crate::{GameAssets, GameState};
 
 /// Residue effects.
@@ -14,7 +23,7 @@ pub struct ResiduePlugin;
 
 impl Plugin for ResiduePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_residue).add_systems(
+        app.add_systems(
             Update,
             create_residue
                 .run_if(in_state(GameState::InGame))
@@ -25,71 +34,161 @@ impl Plugin for ResiduePlugin {
     }
 }
 
-/// A residue.
-///
-/// After playing the animation (defined by a range in the texture atlas), it
-/// will despawn.
-#[derive(Clone, Component, Debug, Default)]
-pub struct Residue {
-    /// The range of animation frames.
-    pub animation_range: Range<usize>,
+/// What a projectile was absorbed by, as far as residue is concerned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ResidueSurface {
+    /// Level collision, platforms and anything else.
+    #[default]
+    Solid,
+    /// An [`Enemy`].
+    Enemy,
+    /// An [`Acceptor`].
+    Acceptor,
+}
+
+/// What a single residue looks like.
+#[derive(Clone, Debug)]
+pub struct ResidueStyle {
+    /// The range of animation frames in the projectile sheet.
+    pub frames: Range<usize>,
     /// The duration of each frame.
-    pub timer: Timer,
+    pub frame_duration: Duration,
+    /// The scale of the residue.
+    pub scale: f32,
+    /// Whether the residue is turned so its top faces away from what was hit.
+    pub align_to_normal: bool,
 }
 
-impl Residue {
-    pub fn new(range: Range<usize>, duration: Duration) -> Residue {
-        Residue {
-            animation_range: range,
-            timer: Timer::new(duration, TimerMode::Once),
+impl ResidueStyle {
+    /// Creates a new `ResidueStyle`.
+    pub fn new(frames: Range<usize>, frame_duration: Duration) -> ResidueStyle {
+        ResidueStyle {
+            frames,
+            frame_duration,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the scale.
+    pub fn scale(self, scale: f32) -> ResidueStyle {
+        ResidueStyle { scale, ..self }
+    }
+
+    /// Turns the residue to face away from what was hit.
+    pub fn align_to_normal(self) -> ResidueStyle {
+        ResidueStyle {
+            align_to_normal: true,
+            ..self
         }
     }
 }
 
-fn update_residue(
-    mut commands: Commands,
-    mut residue_query: Query<(Entity, &mut Residue, &mut TextureAtlasSprite)>,
-    time: Res<Time>,
-) {
-    for (entity, mut residue, mut sprite) in residue_query.iter_mut() {
-        // tick
-        residue.timer.tick(time.delta());
-
-        if residue.timer.finished() {
-            residue.animation_range.start += 1;
-
-            if residue.animation_range.start == residue.animation_range.end {
-                commands.entity(entity).despawn_recursive();
-            } else {
-                residue.timer.reset();
-            }
+impl Default for ResidueStyle {
+    fn default() -> ResidueStyle {
+        ResidueStyle {
+            frames: 18..20,
+            frame_duration: Duration::from_millis(100),
+            scale: 1.,
+            align_to_normal: false,
         }
+    }
+}
+
+/// The residue a projectile leaves behind when it is absorbed.
+///
+/// Projectiles without one leave the default [`ResidueStyle`].
+#[derive(Clone, Component, Debug)]
+pub struct ResidueSpec {
+    /// The style used when there is nothing more specific.
+    pub style: ResidueStyle,
+    /// Styles for specific surfaces.
+    ///
+    /// A surface mapped to `None` leaves no residue.
+    pub surfaces: HashMap<ResidueSurface, Option<ResidueStyle>>,
+}
+
+impl ResidueSpec {
+    /// Creates a new `ResidueSpec` that leaves the same residue everywhere.
+    pub fn new(style: ResidueStyle) -> ResidueSpec {
+        ResidueSpec {
+            style,
+            surfaces: HashMap::new(),
+        }
+    }
 
-        sprite.index = residue.animation_range.start;
+    /// Sets the style for a surface, or `None` for no residue.
+    pub fn on(mut self, surface: ResidueSurface, style: Option<ResidueStyle>) -> ResidueSpec {
+        self.surfaces.insert(surface, style);
+        self
+    }
+
+    /// The style left on a surface, if any.
+    pub fn style(&self, surface: ResidueSurface) -> Option<&ResidueStyle> {
+        match self.surfaces.get(&surface) {
+            Some(style) => style.as_ref(),
+            None => Some(&self.style),
+        }
+    }
+}
+
+impl Default for ResidueSpec {
+    fn default() -> ResidueSpec {
+        ResidueSpec::new(ResidueStyle::default())
     }
 }
 
 fn create_residue(
     mut commands: Commands,
-    projectile_query: Query<(&GlobalTransform, &Hostility, &Projectile)>,
+    mut hit_events: EventReader<HitEvent>,
+    projectile_query: Query<(
+        Entity,
+        &GlobalTransform,
+        &Hostility,
+        &Projectile,
+        Option<&ResidueSpec>,
+    )>,
+    surface_query: Query<(Has<Enemy>, Has<Acceptor>)>,
     assets: Res<GameAssets>,
 ) {
-    for (location, hostility, projectile) in projectile_query.iter() {
+    // the first thing each projectile hit, and how
+    let mut hits: HashMap<Entity, (ResidueSurface, Option<Vec2>)> = HashMap::new();
+
+    for ev in hit_events.iter() {
+        let surface = match surface_query.get(ev.entity) {
+            Ok((true, _)) => ResidueSurface::Enemy,
+            Ok((_, true)) => ResidueSurface::Acceptor,
+            _ => ResidueSurface::Solid,
+        };
+
+        hits.entry(ev.projectile).or_insert((surface, ev.normal));
+    }
+
+    let default_spec = ResidueSpec::default();
+
+    for (entity, location, hostility, projectile, spec) in projectile_query.iter() {
         if !projectile.absorbed {
             continue;
         }
 
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: assets.projectile_sheet.clone(),
-                sprite: TextureAtlasSprite {
-                    color: hostility.color(),
-                    ..TextureAtlasSprite::new(18)
-                },
-                transform: Transform::from_translation(location.translation()),
-                ..Default::default()
-            },
-            Residue::new(18..20, Duration::from_millis(100)),
-        ));
+        let (surface, normal) = hits.get(&entity).copied().unwrap_or_default();
+
+        let Some(style) = spec.unwrap_or(&default_spec).style(surface) else {
+            continue;
+        };
+
+        let mut spec = ParticleSpec::atlas(
+            assets.projectile_sheet.clone(),
+            style.frames.clone(),
+            style.frame_duration,
+            hostility.color(),
+        )
+        .scale(style.scale);
+
+        if let Some(normal) = normal.filter(|_| style.align_to_normal) {
+            // the residue's up is the normal
+            spec = spec.rotation(normal.y.atan2(normal.x) - FRAC_PI_2);
+        }
+
+        commands.add(SpawnParticles::new(spec, location.translation()));
     }
 }
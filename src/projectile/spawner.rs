@@ -5,6 +5,8 @@ use bevy::prelude::*;
 use std::time::Duration;
 
 use super::prefab::{CreateProjectile, ProjectilePrefab};
+use super::split::SplitOnAbsorb;
+use crate::enemy::Hostility;
 use crate::GameState;
 
 pub struct ProjectileSpawnerPlugin;
@@ -15,7 +17,8 @@ impl Plugin for ProjectileSpawnerPlugin {
             .add_systems(Update, update_charge.in_set(SpawnerSystem::TickTimer))
             .add_systems(
                 Update,
-                spawn_projectile
+                (spawn_projectile, fire_bursts)
+                    .chain()
                     .run_if(in_state(GameState::InGame))
                     .in_set(SpawnerSystem::Spawn)
                     .after(SpawnerSystem::TickTimer),
@@ -44,23 +47,137 @@ impl SpawnProjectile {
     pub fn new(subject: Entity) -> SpawnProjectile {
         SpawnProjectile { subject }
     }
+
+    /// The entity whose [`Spawner`] fires.
+    pub fn subject(&self) -> Entity {
+        self.subject
+    }
 }
 
 /// A spawner for projectiles.
+///
+/// Projectiles take the [`Hostility`] of the spawner, or are friendly if it
+/// has none.
 #[derive(Clone, Component, Debug)]
 pub struct Spawner {
     /// The initial velocity of the projectile.
+    ///
+    /// For [`Weapon::Beam`], only the direction is used.
     pub initial_velocity: Vec2,
+    /// What the spawner fires.
+    pub weapon: Weapon,
+    /// How the spawner fires.
+    pub pattern: FirePattern,
+    /// Makes every projectile the spawner fires split when it is absorbed.
+    pub split: Option<SplitOnAbsorb>,
+    burst: Option<Burst>,
+}
+
+impl Spawner {
+    /// The prefab of a single projectile going a direction.
+    pub fn prefab(&self, initial_velocity: Vec2) -> ProjectilePrefab {
+        match self.weapon {
+            Weapon::Note => ProjectilePrefab::QuarterRest { initial_velocity },
+            Weapon::Beam { range } => ProjectilePrefab::Beam {
+                direction: initial_velocity,
+                range,
+            },
+        }
+    }
+
+    /// Checks if the spawner is in the middle of a burst.
+    pub fn is_bursting(&self) -> bool {
+        self.burst.is_some()
+    }
+
+    fn fire(&self, commands: &mut Commands, location: Vec3, hostility: Hostility) {
+        for velocity in self.pattern.volley(self.initial_velocity) {
+            let prefab = self.prefab(velocity);
+
+            let mut create = CreateProjectile::new(prefab, location).hostility(hostility);
+
+            if let Some(split) = &self.split {
+                create = create.split(split.clone());
+            }
+
+            commands.add(create);
+        }
+    }
 }
 
 impl Default for Spawner {
     fn default() -> Spawner {
         Spawner {
             initial_velocity: Vec2::new(0., 0.),
+            weapon: Weapon::default(),
+            pattern: FirePattern::default(),
+            split: None,
+            burst: None,
         }
     }
 }
 
+/// How a [`Spawner`] fires its projectiles.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FirePattern {
+    /// One projectile.
+    #[default]
+    Single,
+    /// A number of projectiles one after the other.
+    ///
+    /// Later projectiles in the burst follow the spawner's aim.
+    Burst { count: u32, interval: Duration },
+    /// A number of projectiles fanned out over an arc, in radians.
+    Spread { count: u32, arc: f32 },
+    /// A number of projectiles evenly spaced in a circle.
+    Radial { count: u32 },
+}
+
+impl FirePattern {
+    /// The velocities of the projectiles fired at once, given the velocity of
+    /// a single projectile.
+    pub fn volley(&self, velocity: Vec2) -> Vec<Vec2> {
+        match *self {
+            FirePattern::Single | FirePattern::Burst { .. } => vec![velocity],
+            FirePattern::Spread { count, arc } => (0..count)
+                .map(|i| {
+                    let angle = if count > 1 {
+                        arc * (i as f32 / (count - 1) as f32 - 0.5)
+                    } else {
+                        0.
+                    };
+
+                    Vec2::from_angle(angle).rotate(velocity)
+                })
+                .collect(),
+            FirePattern::Radial { count } => (0..count)
+                .map(|i| {
+                    let angle = std::f32::consts::TAU * i as f32 / count as f32;
+
+                    Vec2::from_angle(angle).rotate(velocity)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The rest of a [`FirePattern::Burst`].
+#[derive(Clone, Debug)]
+struct Burst {
+    remaining: u32,
+    timer: Timer,
+}
+
+/// The kind of projectile a [`Spawner`] fires.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Weapon {
+    /// A note that travels with the initial velocity.
+    #[default]
+    Note,
+    /// A hitscan beam with a range in world units.
+    Beam { range: f32 },
+}
+
 /// A charge for a spawner.
 #[derive(Clone, Component, Debug)]
 pub struct Charge {
@@ -86,6 +203,13 @@ impl Charge {
         self
     }
 
+    /// Fills every charge right away.
+    pub fn fill(&mut self) {
+        self.charges = self.max_charges;
+        self.timer.reset();
+        self.timer.pause();
+    }
+
     /// Takes a charge.
     pub fn use_charge(&mut self) {
         self.charges -= 1;
@@ -122,14 +246,25 @@ fn update_charge(mut charge_query: Query<&mut Charge>, time: Res<Time>) {
 fn spawn_projectile(
     mut commands: Commands,
     mut projectile_spawns: EventReader<SpawnProjectile>,
-    mut spawner_query: Query<(&GlobalTransform, &Spawner, Option<&mut Charge>)>,
+    mut spawner_query: Query<(
+        &GlobalTransform,
+        &mut Spawner,
+        Option<&mut Charge>,
+        Option<&Hostility>,
+    )>,
 ) {
     for ev in projectile_spawns.iter() {
-        let Ok((transform, spawner, charge)) = spawner_query.get_mut(ev.subject) else {
-            bevy::log::warn!("spawn event for entity without spawner");
+        let Ok((transform, mut spawner, charge, hostility)) = spawner_query.get_mut(ev.subject)
+        else {
+            crate::game_warn!(Projectile, "spawn event for entity without spawner");
             continue;
         };
 
+        // finish the burst first
+        if spawner.is_bursting() {
+            continue;
+        }
+
         let spawn = match charge {
             Some(mut charge) if charge.has_charge() => {
                 charge.use_charge();
@@ -139,13 +274,55 @@ fn spawn_projectile(
             None => true,
         };
 
-        if spawn {
-            commands.add(CreateProjectile::new(
-                ProjectilePrefab::QuarterRest {
-                    initial_velocity: spawner.initial_velocity,
-                },
-                transform.translation(),
-            ));
+        if !spawn {
+            continue;
+        }
+
+        let hostility = hostility.copied().unwrap_or_default();
+
+        spawner.fire(&mut commands, transform.translation(), hostility);
+
+        if let FirePattern::Burst { count, interval } = spawner.pattern {
+            if count > 1 {
+                spawner.burst = Some(Burst {
+                    remaining: count - 1,
+                    timer: Timer::new(interval, TimerMode::Repeating),
+                });
+            }
+        }
+    }
+}
+
+fn fire_bursts(
+    mut commands: Commands,
+    mut spawner_query: Query<(&GlobalTransform, &mut Spawner, Option<&Hostility>)>,
+    time: Res<Time>,
+) {
+    for (transform, mut spawner, hostility) in spawner_query.iter_mut() {
+        // don't trip change detection on idle spawners
+        if !spawner.is_bursting() {
+            continue;
+        }
+
+        let Some(mut burst) = spawner.burst.take() else {
+            continue;
+        };
+
+        burst.timer.tick(time.delta());
+
+        for _ in 0..burst.timer.times_finished_this_tick() {
+            if burst.remaining == 0 {
+                break;
+            }
+
+            let hostility = hostility.copied().unwrap_or_default();
+
+            spawner.fire(&mut commands, transform.translation(), hostility);
+            burst.remaining -= 1;
+        }
+
+        if burst.remaining > 0 {
+            spawner.burst = Some(burst);
         }
     }
 }
@@ -0,0 +1,127 @@
+//! Projectiles that split into more projectiles.
+
+use bevy::prelude::*;
+
+use super::{
+    prefab::{CreateProjectile, ProjectilePrefab},
+    DespawnEvent, HitEvent, Projectile, ProjectileSystem,
+};
+use crate::despawn::DespawnReason;
+use crate::enemy::Hostility;
+
+/// Split plugin.
+pub struct SplitPlugin;
+
+impl Plugin for SplitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            split_projectiles
+                .in_set(ProjectileSystem::Despawn)
+                .after(ProjectileSystem::Event)
+                .before(super::despawn_projectiles),
+        );
+    }
+}
+
+/// Splits a projectile into a fan of projectiles when it is absorbed or runs
+/// out of [`TimeToLive`](super::TimeToLive).
+///
+/// Anything that absorbs the projectile splits it, like a wall, an enemy or a
+/// shield, but not an acceptor, which takes it in whole. The fan bounces back
+/// off of what absorbed it. Projectiles that are culled for going out of
+/// bounds or over the [budget](super::budget) just disappear.
+///
+/// The new projectiles share the strength of the old one between them, so
+/// they are smaller.
+#[derive(Clone, Component, Debug)]
+pub struct SplitOnAbsorb {
+    /// The prefab of the new projectiles.
+    ///
+    /// The velocity of the prefab is replaced, see
+    /// [`ProjectilePrefab::with_velocity`].
+    pub prefab: ProjectilePrefab,
+    /// How many projectiles to split into.
+    pub count: u32,
+    /// The angle between the outermost projectiles of the fan, in radians.
+    pub spread: f32,
+}
+
+fn split_projectiles(
+    mut commands: Commands,
+    mut hit_events: EventReader<HitEvent>,
+    mut despawn_events: EventReader<DespawnEvent>,
+    projectile_query: Query<(&GlobalTransform, &Hostility, &Projectile, &SplitOnAbsorb)>,
+) {
+    // (projectile, direction the fan faces)
+    let mut splits: Vec<(Entity, Vec2)> = Vec::new();
+
+    for ev in hit_events.iter() {
+        let Ok((_, _, projectile, _)) = projectile_query.get(ev.projectile) else {
+            continue;
+        };
+
+        // bouncing and accepted projectiles do not split
+        if !projectile.absorbed {
+            continue;
+        }
+
+        // fan out away from whatever absorbed it
+        let incoming = projectile.incoming_velocity();
+        let velocity = match ev.normal {
+            Some(normal) => incoming - 2. * incoming.dot(normal) * normal,
+            None => -incoming,
+        };
+
+        splits.push((ev.projectile, velocity));
+    }
+
+    for ev in despawn_events.iter() {
+        let Ok((_, _, projectile, _)) = projectile_query.get(ev.projectile) else {
+            continue;
+        };
+
+        // projectiles culled by the budget just disappear
+        if ev.reason != DespawnReason::Expired {
+            continue;
+        }
+
+        splits.push((ev.projectile, projectile.incoming_velocity()));
+    }
+
+    splits.sort_by_key(|(entity, _)| *entity);
+    splits.dedup_by_key(|(entity, _)| *entity);
+
+    for (entity, velocity) in splits {
+        let Ok((transform, hostility, projectile, split)) = projectile_query.get(entity) else {
+            continue;
+        };
+
+        // nowhere to fan out to
+        if split.count == 0 || velocity == Vec2::ZERO {
+            continue;
+        }
+
+        let strength = projectile.strength / split.count as f32;
+
+        for i in 0..split.count {
+            // evenly spaced from one side of the spread to the other
+            let angle = if split.count > 1 {
+                split.spread * (i as f32 / (split.count - 1) as f32 - 0.5)
+            } else {
+                0.
+            };
+
+            let velocity = Vec2::from_angle(angle).rotate(velocity);
+
+            commands.add(
+                CreateProjectile::new(
+                    split.prefab.with_velocity(velocity),
+                    transform.translation(),
+                )
+                .hostility(*hostility)
+                .strength(strength),
+            );
+        }
+    }
+}
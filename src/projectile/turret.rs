@@ -0,0 +1,199 @@
+//! Turrets that fire on their own.
+
+use bevy::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::time::Duration;
+
+use super::spawner::{Charge, SpawnProjectile, Spawner, SpawnerSystem};
+use crate::enemy::Hostility;
+use crate::player::LocalPlayer;
+use crate::GameState;
+
+/// Turret plugin.
+pub struct TurretPlugin;
+
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<TurretBundle>("Turret")
+            .add_systems(Update, create_turret_visual)
+            .add_systems(
+                Update,
+                fire_turrets
+                    .run_if(in_state(GameState::InGame))
+                    .after(SpawnerSystem::TickTimer)
+                    .before(SpawnerSystem::Spawn),
+            );
+    }
+}
+
+/// A bundle for a [`Turret`].
+#[derive(Bundle)]
+pub struct TurretBundle {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub spawner: Spawner,
+    pub charge: Charge,
+    pub hostility: Hostility,
+    pub turret: Turret,
+}
+
+impl Default for TurretBundle {
+    fn default() -> TurretBundle {
+        TurretBundle {
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+            visibility: Visibility::default(),
+            computed_visibility: ComputedVisibility::default(),
+            spawner: Spawner::default(),
+            charge: Charge::default(),
+            hostility: Hostility::Hostile,
+            turret: Turret::default(),
+        }
+    }
+}
+
+impl LdtkEntity for TurretBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        // degrees counterclockwise from the right
+        let angle = entity_instance
+            .get_float_field("Direction")
+            .ok()
+            .copied()
+            .unwrap_or_default()
+            .to_radians();
+        let speed = entity_instance
+            .get_float_field("Speed")
+            .ok()
+            .copied()
+            .unwrap_or(Turret::DEFAULT_SPEED);
+        let interval = entity_instance
+            .get_float_field("Interval")
+            .ok()
+            .copied()
+            .unwrap_or(Turret::DEFAULT_INTERVAL);
+        let sensor_radius = entity_instance
+            .get_maybe_float_field("SensorRadius")
+            .ok()
+            .copied()
+            .flatten();
+        let hostility = match entity_instance.get_bool_field("Friendly") {
+            Ok(true) => Hostility::Friendly,
+            _ => Hostility::Hostile,
+        };
+
+        TurretBundle {
+            spawner: Spawner {
+                initial_velocity: Vec2::from_angle(angle) * speed,
+                ..Default::default()
+            },
+            charge: Charge::new(Duration::from_secs_f32(interval.max(0.05)), 1),
+            hostility,
+            turret: Turret { sensor_radius },
+            ..Default::default()
+        }
+    }
+}
+
+/// A [`Spawner`] that fires whenever it has a [`Charge`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct Turret {
+    /// If set, the turret only fires while the player is this close, in world
+    /// units.
+    pub sensor_radius: Option<f32>,
+}
+
+impl Turret {
+    /// The speed of turret projectiles if the level doesn't say.
+    pub const DEFAULT_SPEED: f32 = 96.;
+    /// The seconds between shots if the level doesn't say.
+    pub const DEFAULT_INTERVAL: f32 = 1.5;
+}
+
+/// The visual part of a [`Turret`].
+#[derive(Clone, Component, Debug, Default)]
+pub struct TurretVisual;
+
+fn create_turret_visual(
+    mut commands: Commands,
+    new_turrets_query: Query<(Entity, &Spawner, &Hostility), Added<Turret>>,
+) {
+    for (entity, spawner, hostility) in new_turrets_query.iter() {
+        let rot = spawner.initial_velocity.y.atan2(spawner.initial_velocity.x);
+
+        commands.entity(entity).with_children(|parent| {
+            // base
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.35, 0.35, 0.4),
+                        custom_size: Some(Vec2::new(10., 10.)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                TurretVisual,
+            ));
+
+            // barrel
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: hostility.color(),
+                        custom_size: Some(Vec2::new(8., 4.)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_rotation(Quat::from_axis_angle(Vec3::Z, rot))
+                        * Transform::from_xyz(4., 0., 0.1),
+                    ..Default::default()
+                },
+                TurretVisual,
+            ));
+        });
+    }
+}
+
+fn fire_turrets(
+    turret_query: Query<(Entity, &GlobalTransform, &Turret, &Charge)>,
+    player_query: Query<&GlobalTransform, With<LocalPlayer>>,
+    mut spawn_events: EventWriter<SpawnProjectile>,
+) {
+    let player = player_query
+        .get_single()
+        .ok()
+        .map(|t| t.translation().truncate());
+
+    for (entity, transform, turret, charge) in turret_query.iter() {
+        if !charge.has_charge() {
+            continue;
+        }
+
+        if let Some(radius) = turret.sensor_radius {
+            let position = transform.translation().truncate();
+
+            let in_range = player
+                .map(|player| player.distance_squared(position) <= radius * radius)
+                .unwrap_or_default();
+
+            if !in_range {
+                continue;
+            }
+        }
+
+        spawn_events.send(SpawnProjectile::new(entity));
+    }
+}
@@ -0,0 +1,155 @@
+//! Seeded randomness.
+//!
+//! Everything random in the game should draw from [`GameRng`] so that a run
+//! can be replayed exactly from its seed.
+
+use bevy::prelude::*;
+
+/// Random number plugin.
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>();
+    }
+}
+
+/// The game's random number generator.
+///
+/// This is a small xorshift generator. It is not at all secure, but it is
+/// fast and gives the same numbers for the same seed on every platform.
+#[derive(Clone, Debug, Resource)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+}
+
+impl GameRng {
+    /// The seed used if none is given.
+    pub const DEFAULT_SEED: u64 = 0x746f_7468_6562_6561;
+
+    /// Creates a new `GameRng` from a seed.
+    pub fn new(seed: u64) -> GameRng {
+        GameRng {
+            seed,
+            state: splitmix(seed),
+        }
+    }
+
+    /// The seed the generator started with.
+    ///
+    /// Recording this is enough to replay everything drawn from the
+    /// generator.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts the generator over from a new seed.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = GameRng::new(seed);
+    }
+
+    /// Returns the next random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a random `f32` in `0..1`.
+    pub fn next_f32(&mut self) -> f32 {
+        // top 24 bits fit exactly in the mantissa
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a random `f32` in `min..max`.
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns a random `u32` in `min..max`.
+    ///
+    /// Returns `min` if the range is empty.
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+
+        min + (self.next_u64() % (max - min) as u64) as u32
+    }
+
+    /// Returns `true` with a chance of `p`.
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+
+    /// Returns `1.` or `-1.` with equal chance.
+    pub fn sign(&mut self) -> f32 {
+        if self.chance(0.5) {
+            1.
+        } else {
+            -1.
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> GameRng {
+        GameRng::new(GameRng::DEFAULT_SEED)
+    }
+}
+
+/// Scrambles a seed so that similar seeds give different sequences.
+///
+/// This never returns zero, which would get xorshift stuck.
+fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    if z == 0 {
+        0x9e37_79b9_7f4a_7c15
+    } else {
+        z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+
+        for _ in 0..64 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn reseed_starts_over() {
+        let mut rng = GameRng::new(7);
+        let first = rng.next_u64();
+
+        rng.reseed(7);
+
+        assert_eq!(rng.seed(), 7);
+        assert_eq!(rng.next_u64(), first);
+    }
+
+    #[test]
+    fn sign_is_unit() {
+        let mut rng = GameRng::default();
+
+        for _ in 0..64 {
+            assert_eq!(rng.sign().abs(), 1.);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Versioned save files.
+//!
+//! Everything the game writes to disk, like the [`Settings`], is
+//! [`Versioned`]. The file keeps the version of the format it was written in
+//! under a `version` field, and files from older versions are upgraded on
+//! load by running each [`Migration`] from their version up to the current
+//! one, instead of being thrown away. Files without a version are version
+//! `0`.
+//!
+//! When a format changes, bump its [`Versioned::VERSION`] and add a migration
+//! to the end of its [`Versioned::MIGRATIONS`] that turns the old shape into
+//! the new one.
+//!
+//! [`Settings`]: crate::settings::Settings
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::fmt;
+
+/// A format that is saved to disk.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The version of the format the game writes.
+    const VERSION: u32;
+    /// The upgrades from each older version to the next.
+    ///
+    /// The migration at index `n` upgrades version `n` to `n + 1`, so there
+    /// is one for every version before [`Versioned::VERSION`].
+    const MIGRATIONS: &'static [Migration];
+}
+
+/// Upgrades a file by one version, in place.
+///
+/// The file is a map of its fields, without its `version`.
+pub type Migration = fn(&mut ron::Map) -> Result<(), String>;
+
+/// Why a file couldn't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file isn't valid RON.
+    Parse(ron::error::SpannedError),
+    /// The file isn't a struct or map, so it can't have a version.
+    NotAMap,
+    /// The file was written by a newer version of the game.
+    TooNew(u32),
+    /// There is no migration from a version.
+    MissingMigration(u32),
+    /// A migration failed.
+    Migration(u32, String),
+    /// The upgraded file doesn't fit the current format.
+    Deserialize(ron::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Parse(err) => write!(f, "{}", err),
+            LoadError::NotAMap => f.write_str("expected a struct"),
+            LoadError::TooNew(version) => write!(f, "version {} is too new", version),
+            LoadError::MissingMigration(version) => {
+                write!(f, "no migration from version {}", version)
+            }
+            LoadError::Migration(version, err) => {
+                write!(f, "migrating from version {}: {}", version, err)
+            }
+            LoadError::Deserialize(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Reads a file, upgrading it from an older version if it needs to be.
+pub fn from_str<T: Versioned>(contents: &str) -> Result<T, LoadError> {
+    let value: ron::Value = ron::from_str(contents).map_err(LoadError::Parse)?;
+
+    let ron::Value::Map(mut map) = value else {
+        return Err(LoadError::NotAMap);
+    };
+
+    let key = ron::Value::String("version".into());
+
+    let version = match map.remove(&key) {
+        Some(ron::Value::Number(number)) => number
+            .as_i64()
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or_default(),
+        _ => 0,
+    };
+
+    if version > T::VERSION {
+        return Err(LoadError::TooNew(version));
+    }
+
+    for from in version..T::VERSION {
+        let migration = T::MIGRATIONS
+            .get(from as usize)
+            .ok_or(LoadError::MissingMigration(from))?;
+
+        migration(&mut map).map_err(|err| LoadError::Migration(from, err))?;
+
+        crate::game_info!(Settings, "migrated save from version {}", from);
+    }
+
+    map.insert(
+        key,
+        ron::Value::Number(ron::Number::from(T::VERSION as i64)),
+    );
+
+    ron::Value::Map(map)
+        .into_rust()
+        .map_err(LoadError::Deserialize)
+}
+
+/// Writes a file.
+///
+/// The format should have a `version` field that is set to
+/// [`Versioned::VERSION`], so the file can be upgraded later.
+pub fn to_string<T: Versioned>(value: &T) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    use crate::settings::Settings;
+
+    /// A format that skipped writing the migration for version `1`.
+    #[derive(Serialize, Deserialize)]
+    struct Gap {
+        version: u32,
+    }
+
+    impl Versioned for Gap {
+        const VERSION: u32 = 2;
+        const MIGRATIONS: &'static [Migration] = &[|_| Ok(())];
+    }
+
+    #[test]
+    fn versionless_file_migrates() {
+        let settings: Settings = from_str("(latency_offset: 0.05, calibrated: true)").unwrap();
+
+        assert_eq!(settings.version, Settings::VERSION);
+        assert_eq!(settings.latency_offset, 0.05);
+        assert!(settings.calibrated);
+        assert!(!settings.telemetry);
+        assert!(settings.resolution.is_none());
+    }
+
+    #[test]
+    fn version_1_migrates() {
+        let settings: Settings = from_str("(version: 1, latency_offset: 0.1)").unwrap();
+
+        assert_eq!(settings.version, Settings::VERSION);
+        assert_eq!(settings.latency_offset, 0.1);
+        assert!(!settings.telemetry);
+        assert!(settings.resolution.is_none());
+    }
+
+    #[test]
+    fn version_2_migrates() {
+        let settings: Settings =
+            from_str("(version: 2, latency_offset: -0.02, telemetry: true)").unwrap();
+
+        assert_eq!(settings.version, Settings::VERSION);
+        assert_eq!(settings.latency_offset, -0.02);
+        // kept from the file, only `resolution` is new
+        assert!(settings.telemetry);
+        assert!(settings.resolution.is_none());
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let settings = Settings {
+            version: Settings::VERSION,
+            latency_offset: 0.08,
+            calibrated: true,
+            telemetry: true,
+            ..Default::default()
+        };
+
+        let loaded: Settings = from_str(&to_string(&settings).unwrap()).unwrap();
+
+        assert_eq!(loaded.version, Settings::VERSION);
+        assert_eq!(loaded.latency_offset, settings.latency_offset);
+        assert_eq!(loaded.calibrated, settings.calibrated);
+        assert_eq!(loaded.telemetry, settings.telemetry);
+        assert_eq!(loaded.resolution, settings.resolution);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let result = from_str::<Settings>("(version: 99)");
+
+        assert!(matches!(result, Err(LoadError::TooNew(99))));
+    }
+
+    #[test]
+    fn missing_migration_is_rejected() {
+        let result = from_str::<Gap>("(version: 0)");
+
+        assert!(matches!(result, Err(LoadError::MissingMigration(1))));
+    }
+}
@@ -0,0 +1,146 @@
+//! Score and combos.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use crate::enemy::{EnemyDiedEvent, EnemySystem};
+
+/// Score plugin.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .add_event::<ScoreEvent>()
+            .add_event::<ComboEvent>()
+            .add_systems(
+                Update,
+                (tick_combo, score_kills)
+                    .chain()
+                    .in_set(ScoreSystem::Score)
+                    .after(EnemySystem::Despawn),
+            );
+    }
+}
+
+/// Score systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
+pub enum ScoreSystem {
+    /// Points are awarded and [`ScoreEvent`]s are sent.
+    Score,
+}
+
+/// The player's score.
+#[derive(Debug, Resource)]
+pub struct Score {
+    points: u64,
+    combo: u32,
+    combo_timer: Timer,
+}
+
+impl Score {
+    /// Points for a single kill, before the combo multiplier.
+    pub const KILL_POINTS: u64 = 100;
+    /// How long the combo lasts without another kill.
+    pub const COMBO_WINDOW: Duration = Duration::from_secs(3);
+    /// Every this many kills in a combo sends a [`ComboEvent`].
+    pub const COMBO_MILESTONE: u32 = 5;
+
+    /// The total points.
+    pub fn points(&self) -> u64 {
+        self.points
+    }
+
+    /// The number of kills in the current combo.
+    pub fn combo(&self) -> u32 {
+        self.combo
+    }
+
+    /// Awards points, multiplied by the current combo.
+    ///
+    /// Returns the points that were actually added.
+    pub fn award(&mut self, points: u64) -> u64 {
+        let points = points * self.combo.max(1) as u64;
+        self.points += points;
+        points
+    }
+
+    /// Adds points as they are, without the combo multiplier.
+    pub fn add(&mut self, points: u64) {
+        self.points += points;
+    }
+
+    /// Resets the score.
+    pub fn reset(&mut self) {
+        self.points = 0;
+        self.combo = 0;
+    }
+}
+
+impl Default for Score {
+    fn default() -> Score {
+        Score {
+            points: 0,
+            combo: 0,
+            combo_timer: Timer::new(Score::COMBO_WINDOW, TimerMode::Once),
+        }
+    }
+}
+
+/// Points were awarded.
+#[derive(Debug, Event)]
+pub struct ScoreEvent {
+    /// How many points were added.
+    pub points: u64,
+    /// The combo at the time.
+    pub combo: u32,
+    /// Where the points were earned, if anywhere.
+    pub position: Option<Vec2>,
+    /// The enemy kill that earned the points, if any.
+    pub kill: Option<Entity>,
+}
+
+/// A combo hit a milestone.
+#[derive(Debug, Event)]
+pub struct ComboEvent {
+    /// The number of kills in the combo.
+    pub combo: u32,
+}
+
+fn tick_combo(mut score: ResMut<Score>, time: Res<Time>) {
+    if score.combo == 0 {
+        return;
+    }
+
+    score.combo_timer.tick(time.delta());
+
+    if score.combo_timer.finished() {
+        score.combo = 0;
+    }
+}
+
+fn score_kills(
+    mut died_events: EventReader<EnemyDiedEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut combo_events: EventWriter<ComboEvent>,
+    mut score: ResMut<Score>,
+) {
+    for ev in died_events.iter() {
+        score.combo += 1;
+        score.combo_timer.reset();
+
+        let points = score.award(Score::KILL_POINTS);
+
+        score_events.send(ScoreEvent {
+            points,
+            combo: score.combo,
+            position: Some(ev.position),
+            kill: Some(ev.entity),
+        });
+
+        if score.combo % Score::COMBO_MILESTONE == 0 {
+            combo_events.send(ComboEvent { combo: score.combo });
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Player settings.
+//!
+//! [`Settings`] are read from [`Settings::PATH`] when the game starts, upgraded
+//! from older versions by [`save`](crate::save), and written back whenever
+//! they change. On the web there is no file to write to, so they are kept
+//! in the page's local storage under the same name.
+//!
+//! Effects read the [`AccessibilitySettings`] resource, which is kept in sync
+//! with [`Settings::accessibility`].
+
+use bevy::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera::resolution::RenderScale;
+use crate::save::{self, Migration, Versioned};
+
+/// Settings plugin.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = Settings::load();
+
+        app.insert_resource(settings.accessibility.clone())
+            .insert_resource(settings)
+            .add_systems(
+                PreUpdate,
+                sync_accessibility.run_if(resource_changed::<Settings>()),
+            )
+            .add_systems(
+                Last,
+                save_settings.run_if(
+                    resource_changed::<Settings>().and_then(not(resource_added::<Settings>())),
+                ),
+            );
+    }
+}
+
+/// The player's settings.
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The version of the format the settings were saved in.
+    pub version: u32,
+    /// How late the player's taps land after a beat, in seconds, from the
+    /// sound and picture lagging behind and input taking time to arrive.
+    ///
+    /// Measured by the [calibration screen](crate::ui::calibration) and
+    /// applied by the [`BeatClock`](crate::beat::BeatClock).
+    pub latency_offset: f32,
+    /// Whether the player has been through the calibration screen, or
+    /// skipped it.
+    pub calibrated: bool,
+    /// Toggles for effects that some players can't or would rather not
+    /// handle.
+    pub accessibility: AccessibilitySettings,
+    /// How loud sounds are and how many play at once.
+    pub audio: AudioSettings,
+    /// Whether the player agreed to record [playtest
+    /// telemetry](crate::telemetry). Off unless they opt in.
+    pub telemetry: bool,
+    /// The resolution the game is drawn at, or `None` to let
+    /// [adaptive resolution](crate::camera::resolution) pick it from how
+    /// quickly frames are drawn.
+    pub resolution: Option<RenderScale>,
+}
+
+impl Settings {
+    /// Where settings are saved, relative to the working directory.
+    pub const PATH: &'static str = "settings.ron";
+
+    /// Loads the settings, or the defaults if there are none or they can't be
+    /// read.
+    pub fn load() -> Settings {
+        let Some(contents) = read() else {
+            return Settings::default();
+        };
+
+        match save::from_str(&contents) {
+            Ok(settings) => settings,
+            Err(err) => {
+                crate::game_warn!(Settings, "failed to parse {}: {}", Settings::PATH, err);
+                Settings::default()
+            }
+        }
+    }
+
+    /// Saves the settings.
+    pub fn save(&self) {
+        let contents = match save::to_string(self) {
+            Ok(contents) => contents,
+            Err(err) => {
+                crate::game_warn!(Settings, "failed to serialize settings: {}", err);
+                return;
+            }
+        };
+
+        write(&contents);
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            version: Settings::VERSION,
+            latency_offset: 0.,
+            calibrated: false,
+            accessibility: AccessibilitySettings::default(),
+            audio: AudioSettings::default(),
+            telemetry: false,
+            resolution: None,
+        }
+    }
+}
+
+impl Versioned for Settings {
+    const VERSION: u32 = 3;
+    const MIGRATIONS: &'static [Migration] = &[
+        // settings from before versions have every field version 1 does
+        |_| Ok(()),
+        // nobody opted into telemetry before it existed
+        |map| {
+            map.insert(
+                ron::Value::String("telemetry".into()),
+                ron::Value::Bool(false),
+            );
+            Ok(())
+        },
+        // the resolution was always picked for the player
+        |map| {
+            map.insert(
+                ron::Value::String("resolution".into()),
+                ron::Value::Option(None),
+            );
+            Ok(())
+        },
+    ];
+}
+
+/// Settings for the [mixer](crate::audio).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// The volume of every sound, where `1.` is the volume of the source.
+    pub volume: f32,
+    /// How many interface sounds play at once.
+    pub ui_voices: usize,
+    /// How many sound effects play at once.
+    pub effect_voices: usize,
+    /// How many projectile impacts play at once.
+    pub impact_voices: usize,
+    /// How many ambient sounds play at once.
+    pub ambience_voices: usize,
+    /// How loud ambient sounds are while an important sound plays.
+    pub duck_volume: f32,
+    /// The lowest priority of a sound that ducks ambient sounds.
+    pub duck_priority: u8,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings {
+            volume: 1.,
+            ui_voices: 4,
+            effect_voices: 12,
+            impact_voices: 6,
+            ambience_voices: 4,
+            duck_volume: 0.4,
+            duck_priority: 2,
+        }
+    }
+}
+
+/// Toggles for rumble, shaking, flashing and pulsing effects.
+///
+/// Every effect that rumbles, shakes, flashes or pulses checks this resource
+/// first. It mirrors [`Settings::accessibility`]; change that instead, so the
+/// change is saved.
+#[derive(Clone, Debug, PartialEq, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Whether gamepads vibrate.
+    pub rumble: bool,
+    /// Whether the camera shakes.
+    pub screen_shake: bool,
+    /// Whether sprites flash brighter than white, like when they are hit or
+    /// die.
+    pub flashing: bool,
+    /// Whether color pulses, like pipes lighting up as signals pass through
+    /// them, are shown at full strength.
+    pub color_pulses: bool,
+}
+
+impl AccessibilitySettings {
+    /// How strong color pulses are with [`AccessibilitySettings::color_pulses`]
+    /// off.
+    pub const CALM_PULSE: f32 = 0.25;
+
+    /// Limits a sprite color to at most white if flashing is off.
+    pub fn limit_flash(&self, color: Color) -> Color {
+        if self.flashing {
+            return color;
+        }
+
+        let [r, g, b, a] = color.as_rgba_f32();
+
+        Color::rgba(r.min(1.), g.min(1.), b.min(1.), a)
+    }
+
+    /// Scales the strength of a color pulse.
+    pub fn pulse(&self, strength: f32) -> f32 {
+        if self.color_pulses {
+            strength
+        } else {
+            strength * AccessibilitySettings::CALM_PULSE
+        }
+    }
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> AccessibilitySettings {
+        AccessibilitySettings {
+            rumble: true,
+            screen_shake: true,
+            flashing: true,
+            color_pulses: true,
+        }
+    }
+}
+
+fn sync_accessibility(settings: Res<Settings>, mut accessibility: ResMut<AccessibilitySettings>) {
+    // do not trip change detection
+    if *accessibility != settings.accessibility {
+        *accessibility = settings.accessibility.clone();
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> Option<String> {
+    match std::fs::read_to_string(Settings::PATH) {
+        Ok(contents) => Some(contents),
+        // first launch
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            crate::game_warn!(Settings, "failed to read {}: {}", Settings::PATH, err);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(contents: &str) {
+    match std::fs::write(Settings::PATH, contents) {
+        Ok(()) => crate::game_debug!(Settings, "saved {}", Settings::PATH),
+        Err(err) => {
+            crate::game_warn!(Settings, "failed to write {}: {}", Settings::PATH, err)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read() -> Option<String> {
+    match local_storage()?.get_item(Settings::PATH) {
+        // `None` on first launch
+        Ok(contents) => contents,
+        Err(err) => {
+            crate::game_warn!(Settings, "failed to read {}: {:?}", Settings::PATH, err);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(contents: &str) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+
+    match storage.set_item(Settings::PATH, contents) {
+        Ok(()) => crate::game_debug!(Settings, "saved {}", Settings::PATH),
+        Err(err) => {
+            crate::game_warn!(Settings, "failed to write {}: {:?}", Settings::PATH, err)
+        }
+    }
+}
+
+/// The page's local storage, which outlives the page.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    // private browsing and some embeds turn storage off
+    let storage = web_sys::window().and_then(|window| window.local_storage().ok().flatten());
+
+    if storage.is_none() {
+        crate::game_warn!(Settings, "no local storage, settings will not be saved");
+    }
+
+    storage
+}
@@ -0,0 +1,370 @@
+//! Playtest telemetry.
+//!
+//! Nothing is recorded unless the player opts in by pressing
+//! [`Telemetry::OPT_IN_KEY`], which flips [`Settings::telemetry`] and is
+//! saved with the rest of the settings. While it is on, the [`Telemetry`] log keeps where
+//! the player died, how long they spent in each level, how many shots they
+//! fired and which pipes signals went through, so level designers can put
+//! many playtests together and see where players struggle. Nothing in it
+//! says who the player is; times are seconds since the game started.
+//!
+//! The log is written as JSON to [`Telemetry::EXPORT_DIR`] when the game
+//! closes, or when [`Telemetry::EXPORT_KEY`] is pressed. On the web there is
+//! no file to write to, so the JSON is logged instead, to be copied out of
+//! the console.
+//!
+//! [`Settings::telemetry`]: crate::settings::Settings::telemetry
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::interactions::{InteractionSystem, SignalEvent};
+use crate::level::CurrentLevel;
+use crate::player::{LocalPlayer, PlayerDeath};
+use crate::projectile::spawner::SpawnProjectile;
+use crate::settings::Settings;
+
+/// Telemetry plugin.
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Telemetry>()
+            .add_systems(Update, toggle_telemetry)
+            .add_systems(
+                Update,
+                (
+                    record_deaths,
+                    record_shots,
+                    record_level_times.run_if(resource_changed::<CurrentLevel>()),
+                    record_signal_routes.after(InteractionSystem::TravelSignal),
+                )
+                    .run_if(telemetry_enabled),
+            )
+            .add_systems(
+                Last,
+                export_telemetry.run_if(telemetry_enabled.and_then(on_event::<AppExit>().or_else(
+                    |keyboard: Res<Input<KeyCode>>| keyboard.just_pressed(Telemetry::EXPORT_KEY),
+                ))),
+            );
+    }
+}
+
+/// Everything recorded this session.
+#[derive(Clone, Debug, Resource)]
+pub struct Telemetry {
+    /// Every time the player died.
+    pub deaths: Vec<Death>,
+    /// Every time the player went into a level, and how long they stayed.
+    pub visits: Vec<LevelVisit>,
+    /// How many shots the player fired in each level.
+    pub shots: HashMap<String, u32>,
+    /// How many signals went through each pipe.
+    pub routes: HashMap<Route, u32>,
+    session: u64,
+}
+
+impl Default for Telemetry {
+    fn default() -> Telemetry {
+        // one file a session, so playtests don't overwrite each other
+        #[cfg(not(target_arch = "wasm32"))]
+        let session = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+
+        #[cfg(target_arch = "wasm32")]
+        let session = 0;
+
+        Telemetry {
+            deaths: Vec::new(),
+            visits: Vec::new(),
+            shots: HashMap::new(),
+            routes: HashMap::new(),
+            session,
+        }
+    }
+}
+
+/// A death of the player.
+#[derive(Clone, Debug)]
+pub struct Death {
+    /// When the player died.
+    pub time: f32,
+    /// The level the player died in.
+    pub level: String,
+    /// Where the player died, in world coordinates.
+    pub position: Vec2,
+}
+
+/// A stay in a level.
+#[derive(Clone, Debug)]
+pub struct LevelVisit {
+    /// The level.
+    pub level: String,
+    /// When the player went in.
+    pub entered: f32,
+    /// How long the player stayed, or `None` if they are still there.
+    pub seconds: Option<f32>,
+}
+
+/// A pipe between two junctions that signals went through.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Route {
+    /// The level of the pipe.
+    pub level: String,
+    /// The tile the signals came from.
+    pub from: IVec2,
+    /// The tile the signals went to.
+    pub to: IVec2,
+}
+
+impl Telemetry {
+    /// The key that opts in to telemetry, or back out.
+    pub const OPT_IN_KEY: KeyCode = KeyCode::F12;
+    /// The key that exports the log.
+    pub const EXPORT_KEY: KeyCode = KeyCode::F11;
+    /// Where exported logs go, relative to the working directory.
+    pub const EXPORT_DIR: &'static str = "telemetry";
+    /// The size of a tile, to turn world positions into tiles.
+    pub const TILE_SIZE: f32 = 16.;
+
+    /// Throws away everything recorded so far.
+    pub fn clear(&mut self) {
+        self.deaths.clear();
+        self.visits.clear();
+        self.shots.clear();
+        self.routes.clear();
+    }
+
+    /// Writes the log as JSON.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("{\"deaths\":[");
+
+        for (i, death) in self.deaths.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(
+                out,
+                "{{\"time\":{},\"level\":{},\"x\":{},\"y\":{}}}",
+                death.time,
+                json_string(&death.level),
+                death.position.x,
+                death.position.y,
+            );
+        }
+
+        out.push_str("],\"visits\":[");
+
+        for (i, visit) in self.visits.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(
+                out,
+                "{{\"level\":{},\"entered\":{}",
+                json_string(&visit.level),
+                visit.entered,
+            );
+
+            if let Some(seconds) = visit.seconds {
+                let _ = write!(out, ",\"seconds\":{}", seconds);
+            }
+
+            out.push('}');
+        }
+
+        out.push_str("],\"shots\":{");
+
+        for (i, (level, count)) in self.shots.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(out, "{}:{}", json_string(level), count);
+        }
+
+        out.push_str("},\"routes\":[");
+
+        for (i, (route, count)) in self.routes.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            let _ = write!(
+                out,
+                "{{\"level\":{},\"from\":[{},{}],\"to\":[{},{}],\"count\":{}}}",
+                json_string(&route.level),
+                route.from.x,
+                route.from.y,
+                route.to.x,
+                route.to.y,
+                count,
+            );
+        }
+
+        out.push_str("]}\n");
+        out
+    }
+}
+
+/// Quotes and escapes a string for JSON.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn telemetry_enabled(settings: Res<Settings>) -> bool {
+    settings.telemetry
+}
+
+fn toggle_telemetry(mut settings: ResMut<Settings>, keyboard: Res<Input<KeyCode>>) {
+    if !keyboard.just_pressed(Telemetry::OPT_IN_KEY) {
+        return;
+    }
+
+    settings.telemetry = !settings.telemetry;
+
+    if settings.telemetry {
+        crate::game_info!(Telemetry, "opted in to telemetry");
+    } else {
+        crate::game_info!(Telemetry, "opted out of telemetry");
+    }
+}
+
+fn record_deaths(
+    mut telemetry: ResMut<Telemetry>,
+    player_query: Query<&GlobalTransform, (With<LocalPlayer>, Added<PlayerDeath>)>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+) {
+    for transform in player_query.iter() {
+        telemetry.deaths.push(Death {
+            time: time.elapsed_seconds(),
+            level: current_level.0.clone(),
+            position: transform.translation().truncate(),
+        });
+    }
+}
+
+fn record_shots(
+    mut telemetry: ResMut<Telemetry>,
+    mut spawn_events: EventReader<SpawnProjectile>,
+    player_query: Query<(), With<LocalPlayer>>,
+    current_level: Res<CurrentLevel>,
+) {
+    let shots = spawn_events
+        .iter()
+        .filter(|ev| player_query.contains(ev.subject()))
+        .count() as u32;
+
+    if shots > 0 {
+        *telemetry.shots.entry(current_level.0.clone()).or_default() += shots;
+    }
+}
+
+fn record_level_times(
+    mut telemetry: ResMut<Telemetry>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+
+    if let Some(visit) = telemetry.visits.last_mut() {
+        if visit.seconds.is_none() {
+            visit.seconds = Some(now - visit.entered);
+        }
+    }
+
+    if !current_level.0.is_empty() {
+        telemetry.visits.push(LevelVisit {
+            level: current_level.0.clone(),
+            entered: now,
+            seconds: None,
+        });
+    }
+}
+
+fn record_signal_routes(
+    mut telemetry: ResMut<Telemetry>,
+    mut signal_events: EventReader<SignalEvent>,
+    transform_query: Query<&GlobalTransform>,
+    current_level: Res<CurrentLevel>,
+) {
+    let tile = |entity: Entity| {
+        transform_query
+            .get(entity)
+            .map(|transform| {
+                (transform.translation().truncate() / Telemetry::TILE_SIZE)
+                    .floor()
+                    .as_ivec2()
+            })
+            .ok()
+    };
+
+    for ev in signal_events.iter() {
+        let (Some(from), Some(to)) = (tile(ev.sender), tile(ev.receiver)) else {
+            continue;
+        };
+
+        let route = Route {
+            level: current_level.0.clone(),
+            from,
+            to,
+        };
+
+        *telemetry.routes.entry(route).or_default() += 1;
+    }
+}
+
+fn export_telemetry(telemetry: Res<Telemetry>) {
+    let contents = telemetry.to_json();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Err(err) = std::fs::create_dir_all(Telemetry::EXPORT_DIR) {
+            crate::game_warn!(
+                Telemetry,
+                "cannot create {}: {}",
+                Telemetry::EXPORT_DIR,
+                err
+            );
+            return;
+        }
+
+        let path = format!(
+            "{}/session-{}.json",
+            Telemetry::EXPORT_DIR,
+            telemetry.session
+        );
+
+        match std::fs::write(&path, contents) {
+            Ok(()) => crate::game_info!(Telemetry, "exported telemetry to {}", path),
+            Err(err) => crate::game_warn!(Telemetry, "cannot export {}: {}", path, err),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    crate::game_info!(Telemetry, "telemetry: {}", contents);
+}
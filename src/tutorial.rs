@@ -0,0 +1,512 @@
+//! Tutorials scripted in levels.
+//!
+//! A tutorial is a chain of [`TutorialStep`]s placed in LDtk, each pointing
+//! to the step after it with its `Next` field. A step waits for the player to
+//! walk into a [`TutorialTrigger`], shows or hides a prompt, waits for the
+//! player to do something, or activates something like a door. Only the
+//! steps marked `Start` are going when the level is spawned; the rest start
+//! once the step before them is done.
+//!
+//! Prompts are images, named by their asset path in the step's `Hint` field,
+//! and are shown along the bottom of the screen until a later step hides or
+//! replaces them.
+
+use bevy::prelude::*;
+
+use bevy_rapier2d::prelude::*;
+
+use bevy_ecs_ldtk::{
+    app::{LdtkEntity, LdtkEntityAppExt as _},
+    ldtk::{ldtk_fields::LdtkFields as _, LayerInstance, TilesetDefinition},
+    EntityInstance,
+};
+
+use std::collections::HashSet;
+
+use crate::drum::DrumHitEvent;
+use crate::interactions::SignalEvent;
+use crate::level::Iid;
+use crate::physics;
+use crate::platform::ActivateEvent;
+use crate::player::LocalPlayer;
+use crate::projectile::spawner::SpawnProjectile;
+
+/// Tutorial plugin.
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<TutorialStepBundle>("TutorialStep")
+            .register_ldtk_entity::<TutorialTriggerBundle>("TutorialTrigger")
+            .init_resource::<TutorialHint>()
+            .init_resource::<PlayerActions>()
+            .add_systems(Update, upgrade_tutorial_steps)
+            .add_systems(
+                Update,
+                (
+                    enter_tutorial_triggers,
+                    record_player_actions,
+                    run_tutorial_steps,
+                )
+                    .chain()
+                    .run_if(any_with_component::<TutorialStep>())
+                    .after(upgrade_tutorial_steps),
+            )
+            .add_systems(
+                Update,
+                show_tutorial_hint
+                    .run_if(resource_changed::<TutorialHint>())
+                    .after(run_tutorial_steps),
+            );
+    }
+}
+
+/// One step of a tutorial.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TutorialStep {
+    /// What the step does.
+    pub kind: TutorialStepKind,
+    /// Whether the step is going.
+    pub active: bool,
+    /// The step that starts once this one is done.
+    pub next: Option<Entity>,
+    /// The trigger a [`TutorialStepKind::WaitForTrigger`] waits for, or the
+    /// entity a [`TutorialStepKind::Activate`] activates.
+    pub target: Option<Entity>,
+    next_iid: Option<String>,
+    target_iid: Option<String>,
+}
+
+impl TutorialStep {
+    /// Creates a new `TutorialStep` that isn't going yet.
+    pub fn new(kind: TutorialStepKind) -> TutorialStep {
+        TutorialStep {
+            kind,
+            ..Default::default()
+        }
+    }
+
+    /// Checks if the step is still waiting for an entity from the level.
+    pub fn is_upgraded(&self) -> bool {
+        self.next_iid.is_none() && self.target_iid.is_none()
+    }
+}
+
+/// What a [`TutorialStep`] does.
+#[derive(Clone, Debug, Default)]
+pub enum TutorialStepKind {
+    /// Waits for the player to walk into [`TutorialStep::target`].
+    #[default]
+    WaitForTrigger,
+    /// Shows a prompt, replacing the one already up.
+    ShowHint(Handle<Image>),
+    /// Hides the prompt.
+    HideHint,
+    /// Waits for the player to do something.
+    RequireAction(PlayerAction),
+    /// Sends an [`ActivateEvent`] to [`TutorialStep::target`], like to open a
+    /// door.
+    Activate,
+    /// Does nothing, for steps that couldn't be read from the level, so the
+    /// steps after them still start.
+    Skip,
+}
+
+/// Something the player can be asked to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerAction {
+    /// Run left or right.
+    Move,
+    /// Jump.
+    Jump,
+    /// Shoot.
+    Shoot,
+    /// Send a signal through the pipes.
+    Signal,
+    /// Hit a drum.
+    Drum,
+}
+
+impl PlayerAction {
+    /// Finds an action by its name, as in LDtk.
+    pub fn from_name(name: &str) -> Option<PlayerAction> {
+        match name {
+            "Move" => Some(PlayerAction::Move),
+            "Jump" => Some(PlayerAction::Jump),
+            "Shoot" => Some(PlayerAction::Shoot),
+            "Signal" => Some(PlayerAction::Signal),
+            "Drum" => Some(PlayerAction::Drum),
+            _ => None,
+        }
+    }
+}
+
+/// A bundle for a [`TutorialStep`].
+#[derive(Bundle, Default)]
+pub struct TutorialStepBundle {
+    pub step: TutorialStep,
+    pub iid: Iid,
+}
+
+impl LdtkEntity for TutorialStepBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let entity_ref = |field: &str| {
+            entity_instance
+                .get_maybe_entity_ref_field(field)
+                .ok() // may not exist
+                .and_then(|a| a.as_ref())
+                .map(|a| a.entity_iid.clone())
+        };
+
+        let kind = step_kind(entity_instance, asset_server).unwrap_or_else(|| {
+            crate::game_warn!(Level, "skipping tutorial step {}", entity_instance.iid);
+            TutorialStepKind::Skip
+        });
+
+        let active = entity_instance
+            .get_bool_field("Start")
+            .ok()
+            .copied()
+            .unwrap_or_default();
+
+        TutorialStepBundle {
+            step: TutorialStep {
+                active,
+                next_iid: entity_ref("Next"),
+                target_iid: entity_ref("Target"),
+                ..TutorialStep::new(kind)
+            },
+            iid: Iid::from(entity_instance),
+        }
+    }
+}
+
+/// Reads what a step does from its LDtk fields, warning and returning `None`
+/// if they are missing or wrong.
+fn step_kind(
+    entity_instance: &EntityInstance,
+    asset_server: &AssetServer,
+) -> Option<TutorialStepKind> {
+    let Ok(step) = entity_instance.get_enum_field("Step") else {
+        crate::game_warn!(Level, "tutorial step has no Step");
+        return None;
+    };
+
+    match step.as_str() {
+        "WaitForTrigger" => Some(TutorialStepKind::WaitForTrigger),
+        "ShowHint" => {
+            let Some(hint) = entity_instance
+                .get_maybe_string_field("Hint")
+                .ok()
+                .and_then(|hint| hint.as_ref())
+            else {
+                crate::game_warn!(Level, "tutorial step shows a hint without a Hint");
+                return None;
+            };
+
+            Some(TutorialStepKind::ShowHint(asset_server.load(hint.as_str())))
+        }
+        "HideHint" => Some(TutorialStepKind::HideHint),
+        "RequireAction" => {
+            let action = entity_instance
+                .get_enum_field("Action")
+                .ok()
+                .and_then(|action| PlayerAction::from_name(action.as_str()));
+
+            if action.is_none() {
+                crate::game_warn!(Level, "tutorial step requires an unknown Action");
+            }
+
+            action.map(TutorialStepKind::RequireAction)
+        }
+        "Activate" => Some(TutorialStepKind::Activate),
+        _ => {
+            crate::game_warn!(Level, "unknown tutorial step {}", step);
+            None
+        }
+    }
+}
+
+/// A space in the level that a [`TutorialStep`] waits for the player to walk
+/// into.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TutorialTrigger {
+    /// Whether the player has walked in.
+    pub entered: bool,
+}
+
+/// A bundle for a [`TutorialTrigger`].
+#[derive(Bundle)]
+pub struct TutorialTriggerBundle {
+    pub trigger: TutorialTrigger,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub sensor: Sensor,
+    pub iid: Iid,
+}
+
+impl TutorialTriggerBundle {
+    /// Creates a new `TutorialTriggerBundle` of a size.
+    pub fn new(size: Vec2) -> TutorialTriggerBundle {
+        TutorialTriggerBundle {
+            trigger: TutorialTrigger::default(),
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            collision_groups: CollisionGroups::new(
+                physics::COLLISION_GROUP_TRIGGER,
+                physics::COLLISION_GROUP_FRIENDLY,
+            ),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sensor: Sensor,
+            iid: Iid::default(),
+        }
+    }
+}
+
+impl LdtkEntity for TutorialTriggerBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _layer_instance: &LayerInstance,
+        _tileset: Option<&Handle<Image>>,
+        _tileset_definition: Option<&TilesetDefinition>,
+        _asset_server: &AssetServer,
+        _texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        TutorialTriggerBundle {
+            iid: Iid::from(entity_instance),
+            ..TutorialTriggerBundle::new(size)
+        }
+    }
+}
+
+/// The prompt shown to the player, if any.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct TutorialHint(pub Option<Handle<Image>>);
+
+impl TutorialHint {
+    /// How big a prompt is on the screen, as a multiple of its pixels.
+    pub const SCALE: f32 = 4.;
+}
+
+/// The prompt on the screen.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TutorialHintNode;
+
+/// The things the player did this frame.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct PlayerActions(pub HashSet<PlayerAction>);
+
+impl PlayerActions {
+    /// How fast the player has to run to count as moving, in world units a
+    /// second.
+    pub const MOVE_SPEED: f32 = 16.;
+    /// How fast the player has to go up to count as jumping, in world units a
+    /// second.
+    pub const JUMP_SPEED: f32 = 32.;
+
+    /// Checks if the player did something this frame.
+    pub fn performed(&self, action: PlayerAction) -> bool {
+        self.0.contains(&action)
+    }
+}
+
+fn upgrade_tutorial_steps(
+    mut step_query: Query<&mut TutorialStep>,
+    iid_query: Query<(Entity, &Iid)>,
+) {
+    let find = |iid_request: &str| {
+        iid_query
+            .iter()
+            .find(|(_, iid)| iid.0 == iid_request)
+            .map(|(e, _)| e)
+    };
+
+    for mut step in step_query.iter_mut() {
+        // do not trip change detection
+        if step.is_upgraded() {
+            continue;
+        }
+
+        if let Some(found) = step.next_iid.as_deref().and_then(find) {
+            step.next = Some(found);
+            step.next_iid = None;
+        }
+
+        if let Some(found) = step.target_iid.as_deref().and_then(find) {
+            step.target = Some(found);
+            step.target_iid = None;
+        }
+    }
+}
+
+fn enter_tutorial_triggers(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut trigger_query: Query<&mut TutorialTrigger>,
+    player_query: Query<(), With<LocalPlayer>>,
+) {
+    for ev in collision_events.iter() {
+        let CollisionEvent::Started(e1, e2, _) = *ev else {
+            continue;
+        };
+
+        // find trigger and subject
+        let (trigger, subject) = if trigger_query.contains(e1) {
+            (e1, e2)
+        } else if trigger_query.contains(e2) {
+            (e2, e1)
+        } else {
+            continue;
+        };
+
+        if !player_query.contains(subject) {
+            continue;
+        }
+
+        let Ok(mut entered) = trigger_query.get_mut(trigger) else {
+            continue;
+        };
+
+        // do not trip change detection
+        if !entered.entered {
+            crate::game_debug!(Level, "tutorial trigger {:?} entered", trigger);
+
+            entered.entered = true;
+        }
+    }
+}
+
+fn record_player_actions(
+    mut actions: ResMut<PlayerActions>,
+    mut spawn_events: EventReader<SpawnProjectile>,
+    mut signal_events: EventReader<SignalEvent>,
+    mut drum_events: EventReader<DrumHitEvent>,
+    player_query: Query<(Entity, &Velocity), With<LocalPlayer>>,
+) {
+    actions.0.clear();
+
+    if let Ok((player, velocity)) = player_query.get_single() {
+        if velocity.linvel.x.abs() > PlayerActions::MOVE_SPEED {
+            actions.0.insert(PlayerAction::Move);
+        }
+
+        if velocity.linvel.y > PlayerActions::JUMP_SPEED {
+            actions.0.insert(PlayerAction::Jump);
+        }
+
+        if spawn_events.iter().any(|ev| ev.subject() == player) {
+            actions.0.insert(PlayerAction::Shoot);
+        }
+    }
+
+    if signal_events.iter().count() > 0 {
+        actions.0.insert(PlayerAction::Signal);
+    }
+
+    if drum_events.iter().count() > 0 {
+        actions.0.insert(PlayerAction::Drum);
+    }
+}
+
+fn run_tutorial_steps(
+    mut step_query: Query<(Entity, &mut TutorialStep)>,
+    mut activate_events: EventWriter<ActivateEvent>,
+    mut hint: ResMut<TutorialHint>,
+    trigger_query: Query<&TutorialTrigger>,
+    actions: Res<PlayerActions>,
+) {
+    let mut started = Vec::new();
+
+    for (entity, mut step) in step_query.iter_mut() {
+        // do not trip change detection
+        if !step.active || !step.is_upgraded() {
+            continue;
+        }
+
+        let done = match &step.kind {
+            TutorialStepKind::WaitForTrigger => step
+                .target
+                .and_then(|target| trigger_query.get(target).ok())
+                .is_some_and(|trigger| trigger.entered),
+            TutorialStepKind::ShowHint(image) => {
+                hint.0 = Some(image.clone());
+                true
+            }
+            TutorialStepKind::HideHint => {
+                // do not trip change detection
+                if hint.0.is_some() {
+                    hint.0 = None;
+                }
+                true
+            }
+            TutorialStepKind::RequireAction(action) => actions.performed(*action),
+            TutorialStepKind::Activate => {
+                if let Some(target) = step.target {
+                    activate_events.send(ActivateEvent(target));
+                }
+                true
+            }
+            TutorialStepKind::Skip => true,
+        };
+
+        if !done {
+            continue;
+        }
+
+        crate::game_debug!(Level, "tutorial step {:?} done", entity);
+
+        step.active = false;
+        started.extend(step.next);
+    }
+
+    // started after the loop, so each step gets a frame of its own
+    for next in started {
+        if let Ok((_, mut step)) = step_query.get_mut(next) {
+            step.active = true;
+        }
+    }
+}
+
+fn show_tutorial_hint(
+    mut commands: Commands,
+    hint_query: Query<Entity, With<TutorialHintNode>>,
+    hint: Res<TutorialHint>,
+) {
+    for entity in hint_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(image) = &hint.0 else {
+        return;
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent(10.),
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            TutorialHintNode,
+        ))
+        .with_children(|parent| {
+            parent.spawn(ImageBundle {
+                image: UiImage::new(image.clone()),
+                transform: Transform::from_scale(Vec3::splat(TutorialHint::SCALE)),
+                ..Default::default()
+            });
+        });
+}
@@ -0,0 +1,319 @@
+//! The latency calibration screen.
+//!
+//! The first time the game starts, the player taps along to a click track
+//! before playing. How late their taps land on average is how far behind the
+//! sound and picture are, which is saved to
+//! [`Settings::latency_offset`] for the [`BeatClock`] to take out when judging
+//! on-beat actions.
+//!
+//! A mark under the taps shows whether the player has opted in to
+//! [telemetry](crate::telemetry), which they can flip here with
+//! [`Telemetry::OPT_IN_KEY`] before the game starts.
+//!
+//! [`Telemetry::OPT_IN_KEY`]: crate::telemetry::Telemetry::OPT_IN_KEY
+
+use bevy::prelude::*;
+
+use super::kill_feed::PIXEL_SCALE;
+use crate::audio::{PlaySound, SoundCategory};
+use crate::beat::BeatClock;
+use crate::settings::{AccessibilitySettings, Settings};
+use crate::{GameAssets, GameState};
+
+/// Calibration screen plugin.
+pub struct CalibrationPlugin;
+
+impl Plugin for CalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Calibration), start_calibration)
+            .add_systems(
+                Update,
+                (
+                    (play_calibration_beats, record_calibration_taps).chain(),
+                    show_calibration_telemetry,
+                )
+                    .run_if(in_state(GameState::Calibration)),
+            )
+            .add_systems(OnExit(GameState::Calibration), close_calibration);
+    }
+}
+
+/// The calibration screen.
+#[derive(Clone, Component, Debug)]
+pub struct CalibrationScreen {
+    clock: BeatClock,
+    taps: Vec<f32>,
+}
+
+impl CalibrationScreen {
+    /// The tempo of the click track.
+    pub const BPM: f32 = 100.;
+    /// How many beats play before taps count.
+    pub const LEAD_IN: u64 = 4;
+    /// How many taps are measured.
+    pub const TAPS: usize = 8;
+    /// The key to tap with.
+    pub const TAP_KEY: KeyCode = KeyCode::Space;
+    /// The key that skips calibration.
+    pub const SKIP_KEY: KeyCode = KeyCode::Escape;
+
+    /// The measured latency, if all the taps are in.
+    ///
+    /// This is the median of the taps, so a single stray tap doesn't throw it
+    /// off.
+    pub fn latency(&self) -> Option<f32> {
+        if self.taps.len() < CalibrationScreen::TAPS {
+            return None;
+        }
+
+        let mut taps = self.taps.clone();
+        taps.sort_by(f32::total_cmp);
+
+        let mid = taps.len() / 2;
+
+        Some(if taps.len() % 2 == 0 {
+            (taps[mid - 1] + taps[mid]) / 2.
+        } else {
+            taps[mid]
+        })
+    }
+}
+
+impl Default for CalibrationScreen {
+    fn default() -> CalibrationScreen {
+        CalibrationScreen {
+            clock: BeatClock::new(CalibrationScreen::BPM),
+            taps: Vec::with_capacity(CalibrationScreen::TAPS),
+        }
+    }
+}
+
+/// The square that flashes on every beat.
+#[derive(Clone, Component, Debug, Default)]
+struct CalibrationPulse;
+
+/// A mark that lights up for each measured tap.
+#[derive(Clone, Component, Debug)]
+struct CalibrationTapMark(usize);
+
+/// The mark that is lit while the player is opted in to telemetry.
+#[derive(Clone, Component, Debug, Default)]
+struct CalibrationTelemetryMark;
+
+/// The color of the pulse between beats.
+const PULSE_IDLE: Color = Color::rgba(1., 1., 1., 0.15);
+/// The color of a tap mark with no tap yet.
+const MARK_EMPTY: Color = Color::rgba(1., 1., 1., 0.2);
+
+fn start_calibration(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    settings: Res<Settings>,
+) {
+    if settings.calibrated {
+        next_state.set(GameState::InGame);
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(PIXEL_SCALE * 8.),
+                    ..Default::default()
+                },
+                background_color: Color::BLACK.into(),
+                ..Default::default()
+            },
+            CalibrationScreen::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(PIXEL_SCALE * 16.),
+                        height: Val::Px(PIXEL_SCALE * 16.),
+                        ..Default::default()
+                    },
+                    background_color: PULSE_IDLE.into(),
+                    ..Default::default()
+                },
+                CalibrationPulse,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(PIXEL_SCALE * 2.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for i in 0..CalibrationScreen::TAPS {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(PIXEL_SCALE * 4.),
+                                    height: Val::Px(PIXEL_SCALE * 4.),
+                                    ..Default::default()
+                                },
+                                background_color: MARK_EMPTY.into(),
+                                ..Default::default()
+                            },
+                            CalibrationTapMark(i),
+                        ));
+                    }
+                });
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(PIXEL_SCALE * 4.),
+                        height: Val::Px(PIXEL_SCALE * 4.),
+                        ..Default::default()
+                    },
+                    background_color: telemetry_mark_color(&settings).into(),
+                    ..Default::default()
+                },
+                CalibrationTelemetryMark,
+            ));
+        });
+
+    crate::game_info!(Settings, "calibrating latency");
+}
+
+fn play_calibration_beats(
+    mut screen_query: Query<&mut CalibrationScreen>,
+    mut pulse_query: Query<&mut BackgroundColor, With<CalibrationPulse>>,
+    mut play_sound_events: EventWriter<PlaySound>,
+    accessibility: Res<AccessibilitySettings>,
+    assets: Res<GameAssets>,
+    time: Res<Time>,
+) {
+    let Ok(mut screen) = screen_query.get_single_mut() else {
+        return;
+    };
+
+    let ticked = screen.clock.tick(time.delta());
+
+    if ticked {
+        // the lead-in clicks are quieter
+        let volume = if screen.clock.beats() <= CalibrationScreen::LEAD_IN {
+            0.5
+        } else {
+            1.
+        };
+
+        // the click track is never cut off
+        play_sound_events.send(
+            PlaySound::new(assets.sound_bounce.clone())
+                .volume(volume)
+                .category(SoundCategory::Ui)
+                .priority(u8::MAX),
+        );
+    }
+
+    // flash on the beat, then fade back
+    let flash = 1. - screen.clock.phase();
+
+    for mut color in pulse_query.iter_mut() {
+        color.0 = PULSE_IDLE.with_a(PULSE_IDLE.a().max(accessibility.pulse(flash * flash)));
+    }
+}
+
+fn record_calibration_taps(
+    mut screen_query: Query<&mut CalibrationScreen>,
+    mut mark_query: Query<(&mut BackgroundColor, &CalibrationTapMark)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut settings: ResMut<Settings>,
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    let Ok(mut screen) = screen_query.get_single_mut() else {
+        return;
+    };
+
+    if keyboard.just_pressed(CalibrationScreen::SKIP_KEY) {
+        // keep whatever offset there was
+        settings.calibrated = true;
+        next_state.set(GameState::InGame);
+
+        crate::game_info!(Settings, "skipped calibration");
+        return;
+    }
+
+    let tapped = keyboard.just_pressed(CalibrationScreen::TAP_KEY)
+        || mouse.just_pressed(MouseButton::Left)
+        || gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == GamepadButtonType::South);
+
+    if !tapped {
+        return;
+    }
+
+    // taps during the lead-in don't count, but a tap just before the first
+    // counted beat does
+    let first_beat = CalibrationScreen::LEAD_IN as f32 + 0.5;
+
+    if (screen.clock.beats() as f32 + screen.clock.phase()) < first_beat {
+        return;
+    }
+
+    let offset = screen.clock.offset();
+    let index = screen.taps.len();
+
+    screen.taps.push(offset);
+
+    for (mut color, mark) in mark_query.iter_mut() {
+        if mark.0 == index {
+            color.0 = Color::WHITE;
+        }
+    }
+
+    let Some(latency) = screen.latency() else {
+        return;
+    };
+
+    settings.latency_offset = latency;
+    settings.calibrated = true;
+    next_state.set(GameState::InGame);
+
+    crate::game_info!(Settings, "latency is {:.0}ms", latency * 1000.);
+}
+
+fn show_calibration_telemetry(
+    mut mark_query: Query<&mut BackgroundColor, With<CalibrationTelemetryMark>>,
+    settings: Res<Settings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut color in mark_query.iter_mut() {
+        color.0 = telemetry_mark_color(&settings);
+    }
+}
+
+fn telemetry_mark_color(settings: &Settings) -> Color {
+    if settings.telemetry {
+        Color::WHITE
+    } else {
+        MARK_EMPTY
+    }
+}
+
+fn close_calibration(mut commands: Commands, screen_query: Query<Entity, With<CalibrationScreen>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
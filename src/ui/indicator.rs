@@ -0,0 +1,175 @@
+//! Markers at the edge of the screen for things just off of it.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use bevy_rapier2d::prelude::*;
+
+use std::collections::HashMap;
+
+use crate::camera::PlayerCamera;
+use crate::enemy::{DeathTimer, Enemy, Hostility};
+use crate::player::LocalPlayer;
+use crate::projectile::Projectile;
+use crate::GameState;
+
+/// Off-screen indicator plugin.
+pub struct OffscreenIndicatorPlugin;
+
+impl Plugin for OffscreenIndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IndicatorSettings>().add_systems(
+            Update,
+            update_offscreen_indicators.run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// Settings for off-screen indicators.
+#[derive(Clone, Debug, Resource)]
+pub struct IndicatorSettings {
+    /// Whether indicators are shown at all.
+    pub enabled: bool,
+    /// How far from the player something can be and still get an indicator,
+    /// in world units. Indicators fade out as things get this far.
+    pub max_distance: f32,
+}
+
+impl Default for IndicatorSettings {
+    fn default() -> IndicatorSettings {
+        IndicatorSettings {
+            enabled: true,
+            max_distance: 256.,
+        }
+    }
+}
+
+/// A marker for an entity that is off-screen.
+#[derive(Clone, Component, Debug)]
+pub struct OffscreenIndicator {
+    /// The entity being pointed at.
+    pub target: Entity,
+}
+
+/// The size of an indicator in pixels.
+const INDICATOR_SIZE: f32 = 8.;
+/// How far indicators are kept from the edge of the screen in pixels.
+const EDGE_MARGIN: f32 = 6.;
+
+fn update_offscreen_indicators(
+    mut commands: Commands,
+    settings: Res<IndicatorSettings>,
+    mut indicator_query: Query<(
+        Entity,
+        &OffscreenIndicator,
+        &mut Style,
+        &mut BackgroundColor,
+    )>,
+    enemies_query: Query<
+        (Entity, &GlobalTransform, &Hostility),
+        (With<Enemy>, Without<DeathTimer>),
+    >,
+    projectiles_query: Query<(Entity, &GlobalTransform, &Hostility, &Velocity), With<Projectile>>,
+    player_query: Query<&GlobalTransform, With<LocalPlayer>>,
+    camera_query: Query<(&GlobalTransform, &Camera), With<PlayerCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let mut targets: HashMap<Entity, (Vec3, Color)> = HashMap::new();
+
+    let player = player_query.get_single().ok().map(|t| t.translation());
+    let camera = camera_query.get_single().ok();
+
+    if let (true, Some(player)) = (settings.enabled, player) {
+        let max_distance_sq = settings.max_distance * settings.max_distance;
+
+        for (entity, transform, hostility) in enemies_query.iter() {
+            if *hostility == Hostility::Hostile
+                && transform.translation().distance_squared(player) <= max_distance_sq
+            {
+                targets.insert(entity, (transform.translation(), hostility.color()));
+            }
+        }
+
+        for (entity, transform, hostility, velocity) in projectiles_query.iter() {
+            let offset = (player - transform.translation()).truncate();
+
+            // only projectiles coming this way
+            if *hostility == Hostility::Hostile
+                && offset.length_squared() <= max_distance_sq
+                && offset.dot(velocity.linvel) > 0.
+            {
+                targets.insert(entity, (transform.translation(), hostility.color()));
+            }
+        }
+    }
+
+    // find where each indicator goes on the screen
+    let mut positions: HashMap<Entity, (Vec2, Color)> = HashMap::new();
+
+    // the camera may draw into a smaller image, but the ui is the size of the
+    // window
+    let window = window_query.get_single().ok();
+
+    if let (Some((camera_transform, camera)), Some(player)) = (camera, player) {
+        if let Some(window) = window {
+            let viewport_size = Vec2::new(window.width(), window.height());
+
+            for (&entity, &(position, color)) in targets.iter() {
+                let Some(ndc) = camera.world_to_ndc(camera_transform, position) else {
+                    continue;
+                };
+
+                let ndc = ndc.truncate();
+
+                // on-screen things don't need an indicator
+                if ndc.x.abs() <= 1. && ndc.y.abs() <= 1. {
+                    continue;
+                }
+
+                // push the point back onto the edge of the screen
+                let edge = Vec2::ONE - (EDGE_MARGIN + INDICATOR_SIZE / 2.) * 2. / viewport_size;
+                let ndc = ndc / (ndc.abs() / edge).max_element();
+
+                // flip y and get pixels
+                let pos = (Vec2::new(ndc.x, -ndc.y) + Vec2::ONE) / 2. * viewport_size;
+
+                // fade out with distance
+                let distance = position.distance(player);
+                let alpha = (1. - distance / settings.max_distance).clamp(0.2, 1.);
+
+                positions.insert(entity, (pos, color.with_a(alpha)));
+            }
+        }
+    }
+
+    // update existing indicators
+    for (indicator_entity, indicator, mut style, mut background) in indicator_query.iter_mut() {
+        match positions.remove(&indicator.target) {
+            Some((pos, color)) => {
+                style.left = Val::Px(pos.x - INDICATOR_SIZE / 2.);
+                style.top = Val::Px(pos.y - INDICATOR_SIZE / 2.);
+                background.0 = color;
+            }
+            None => commands.entity(indicator_entity).despawn_recursive(),
+        }
+    }
+
+    // create new ones
+    for (target, (pos, color)) in positions {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(INDICATOR_SIZE),
+                    height: Val::Px(INDICATOR_SIZE),
+                    left: Val::Px(pos.x - INDICATOR_SIZE / 2.),
+                    top: Val::Px(pos.y - INDICATOR_SIZE / 2.),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(color),
+                ..Default::default()
+            },
+            OffscreenIndicator { target },
+        ));
+    }
+}
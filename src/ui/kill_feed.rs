@@ -0,0 +1,238 @@
+//! A ticker of recent kills and combos.
+
+use bevy::prelude::*;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::atlas::AtlasRegistry;
+use crate::enemy::{prefab::EnemyPrefab, EnemyDiedEvent, Hostility};
+use crate::score::{ComboEvent, ScoreEvent, ScoreSystem};
+use crate::{GameAssets, GameState};
+
+/// Kill feed plugin.
+pub struct KillFeedPlugin;
+
+impl Plugin for KillFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), setup_kill_feed)
+            .add_systems(
+                Update,
+                (push_kill_feed_entries, fade_kill_feed_entries)
+                    .chain()
+                    .run_if(in_state(GameState::InGame))
+                    .after(ScoreSystem::Score),
+            );
+    }
+}
+
+/// The container of the kill feed.
+#[derive(Clone, Component, Debug, Default)]
+pub struct KillFeed;
+
+/// A single line in the kill feed.
+#[derive(Clone, Component, Debug)]
+pub struct KillFeedEntry {
+    timer: Timer,
+}
+
+impl KillFeedEntry {
+    /// How long an entry stays up.
+    pub const LIFETIME: Duration = Duration::from_secs(3);
+    /// How long an entry takes to fade out at the end of its life.
+    pub const FADE: Duration = Duration::from_millis(500);
+    /// The most entries shown at once.
+    pub const MAX_ENTRIES: usize = 6;
+}
+
+impl Default for KillFeedEntry {
+    fn default() -> KillFeedEntry {
+        KillFeedEntry {
+            timer: Timer::new(KillFeedEntry::LIFETIME, TimerMode::Once),
+        }
+    }
+}
+
+/// How many screen pixels each pixel of the feed art takes up.
+pub(crate) const PIXEL_SCALE: f32 = 3.;
+
+/// The size of a glyph in `ui/digits.png`.
+const GLYPH_SIZE: Vec2 = Vec2::new(4., 6.);
+/// The index of the `x` glyph.
+pub(crate) const GLYPH_TIMES: usize = 10;
+/// The index of the `+` glyph.
+pub(crate) const GLYPH_PLUS: usize = 11;
+
+fn setup_kill_feed(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                right: Val::Px(8.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                row_gap: Val::Px(PIXEL_SCALE * 2.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        KillFeed,
+    ));
+}
+
+fn push_kill_feed_entries(
+    mut commands: Commands,
+    mut died_events: EventReader<EnemyDiedEvent>,
+    mut score_events: EventReader<ScoreEvent>,
+    mut combo_events: EventReader<ComboEvent>,
+    feed_query: Query<(Entity, Option<&Children>), With<KillFeed>>,
+    assets: Res<GameAssets>,
+    registry: Res<AtlasRegistry>,
+) {
+    let Ok((feed, children)) = feed_query.get_single() else {
+        return;
+    };
+
+    let prefabs = died_events
+        .iter()
+        .filter_map(|ev| Some((ev.entity, ev.prefab.clone()?)))
+        .collect::<HashMap<_, _>>();
+
+    let mut entries = Vec::new();
+
+    for ev in score_events.iter() {
+        let icon = ev
+            .kill
+            .and_then(|kill| prefabs.get(&kill))
+            .and_then(|prefab| Some((prefab.icon_size(), prefab.atlas(&registry)?)));
+
+        let entry = commands
+            .spawn(entry_bundle())
+            .with_children(|parent| {
+                if let Some((size, atlas)) = icon {
+                    let size = size * PIXEL_SCALE;
+
+                    parent.spawn(AtlasImageBundle {
+                        style: Style {
+                            width: Val::Px(size.x),
+                            height: Val::Px(size.y),
+                            ..Default::default()
+                        },
+                        texture_atlas: atlas,
+                        texture_atlas_image: UiTextureAtlasImage::default(),
+                        ..Default::default()
+                    });
+                }
+
+                spawn_number(parent, &assets, Some(GLYPH_PLUS), ev.points, Color::WHITE);
+            })
+            .id();
+
+        entries.push(entry);
+    }
+
+    for ev in combo_events.iter() {
+        let entry = commands
+            .spawn(entry_bundle())
+            .with_children(|parent| {
+                let color = Hostility::Friendly.color();
+
+                spawn_number(parent, &assets, Some(GLYPH_TIMES), ev.combo as u64, color);
+            })
+            .id();
+
+        entries.push(entry);
+    }
+
+    if entries.is_empty() {
+        return;
+    }
+
+    // newest on top, and get rid of the oldest ones
+    let old = children.map(|c| c.to_vec()).unwrap_or_default();
+    let keep = KillFeedEntry::MAX_ENTRIES.saturating_sub(entries.len());
+
+    for &entity in old.iter().skip(keep) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    entries.reverse();
+    commands.entity(feed).insert_children(0, &entries);
+}
+
+fn fade_kill_feed_entries(
+    mut commands: Commands,
+    mut entry_query: Query<(Entity, &mut KillFeedEntry, &Children)>,
+    mut color_query: Query<&mut BackgroundColor, With<UiTextureAtlasImage>>,
+    time: Res<Time>,
+) {
+    for (entity, mut entry, children) in entry_query.iter_mut() {
+        entry.timer.tick(time.delta());
+
+        if entry.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let left = entry.timer.remaining_secs() / KillFeedEntry::FADE.as_secs_f32();
+
+        if left >= 1. {
+            continue;
+        }
+
+        for &child in children.iter() {
+            if let Ok(mut color) = color_query.get_mut(child) {
+                color.0.set_a(left);
+            }
+        }
+    }
+}
+
+fn entry_bundle() -> (NodeBundle, KillFeedEntry) {
+    (
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(PIXEL_SCALE),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        KillFeedEntry::default(),
+    )
+}
+
+/// Spawns the digits of a number, after a glyph if there is one.
+pub(crate) fn spawn_number(
+    parent: &mut ChildBuilder,
+    assets: &GameAssets,
+    prefix: Option<usize>,
+    number: u64,
+    color: Color,
+) {
+    let size = GLYPH_SIZE * PIXEL_SCALE;
+
+    let digits = number.to_string();
+    let glyphs = prefix
+        .into_iter()
+        .chain(digits.bytes().map(|digit| (digit - b'0') as usize));
+
+    for index in glyphs {
+        parent.spawn(AtlasImageBundle {
+            style: Style {
+                width: Val::Px(size.x),
+                height: Val::Px(size.y),
+                ..Default::default()
+            },
+            texture_atlas: assets.ui_digits.clone(),
+            texture_atlas_image: UiTextureAtlasImage {
+                index,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(color),
+            ..Default::default()
+        });
+    }
+}
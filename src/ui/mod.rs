@@ -1,8 +1,14 @@
 //! UI things.
 
+pub mod calibration;
+pub mod indicator;
+pub mod kill_feed;
+pub mod wave;
+
 use bevy::prelude::*;
-use bevy::render::camera::ScalingMode;
-use bevy::window::PrimaryWindow;
+use bevy::render::camera::{CameraUpdateSystem, ScalingMode};
+use bevy::ui::UiSystem as BevyUiSystem;
+use bevy::window::{PrimaryWindow, WindowResized};
 
 use crate::camera::{cursor::CursorWorldPosition, PlayerCamera};
 use crate::player::{
@@ -31,6 +37,17 @@ impl Plugin for UiPlugin {
                     update_cursor_grab,
                 )
                     .after(ControllerSystem::ScanInput),
+            )
+            // the camera only picks up a new window size in `PostUpdate`, so
+            // everything sized off of it is redone once it has, before the
+            // layout, or it would be stale for a frame (or until something
+            // else changes)
+            .add_systems(
+                PostUpdate,
+                (scale_world_ui, sync_player_crosshair, sync_beta_crosshair)
+                    .run_if(on_event::<WindowResized>())
+                    .after(CameraUpdateSystem)
+                    .before(BevyUiSystem::Layout),
             );
     }
 }
@@ -209,21 +226,26 @@ fn do_wipe_effect(
 
 fn scale_world_ui(
     mut ui_query: Query<(&mut Style, &UiImage), With<ScaleWorld>>,
-    camera_query: Query<(&Camera, &OrthographicProjection), With<PlayerCamera>>,
+    camera_query: Query<&OrthographicProjection, With<PlayerCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
     images: Res<Assets<Image>>,
 ) {
-    let Ok((camera, projection)) = camera_query.get_single() else {
+    let Ok(projection) = camera_query.get_single() else {
         return;
     };
 
-    let Some(viewport_size) = camera.logical_viewport_size() else {
+    // the camera may draw into a smaller image, but the ui is the size of the
+    // window
+    let Ok(window) = window_query.get_single() else {
         return;
     };
 
+    let viewport_size = Vec2::new(window.width(), window.height());
+
     let size = match projection.scaling_mode {
         ScalingMode::FixedVertical(height) => {
             let aspect = viewport_size.x / viewport_size.y;
-            Vec2::new(height * aspect, height)
+            Vec2::new(height * aspect, height) * projection.scale
         }
         _ => unimplemented!(),
     };
@@ -260,6 +282,7 @@ fn sync_beta_crosshair(
     mut crosshair_query: Query<(&Node, &BetaCrosshair, &mut Style)>,
     player_query: Query<(&GlobalTransform, &Controller), With<LocalPlayer>>,
     camera_query: Query<(&GlobalTransform, &Camera), With<PlayerCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
     // get controller state
     let Ok((transform, controller)) = player_query.get_single() else {
@@ -271,10 +294,12 @@ fn sync_beta_crosshair(
         return;
     };
 
-    let Some(viewport_size) = camera.logical_viewport_size() else {
+    let Ok(window) = window_query.get_single() else {
         return;
     };
 
+    let viewport_size = Vec2::new(window.width(), window.height());
+
     for (node, crosshair, mut style) in crosshair_query.iter_mut() {
         let pos = controller.shoot_dir() * crosshair.0;
         let pos = transform.translation() + pos.extend(0.);
@@ -301,6 +326,7 @@ fn sync_player_crosshair(
     mut crosshair_query: Query<(&Node, &mut Style), With<PlayerCrosshair>>,
     player_query: Query<(&GlobalTransform, &Controller, &UseGamepad), With<LocalPlayer>>,
     camera_query: Query<(&GlobalTransform, &Camera, &CursorWorldPosition), With<PlayerCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
     // get controller state
     let Ok((transform, controller, gamepad)) = player_query.get_single() else {
@@ -312,10 +338,12 @@ fn sync_player_crosshair(
         return;
     };
 
-    let Some(viewport_size) = camera.logical_viewport_size() else {
+    let Ok(window) = window_query.get_single() else {
         return;
     };
 
+    let viewport_size = Vec2::new(window.width(), window.height());
+
     // get position
     let world_pos = if gamepad.has_gamepad() {
         transform.translation() + (controller.shoot_dir() * 48.).extend(1.)
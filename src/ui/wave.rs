@@ -0,0 +1,112 @@
+//! Announcements of arena waves.
+
+use bevy::prelude::*;
+
+use std::time::Duration;
+
+use super::kill_feed::{spawn_number, PIXEL_SCALE};
+use crate::arena::WaveStartedEvent;
+use crate::enemy::Hostility;
+use crate::{GameAssets, GameState};
+
+/// Wave announcement plugin.
+pub struct WaveAnnouncementPlugin;
+
+impl Plugin for WaveAnnouncementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (announce_waves, fade_wave_announcements)
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// The number of a wave, big in the middle of the screen.
+#[derive(Clone, Component, Debug)]
+pub struct WaveAnnouncement {
+    timer: Timer,
+}
+
+impl WaveAnnouncement {
+    /// How long an announcement stays up.
+    pub const LIFETIME: Duration = Duration::from_secs(2);
+    /// How long an announcement takes to fade out at the end of its life.
+    pub const FADE: Duration = Duration::from_millis(750);
+    /// How much bigger than the kill feed the announcement is.
+    pub const SCALE: f32 = 3.;
+}
+
+impl Default for WaveAnnouncement {
+    fn default() -> WaveAnnouncement {
+        WaveAnnouncement {
+            timer: Timer::new(WaveAnnouncement::LIFETIME, TimerMode::Once),
+        }
+    }
+}
+
+fn announce_waves(
+    mut commands: Commands,
+    mut wave_events: EventReader<WaveStartedEvent>,
+    announcement_query: Query<Entity, With<WaveAnnouncement>>,
+    assets: Res<GameAssets>,
+) {
+    let Some(ev) = wave_events.iter().last() else {
+        return;
+    };
+
+    for entity in announcement_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(25.),
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(PIXEL_SCALE),
+                    ..Default::default()
+                },
+                transform: Transform::from_scale(Vec3::splat(WaveAnnouncement::SCALE)),
+                ..Default::default()
+            },
+            WaveAnnouncement::default(),
+        ))
+        .with_children(|parent| {
+            let color = Hostility::Hostile.color();
+
+            spawn_number(parent, &assets, None, ev.wave as u64, color);
+        });
+}
+
+fn fade_wave_announcements(
+    mut commands: Commands,
+    mut announcement_query: Query<(Entity, &mut WaveAnnouncement, &Children)>,
+    mut color_query: Query<&mut BackgroundColor, With<UiTextureAtlasImage>>,
+    time: Res<Time>,
+) {
+    for (entity, mut announcement, children) in announcement_query.iter_mut() {
+        announcement.timer.tick(time.delta());
+
+        if announcement.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let left = announcement.timer.remaining_secs() / WaveAnnouncement::FADE.as_secs_f32();
+
+        if left >= 1. {
+            continue;
+        }
+
+        for &child in children.iter() {
+            if let Ok(mut color) = color_query.get_mut(child) {
+                color.0.set_a(left);
+            }
+        }
+    }
+}